@@ -1,3 +1,5 @@
+import 'dart:async';
+
 import 'package:flutter/foundation.dart';
 import '../services/database_service.dart';
 
@@ -29,6 +31,11 @@ class SyncProvider with ChangeNotifier {
   String _clientAddress = '';
   String? _message;
   String? _errorMessage;
+  Map<String, dynamic>? _progress;
+  Timer? _progressTimer;
+  String? _pairingCode;
+  bool _pairing = false;
+  String? _pairMessage;
 
   // Getters
   SyncMode get mode => _mode;
@@ -38,6 +45,24 @@ class SyncProvider with ChangeNotifier {
   String? get message => _message;
   String? get errorMessage => _errorMessage;
 
+  /// One-time code a client enters to pair, while the server runs
+  String? get pairingCode => _pairingCode;
+  bool get isPairing => _pairing;
+  String? get pairMessage => _pairMessage;
+
+  /// Current sync phase, e.g. pushing or pulling
+  String? get phase => _progress?['phase'] as String?;
+
+  /// Fraction done, null while the total is unknown
+  double? get progress {
+    final total = _progress?['total'] as int? ?? 0;
+    if (total == 0) return null;
+    final done = _progress?['done'] as int? ?? 0;
+    return (done / total).clamp(0.0, 1.0);
+  }
+
+  int get bytes => _progress?['bytes'] as int? ?? 0;
+
   bool get isServerRunning => _mode == SyncMode.server && _status == SyncStatus.running;
   bool get isIdle => _status == SyncStatus.idle;
 
@@ -57,6 +82,7 @@ class SyncProvider with ChangeNotifier {
       _mode = SyncMode.server;
       _status = SyncStatus.running;
       _message = 'Server running on $_serverAddress\n$result';
+      _pairingCode = await _db.pairingCode();
       notifyListeners();
       return true;
     } catch (e) {
@@ -79,6 +105,7 @@ class SyncProvider with ChangeNotifier {
       _mode = SyncMode.idle;
       _status = SyncStatus.idle;
       _message = null;
+      _pairingCode = null;
       notifyListeners();
       return true;
     } catch (e) {
@@ -88,6 +115,35 @@ class SyncProvider with ChangeNotifier {
     }
   }
 
+  /// Replace the pairing code shown, the old one stops working
+  Future<void> newPairingCode() async {
+    if (!isServerRunning) {
+      return;
+    }
+    _pairingCode = await _db.pairingCode();
+    notifyListeners();
+  }
+
+  /// Pair with a server using the code it shows, needed once before syncing
+  Future<bool> pairWithServer(String address, String code) async {
+    _pairing = true;
+    _errorMessage = null;
+    _pairMessage = null;
+    notifyListeners();
+
+    try {
+      final fingerprint = await _db.pairWithServer(addr: address, code: code);
+      _pairMessage = 'Paired with $address\nServer fingerprint: $fingerprint';
+      return true;
+    } catch (e) {
+      _errorMessage = 'Pairing failed, check the code and try again: $e';
+      return false;
+    } finally {
+      _pairing = false;
+      notifyListeners();
+    }
+  }
+
   /// Sync with remote server
   Future<bool> syncWithServer(String address) async {
     _clientAddress = address;
@@ -95,10 +151,17 @@ class SyncProvider with ChangeNotifier {
     _status = SyncStatus.syncing;
     _errorMessage = null;
     _message = 'Syncing with $address...';
+    _progress = null;
     notifyListeners();
 
+    _progressTimer = Timer.periodic(const Duration(milliseconds: 300), (_) async {
+      _progress = await _db.syncProgress();
+      notifyListeners();
+    });
+
     try {
       final result = await _db.syncWithServer(addr: address);
+      _stopProgress();
       _status = SyncStatus.success;
       _message = 'Sync completed successfully\n$result';
       notifyListeners();
@@ -113,14 +176,40 @@ class SyncProvider with ChangeNotifier {
 
       return true;
     } catch (e) {
+      _stopProgress();
+      final cancelled = (await _db.syncProgress())?['phase'] == 'cancelled';
       _status = SyncStatus.error;
-      _errorMessage = 'Sync failed: $e';
+      _errorMessage = cancelled
+          ? 'Sync cancelled, notes already exchanged are kept'
+          : 'Sync failed: $e';
       _message = null;
       notifyListeners();
       return false;
     }
   }
 
+  /// Cancel the running sync
+  Future<void> cancelSync() async {
+    if (_status != SyncStatus.syncing) {
+      return;
+    }
+    _message = 'Cancelling...';
+    notifyListeners();
+    await _db.cancelSync();
+  }
+
+  void _stopProgress() {
+    _progressTimer?.cancel();
+    _progressTimer = null;
+    _progress = null;
+  }
+
+  @override
+  void dispose() {
+    _progressTimer?.cancel();
+    super.dispose();
+  }
+
   /// Set server address
   void setServerAddress(String address) {
     _serverAddress = address;
@@ -16,11 +16,13 @@ class SyncScreen extends StatefulWidget {
 
 class _SyncScreenState extends State<SyncScreen> {
   final TextEditingController _addressController = TextEditingController();
+  final TextEditingController _codeController = TextEditingController();
   bool _showScanner = false;
 
   @override
   void dispose() {
     _addressController.dispose();
+    _codeController.dispose();
     super.dispose();
   }
 
@@ -44,16 +46,39 @@ class _SyncScreenState extends State<SyncScreen> {
     }
   }
 
+  void _pairWithServer() async {
+    final address = _addressController.text.trim();
+    final code = _codeController.text.trim();
+    if (address.isEmpty || code.isEmpty) {
+      return;
+    }
+    final syncProvider = context.read<SyncProvider>();
+    if (await syncProvider.pairWithServer(address, code)) {
+      _codeController.clear();
+    }
+  }
+
   void _showQRScanner() {
     setState(() {
       _showScanner = true;
     });
   }
 
+  // a qr code only ever carries the server's ip:port, never a command to run
+  static final _scannedAddress =
+      RegExp(r'^(\d{1,3}(\.\d{1,3}){3}|\[[0-9A-Fa-f:]+\]):\d{1,5}$');
+
   void _onQRScanned(String code) {
     setState(() {
       _showScanner = false;
     });
+    code = code.trim();
+    if (!_scannedAddress.hasMatch(code)) {
+      ScaffoldMessenger.of(context).showSnackBar(
+        const SnackBar(content: Text('Not a Local Native server address')),
+      );
+      return;
+    }
     _addressController.text = code;
     _syncWithServer(code);
   }
@@ -104,6 +129,28 @@ class _SyncScreenState extends State<SyncScreen> {
                             fontWeight: FontWeight.bold,
                           ),
                         ),
+                        if (syncProvider.pairingCode != null) ...[
+                          const SizedBox(height: 8),
+                          Row(
+                            children: [
+                              Text(
+                                'Pairing Code: ${syncProvider.pairingCode}',
+                                style: Theme.of(context).textTheme.titleLarge?.copyWith(
+                                  fontFamily: 'monospace',
+                                ),
+                              ),
+                              IconButton(
+                                icon: const Icon(Icons.refresh),
+                                tooltip: 'New Code',
+                                onPressed: syncProvider.newPairingCode,
+                              ),
+                            ],
+                          ),
+                          Text(
+                            'Enter this code on the other device to pair, once per device.',
+                            style: Theme.of(context).textTheme.bodySmall,
+                          ),
+                        ],
                         if (syncProvider.message != null) ...[
                           const SizedBox(height: 8),
                           Text(
@@ -206,11 +253,57 @@ class _SyncScreenState extends State<SyncScreen> {
                         ],
                       ),
 
+                      const SizedBox(height: 16),
+                      Row(
+                        children: [
+                          Expanded(
+                            child: TextField(
+                              controller: _codeController,
+                              keyboardType: TextInputType.number,
+                              decoration: const InputDecoration(
+                                labelText: 'Pairing Code',
+                                hintText: 'Code shown on the server',
+                                border: OutlineInputBorder(),
+                              ),
+                            ),
+                          ),
+                          const SizedBox(width: 12),
+                          ElevatedButton.icon(
+                            onPressed: syncProvider.isPairing ? null : _pairWithServer,
+                            icon: const Icon(Icons.link),
+                            label: const Text('Pair'),
+                          ),
+                        ],
+                      ),
+                      if (syncProvider.pairMessage != null) ...[
+                        const SizedBox(height: 8),
+                        Text(
+                          syncProvider.pairMessage!,
+                          style: const TextStyle(color: Colors.green),
+                        ),
+                      ],
+
                       if (syncProvider.status == SyncStatus.syncing) ...[
                         const SizedBox(height: 16),
-                        const LinearProgressIndicator(),
+                        // indeterminate until the server says how much there is
+                        LinearProgressIndicator(value: syncProvider.progress),
                         const SizedBox(height: 8),
-                        Text(syncProvider.message ?? 'Syncing...'),
+                        Row(
+                          children: [
+                            Expanded(
+                              child: Text(
+                                syncProvider.phase == null
+                                    ? (syncProvider.message ?? 'Syncing...')
+                                    : '${syncProvider.phase} ${syncProvider.bytes ~/ 1024} KB',
+                              ),
+                            ),
+                            TextButton.icon(
+                              onPressed: syncProvider.cancelSync,
+                              icon: const Icon(Icons.cancel),
+                              label: const Text('Cancel'),
+                            ),
+                          ],
+                        ),
                       ],
 
                       if (syncProvider.status == SyncStatus.success) ...[
@@ -101,6 +101,34 @@ class DatabaseService {
     throw UnimplementedError('Run flutter_rust_bridge_codegen to generate bridge code');
   }
 
+  /// Progress of the running or last sync, null before any
+  ///
+  /// Map with phase, done, total (0 when unknown) and bytes.
+  Future<Map<String, dynamic>?> syncProgress() async {
+    // Will call: return await syncProgress();
+    throw UnimplementedError('Run flutter_rust_bridge_codegen to generate bridge code');
+  }
+
+  /// Cancel the running sync, notes already exchanged are kept
+  Future<String> cancelSync() async {
+    // Will call: return await cancelSync();
+    throw UnimplementedError('Run flutter_rust_bridge_codegen to generate bridge code');
+  }
+
+  /// Pair with a server using the one-time code it shows
+  ///
+  /// Returns the server's fingerprint, both sides trust each other after.
+  Future<String> pairWithServer({required String addr, required String code}) async {
+    // Will call: return await pairWithServer(addr: addr, code: code);
+    throw UnimplementedError('Run flutter_rust_bridge_codegen to generate bridge code');
+  }
+
+  /// New one-time pairing code for the server running in this app
+  Future<String> pairingCode() async {
+    // Will call: return await pairingCode();
+    throw UnimplementedError('Run flutter_rust_bridge_codegen to generate bridge code');
+  }
+
   /// Stop remote RPC server
   Future<String> stopServer({required String addr}) async {
     // Will call: return await stopServer(addr: addr);
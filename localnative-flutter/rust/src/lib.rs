@@ -164,16 +164,89 @@ pub fn start_server(addr: String) -> Result<String, String> {
     run_command(&cmd.to_string())
 }
 
-#[frb(sync)]
+// not sync: it runs on a worker so the ui can poll sync_progress meanwhile
 pub fn sync_with_server(addr: String) -> Result<String, String> {
     let cmd = serde_json::json!({
         "action": "client-sync",
         "addr": addr
     });
 
+    // failures and cancellation come back as an error object
+    let result = run_command(&cmd.to_string())?;
+    if result.contains("\"client-sync\"") {
+        Ok(result)
+    } else {
+        Err(result)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncProgress {
+    // connecting, pushing, pulling, done, cancelled or failed
+    pub phase: String,
+    pub done: u64,
+    // 0 when the server cannot tell
+    pub total: u64,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SyncProgressResponse {
+    #[serde(rename = "sync-progress")]
+    sync_progress: Option<SyncProgress>,
+}
+
+#[frb(sync)]
+pub fn sync_progress() -> Result<Option<SyncProgress>, String> {
+    let cmd = serde_json::json!({
+        "action": "sync-progress"
+    });
+
+    let result = run_command(&cmd.to_string())?;
+    serde_json::from_str::<SyncProgressResponse>(&result)
+        .map(|resp| resp.sync_progress)
+        .map_err(|e| e.to_string())
+}
+
+#[frb(sync)]
+pub fn cancel_sync() -> Result<String, String> {
+    let cmd = serde_json::json!({
+        "action": "cancel-sync"
+    });
+
     run_command(&cmd.to_string())
 }
 
+// the server's fingerprint, a rejected code comes back as an error object
+#[frb(sync)]
+pub fn pair_with_server(addr: String, code: String) -> Result<String, String> {
+    let cmd = serde_json::json!({
+        "action": "client-pair",
+        "addr": addr,
+        "code": code
+    });
+
+    let result = run_command(&cmd.to_string())?;
+    serde_json::from_str::<serde_json::Value>(&result)
+        .ok()
+        .and_then(|resp| resp["client-pair"].as_str().map(str::to_string))
+        .ok_or(result)
+}
+
+// a new one-time code for the server started in this app to show
+#[frb(sync)]
+pub fn pairing_code() -> Result<String, String> {
+    let cmd = serde_json::json!({
+        "action": "pairing-code"
+    });
+
+    let result = run_command(&cmd.to_string())?;
+    serde_json::from_str::<serde_json::Value>(&result)
+        .ok()
+        .and_then(|resp| resp["pairing-code"].as_str().map(str::to_string))
+        .ok_or(result)
+}
+
 #[frb(sync)]
 pub fn stop_server(addr: String) -> Result<String, String> {
     let cmd = serde_json::json!({
@@ -11,6 +11,22 @@ tags = tags:
 # sync page 
 sync-waiting = Waiting for your operating...
 sync-syncing = Attempting to sync...
+sync-connecting = Connecting...
+sync-pushing = Sending
+sync-pulling = Receiving
+sync-cancelled = Sync cancelled, notes already exchanged are kept.
+sync-preview = Preview sync
+sync-previewing = Checking what a sync would change...
+sync-preview-fail = Could not preview the sync.
+sync-preview-title = This sync would:
+sync-preview-empty = Already in sync, nothing to exchange.
+sync-preview-push = Send notes:
+sync-preview-pull = Receive new notes:
+sync-preview-update = Update notes here:
+sync-preview-conflicts = Notes edited on both devices:
+sync-preview-delete = Delete notes here:
+sync-preview-tombstones = Send deletes:
+sync-confirm = Sync now
 sync-error = Synchronization process error:
 sync-complete = Synchronization process was complete！
 sync-ip-parse-error = Your IP address input format error,please check it.
@@ -20,6 +36,18 @@ sync-from-file-unknown-error = An unknown error occurred during file synchroniza
 
 sync-client-tip = Enter the IP address and port number, and your device will connect to the synchronization service of other devices.
 sync-server-tip = After enabling the synchronization service, your device will be able to be synchronized by Local Native on other devices
+server-read-only = Read only
+server-remote-stop = Clients may stop the server
+server-allow = Allowed addresses
+server-rate-limit = Requests a minute (0 for no limit)
+server-idle-timeout = Idle timeout in seconds
+server-feed-port = Feed port (0 for no feeds)
+server-name = Server:
+server-version = version
+server-note-count = Notes:
+server-last-modified = last modified:
+server-last-sync = Last synced with this device:
+server-never-synced = never
 input-ip-tip = If you want to enter the IPv6 address, you can copy it and paste it into the ip input box via keyboard shortcuts.
 input-ip = IP address and port number:
 sync-from-server = Sync from server to local
@@ -32,6 +60,7 @@ starting = Starting local service
 closing = Closing local service
 unknow-error = Unknown error click to try again
 ip-qr = Use the Local Native app on the device that needs to be synchronized, scan the QR code to synchronize, or manually enter: {$ip} to synchronize.
+feed-url = Feed of your public notes:
 
 settings = Settings
 disable-delete-tip = When deleting, delete directly instead of warning.
@@ -55,4 +84,35 @@ sync-file-title = Select the sqlite3 file you will use for synchronization
 sync-file = *.sqlite3 file for synchronization
 
 date = Date
-count = Count
\ No newline at end of file
+count = Count
+
+link-dead = Broken link:
+link-redirected = Link now redirects to
+open-archive = Archived copy
+local-only = Local only
+shared = Shared
+
+database-locked = The database is encrypted
+passphrase = Passphrase
+unlock = Unlock
+unlock-fail = Unlock failed:
+
+device-fingerprint = This device's fingerprint:
+peer-fingerprint = Server fingerprint:
+pairing-code = Pairing code:
+pair = Pair
+new-pairing-code = New code
+pair-complete = Paired, the two devices can sync now.
+pair-fail = Pairing failed, check the code shown on the other device and try again.
+sync-not-paired = This device is not paired with the server yet, enter the pairing code it shows and click Pair.
+sync-upgrade-server = The server runs an older Local Native that cannot sync with this device, upgrade Local Native on the server.
+sync-upgrade-client = The server needs a newer Local Native, upgrade Local Native on this device.
+paired-devices = Paired devices
+discover = Find devices nearby
+background-sync = Background sync
+revoke = Revoke
+peer-sync-filter = Only sync notes like tag:work
+conflicts = Edited on two devices
+keep-note = Keep this
+keep-copy = Keep the copy
+keep-both = Keep both
@@ -11,6 +11,22 @@ tags = 标签:
 # 同步页面
 sync-waiting = 等待操作...
 sync-syncing = 正在尝试同步...
+sync-connecting = 正在连接...
+sync-pushing = 发送中
+sync-pulling = 接收中
+sync-cancelled = 同步已取消，已交换的笔记会保留。
+sync-preview = 预览同步
+sync-previewing = 正在检查同步将带来的变化...
+sync-preview-fail = 无法预览同步。
+sync-preview-title = 本次同步将会：
+sync-preview-empty = 已经同步，没有需要交换的笔记。
+sync-preview-push = 发送笔记：
+sync-preview-pull = 接收新笔记：
+sync-preview-update = 更新本地笔记：
+sync-preview-conflicts = 两台设备都编辑过的笔记：
+sync-preview-delete = 删除本地笔记：
+sync-preview-tombstones = 发送删除：
+sync-confirm = 立即同步
 sync-error = 同步过程出错:
 sync-complete = 同步过程已完成！
 sync-ip-parse-error = 您输入的 IP 地址格式有误，请检查。
@@ -20,6 +36,18 @@ sync-from-file-unknown-error = 文件同步过程中发生未知错误。您要
 
 sync-client-tip = 输入 IP 地址和端口号，您的设备将连接到其他设备的同步服务。
 sync-server-tip = 启用同步服务后，其他设备上的本地应用程序将能够同步您的设备。
+server-read-only = 只读
+server-remote-stop = 允许客户端停止服务
+server-allow = 允许的地址
+server-rate-limit = 每分钟请求数（0 为不限）
+server-idle-timeout = 空闲超时（秒）
+server-feed-port = 订阅源端口（0 为不提供）
+server-name = 服务器：
+server-version = 版本
+server-note-count = 笔记数：
+server-last-modified = 最后修改：
+server-last-sync = 与本设备上次同步：
+server-never-synced = 从未
 input-ip-tip = 如果您要输入 IPv6 地址，可以通过键盘快捷键将其复制并粘贴到 IP 输入框中。
 input-ip = IP 地址和端口号：
 sync-from-server = 从服务器同步到本地
@@ -32,6 +60,7 @@ starting = 正在启动本地服务
 closing = 正在关闭本地服务
 unknow-error = 未知错误，请点击重试
 ip-qr = 使用需要同步的设备上的 Local Native 应用程序，扫描 QR 码进行同步，或手动输入：{$ip} 进行同步。
+feed-url = 公开笔记订阅地址:
 
 settings = 设置
 disable-delete-tip = 删除时不进行提示，直接删除。
@@ -55,4 +84,35 @@ sync-file-title = 选择您将用于同步的 sqlite3 文件
 sync-file = 用于同步的 *.sqlite3 文件
 
 date = 日期
-count = 数量
\ No newline at end of file
+count = 数量
+
+link-dead = 链接已失效:
+link-redirected = 链接已重定向到
+open-archive = 离线存档
+local-only = 仅本机
+shared = 可同步
+
+database-locked = 数据库已加密
+passphrase = 密码
+unlock = 解锁
+unlock-fail = 解锁失败:
+
+device-fingerprint = 本设备指纹:
+peer-fingerprint = 服务端指纹:
+pairing-code = 配对码:
+pair = 配对
+new-pairing-code = 更换配对码
+pair-complete = 配对成功，两台设备现在可以同步了。
+pair-fail = 配对失败，请核对另一台设备上显示的配对码后重试。
+sync-not-paired = 本设备尚未与服务端配对，请输入服务端显示的配对码并点击配对。
+sync-upgrade-server = 服务端的 Local Native 版本过旧，无法与本设备同步，请升级服务端的 Local Native。
+sync-upgrade-client = 服务端需要更新版本的 Local Native，请升级本设备上的 Local Native。
+paired-devices = 已配对设备
+discover = 查找附近设备
+background-sync = 后台同步
+revoke = 撤销
+peer-sync-filter = 只同步匹配的笔记，如 tag:work
+conflicts = 两台设备上同时编辑
+keep-note = 保留此条
+keep-copy = 保留副本
+keep-both = 都保留
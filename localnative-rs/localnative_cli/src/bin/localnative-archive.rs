@@ -0,0 +1,47 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use clap::{arg, value_parser, Command};
+use localnative_core::cmd::link_check::{DEFAULT_CONCURRENCY, DEFAULT_TIMEOUT_SECS};
+use localnative_core::exe::run;
+fn main() {
+    let matches = Command::new("localnative-archive")
+        .arg(
+            arg!(-l --limit <LIMIT> "max notes to archive, newest first")
+                .value_parser(value_parser!(u32)),
+        )
+        .arg(arg!(-c --concurrency <N> "pages in flight").value_parser(value_parser!(usize)))
+        .arg(arg!(-t --timeout <SECS> "per request timeout").value_parser(value_parser!(u64)))
+        .get_matches();
+    let limit = matches.get_one::<u32>("limit").copied().unwrap_or(u32::MAX);
+    let concurrency = matches
+        .get_one::<usize>("concurrency")
+        .copied()
+        .unwrap_or(DEFAULT_CONCURRENCY);
+    let timeout = matches
+        .get_one::<u64>("timeout")
+        .copied()
+        .unwrap_or(DEFAULT_TIMEOUT_SECS);
+    println!(
+        "{}",
+        run(&format!(
+            r#"{{"action":"archive", "limit": {}, "concurrency": {}, "timeout_secs": {}}}"#,
+            limit, concurrency, timeout
+        ))
+    );
+}
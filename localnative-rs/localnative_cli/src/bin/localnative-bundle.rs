@@ -0,0 +1,45 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+use clap::{arg, Command};
+use localnative_core::exe::run;
+use localnative_core::serde_json::json;
+fn main() {
+    let matches = Command::new("localnative-bundle")
+        .about("move notes between devices that never share a network")
+        .arg(arg!(-e --export <FILE> "write the notes changed since --since to a bundle"))
+        .arg(
+            arg!(--since <SEQ> "until of the last bundle taken to the other device, 0 for all")
+                .value_parser(clap::value_parser!(i64))
+                .default_value("0"),
+        )
+        .arg(arg!(-i --import <FILE> "merge a bundle from another device"))
+        .get_matches();
+    let cmd = if let Some(path) = matches.get_one::<String>("import") {
+        json!({"action": "import-bundle", "path": path})
+    } else if let Some(path) = matches.get_one::<String>("export") {
+        json!({
+            "action": "export-bundle",
+            "path": path,
+            "since": matches.get_one::<i64>("since"),
+        })
+    } else {
+        eprintln!("pass --export FILE or --import FILE");
+        std::process::exit(2);
+    };
+    println!("{}", run(&cmd.to_string()));
+}
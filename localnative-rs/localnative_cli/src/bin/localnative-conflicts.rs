@@ -0,0 +1,43 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+use clap::{arg, Command};
+use localnative_core::exe::run;
+use localnative_core::serde_json::json;
+fn main() {
+    let matches = Command::new("localnative-conflicts")
+        .about("list notes edited on two devices at once, resolve them or set the sync policy")
+        .arg(arg!(-p --policy <POLICY> "last-writer-wins, merge-fields or keep-both"))
+        .arg(arg!(-r --resolve <UUID4> "resolve the conflict of this note"))
+        .arg(
+            arg!(-k --keep <KEEP> "keep-note, keep-copy or keep-both")
+                .default_value("keep-note"),
+        )
+        .get_matches();
+    let cmd = if let Some(uuid4) = matches.get_one::<String>("resolve") {
+        json!({
+            "action": "resolve-conflict",
+            "uuid4": uuid4,
+            "resolution": matches.get_one::<String>("keep").unwrap(),
+        })
+    } else if let Some(policy) = matches.get_one::<String>("policy") {
+        json!({"action": "conflict-policy", "policy": policy})
+    } else {
+        json!({"action": "list-conflicts"})
+    };
+    println!("{}", run(&cmd.to_string()));
+}
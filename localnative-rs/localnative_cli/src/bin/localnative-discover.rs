@@ -0,0 +1,32 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+use clap::{arg, value_parser, Command};
+use localnative_core::exe::run;
+fn main() {
+    let matches = Command::new("localnative-discover")
+        .about("list sync servers advertised on the local network")
+        .arg(
+            arg!(-t --timeout <SECONDS> "how long to listen")
+                .value_parser(value_parser!(u64))
+                .default_value("3"),
+        )
+        .get_matches();
+    let timeout = matches.get_one::<u64>("timeout").unwrap();
+    let cmd = format!(r#"{{"action":"discover", "timeout_secs": {}}}"#, timeout);
+    println!("{}", run(&cmd));
+}
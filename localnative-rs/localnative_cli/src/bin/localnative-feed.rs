@@ -0,0 +1,50 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use clap::{arg, value_parser, Command};
+use localnative_core::cmd::feed::DEFAULT_LIMIT;
+use localnative_core::exe::run;
+use localnative_core::serde_json::{self, json, Value};
+fn main() {
+    let matches = Command::new("localnative-feed")
+        .about("export the latest public notes as an atom or rss 2.0 feed")
+        .arg(
+            arg!(-f --format <FORMAT> "feed format")
+                .value_parser(["atom", "rss"])
+                .default_value("atom"),
+        )
+        .arg(arg!(-t --tag <TAG> "only notes with this tag"))
+        .arg(arg!(-l --limit <N> "max entries").value_parser(value_parser!(u32)))
+        .arg(arg!(-o --out <FILE> "write to FILE instead of stdout"))
+        .get_matches();
+    let cmd = json!({
+        "action": "feed",
+        "format": matches.get_one::<String>("format").unwrap(),
+        "tag": matches.get_one::<String>("tag"),
+        "limit": matches.get_one::<u32>("limit").copied().unwrap_or(DEFAULT_LIMIT),
+    });
+    let resp: Value = serde_json::from_str(&run(&cmd.to_string())).unwrap();
+    match (resp["feed"].as_str(), matches.get_one::<String>("out")) {
+        (Some(xml), Some(out)) => std::fs::write(out, xml).unwrap(),
+        (Some(xml), None) => print!("{}", xml),
+        (None, _) => {
+            eprintln!("{}", resp);
+            std::process::exit(1);
+        }
+    }
+}
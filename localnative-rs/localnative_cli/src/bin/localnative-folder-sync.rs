@@ -0,0 +1,33 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+use clap::{arg, Command};
+use localnative_core::exe::run;
+use localnative_core::serde_json::json;
+fn main() {
+    let matches = Command::new("localnative-folder-sync")
+        .about("sync through a folder shared by syncthing, nextcloud or the like")
+        .arg(arg!(-d --dir <DIR> "the shared folder").required(true))
+        .arg(arg!(-w --watch "keep syncing as the folder changes and notes are edited"))
+        .get_matches();
+    let cmd = json!({
+        "action": "folder-sync",
+        "dir": matches.get_one::<String>("dir"),
+        "watch": matches.get_flag("watch"),
+    });
+    println!("{}", run(&cmd.to_string()));
+}
@@ -0,0 +1,41 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use clap::{arg, Command};
+use localnative_core::exe::run;
+use localnative_core::serde_json::json;
+fn main() {
+    let matches = Command::new("localnative-peers")
+        .about("list devices paired for sync")
+        .arg(arg!(-r --revoke <FINGERPRINT> "stop trusting the device with this fingerprint"))
+        .arg(arg!(-f --filter <FINGERPRINT> "only sync notes matching QUERY with the device with this fingerprint"))
+        .arg(arg!([QUERY] "search query for --filter, like 'tag:work' or 'since:365d'; none syncs every note"))
+        .get_matches();
+    let cmd = if let Some(fingerprint) = matches.get_one::<String>("revoke") {
+        json!({"action": "revoke-peer", "fingerprint": fingerprint})
+    } else if let Some(fingerprint) = matches.get_one::<String>("filter") {
+        json!({
+            "action": "set-peer-filter",
+            "fingerprint": fingerprint,
+            "filter": matches.get_one::<String>("QUERY").cloned().unwrap_or_default(),
+        })
+    } else {
+        json!({"action": "list-peers"})
+    };
+    println!("{}", run(&cmd.to_string()));
+}
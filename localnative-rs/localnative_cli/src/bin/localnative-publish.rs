@@ -0,0 +1,39 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use clap::{arg, value_parser, Command};
+use localnative_core::cmd::publish::DEFAULT_PER_PAGE;
+use localnative_core::exe::run;
+use localnative_core::serde_json::json;
+fn main() {
+    let matches = Command::new("localnative-publish")
+        .about("render public notes into a static html site")
+        .arg(arg!(-o --out <DIR> "output directory").required(true))
+        .arg(arg!(-t --templates <DIR> "directory with templates overriding the bundled ones"))
+        .arg(arg!(-n --"per-page" <N> "notes per index page").value_parser(value_parser!(usize)))
+        .arg(arg!(--title <TITLE> "site title"))
+        .get_matches();
+    let cmd = json!({
+        "action": "publish",
+        "dir": matches.get_one::<String>("out").unwrap(),
+        "template_dir": matches.get_one::<String>("templates"),
+        "per_page": matches.get_one::<usize>("per-page").copied().unwrap_or(DEFAULT_PER_PAGE),
+        "title": matches.get_one::<String>("title"),
+    });
+    println!("{}", run(&cmd.to_string()));
+}
@@ -0,0 +1,44 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use clap::{arg, Command};
+use localnative_core::exe::prepare_db;
+use localnative_core::rpc::client;
+use localnative_core::serde_json::json;
+fn main() {
+    let matches = Command::new("localnative-rpc-client-pair")
+        .arg(arg!(-a --addr <ADDR> "server address").required(true))
+        .arg(arg!(-c --code <CODE> "pairing code shown by the server").required(true))
+        .get_matches();
+    let addr = matches.get_one::<String>("addr").unwrap();
+    let code = matches.get_one::<String>("code").unwrap();
+    if let Err(err) = prepare_db() {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+    match addr
+        .parse()
+        .and_then(|endpoint| client::pair(&endpoint, code.trim()))
+    {
+        Ok(fingerprint) => println!("{}", json!({ "client-pair": fingerprint })),
+        Err(err) => {
+            eprintln!("client pair error:{}", err);
+            std::process::exit(1);
+        }
+    }
+}
@@ -17,13 +17,25 @@
 */
 
 use clap::{arg, Command};
-use localnative_core::exe::run;
+use localnative_core::exe::prepare_db;
+use localnative_core::rpc::client;
 fn main() {
     let matches = Command::new("localnative-rpc-client")
         .arg(arg!(-a - -addr[ADDR]))
         .get_matches();
 
-    let addr = matches.get_one::<&str>("addr").unwrap_or(&"127.0.0.1:2345");
+    let addr = matches
+        .get_one::<String>("addr")
+        .map_or("127.0.0.1:2345", String::as_str);
     eprintln!("addr: {}", addr);
-    run(&(r#"{"action":"client-stop-server", "addr": ""#.to_string() + addr + r#""}"#));
+    if let Err(err) = prepare_db() {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+    if let Err(err) = addr
+        .parse()
+        .and_then(|endpoint| client::stop_server(&endpoint))
+    {
+        eprintln!("client-stop-server error: {}", err);
+    }
 }
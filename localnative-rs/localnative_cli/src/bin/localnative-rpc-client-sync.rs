@@ -17,12 +17,42 @@
 */
 
 use clap::{arg, Command};
-use localnative_core::exe::run;
+use localnative_core::exe::prepare_db;
+use localnative_core::rpc::client;
+use localnative_core::rpc::stream::Endpoint;
+use localnative_core::serde_json::json;
 fn main() {
     let matches = Command::new("localnative-rpc-client")
         .arg(arg!(-a - -addr[ADDR]))
+        .arg(arg!(--"dry-run" "show what would be pushed and pulled without syncing"))
         .get_matches();
-    let addr = matches.get_one::<&str>("addr").unwrap_or(&"127.0.0.1:2345");
+    let addr = matches
+        .get_one::<String>("addr")
+        .map_or("127.0.0.1:2345", String::as_str);
     eprintln!("addr: {}", addr);
-    run(&(r#"{"action":"client-sync", "addr": ""#.to_string() + addr + r#""}"#));
+    // ssh: and exec: are taken from here, the json api refuses them
+    let endpoint: Endpoint = match addr.parse() {
+        Ok(endpoint) => endpoint,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
+    if let Err(err) = prepare_db() {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+    // who is on the other end, before anything is exchanged
+    match client::info(&endpoint) {
+        Ok(info) => println!("{}", json!({ "client-info": info })),
+        Err(err) => eprintln!("client-info error:{}", err),
+    }
+    if matches.get_flag("dry-run") {
+        match client::preview(&endpoint) {
+            Ok(preview) => println!("{}", json!({ "client-sync-preview": preview })),
+            Err(err) => eprintln!("client-sync error:{}", err),
+        }
+    } else if let Err(err) = client::sync(&endpoint) {
+        eprintln!("client-sync error:{}", err);
+    }
 }
@@ -16,13 +16,75 @@
     along with this program.  If not, see <https://www.gnu.org/licenses/>.
 */
 
-use clap::{arg, Command};
+use clap::{arg, value_parser, ArgAction, Command};
 use localnative_core::exe::run;
+use localnative_core::serde_json::{json, Map, Value};
 fn main() {
     let matches = Command::new("localnative-rpc-server")
-        .arg(arg!(-a - -addr[ADDR]))
+        .arg(arg!(-a --addr [ADDR] "ip:port, or unix:<path> of a socket for this user only"))
+        .arg(arg!(-c --config <FILE> "server config, sync_server.json next to the database by default"))
+        .arg(
+            arg!(--allow <CIDR> "ip address or cidr range that may connect, repeat for more")
+                .action(ArgAction::Append),
+        )
+        .arg(arg!(--"read-only" "refuse notes sent by clients"))
+        .arg(
+            arg!(--"rate-limit" <N> "rpcs a minute from one peer, 0 for no limit")
+                .value_parser(value_parser!(u32)),
+        )
+        .arg(
+            arg!(--"max-payload" <BYTES> "of the notes in one rpc, 0 for no limit")
+                .value_parser(value_parser!(u64)),
+        )
+        .arg(arg!(--"no-remote-stop" "clients may not stop the server"))
+        .arg(
+            arg!(--"idle-timeout" <SECS> "close quiet connections, 0 keeps them")
+                .value_parser(value_parser!(u64)),
+        )
+        .arg(arg!(--log <FILE> "append connection logs to this file"))
+        .arg(
+            arg!(--"feed-port" <PORT> "serve atom and rss feeds of public notes on this port")
+                .value_parser(value_parser!(u16).range(1..)),
+        )
         .get_matches();
-    let addr = matches.get_one::<&str>("addr").unwrap_or(&"127.0.0.1:2345");
+    let addr = matches
+        .get_one::<String>("addr")
+        .map_or("127.0.0.1:2345", String::as_str);
     eprintln!("addr: {}", addr);
-    run(&(r#"{"action":"server", "addr": ""#.to_string() + addr + r#""}"#));
+    // flags given replace what the config file says
+    let mut settings = Map::new();
+    if let Some(allow) = matches.get_many::<String>("allow") {
+        settings.insert("allow".into(), json!(allow.collect::<Vec<_>>()));
+    }
+    if matches.get_flag("read-only") {
+        settings.insert("read_only".into(), json!(true));
+    }
+    if let Some(rate_limit) = matches.get_one::<u32>("rate-limit") {
+        settings.insert("rate_limit".into(), json!(rate_limit));
+    }
+    if let Some(max_payload) = matches.get_one::<u64>("max-payload") {
+        settings.insert("max_payload_bytes".into(), json!(max_payload));
+    }
+    if matches.get_flag("no-remote-stop") {
+        settings.insert("remote_stop".into(), json!(false));
+    }
+    if let Some(idle_timeout) = matches.get_one::<u64>("idle-timeout") {
+        settings.insert("idle_timeout_secs".into(), json!(idle_timeout));
+    }
+    if let Some(log) = matches.get_one::<String>("log") {
+        settings.insert("log_file".into(), json!(log));
+    }
+    if let Some(feed_port) = matches.get_one::<u16>("feed-port") {
+        settings.insert("feed_port".into(), json!(feed_port));
+    }
+    println!(
+        "{}",
+        run(&json!({
+            "action": "server",
+            "addr": addr,
+            "config": matches.get_one::<String>("config"),
+            "settings": Value::Object(settings),
+        })
+        .to_string())
+    );
 }
@@ -0,0 +1,31 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+use clap::{arg, Command};
+use localnative_core::exe::run;
+use localnative_core::serde_json::json;
+fn main() {
+    let matches = Command::new("localnative-s3-sync")
+        .about("sync through an s3 compatible bucket, segments encrypted with a shared passphrase")
+        .arg(arg!(-c --config <CONFIG> "s3_sync.json next to the database when omitted"))
+        .get_matches();
+    let cmd = json!({
+        "action": "s3-sync",
+        "config": matches.get_one::<String>("config"),
+    });
+    println!("{}", run(&cmd.to_string()));
+}
@@ -0,0 +1,40 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+use clap::{arg, Command};
+use localnative_core::exe::run;
+use localnative_core::serde_json::json;
+fn main() {
+    let matches = Command::new("localnative-sync-daemon")
+        .about("sync with the peers in sync_daemon.json on an interval and after local changes")
+        .arg(
+            arg!(-c --config <FILE> "peer list, defaults to sync_daemon.json next to the database"),
+        )
+        .arg(arg!(--once "sync the peers that are due once, then exit"))
+        .arg(arg!(-s --status "show the last sync result of each peer"))
+        .get_matches();
+    let cmd = if matches.get_flag("status") {
+        json!({"action": "sync-status"})
+    } else {
+        json!({
+            "action": "sync-daemon",
+            "config": matches.get_one::<String>("config"),
+            "once": matches.get_flag("once"),
+        })
+    };
+    println!("{}", run(&cmd.to_string()));
+}
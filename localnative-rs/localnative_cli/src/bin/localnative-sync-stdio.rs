@@ -0,0 +1,30 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+use clap::Command;
+use localnative_core::exe::run;
+use localnative_core::serde_json::json;
+fn main() {
+    Command::new("localnative-sync-stdio")
+        .about("serve sync on stdin and stdout, for clients syncing with ssh:<host>")
+        .get_matches();
+    // stdout is the sync stream, only errors are reported, on stderr
+    let result = run(&json!({"action": "sync-stdio"}).to_string());
+    if !result.is_empty() {
+        eprintln!("{}", result);
+    }
+}
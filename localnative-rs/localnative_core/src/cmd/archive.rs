@@ -0,0 +1,305 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+use base64::{engine::general_purpose, Engine as _};
+use futures::prelude::*;
+use regex::{Captures, Regex};
+use reqwest::Url;
+use rusqlite::types::ToSql;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use tokio::runtime::Runtime;
+
+// images or stylesheets above this are left as remote links
+const MAX_RESOURCE_BYTES: usize = 2 * 1024 * 1024;
+const RESOURCE_CONCURRENCY: usize = 4;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Default)]
+pub struct Archive {
+    pub uuid4: String,
+    pub url: String,
+    // self-contained html, scripts removed and images/stylesheets inlined
+    pub html: String,
+    // readable text extracted from html, searched along with the note
+    pub text: String,
+    pub archived_at: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct ArchiveSummary {
+    pub archived: u32,
+    pub failed: u32,
+}
+
+pub fn extract_text(html: &str) -> String {
+    let mut s = Regex::new(r"(?s)<!--.*?-->")
+        .unwrap()
+        .replace_all(html, " ")
+        .to_string();
+    for tag in ["script", "style", "noscript", "template", "svg"] {
+        let re = Regex::new(&format!(r"(?is)<{0}\b[^>]*>.*?</{0}\s*>", tag)).unwrap();
+        s = re.replace_all(&s, " ").to_string();
+    }
+    let block = Regex::new(
+        r"(?i)</?(p|div|br|li|ul|ol|h[1-6]|tr|table|section|article|title|pre|blockquote)\b[^>]*>",
+    )
+    .unwrap();
+    let s = block.replace_all(&s, "\n");
+    let s = Regex::new(r"<[^>]*>").unwrap().replace_all(&s, " ");
+    let s = decode_entities(&s);
+    let space = Regex::new(r"[ \t\r\f\v]+").unwrap();
+    s.lines()
+        .map(|l| space.replace_all(l, " ").trim().to_string())
+        .filter(|l| !l.is_empty())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn decode_entities(s: &str) -> String {
+    s.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+async fn fetch_resource(client: &reqwest::Client, url: Url) -> Option<(String, String)> {
+    let resp = client.get(url.clone()).send().await.ok()?;
+    if !resp.status().is_success() {
+        return None;
+    }
+    let mime = resp
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("application/octet-stream")
+        .to_string();
+    let bytes = resp.bytes().await.ok()?;
+    if bytes.len() > MAX_RESOURCE_BYTES {
+        return None;
+    }
+    if mime.starts_with("text/css") {
+        Some((url.to_string(), String::from_utf8_lossy(&bytes).to_string()))
+    } else {
+        let data = general_purpose::STANDARD.encode(&bytes);
+        Some((url.to_string(), format!("data:{};base64,{}", mime, data)))
+    }
+}
+
+// drop scripts, inline <img src> as data urls and <link rel=stylesheet> as <style>
+pub async fn make_self_contained(client: &reqwest::Client, base: &Url, html: &str) -> String {
+    let script = Regex::new(r"(?is)<script\b[^>]*>.*?</script\s*>").unwrap();
+    let html = script.replace_all(html, "").to_string();
+    let img = Regex::new(r#"(?i)(<img\b[^>]*?\ssrc\s*=\s*)["']([^"']+)["']"#).unwrap();
+    let link = Regex::new(r"(?i)<link\b[^>]*>").unwrap();
+    let stylesheet = Regex::new(r#"(?i)\srel\s*=\s*["']?stylesheet"#).unwrap();
+    let href = Regex::new(r#"(?i)\shref\s*=\s*["']([^"']+)["']"#).unwrap();
+
+    let mut urls = HashSet::new();
+    for c in img.captures_iter(&html) {
+        if let Ok(u) = base.join(&c[2]) {
+            urls.insert(u);
+        }
+    }
+    for l in link.find_iter(&html) {
+        if stylesheet.is_match(l.as_str()) {
+            if let Some(u) = href
+                .captures(l.as_str())
+                .and_then(|c| base.join(&c[1]).ok())
+            {
+                urls.insert(u);
+            }
+        }
+    }
+    let fetched: HashMap<String, String> = stream::iter(urls)
+        .filter(|u| future::ready(u.scheme() == "http" || u.scheme() == "https"))
+        .map(|u| fetch_resource(client, u))
+        .buffer_unordered(RESOURCE_CONCURRENCY)
+        .filter_map(future::ready)
+        .collect()
+        .await;
+
+    let lookup = |src: &str| {
+        base.join(src)
+            .ok()
+            .and_then(|u| fetched.get(u.as_str()).cloned())
+    };
+    let html = img.replace_all(&html, |c: &Captures| match lookup(&c[2]) {
+        Some(data) => format!(r#"{}"{}""#, &c[1], data),
+        None => c[0].to_string(),
+    });
+    let html = link.replace_all(&html, |c: &Captures| {
+        let tag = &c[0];
+        if !stylesheet.is_match(tag) {
+            return tag.to_string();
+        }
+        match href.captures(tag).and_then(|h| lookup(&h[1])) {
+            Some(css) => format!("<style>\n{}\n</style>", css),
+            None => tag.to_string(),
+        }
+    });
+    html.to_string()
+}
+
+pub async fn fetch_archive(
+    client: &reqwest::Client,
+    uuid4: String,
+    url: String,
+) -> anyhow::Result<Archive> {
+    let resp = client.get(&url).send().await?.error_for_status()?;
+    let base = resp.url().clone();
+    let page = resp.text().await?;
+    let html = make_self_contained(client, &base, &page).await;
+    let text = extract_text(&page);
+    Ok(Archive {
+        uuid4,
+        url,
+        html,
+        text,
+        archived_at: crate::exe::created_time(),
+    })
+}
+
+pub fn save_archive(conn: &Connection, archive: &Archive) -> anyhow::Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO archive (uuid4, url, html, text, archived_at)
+        VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            archive.uuid4,
+            archive.url,
+            archive.html,
+            archive.text,
+            archive.archived_at
+        ],
+    )?;
+    Ok(())
+}
+
+pub fn get_archive(conn: &Connection, uuid4: &str) -> anyhow::Result<Option<Archive>> {
+    let mut stmt = conn.prepare(
+        "SELECT uuid4, url, html, text, archived_at
+        FROM archive where uuid4 = ?",
+    )?;
+    let archive = stmt
+        .query_row([uuid4], |row| {
+            Ok(Archive {
+                uuid4: row.get(0)?,
+                url: row.get(1)?,
+                html: row.get(2)?,
+                text: row.get(3)?,
+                archived_at: row.get(4)?,
+            })
+        })
+        .optional()?;
+    Ok(archive)
+}
+
+// which of uuid4s have a snapshot
+pub fn archived_uuid4s(conn: &Connection, uuid4s: &[String]) -> anyhow::Result<HashSet<String>> {
+    let mut r = HashSet::new();
+    let mut stmt = conn.prepare("select 1 FROM archive where uuid4 = ? ")?;
+    for uuid4 in uuid4s {
+        if stmt.exists([uuid4])? {
+            r.insert(uuid4.to_string());
+        }
+    }
+    Ok(r)
+}
+
+// notes with an http(s) url and no snapshot yet, newest first
+pub fn archive_candidates(conn: &Connection, limit: u32) -> anyhow::Result<Vec<(String, String)>> {
+    let mut stmt = conn.prepare(
+        "SELECT uuid4, url
+        FROM note
+        where (url like 'http://%' or url like 'https://%')
+        and uuid4 not in (select uuid4 from archive)
+        order by created_at desc limit :limit",
+    )?;
+    let iter = stmt.query_map(&[(":limit", &limit as &dyn ToSql)], |row| {
+        Ok((row.get(0)?, row.get(1)?))
+    })?;
+    let mut r = Vec::new();
+    for c in iter {
+        r.push(c?);
+    }
+    Ok(r)
+}
+
+pub async fn fetch_archives(
+    targets: Vec<(String, String)>,
+    concurrency: usize,
+    timeout: Duration,
+) -> anyhow::Result<Vec<anyhow::Result<Archive>>> {
+    let client = super::utils::http_client(timeout)?;
+    let client = &client;
+    let r = stream::iter(targets)
+        .map(|(uuid4, url)| fetch_archive(client, uuid4, url))
+        .buffer_unordered(concurrency.max(1))
+        .collect()
+        .await;
+    Ok(r)
+}
+
+pub fn archive_notes(
+    conn: &Connection,
+    limit: u32,
+    concurrency: usize,
+    timeout: Duration,
+) -> anyhow::Result<ArchiveSummary> {
+    let targets = archive_candidates(conn, limit)?;
+    #[cfg(not(feature = "no_print"))]
+    eprintln!("archive_notes targets len: {}", targets.len());
+    let rt = Runtime::new()?;
+    let archives = rt.block_on(fetch_archives(targets, concurrency, timeout))?;
+    let mut summary = ArchiveSummary::default();
+    for archive in archives {
+        match archive {
+            Ok(archive) => {
+                save_archive(conn, &archive)?;
+                summary.archived += 1;
+            }
+            Err(_err) => {
+                #[cfg(not(feature = "no_print"))]
+                eprintln!("archive error: {:?}", _err);
+                summary.failed += 1;
+            }
+        }
+    }
+    Ok(summary)
+}
+
+pub fn archive_note(
+    conn: &Connection,
+    uuid4: &str,
+    url: &str,
+    timeout: Duration,
+) -> anyhow::Result<()> {
+    let rt = Runtime::new()?;
+    let mut archives = rt.block_on(fetch_archives(
+        vec![(uuid4.to_string(), url.to_string())],
+        1,
+        timeout,
+    ))?;
+    if let Some(archive) = archives.pop() {
+        save_archive(conn, &archive?)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,405 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+use base64::{engine::general_purpose, Engine as _};
+use image::io::{Limits, Reader};
+use image::{ImageError, ImageOutputFormat};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+// longest side of a thumbnail in pixels
+pub const THUMBNAIL_SIZE: u32 = 256;
+pub const THUMBNAIL_MIME: &str = "image/png";
+// images past these are kept without a thumbnail rather than decoded
+pub const THUMBNAIL_MAX_SIDE: u32 = 16384;
+pub const THUMBNAIL_MAX_ALLOC: u64 = 256 * 1024 * 1024;
+// attachments per sync request, they run much larger than notes
+pub const ATTACHMENT_BATCH: usize = 16;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Default)]
+pub struct Attachment {
+    pub uuid4: String,
+    pub note_uuid4: String,
+    pub mime: String,
+    pub size: i64,
+    // hex sha256 of data
+    pub sha256: String,
+    pub created_at: String,
+}
+
+// which attachment sync means, one of a note's attachments by its content;
+// the uuid4 differs between devices that added the same file on their own
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Default)]
+pub struct AttachmentKey {
+    pub note_uuid4: String,
+    pub sha256: String,
+}
+
+// an attachment with its bytes, as sync sends it
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Default)]
+pub struct AttachmentData {
+    pub attachment: Attachment,
+    pub data: Vec<u8>,
+}
+
+impl Attachment {
+    pub fn key(&self) -> AttachmentKey {
+        AttachmentKey {
+            note_uuid4: self.note_uuid4.clone(),
+            sha256: self.sha256.clone(),
+        }
+    }
+}
+
+// mime type from the leading magic bytes, None if not a supported type
+pub fn sniff_mime(data: &[u8]) -> Option<&'static str> {
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if data.starts_with(b"\xff\xd8\xff") {
+        Some("image/jpeg")
+    } else if data.len() >= 12 && &data[0..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        Some("image/gif")
+    } else if data.starts_with(b"%PDF-") {
+        Some("application/pdf")
+    } else {
+        None
+    }
+}
+
+// data:<mime>;base64,<data>
+pub fn parse_data_url(data_url: &str) -> anyhow::Result<(String, Vec<u8>)> {
+    let rest = data_url
+        .strip_prefix("data:")
+        .ok_or_else(|| anyhow::anyhow!("not a data url"))?;
+    let (mime, data) = rest
+        .split_once(";base64,")
+        .ok_or_else(|| anyhow::anyhow!("data url is not base64"))?;
+    let data = general_purpose::STANDARD.decode(data.trim())?;
+    Ok((mime.to_string(), data))
+}
+
+pub fn to_data_url(mime: &str, data: &[u8]) -> String {
+    format!(
+        "data:{};base64,{}",
+        mime,
+        general_purpose::STANDARD.encode(data)
+    )
+}
+
+pub fn sha256_hex(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+// png scaled down to fit THUMBNAIL_SIZE, None for non-images, undecodable
+// data or images past the decode limits
+pub fn make_thumbnail(data: &[u8]) -> Option<Vec<u8>> {
+    if !sniff_mime(data)?.starts_with("image/") {
+        return None;
+    }
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(THUMBNAIL_MAX_SIDE);
+    limits.max_image_height = Some(THUMBNAIL_MAX_SIDE);
+    limits.max_alloc = Some(THUMBNAIL_MAX_ALLOC);
+    let mut reader = Reader::new(std::io::Cursor::new(data))
+        .with_guessed_format()
+        .ok()?;
+    reader.limits(limits);
+    let img = reader
+        .decode()
+        .map_err(|err| match err {
+            ImageError::Limits(err) => eprintln!("thumbnail skipped: {}", err),
+            err => eprintln!("thumbnail decode error: {:?}", err),
+        })
+        .ok()?;
+    let mut buf = std::io::Cursor::new(Vec::new());
+    img.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+        .write_to(&mut buf, ImageOutputFormat::Png)
+        .ok()?;
+    Some(buf.into_inner())
+}
+
+// sniffs data and refuses anything that is not png, jpeg, webp, gif or pdf;
+// adding the same content to a note twice returns the existing attachment
+pub fn add_attachment(
+    conn: &Connection,
+    note_uuid4: &str,
+    data: &[u8],
+) -> anyhow::Result<Attachment> {
+    let mime = sniff_mime(data).ok_or_else(|| anyhow::anyhow!("unsupported attachment type"))?;
+    let note_exists = conn
+        .prepare("select 1 FROM note where uuid4 = ?")?
+        .exists([note_uuid4])?;
+    if !note_exists {
+        return Err(anyhow::anyhow!("note {} not found", note_uuid4));
+    }
+    let sha256 = sha256_hex(data);
+    let existing = conn
+        .query_row(
+            "SELECT uuid4, note_uuid4, mime, size, sha256, created_at
+            FROM attachment where note_uuid4 = ?1 and sha256 = ?2",
+            [note_uuid4, &sha256],
+            attachment_from_row,
+        )
+        .optional()?;
+    if let Some(a) = existing {
+        return Ok(a);
+    }
+    // adding it again by hand takes back the removal
+    conn.execute(
+        "delete from attachment_tombstone where note_uuid4 = ?1 and sha256 = ?2",
+        [note_uuid4, &sha256],
+    )?;
+    let a = Attachment {
+        uuid4: Uuid::new_v4().to_string(),
+        note_uuid4: note_uuid4.to_string(),
+        mime: mime.to_string(),
+        size: data.len() as i64,
+        sha256,
+        created_at: crate::exe::created_time(),
+    };
+    conn.execute(
+        "INSERT INTO attachment (uuid4, note_uuid4, mime, size, sha256, data, thumbnail, created_at)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            a.uuid4,
+            a.note_uuid4,
+            a.mime,
+            a.size,
+            a.sha256,
+            data,
+            make_thumbnail(data),
+            a.created_at
+        ],
+    )?;
+    Ok(a)
+}
+
+fn attachment_from_row(row: &rusqlite::Row) -> rusqlite::Result<Attachment> {
+    Ok(Attachment {
+        uuid4: row.get(0)?,
+        note_uuid4: row.get(1)?,
+        mime: row.get(2)?,
+        size: row.get(3)?,
+        sha256: row.get(4)?,
+        created_at: row.get(5)?,
+    })
+}
+
+// metadata only, in the order they were added
+pub fn list_attachments(conn: &Connection, note_uuid4: &str) -> anyhow::Result<Vec<Attachment>> {
+    let mut stmt = conn.prepare(
+        "SELECT uuid4, note_uuid4, mime, size, sha256, created_at
+        FROM attachment where note_uuid4 = ? order by rowid",
+    )?;
+    let iter = stmt.query_map([note_uuid4], attachment_from_row)?;
+    let mut r = Vec::new();
+    for a in iter {
+        r.push(a?);
+    }
+    Ok(r)
+}
+
+pub fn get_attachment(
+    conn: &Connection,
+    uuid4: &str,
+) -> anyhow::Result<Option<(Attachment, Vec<u8>)>> {
+    let r = conn
+        .query_row(
+            "SELECT uuid4, note_uuid4, mime, size, sha256, created_at, data
+            FROM attachment where uuid4 = ?",
+            [uuid4],
+            |row| Ok((attachment_from_row(row)?, row.get(6)?)),
+        )
+        .optional()?;
+    Ok(r)
+}
+
+// original bytes of the first image attached to a note, thumbnails are what result pages carry
+pub fn get_image(conn: &Connection, note_uuid4: &str) -> anyhow::Result<Option<(String, Vec<u8>)>> {
+    let r = conn
+        .query_row(
+            "SELECT mime, data FROM attachment
+            where note_uuid4 = ? and mime like 'image/%' order by rowid limit 1",
+            [note_uuid4],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+    Ok(r)
+}
+
+// leaves a tombstone for its note and content, which sync honours
+pub fn remove_attachment(conn: &Connection, uuid4: &str) -> anyhow::Result<bool> {
+    let tx = conn.unchecked_transaction()?;
+    let key = tx
+        .query_row(
+            "SELECT note_uuid4, sha256 FROM attachment where uuid4 = ?1",
+            [uuid4],
+            |row| {
+                Ok(AttachmentKey {
+                    note_uuid4: row.get(0)?,
+                    sha256: row.get(1)?,
+                })
+            },
+        )
+        .optional()?;
+    let key = match key {
+        Some(key) => key,
+        None => return Ok(false),
+    };
+    tx.execute("delete from attachment where uuid4 = ?1", [uuid4])?;
+    tx.execute(
+        "INSERT OR REPLACE INTO attachment_tombstone (note_uuid4, sha256, removed_at)
+        VALUES (?1, ?2, ?3)",
+        params![key.note_uuid4, key.sha256, crate::exe::created_time()],
+    )?;
+    tx.commit()?;
+    Ok(true)
+}
+
+pub fn is_removed(conn: &Connection, key: &AttachmentKey) -> anyhow::Result<bool> {
+    let removed = conn
+        .prepare("select 1 FROM attachment_tombstone where note_uuid4 = ?1 and sha256 = ?2")?
+        .exists([&key.note_uuid4, &key.sha256])?;
+    Ok(removed)
+}
+
+// keys of attachments on syncable notes after the given one, in key order
+pub fn attachment_key_page(
+    conn: &Connection,
+    after: &AttachmentKey,
+    limit: u32,
+) -> anyhow::Result<Vec<AttachmentKey>> {
+    let mut stmt = conn.prepare(
+        "SELECT distinct attachment.note_uuid4, attachment.sha256
+        FROM attachment join note on note.uuid4 = attachment.note_uuid4
+        where note.is_local = 0 and (attachment.note_uuid4, attachment.sha256) > (?1, ?2)
+        order by attachment.note_uuid4, attachment.sha256 limit ?3",
+    )?;
+    let iter = stmt.query_map(params![after.note_uuid4, after.sha256, limit], |row| {
+        Ok(AttachmentKey {
+            note_uuid4: row.get(0)?,
+            sha256: row.get(1)?,
+        })
+    })?;
+    let mut r = Vec::new();
+    for key in iter {
+        r.push(key?);
+    }
+    Ok(r)
+}
+
+// candidates this database lacks, for notes it holds and syncs; removed
+// ones are never missing
+pub fn missing_attachments(
+    conn: &Connection,
+    candidates: Vec<AttachmentKey>,
+) -> anyhow::Result<Vec<AttachmentKey>> {
+    let mut stmt = conn.prepare(
+        "SELECT 1 FROM note where uuid4 = ?1 and is_local = 0
+        and not exists (select 1 from attachment where note_uuid4 = ?1 and sha256 = ?2)
+        and not exists (select 1 from attachment_tombstone where note_uuid4 = ?1 and sha256 = ?2)",
+    )?;
+    let mut r = Vec::new();
+    for key in candidates {
+        if stmt.exists([&key.note_uuid4, &key.sha256])? {
+            r.push(key);
+        }
+    }
+    Ok(r)
+}
+
+pub fn get_attachments_by_keys(
+    conn: &Connection,
+    keys: &[AttachmentKey],
+) -> anyhow::Result<Vec<AttachmentData>> {
+    let mut stmt = conn.prepare(
+        "SELECT uuid4, note_uuid4, mime, size, sha256, created_at, data
+        FROM attachment where note_uuid4 = ?1 and sha256 = ?2 order by rowid limit 1",
+    )?;
+    let mut r = Vec::new();
+    for key in keys {
+        let a = stmt
+            .query_row([&key.note_uuid4, &key.sha256], |row| {
+                Ok(AttachmentData {
+                    attachment: attachment_from_row(row)?,
+                    data: row.get(6)?,
+                })
+            })
+            .optional()?;
+        r.extend(a);
+    }
+    Ok(r)
+}
+
+// attachments from another device, whose bytes have to hash to the key they
+// came under; ones already here or for notes missing here are skipped
+pub fn save_attachments(conn: &Connection, attachments: &[AttachmentData]) -> anyhow::Result<u32> {
+    let mut saved = 0;
+    for AttachmentData {
+        attachment: a,
+        data,
+    } in attachments
+    {
+        if sha256_hex(data) != a.sha256 {
+            eprintln!("attachment {} does not match its hash, skipped", a.uuid4);
+            continue;
+        }
+        let mime = match sniff_mime(data) {
+            Some(mime) => mime,
+            None => {
+                eprintln!("attachment {} has an unsupported type, skipped", a.uuid4);
+                continue;
+            }
+        };
+        if missing_attachments(conn, vec![a.key()])?.is_empty() {
+            continue;
+        }
+        // the same uuid4 on another note would be a clash, the copy gets its own
+        let taken = conn
+            .prepare("select 1 FROM attachment where uuid4 = ?")?
+            .exists([&a.uuid4])?;
+        let uuid4 = if taken {
+            Uuid::new_v4().to_string()
+        } else {
+            a.uuid4.clone()
+        };
+        conn.execute(
+            "INSERT INTO attachment (uuid4, note_uuid4, mime, size, sha256, data, thumbnail, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                uuid4,
+                a.note_uuid4,
+                mime,
+                data.len() as i64,
+                a.sha256,
+                data,
+                make_thumbnail(data),
+                a.created_at
+            ],
+        )?;
+        saved += 1;
+    }
+    Ok(saved)
+}
+
+pub fn attachment_bytes(attachments: &[AttachmentData]) -> u64 {
+    attachments.iter().map(|a| a.data.len() as u64).sum()
+}
@@ -0,0 +1,311 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+// portable sync bundles for devices that never share a network: the notes,
+// tombstones and attachments changed since a change log seq, gzipped behind
+// a plain json manifest that carries the payload checksum
+//
+// LNBUNDLE\n<manifest json>\n<gzipped payload json>
+use super::attachment::{
+    add_attachment, get_attachment, is_removed, list_attachments, sha256_hex, Attachment,
+};
+use super::conflict::{compare, device_id, get_note, get_policy, merge_note, Clock, Merge, Order};
+use super::sync::{get_notes_by_uuid4s, last_change_seq, MergeSummary};
+use crate::upgrade::get_meta_version;
+use crate::Note;
+use base64::{engine::general_purpose, Engine as _};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use rusqlite::{params, Connection, Row};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{Read, Write};
+use std::path::Path;
+
+const MAGIC: &[u8] = b"LNBUNDLE\n";
+// bumped when older builds could not read the payload
+pub const FORMAT: u32 = 1;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Default)]
+pub struct Manifest {
+    pub format: u32,
+    // meta version of the exporting database, for the user only
+    pub schema: String,
+    pub device: String,
+    pub created_at: String,
+    // the exporting device's change log seqs covered, after since up to until;
+    // the next bundle for the same device can start at until
+    pub since: i64,
+    pub until: i64,
+    pub notes: usize,
+    pub tombstones: usize,
+    pub attachments: usize,
+    // hex sha256 of the gzipped payload
+    pub sha256: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct Tombstone {
+    pub uuid4: String,
+    // of the note when it was deleted, a later edit elsewhere outlives it
+    pub clock: Clock,
+    pub seq: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+struct BundleAttachment {
+    #[serde(flatten)]
+    attachment: Attachment,
+    // base64
+    data: String,
+}
+
+// fields missing from a bundle of another release fall back to defaults
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct Payload {
+    #[serde(default)]
+    notes: Vec<Note>,
+    #[serde(default)]
+    tombstones: Vec<Tombstone>,
+    #[serde(default)]
+    attachments: Vec<BundleAttachment>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Default)]
+pub struct ImportSummary {
+    pub manifest: Manifest,
+    pub merged: MergeSummary,
+    pub deleted: usize,
+    pub attachments: usize,
+}
+
+fn tombstone_from_row(row: &Row) -> rusqlite::Result<Tombstone> {
+    let clock: String = row.get(1)?;
+    Ok(Tombstone {
+        uuid4: row.get(0)?,
+        clock: serde_json::from_str(&clock).unwrap_or_default(),
+        seq: row.get(2)?,
+    })
+}
+
+pub fn list_tombstones(conn: &Connection, after: i64) -> anyhow::Result<Vec<Tombstone>> {
+    tombstones_since(conn, after, u32::MAX)
+}
+
+// a page of tombstones in seq order
+pub fn tombstones_since(
+    conn: &Connection,
+    after: i64,
+    limit: u32,
+) -> anyhow::Result<Vec<Tombstone>> {
+    let mut stmt = conn
+        .prepare("select uuid4, clock, seq FROM tombstone where seq > ?1 order by seq limit ?2")?;
+    let iter = stmt.query_map(params![after, limit], tombstone_from_row)?;
+    let mut r = Vec::new();
+    for tombstone in iter {
+        r.push(tombstone?);
+    }
+    Ok(r)
+}
+
+// syncable notes changed after the seq, each once
+fn changed_notes(conn: &Connection, after: i64) -> anyhow::Result<Vec<Note>> {
+    let mut stmt = conn.prepare(
+        "select uuid4 FROM note where is_local = 0
+        and uuid4 in (select uuid4 FROM change_log where seq > ?1) order by rowid",
+    )?;
+    let iter = stmt.query_map([after], |row| row.get::<_, String>(0))?;
+    let mut uuid4s = Vec::new();
+    for uuid4 in iter {
+        uuid4s.push(uuid4?);
+    }
+    get_notes_by_uuid4s(conn, &uuid4s)
+}
+
+// the bundle file as bytes, since 0 takes every syncable note
+pub fn encode_bundle(conn: &Connection, since: i64) -> anyhow::Result<(Manifest, Vec<u8>)> {
+    let until = last_change_seq(conn)?;
+    let notes = changed_notes(conn, since)?;
+    let mut attachments = Vec::new();
+    for note in &notes {
+        for attachment in list_attachments(conn, &note.uuid4)? {
+            if let Some((attachment, data)) = get_attachment(conn, &attachment.uuid4)? {
+                attachments.push(BundleAttachment {
+                    attachment,
+                    data: general_purpose::STANDARD.encode(data),
+                });
+            }
+        }
+    }
+    let payload = Payload {
+        notes,
+        tombstones: list_tombstones(conn, since)?,
+        attachments,
+    };
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    serde_json::to_writer(&mut encoder, &payload)?;
+    let compressed = encoder.finish()?;
+
+    let manifest = Manifest {
+        format: FORMAT,
+        schema: get_meta_version(conn)?,
+        device: device_id(conn)?,
+        created_at: crate::exe::created_time(),
+        since,
+        until,
+        notes: payload.notes.len(),
+        tombstones: payload.tombstones.len(),
+        attachments: payload.attachments.len(),
+        sha256: sha256_hex(&compressed),
+    };
+    let mut bytes = MAGIC.to_vec();
+    serde_json::to_writer(&mut bytes, &manifest)?;
+    bytes.push(b'\n');
+    bytes.extend_from_slice(&compressed);
+    Ok((manifest, bytes))
+}
+
+pub fn export_bundle(conn: &Connection, path: &Path, since: i64) -> anyhow::Result<Manifest> {
+    let (manifest, bytes) = encode_bundle(conn, since)?;
+    let mut file = fs::File::create(path)?;
+    file.write_all(&bytes)?;
+    file.sync_all()?;
+    Ok(manifest)
+}
+
+// manifest and checked payload bytes
+fn parse_bundle(bytes: &[u8]) -> anyhow::Result<(Manifest, &[u8])> {
+    let rest = bytes
+        .strip_prefix(MAGIC)
+        .ok_or_else(|| anyhow::anyhow!("not a sync bundle"))?;
+    let end = rest
+        .iter()
+        .position(|b| *b == b'\n')
+        .ok_or_else(|| anyhow::anyhow!("sync bundle manifest is cut off"))?;
+    let manifest: Manifest = serde_json::from_slice(&rest[..end])?;
+    if manifest.format > FORMAT {
+        return Err(anyhow::anyhow!(
+            "sync bundle format {} is newer than this version reads ({}), upgrade Local Native first",
+            manifest.format,
+            FORMAT
+        ));
+    }
+    let compressed = &rest[end + 1..];
+    if sha256_hex(compressed) != manifest.sha256 {
+        return Err(anyhow::anyhow!(
+            "sync bundle checksum does not match, the file is damaged"
+        ));
+    }
+    Ok((manifest, compressed))
+}
+
+pub fn read_manifest(path: &Path) -> anyhow::Result<Manifest> {
+    Ok(parse_bundle(&fs::read(path)?)?.0)
+}
+
+// removes the note with what hangs off it, the delete trigger leaves a tombstone here too
+fn delete_note(conn: &Connection, uuid4: &str) -> anyhow::Result<()> {
+    for sql in [
+        "delete from archive where uuid4 = ?1",
+        "delete from link_status where uuid4 = ?1",
+        "delete from attachment where note_uuid4 = ?1",
+        "delete from note where uuid4 = ?1",
+    ] {
+        conn.execute(sql, params![uuid4])?;
+    }
+    Ok(())
+}
+
+// the notes no one has edited since another device deleted them, local
+// only notes are never touched
+pub fn deleted_by_tombstones(
+    conn: &Connection,
+    tombstones: &[Tombstone],
+) -> anyhow::Result<Vec<Note>> {
+    let mut r = Vec::new();
+    for tombstone in tombstones {
+        match get_note(conn, &tombstone.uuid4)? {
+            Some(note)
+                if !note.is_local
+                    && matches!(
+                        compare(&note.version.clock, &tombstone.clock),
+                        Order::Before | Order::Equal
+                    ) =>
+            {
+                r.push(note)
+            }
+            _ => {}
+        }
+    }
+    Ok(r)
+}
+
+// returns how many notes were deleted
+pub fn delete_by_tombstones(conn: &Connection, tombstones: &[Tombstone]) -> anyhow::Result<usize> {
+    let notes = deleted_by_tombstones(conn, tombstones)?;
+    for note in &notes {
+        delete_note(conn, &note.uuid4)?;
+    }
+    Ok(notes.len())
+}
+
+// notes merge like a sync with the exporting device; a tombstone only removes
+// a note no one has edited since it was deleted. importing twice is harmless
+pub fn import_bundle(conn: &Connection, path: &Path) -> anyhow::Result<ImportSummary> {
+    import_bundle_bytes(conn, &fs::read(path)?)
+        .map_err(|err| anyhow::anyhow!("{:?}: {}", path, err))
+}
+
+pub fn import_bundle_bytes(conn: &Connection, bytes: &[u8]) -> anyhow::Result<ImportSummary> {
+    let (manifest, compressed) = parse_bundle(bytes)?;
+    let mut json = Vec::new();
+    GzDecoder::new(compressed).read_to_end(&mut json)?;
+    let payload: Payload = serde_json::from_slice(&json)?;
+
+    let policy = get_policy(conn)?;
+    let tx = conn.unchecked_transaction()?;
+    let mut summary = ImportSummary {
+        manifest,
+        ..Default::default()
+    };
+    for note in payload.notes.iter().filter(|note| !note.is_local) {
+        match merge_note(&tx, note, policy)? {
+            Merge::Inserted => summary.merged.inserted += 1,
+            Merge::Updated => summary.merged.updated += 1,
+            Merge::Conflict => summary.merged.conflicts += 1,
+            Merge::Unchanged => {}
+        }
+    }
+    summary.deleted = delete_by_tombstones(&tx, &payload.tombstones)?;
+    for bundled in &payload.attachments {
+        let note_uuid4 = &bundled.attachment.note_uuid4;
+        if get_note(&tx, note_uuid4)?.is_none()
+            || is_removed(&tx, &bundled.attachment.key())?
+            || list_attachments(&tx, note_uuid4)?
+                .iter()
+                .any(|a| a.sha256 == bundled.attachment.sha256)
+        {
+            continue;
+        }
+        let data = general_purpose::STANDARD.decode(&bundled.data)?;
+        add_attachment(&tx, note_uuid4, &data)?;
+        summary.attachments += 1;
+    }
+    tx.commit()?;
+    Ok(summary)
+}
@@ -0,0 +1,526 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+// note versions and what sync does when two devices edited the same note
+use crate::{Note, NoteVersion};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use uuid::Uuid;
+
+pub type Clock = BTreeMap<String, u64>;
+
+// content that travels with a note, each is versioned on its own
+const FIELDS: [&str; 7] = [
+    "title",
+    "url",
+    "tags",
+    "description",
+    "comments",
+    "annotations",
+    "is_public",
+];
+
+const NOTE_COLUMNS: &str = "rowid, uuid4, title, url, tags, description, comments, annotations, created_at, is_public, is_local, updated_at, device, clock, field_clock";
+
+fn note_from_row(row: &Row) -> rusqlite::Result<Note> {
+    let clock: String = row.get(13)?;
+    let fields: String = row.get(14)?;
+    Ok(Note {
+        rowid: row.get(0)?,
+        uuid4: row.get(1)?,
+        title: row.get(2)?,
+        url: row.get(3)?,
+        tags: row.get(4)?,
+        description: row.get(5)?,
+        comments: row.get(6)?,
+        annotations: row.get(7)?,
+        created_at: row.get(8)?,
+        is_public: row.get(9)?,
+        is_local: row.get(10)?,
+        version: NoteVersion {
+            updated_at: row.get(11)?,
+            device: row.get(12)?,
+            clock: serde_json::from_str(&clock).unwrap_or_default(),
+            fields: serde_json::from_str(&fields).unwrap_or_default(),
+        },
+    })
+}
+
+pub fn get_note(conn: &Connection, uuid4: &str) -> anyhow::Result<Option<Note>> {
+    let note = conn
+        .query_row(
+            &format!("select {} FROM note where uuid4 = ?1", NOTE_COLUMNS),
+            [uuid4],
+            note_from_row,
+        )
+        .optional()?;
+    Ok(note)
+}
+
+// clock and field clock as stored in the note table
+pub fn encode_version(version: &NoteVersion) -> anyhow::Result<(String, String)> {
+    Ok((
+        serde_json::to_string(&version.clock)?,
+        serde_json::to_string(&version.fields)?,
+    ))
+}
+
+// random id of this database, set the first time it is asked for
+pub fn device_id(conn: &Connection) -> anyhow::Result<String> {
+    conn.execute(
+        "INSERT OR IGNORE INTO meta (meta_key, meta_value) VALUES ('device_id', ?1)",
+        [Uuid::new_v4().to_string()],
+    )?;
+    let id = conn.query_row(
+        "select meta_value FROM meta where meta_key = 'device_id'",
+        [],
+        |row| row.get(0),
+    )?;
+    Ok(id)
+}
+
+// version of a note created on this device
+pub fn new_version(conn: &Connection, updated_at: &str) -> anyhow::Result<NoteVersion> {
+    Ok(NoteVersion {
+        updated_at: updated_at.to_string(),
+        device: device_id(conn)?,
+        ..Default::default()
+    })
+}
+
+fn same_field(a: &Note, b: &Note, name: &str) -> bool {
+    match name {
+        "title" => a.title == b.title,
+        "url" => a.url == b.url,
+        "tags" => a.tags == b.tags,
+        "description" => a.description == b.description,
+        "comments" => a.comments == b.comments,
+        "annotations" => a.annotations == b.annotations,
+        "is_public" => a.is_public == b.is_public,
+        _ => true,
+    }
+}
+
+fn copy_field(to: &mut Note, from: &Note, name: &str) {
+    match name {
+        "title" => to.title = from.title.clone(),
+        "url" => to.url = from.url.clone(),
+        "tags" => to.tags = from.tags.clone(),
+        "description" => to.description = from.description.clone(),
+        "comments" => to.comments = from.comments.clone(),
+        "annotations" => to.annotations = from.annotations.clone(),
+        "is_public" => to.is_public = from.is_public,
+        _ => {}
+    }
+}
+
+fn write_note(conn: &Connection, note: &Note) -> anyhow::Result<()> {
+    let (clock, fields) = encode_version(&note.version)?;
+    conn.execute(
+        "UPDATE note SET title = ?1, url = ?2, tags = ?3, description = ?4, comments = ?5,
+        annotations = ?6, is_public = ?7, updated_at = ?8, device = ?9, clock = ?10, field_clock = ?11
+        WHERE uuid4 = ?12",
+        params![
+            note.title,
+            note.url,
+            super::make_tags(&note.tags),
+            note.description,
+            note.comments,
+            note.annotations,
+            note.is_public,
+            note.version.updated_at,
+            note.version.device,
+            clock,
+            fields,
+            note.uuid4,
+        ],
+    )?;
+    Ok(())
+}
+
+// notes already present are left alone, returns 1 when inserted
+pub fn insert_note(conn: &Connection, note: &Note) -> anyhow::Result<usize> {
+    let (clock, fields) = encode_version(&note.version)?;
+    let n = conn.execute(
+        "INSERT OR IGNORE INTO note (uuid4, title, url, tags, description, comments, annotations, created_at, is_public, updated_at, device, clock, field_clock)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+        params![
+            note.uuid4,
+            note.title,
+            note.url,
+            super::make_tags(&note.tags),
+            note.description,
+            note.comments,
+            note.annotations,
+            note.created_at,
+            note.is_public,
+            note.version.updated_at,
+            note.version.device,
+            clock,
+            fields,
+        ],
+    )?;
+    Ok(n)
+}
+
+// applies `edit` to a note as an edit from this device, only changed fields
+// get a new version; returns false when there is no such note
+pub fn edit_note(
+    conn: &Connection,
+    uuid4: &str,
+    edit: impl FnOnce(&mut Note),
+) -> anyhow::Result<bool> {
+    let Some(old) = get_note(conn, uuid4)? else {
+        return Ok(false);
+    };
+    let mut note = old.clone();
+    edit(&mut note);
+    note.tags = super::make_tags(&note.tags);
+    let device = device_id(conn)?;
+    let counter = note.version.clock.get(&device).copied().unwrap_or(0) + 1;
+    let mut changed = false;
+    for name in FIELDS {
+        if !same_field(&old, &note, name) {
+            note.version
+                .fields
+                .insert(name.to_string(), (device.clone(), counter));
+            changed = true;
+        }
+    }
+    if changed {
+        note.version.clock.insert(device.clone(), counter);
+        note.version.updated_at = crate::exe::created_time();
+        note.version.device = device;
+        write_note(conn, &note)?;
+    }
+    Ok(true)
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Order {
+    Equal,
+    Before,
+    After,
+    Concurrent,
+}
+
+// how clock a relates to clock b
+pub fn compare(a: &Clock, b: &Clock) -> Order {
+    let (mut behind, mut ahead) = (false, false);
+    for device in a.keys().chain(b.keys()) {
+        let x = a.get(device).copied().unwrap_or(0);
+        let y = b.get(device).copied().unwrap_or(0);
+        behind |= x < y;
+        ahead |= x > y;
+    }
+    match (behind, ahead) {
+        (false, false) => Order::Equal,
+        (true, false) => Order::Before,
+        (false, true) => Order::After,
+        (true, true) => Order::Concurrent,
+    }
+}
+
+fn has_seen(clock: &Clock, dot: Option<&(String, u64)>) -> bool {
+    match dot {
+        Some((device, counter)) => clock.get(device).copied().unwrap_or(0) >= *counter,
+        None => true,
+    }
+}
+
+fn merge_clocks(a: &Clock, b: &Clock) -> Clock {
+    let mut r = a.clone();
+    for (device, counter) in b {
+        let c = r.entry(device.clone()).or_insert(0);
+        *c = (*c).max(*counter);
+    }
+    r
+}
+
+// created_time() does not pad the nanoseconds, so they compare as a number
+fn time_key(t: &str) -> (&str, u64) {
+    match t.trim_end_matches(" UTC").rsplit_once(':') {
+        Some((seconds, nanos)) => (seconds, nanos.parse().unwrap_or(0)),
+        None => (t, 0),
+    }
+}
+
+// later updated_at wins, the device id breaks ties so every device picks the same
+fn is_newer(a: &NoteVersion, b: &NoteVersion) -> bool {
+    (time_key(&a.updated_at), &a.device) > (time_key(&b.updated_at), &b.device)
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum ConflictPolicy {
+    // the most recent edit replaces the whole note
+    #[default]
+    LastWriterWins,
+    // fields edited on one side only are combined, a field edited on both
+    // takes the most recent value and the other note is kept as a copy
+    MergeFields,
+    // the most recent note stays, the other one is kept as a copy; picking
+    // the same winner on every device lets them converge
+    KeepBoth,
+}
+
+impl ConflictPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConflictPolicy::LastWriterWins => "last-writer-wins",
+            ConflictPolicy::MergeFields => "merge-fields",
+            ConflictPolicy::KeepBoth => "keep-both",
+        }
+    }
+}
+
+impl FromStr for ConflictPolicy {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "last-writer-wins" => Ok(ConflictPolicy::LastWriterWins),
+            "merge-fields" => Ok(ConflictPolicy::MergeFields),
+            "keep-both" => Ok(ConflictPolicy::KeepBoth),
+            _ => Err(anyhow::anyhow!("unknown conflict policy {}", s)),
+        }
+    }
+}
+
+pub fn get_policy(conn: &Connection) -> anyhow::Result<ConflictPolicy> {
+    let policy: Option<String> = conn
+        .query_row(
+            "select meta_value FROM meta where meta_key = 'conflict_policy'",
+            [],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(policy.and_then(|p| p.parse().ok()).unwrap_or_default())
+}
+
+pub fn set_policy(conn: &Connection, policy: ConflictPolicy) -> anyhow::Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO meta (meta_key, meta_value) VALUES ('conflict_policy', ?1)",
+        [policy.as_str()],
+    )?;
+    Ok(())
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Merge {
+    Inserted,
+    Unchanged,
+    Updated,
+    // resolved by policy, a copy waits for the user
+    Conflict,
+}
+
+// folds a note from another device into this one
+pub fn merge_note(
+    conn: &Connection,
+    remote: &Note,
+    policy: ConflictPolicy,
+) -> anyhow::Result<Merge> {
+    let Some(local) = get_note(conn, &remote.uuid4)? else {
+        return Ok(if insert_note(conn, remote)? > 0 {
+            Merge::Inserted
+        } else {
+            Merge::Unchanged
+        });
+    };
+    if local.is_local {
+        return Ok(Merge::Unchanged);
+    }
+    match compare(&local.version.clock, &remote.version.clock) {
+        Order::Equal | Order::After => Ok(Merge::Unchanged),
+        Order::Before => {
+            write_note(conn, remote)?;
+            Ok(Merge::Updated)
+        }
+        Order::Concurrent => resolve(conn, &local, remote, policy),
+    }
+}
+
+// both devices edited the note since they last agreed; the result carries both
+// clocks so the other device takes it as a plain update
+fn resolve(
+    conn: &Connection,
+    local: &Note,
+    remote: &Note,
+    policy: ConflictPolicy,
+) -> anyhow::Result<Merge> {
+    let remote_wins = is_newer(&remote.version, &local.version);
+    let (winner, loser) = if remote_wins {
+        (remote, local)
+    } else {
+        (local, remote)
+    };
+    let clock = merge_clocks(&local.version.clock, &remote.version.clock);
+    let (mut note, copy) = match policy {
+        ConflictPolicy::LastWriterWins => (winner.clone(), None),
+        ConflictPolicy::MergeFields => {
+            let mut note = local.clone();
+            note.version.updated_at = winner.version.updated_at.clone();
+            note.version.device = winner.version.device.clone();
+            let mut clash = false;
+            for name in FIELDS {
+                let local_dot = local.version.fields.get(name);
+                let remote_dot = remote.version.fields.get(name);
+                // keep ours when equal or when we already saw their edit
+                let take_remote = if same_field(local, remote, name)
+                    || has_seen(&local.version.clock, remote_dot)
+                {
+                    false
+                } else if has_seen(&remote.version.clock, local_dot) {
+                    true
+                } else {
+                    clash = true;
+                    remote_wins
+                };
+                if take_remote {
+                    copy_field(&mut note, remote, name);
+                    match remote_dot {
+                        Some(dot) => note.version.fields.insert(name.to_string(), dot.clone()),
+                        None => note.version.fields.remove(name),
+                    };
+                }
+            }
+            (note, clash.then_some(loser))
+        }
+        ConflictPolicy::KeepBoth => (winner.clone(), Some(loser)),
+    };
+    note.uuid4 = local.uuid4.clone();
+    note.version.clock = clock;
+    write_note(conn, &note)?;
+    match copy {
+        Some(copy) => {
+            keep_copy(conn, &local.uuid4, copy)?;
+            Ok(Merge::Conflict)
+        }
+        None => Ok(Merge::Updated),
+    }
+}
+
+// the losing side of a conflict as a note of its own
+fn keep_copy(conn: &Connection, uuid4: &str, from: &Note) -> anyhow::Result<()> {
+    let copy = Note {
+        rowid: 0,
+        uuid4: Uuid::new_v4().to_string(),
+        is_local: false,
+        version: new_version(conn, &crate::exe::created_time())?,
+        ..from.clone()
+    };
+    insert_note(conn, &copy)?;
+    conn.execute(
+        "INSERT OR REPLACE INTO conflict (uuid4, copy_uuid4, detected_at) VALUES (?1, ?2, ?3)",
+        params![uuid4, copy.uuid4, crate::exe::created_time()],
+    )?;
+    Ok(())
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct Conflict {
+    pub note: Note,
+    pub copy: Note,
+    pub detected_at: String,
+}
+
+// conflicts whose notes are both still there
+pub fn list_conflicts(conn: &Connection) -> anyhow::Result<Vec<Conflict>> {
+    let mut stmt =
+        conn.prepare("select uuid4, copy_uuid4, detected_at FROM conflict order by detected_at")?;
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+        ))
+    })?;
+    let mut r = Vec::new();
+    for row in rows {
+        let (uuid4, copy_uuid4, detected_at) = row?;
+        if let (Some(note), Some(copy)) = (get_note(conn, &uuid4)?, get_note(conn, &copy_uuid4)?) {
+            r.push(Conflict {
+                note,
+                copy,
+                detected_at,
+            });
+        }
+    }
+    Ok(r)
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub enum Resolution {
+    // drop the copy
+    KeepNote,
+    // the copy's content replaces the note, then the copy is dropped
+    KeepCopy,
+    // both stay as separate notes
+    KeepBoth,
+}
+
+impl FromStr for Resolution {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "keep-note" => Ok(Resolution::KeepNote),
+            "keep-copy" => Ok(Resolution::KeepCopy),
+            "keep-both" => Ok(Resolution::KeepBoth),
+            _ => Err(anyhow::anyhow!("unknown resolution {}", s)),
+        }
+    }
+}
+
+// returns false when there is no such conflict
+pub fn resolve_conflict(
+    conn: &Connection,
+    uuid4: &str,
+    resolution: Resolution,
+) -> anyhow::Result<bool> {
+    let copy_uuid4: Option<String> = conn
+        .query_row(
+            "select copy_uuid4 FROM conflict where uuid4 = ?1",
+            [uuid4],
+            |row| row.get(0),
+        )
+        .optional()?;
+    let Some(copy_uuid4) = copy_uuid4 else {
+        return Ok(false);
+    };
+    let tx = conn.unchecked_transaction()?;
+    let copy = get_note(&tx, &copy_uuid4)?;
+    if resolution == Resolution::KeepCopy {
+        if let Some(ref copy) = copy {
+            edit_note(&tx, uuid4, |note| {
+                for name in FIELDS {
+                    copy_field(note, copy, name);
+                }
+            })?;
+        }
+    }
+    if resolution != Resolution::KeepBoth {
+        if let Some(copy) = copy {
+            super::delete(&tx, copy.rowid)?;
+        }
+    }
+    tx.execute("delete from conflict where uuid4 = ?1", [uuid4])?;
+    tx.commit()?;
+    Ok(true)
+}
@@ -0,0 +1,265 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+// atom and rss 2.0 feeds of the latest public notes, optionally for one tag
+use crate::Note;
+use rusqlite::types::ToSql;
+use rusqlite::Connection;
+use std::str::FromStr;
+use time::format_description::well_known::{Rfc2822, Rfc3339};
+use time::macros::format_description;
+use time::{OffsetDateTime, PrimitiveDateTime};
+
+pub const DEFAULT_LIMIT: u32 = 50;
+// most entries a feed reader can ask for over http
+pub const MAX_LIMIT: u32 = 500;
+const FEED_TITLE: &str = "Local Native";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeedFormat {
+    Atom,
+    Rss,
+}
+
+impl FromStr for FeedFormat {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "atom" => Ok(FeedFormat::Atom),
+            "rss" => Ok(FeedFormat::Rss),
+            _ => Err(anyhow::anyhow!(
+                "unknown feed format {}, use atom or rss",
+                s
+            )),
+        }
+    }
+}
+
+impl FeedFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            FeedFormat::Atom => "application/atom+xml; charset=utf-8",
+            FeedFormat::Rss => "application/rss+xml; charset=utf-8",
+        }
+    }
+}
+
+// newest public notes first, local only notes never show up in a feed
+pub fn feed_notes(conn: &Connection, tag: Option<&str>, limit: u32) -> anyhow::Result<Vec<Note>> {
+    let mut stmt = conn.prepare(
+        "SELECT rowid, uuid4, title, url, tags, description, comments, created_at
+        FROM note where is_public = 1 and is_local = 0
+        and (:tag is null or instr(',' || tags || ',', ',' || :tag || ',') > 0)
+        order by created_at desc limit :limit",
+    )?;
+    let iter = stmt.query_map(
+        &[
+            (":tag", &tag as &dyn ToSql),
+            (":limit", &limit as &dyn ToSql),
+        ],
+        |row| {
+            Ok(Note {
+                rowid: row.get(0)?,
+                uuid4: row.get(1)?,
+                title: row.get(2)?,
+                url: row.get(3)?,
+                tags: row.get(4)?,
+                description: row.get(5)?,
+                comments: row.get(6)?,
+                annotations: "".to_string(),
+                created_at: row.get(7)?,
+                is_public: true,
+                is_local: false,
+                version: Default::default(),
+            })
+        },
+    )?;
+    let mut r = Vec::new();
+    for note in iter {
+        r.push(note?);
+    }
+    Ok(r)
+}
+
+// created_at looks like `2026-01-05 08:30:00:123456789 UTC`, the seconds are enough here
+fn parse_created_at(created_at: &str) -> OffsetDateTime {
+    created_at
+        .get(..19)
+        .and_then(|s| {
+            PrimitiveDateTime::parse(
+                s,
+                format_description!("[year]-[month]-[day] [hour]:[minute]:[second]"),
+            )
+            .ok()
+        })
+        .map(|t| t.assume_utc())
+        .unwrap_or(OffsetDateTime::UNIX_EPOCH)
+}
+
+pub fn xml_escape(s: &str) -> String {
+    let mut r = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => r.push_str("&amp;"),
+            '<' => r.push_str("&lt;"),
+            '>' => r.push_str("&gt;"),
+            '"' => r.push_str("&quot;"),
+            '\'' => r.push_str("&apos;"),
+            // not allowed in xml 1.0
+            c if c.is_control() && !matches!(c, '\n' | '\r' | '\t') => {}
+            c => r.push(c),
+        }
+    }
+    r
+}
+
+// stable across devices and syncs
+pub fn entry_id(note: &Note) -> String {
+    format!("urn:uuid:{}", note.uuid4)
+}
+
+fn entry_title(note: &Note) -> &str {
+    if note.title.is_empty() {
+        &note.url
+    } else {
+        &note.title
+    }
+}
+
+fn entry_link(note: &Note) -> Option<&str> {
+    if note.url.starts_with("http://") || note.url.starts_with("https://") {
+        Some(&note.url)
+    } else {
+        None
+    }
+}
+
+fn entry_summary(note: &Note) -> String {
+    [note.description.as_str(), note.comments.as_str()]
+        .iter()
+        .filter(|s| !s.is_empty())
+        .cloned()
+        .collect::<Vec<&str>>()
+        .join("\n\n")
+}
+
+fn feed_title(tag: Option<&str>) -> String {
+    match tag {
+        Some(tag) => format!("{} - {}", FEED_TITLE, tag),
+        None => FEED_TITLE.to_string(),
+    }
+}
+
+fn tags(note: &Note) -> impl Iterator<Item = &str> {
+    note.tags.split(',').filter(|t| !t.is_empty())
+}
+
+pub fn atom(notes: &[Note], tag: Option<&str>) -> anyhow::Result<String> {
+    let updated = notes
+        .first()
+        .map(|n| parse_created_at(&n.created_at))
+        .unwrap_or(OffsetDateTime::UNIX_EPOCH);
+    let mut r = String::from(r#"<?xml version="1.0" encoding="utf-8"?>"#);
+    r.push('\n');
+    r.push_str(r#"<feed xmlns="http://www.w3.org/2005/Atom">"#);
+    r.push_str(&format!(
+        "\n<title>{}</title>\n<id>urn:localnative:feed{}</id>\n<updated>{}</updated>\n<author><name>{}</name></author>\n",
+        xml_escape(&feed_title(tag)),
+        tag.map(|tag| format!(":{}", xml_escape(tag)))
+            .unwrap_or_default(),
+        updated.format(&Rfc3339)?,
+        FEED_TITLE,
+    ));
+    for note in notes {
+        r.push_str("<entry>\n");
+        r.push_str(&format!(
+            "<id>{}</id>\n<title>{}</title>\n<updated>{}</updated>\n",
+            entry_id(note),
+            xml_escape(entry_title(note)),
+            parse_created_at(&note.created_at).format(&Rfc3339)?,
+        ));
+        if let Some(link) = entry_link(note) {
+            r.push_str(&format!(r#"<link href="{}"/>"#, xml_escape(link)));
+            r.push('\n');
+        }
+        for tag in tags(note) {
+            r.push_str(&format!(r#"<category term="{}"/>"#, xml_escape(tag)));
+            r.push('\n');
+        }
+        let summary = entry_summary(note);
+        if !summary.is_empty() {
+            r.push_str(&format!("<summary>{}</summary>\n", xml_escape(&summary)));
+        }
+        r.push_str("</entry>\n");
+    }
+    r.push_str("</feed>\n");
+    Ok(r)
+}
+
+pub fn rss(notes: &[Note], tag: Option<&str>) -> anyhow::Result<String> {
+    let mut r = String::from(r#"<?xml version="1.0" encoding="utf-8"?>"#);
+    r.push_str("\n<rss version=\"2.0\">\n<channel>\n");
+    r.push_str(&format!(
+        "<title>{}</title>\n<link>https://localnative.app</link>\n<description>{}</description>\n",
+        xml_escape(&feed_title(tag)),
+        xml_escape(&feed_title(tag)),
+    ));
+    if let Some(note) = notes.first() {
+        r.push_str(&format!(
+            "<lastBuildDate>{}</lastBuildDate>\n",
+            parse_created_at(&note.created_at).format(&Rfc2822)?
+        ));
+    }
+    for note in notes {
+        r.push_str("<item>\n");
+        r.push_str(&format!(
+            "<guid isPermaLink=\"false\">{}</guid>\n<title>{}</title>\n<pubDate>{}</pubDate>\n",
+            entry_id(note),
+            xml_escape(entry_title(note)),
+            parse_created_at(&note.created_at).format(&Rfc2822)?,
+        ));
+        if let Some(link) = entry_link(note) {
+            r.push_str(&format!("<link>{}</link>\n", xml_escape(link)));
+        }
+        for tag in tags(note) {
+            r.push_str(&format!("<category>{}</category>\n", xml_escape(tag)));
+        }
+        let summary = entry_summary(note);
+        if !summary.is_empty() {
+            r.push_str(&format!(
+                "<description>{}</description>\n",
+                xml_escape(&summary)
+            ));
+        }
+        r.push_str("</item>\n");
+    }
+    r.push_str("</channel>\n</rss>\n");
+    Ok(r)
+}
+
+pub fn feed(
+    conn: &Connection,
+    format: FeedFormat,
+    tag: Option<&str>,
+    limit: u32,
+) -> anyhow::Result<String> {
+    let notes = feed_notes(conn, tag, limit)?;
+    match format {
+        FeedFormat::Atom => atom(&notes, tag),
+        FeedFormat::Rss => rss(&notes, tag),
+    }
+}
@@ -17,6 +17,7 @@
 */
 use super::make_tags;
 use super::select::{select, select_by_tag, select_count};
+use super::utils::split_filters;
 use crate::{KVStringI64, Note, Tags};
 use regex::Regex;
 use rusqlite::types::ToSql;
@@ -29,23 +30,26 @@ pub fn filter_by_tag(
     from: &str,
     to: &str,
 ) -> anyhow::Result<String> {
-    let words = make_words(query);
-    if words.len() == 1
-        && words
-            .get(0)
-            .ok_or_else(|| anyhow::anyhow!("words is empty"))?
-            .is_empty()
-    {
+    let (query, filter_clauses) = split_filters(query);
+    if query.is_empty() && filter_clauses.is_empty() {
         return select_by_tag(conn);
     }
+    let words = if query.is_empty() {
+        Vec::new()
+    } else {
+        make_words(&query)
+    };
     let num_words = words.len();
-    let r: Vec<String> = where_vec(num_words);
+    let mut r: Vec<String> = vec![
+        "substr(created_at, 0, 11) >= :from".to_string(),
+        "substr(created_at, 0, 11) <= :to".to_string(),
+    ];
+    r.extend(where_vec(num_words));
+    r.extend(filter_clauses.iter().map(|c| c.to_string()));
     let sql = format!(
         "SELECT tags
         FROM note where
-        substr(created_at, 0, 11) >= :from
-        and substr(created_at, 0, 11) <= :to
-        and {}",
+        {}",
         r.join(" and ")
     );
     #[cfg(not(feature = "no_print"))]
@@ -99,21 +103,29 @@ pub fn filter_by_tag(
 }
 
 pub fn filter_count(conn: &Connection, query: &str, from: &str, to: &str) -> anyhow::Result<u32> {
-    let words = make_words(query);
-    if words.len() == 1 && words.get(0).unwrap().is_empty() {
+    let (query, filter_clauses) = split_filters(query);
+    if query.is_empty() && filter_clauses.is_empty() {
         return select_count(conn);
     }
+    let words = if query.is_empty() {
+        Vec::new()
+    } else {
+        make_words(&query)
+    };
     let num_words = words.len();
     #[cfg(not(feature = "no_print"))]
     eprintln!("{} words {:?}", num_words, words);
 
-    let r: Vec<String> = where_vec(num_words);
+    let mut r: Vec<String> = vec![
+        "substr(created_at, 0, 11) >= :from".to_string(),
+        "substr(created_at, 0, 11) <= :to".to_string(),
+    ];
+    r.extend(where_vec(num_words));
+    r.extend(filter_clauses.iter().map(|c| c.to_string()));
     let sql = format!(
         "SELECT count(1)
         FROM note where
-        substr(created_at, 0, 11) >= :from
-        and substr(created_at, 0, 11) <= :to
-        and {}",
+        {}",
         r.join(" and ")
     );
     #[cfg(not(feature = "no_print"))]
@@ -152,28 +164,33 @@ pub fn filter(
     limit: u32,
     offset: u32,
 ) -> anyhow::Result<String> {
-    let words = make_words(query);
-    if words.len() == 1
-        && words
-            .get(0)
-            .ok_or_else(|| anyhow::anyhow!("words is empty"))?
-            .is_empty()
-    {
+    let (query, filter_clauses) = split_filters(query);
+    if query.is_empty() && filter_clauses.is_empty() {
         return select(conn, limit, offset);
     }
+    let words = if query.is_empty() {
+        Vec::new()
+    } else {
+        make_words(&query)
+    };
     let num_words = words.len();
     #[cfg(not(feature = "no_print"))]
     eprintln!("{} words {:?}", num_words, words);
 
-    let r: Vec<String> = where_vec(num_words);
+    let mut r: Vec<String> = vec![
+        "substr(created_at, 0, 11) >= :from".to_string(),
+        "substr(created_at, 0, 11) <= :to".to_string(),
+    ];
+    r.extend(where_vec(num_words));
+    r.extend(filter_clauses.iter().map(|c| c.to_string()));
     let sql = format!(
         "SELECT rowid, uuid4, title, url, tags, description, comments
-        , hex(annotations)
-        , created_at, is_public
+        , (select thumbnail from attachment
+            where attachment.note_uuid4 = note.uuid4 and thumbnail is not null
+            order by attachment.rowid limit 1)
+        , created_at, is_public, is_local
         FROM note where
-        substr(created_at, 0, 11) >= :from
-        and substr(created_at, 0, 11) <= :to
-        and {}
+        {}
         order by created_at desc limit :limit offset :offset",
         r.join(" and ")
     );
@@ -214,6 +231,8 @@ pub fn filter(
             annotations: super::utils::make_data_url(row).unwrap_or_else(|_| "".into()),
             created_at: row.get(8)?,
             is_public: row.get(9)?,
+            is_local: row.get(10)?,
+            version: Default::default(),
         })
     })?;
 
@@ -255,6 +274,7 @@ fn where_vec(num_words: usize) -> Vec<String> {
         or url like :w{i}
         or tags like :w{i}
         or description like :w{i}
+        or uuid4 in (select uuid4 from archive where text like :w{i})
         )"
             )
         })
@@ -0,0 +1,162 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+// sync through a folder some other tool keeps in step between devices. each
+// device only ever adds bundles to its own subfolder, named by the change log
+// seq they end at, and reads everyone else's in order:
+//
+// <dir>/<device id>/<until>.lnbundle
+//
+// no database file is shared, so a half copied file is all a file sync tool
+// can get wrong, and such a file fails its checksum and is read again later
+use super::segment::{sync_store, Segment, SegmentStore, SegmentSummary};
+use super::sync::last_change_seq;
+use notify::{RecursiveMode, Watcher};
+use rusqlite::Connection;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+
+const EXTENSION: &str = "lnbundle";
+// how often local edits are looked for while watching
+const POLL: Duration = Duration::from_secs(5);
+// file sync tools write in bursts, wait for the folder to settle
+const SETTLE: Duration = Duration::from_secs(1);
+
+pub type FolderSummary = SegmentSummary;
+
+pub struct Folder {
+    dir: PathBuf,
+}
+
+impl Folder {
+    pub fn open(dir: &Path) -> anyhow::Result<Self> {
+        fs::create_dir_all(dir)?;
+        Ok(Folder {
+            dir: fs::canonicalize(dir)?,
+        })
+    }
+}
+
+// zero padded so names sort in seq order
+fn bundle_name(until: i64) -> String {
+    format!("{:020}.{}", until, EXTENSION)
+}
+
+// the bundles in a device folder
+fn list_bundles(device: &str, dir: &Path) -> anyhow::Result<Vec<Segment>> {
+    let mut r = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension() != Some(OsStr::new(EXTENSION)) {
+            continue;
+        }
+        if let Some(until) = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| stem.parse().ok())
+        {
+            r.push(Segment {
+                device: device.to_string(),
+                until,
+                name: path.display().to_string(),
+            });
+        }
+    }
+    Ok(r)
+}
+
+impl SegmentStore for Folder {
+    fn kind(&self) -> &str {
+        "folder"
+    }
+
+    // the folder's own path, so moving to another folder starts over
+    fn location(&self) -> String {
+        self.dir.display().to_string()
+    }
+
+    fn list(&self) -> anyhow::Result<Vec<Segment>> {
+        let mut r = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            let Some(device) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            if device.starts_with('.') || !path.is_dir() {
+                continue;
+            }
+            r.extend(list_bundles(device, &path)?);
+        }
+        Ok(r)
+    }
+
+    fn get(&self, segment: &Segment) -> anyhow::Result<Vec<u8>> {
+        Ok(fs::read(&segment.name)?)
+    }
+
+    fn put(&self, device: &str, until: i64, bundle: &[u8]) -> anyhow::Result<String> {
+        let own = self.dir.join(device);
+        fs::create_dir_all(&own)?;
+        let name = bundle_name(until);
+        // a file sync tool never sees a bundle half written
+        let tmp = own.join(format!(".{}.tmp", name));
+        fs::write(&tmp, bundle)?;
+        fs::rename(&tmp, own.join(&name))?;
+        Ok(name)
+    }
+}
+
+pub fn sync_folder(conn: &Connection, dir: &Path) -> anyhow::Result<FolderSummary> {
+    sync_store(conn, &Folder::open(dir)?)
+}
+
+// a pass now, then one whenever the folder changes or notes are edited here
+pub fn watch(dir: &Path, conn: impl Fn() -> Connection) -> anyhow::Result<()> {
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    fs::create_dir_all(dir)?;
+    watcher.watch(dir, RecursiveMode::Recursive)?;
+    let mut seen = -1;
+    loop {
+        let conn = conn();
+        let seq = last_change_seq(&conn)?;
+        if seq != seen {
+            let summary = sync_folder(&conn, dir)?;
+            if summary.written.is_some() || summary.read > 0 || !summary.skipped.is_empty() {
+                eprintln!("folder sync {:?}", summary);
+            }
+            seen = last_change_seq(&conn)?;
+        }
+        drop(conn);
+        match rx.recv_timeout(POLL) {
+            // our own bundle shows up as a change too, the pass it causes
+            // finds nothing new
+            Ok(_) => {
+                std::thread::sleep(SETTLE);
+                while rx.try_recv().is_ok() {}
+                seen = -1;
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                return Err(anyhow::anyhow!("folder watcher stopped"))
+            }
+        }
+    }
+}
@@ -15,46 +15,43 @@
     You should have received a copy of the GNU Affero General Public License
     along with this program.  If not, see <https://www.gnu.org/licenses/>.
 */
+use super::attachment::{add_attachment, parse_data_url, sniff_mime};
 use super::make_tags;
 use crate::Note;
-use base64::{Engine as _, engine::general_purpose};
-use rusqlite::types::ToSql;
+use rusqlite::{params, Connection};
 
-pub fn insert_image(note: Note) -> anyhow::Result<()> {
-    let data64 = note.annotations.replace("data:image/png;base64,", "");
-    let decoded = general_purpose::STANDARD.decode(&data64)?;
-    let conn = &mut super::super::exe::get_sqlite_connection();
-    let tx = conn.transaction()?;
+// note.annotations carries the data url from the client,
+// the decoded bytes go to the attachment table and the note url records the mime type
+pub fn insert_image(conn: &Connection, note: Note) -> anyhow::Result<()> {
+    let (_, decoded) = parse_data_url(&note.annotations)?;
+    let mime = sniff_mime(&decoded).ok_or_else(|| anyhow::anyhow!("unsupported image type"))?;
+    let tx = conn.unchecked_transaction()?;
     {
+        let version = super::conflict::new_version(&tx, &note.created_at)?;
+        let (clock, fields) = super::conflict::encode_version(&version)?;
         tx.execute(
             "
-        INSERT INTO note (title, uuid4, url, tags, description, comments, annotations, created_at, is_public)
-        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8);
+        INSERT INTO note (title, uuid4, url, tags, description, comments, annotations, created_at, is_public, is_local, updated_at, device, clock, field_clock)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, '', ?7, ?8, ?9, ?10, ?11, ?12, ?13);
 
         ",
-            &[
-                &note.title,
-                &note.uuid4,
-                &note.url,
-                &make_tags(&note.tags),
-                &note.description,
-                &note.comments,
-                &decoded as &dyn ToSql,
-                // &note.annotations,
-                &note.created_at,
-                &note.is_public as &dyn ToSql,
+            params![
+                note.title,
+                note.uuid4,
+                format!("mime://{}", mime),
+                make_tags(&note.tags),
+                note.description,
+                note.comments,
+                note.created_at,
+                note.is_public,
+                note.is_local,
+                version.updated_at,
+                version.device,
+                clock,
+                fields,
             ],
         )?;
-    }
-    {
-        // mark is_last_note = 0 to indicate out of sync, i.e. db > ssb
-        tx.execute(
-            "
-        UPDATE ssb SET is_last_note = 0
-        WHERE is_active_author = 1
-        ",
-            [],
-        )?;
+        add_attachment(&tx, &note.uuid4, &decoded)?;
     }
     tx.commit()?;
     Ok(())
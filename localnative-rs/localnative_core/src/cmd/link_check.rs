@@ -0,0 +1,211 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+use futures::prelude::*;
+use rusqlite::types::ToSql;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::runtime::Runtime;
+
+pub const DEFAULT_CONCURRENCY: usize = 8;
+pub const DEFAULT_TIMEOUT_SECS: u64 = 10;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Default)]
+pub struct LinkStatus {
+    pub uuid4: String,
+    // 0 when the request failed before any response, see error
+    pub status: u16,
+    pub redirect_url: String,
+    pub error: String,
+    pub checked_at: String,
+}
+
+impl LinkStatus {
+    pub fn is_dead(&self) -> bool {
+        self.status == 0 || self.status >= 400
+    }
+    pub fn is_redirected(&self) -> bool {
+        !self.redirect_url.is_empty()
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct LinkCheckSummary {
+    pub checked: u32,
+    pub dead: u32,
+    pub redirected: u32,
+}
+
+// sql condition on note for the `is:` query filters
+pub fn is_filter_clause(name: &str) -> Option<&'static str> {
+    match name {
+        "dead" => Some(
+            "uuid4 in (select uuid4 from link_status
+            where status = 0 or status >= 400)",
+        ),
+        "redirected" => Some(
+            "uuid4 in (select uuid4 from link_status
+            where redirect_url != '')",
+        ),
+        _ => None,
+    }
+}
+
+// notes with an http(s) url, least recently checked first
+pub fn link_check_candidates(
+    conn: &Connection,
+    limit: u32,
+) -> anyhow::Result<Vec<(String, String)>> {
+    let mut stmt = conn.prepare(
+        "SELECT note.uuid4, note.url
+        FROM note left join link_status on note.uuid4 = link_status.uuid4
+        where note.url like 'http://%' or note.url like 'https://%'
+        order by ifnull(link_status.checked_at, '') limit :limit",
+    )?;
+    let iter = stmt.query_map(&[(":limit", &limit as &dyn ToSql)], |row| {
+        Ok((row.get(0)?, row.get(1)?))
+    })?;
+    let mut r = Vec::new();
+    for c in iter {
+        r.push(c?);
+    }
+    Ok(r)
+}
+
+pub async fn check_url(client: &reqwest::Client, uuid4: String, url: String) -> LinkStatus {
+    let mut resp = client.head(&url).send().await;
+    // some servers reject or mishandle HEAD, retry those with GET
+    let retry = match &resp {
+        Ok(r) => {
+            r.status() == reqwest::StatusCode::METHOD_NOT_ALLOWED
+                || r.status() == reqwest::StatusCode::NOT_IMPLEMENTED
+        }
+        Err(_) => true,
+    };
+    if retry {
+        resp = client.get(&url).send().await;
+    }
+    let checked_at = crate::exe::created_time();
+    match resp {
+        Ok(r) => {
+            let redirect_url = if r.url().as_str() != url {
+                r.url().to_string()
+            } else {
+                "".to_string()
+            };
+            LinkStatus {
+                uuid4,
+                status: r.status().as_u16(),
+                redirect_url,
+                error: "".to_string(),
+                checked_at,
+            }
+        }
+        Err(err) => LinkStatus {
+            uuid4,
+            status: 0,
+            redirect_url: "".to_string(),
+            error: err.to_string(),
+            checked_at,
+        },
+    }
+}
+
+pub async fn check_urls(
+    targets: Vec<(String, String)>,
+    concurrency: usize,
+    timeout: Duration,
+) -> anyhow::Result<Vec<LinkStatus>> {
+    let client = super::utils::http_client(timeout)?;
+    let client = &client;
+    let r = stream::iter(targets)
+        .map(|(uuid4, url)| check_url(client, uuid4, url))
+        .buffer_unordered(concurrency.max(1))
+        .collect()
+        .await;
+    Ok(r)
+}
+
+pub fn save_link_status(conn: &Connection, statuses: &[LinkStatus]) -> anyhow::Result<()> {
+    conn.execute_batch("BEGIN;")?;
+    {
+        let mut stmt = conn.prepare(
+            "INSERT OR REPLACE INTO link_status (uuid4, status, redirect_url, error, checked_at)
+            VALUES (?1, ?2, ?3, ?4, ?5)",
+        )?;
+        for s in statuses {
+            stmt.execute(params![
+                s.uuid4,
+                s.status,
+                s.redirect_url,
+                s.error,
+                s.checked_at
+            ])?;
+        }
+    }
+    conn.execute_batch("COMMIT;")?;
+    Ok(())
+}
+
+pub fn get_link_status(
+    conn: &Connection,
+    uuid4s: &[String],
+) -> anyhow::Result<HashMap<String, LinkStatus>> {
+    let mut r = HashMap::new();
+    let mut stmt = conn.prepare(
+        "SELECT uuid4, status, redirect_url, error, checked_at
+        FROM link_status where uuid4 = ?",
+    )?;
+    for uuid4 in uuid4s {
+        let status = stmt
+            .query_row([uuid4], |row| {
+                Ok(LinkStatus {
+                    uuid4: row.get(0)?,
+                    status: row.get(1)?,
+                    redirect_url: row.get(2)?,
+                    error: row.get(3)?,
+                    checked_at: row.get(4)?,
+                })
+            })
+            .optional()?;
+        if let Some(s) = status {
+            r.insert(uuid4.to_string(), s);
+        }
+    }
+    Ok(r)
+}
+
+pub fn check_links(
+    conn: &Connection,
+    limit: u32,
+    concurrency: usize,
+    timeout: Duration,
+) -> anyhow::Result<LinkCheckSummary> {
+    let targets = link_check_candidates(conn, limit)?;
+    #[cfg(not(feature = "no_print"))]
+    eprintln!("check_links targets len: {}", targets.len());
+    let rt = Runtime::new()?;
+    let statuses = rt.block_on(check_urls(targets, concurrency, timeout))?;
+    save_link_status(conn, &statuses)?;
+    Ok(LinkCheckSummary {
+        checked: statuses.len() as u32,
+        dead: statuses.iter().filter(|s| s.is_dead()).count() as u32,
+        redirected: statuses.iter().filter(|s| s.is_redirected()).count() as u32,
+    })
+}
@@ -18,46 +18,69 @@
 use crate::Note;
 use linked_hash_set::LinkedHashSet;
 use rusqlite::types::ToSql;
-use rusqlite::Connection;
+use rusqlite::{Connection, OpenFlags};
+pub mod archive;
+pub mod attachment;
+pub mod bundle;
+pub mod conflict;
+pub mod feed;
 mod filter;
+pub mod folder;
 pub mod image;
+pub mod link_check;
+pub mod peer;
+pub mod preview;
+pub mod publish;
+pub mod s3;
 mod search;
+pub mod segment;
 mod select;
 pub mod sync;
+pub mod sync_filter;
 mod utils;
+pub mod visibility;
 pub use self::filter::{filter, filter_by_tag, filter_count};
 pub use self::search::{search, search_by_day, search_by_tag, search_count};
 pub use self::select::{select, select_by_day, select_by_tag, select_count};
 
-pub fn sync_via_attach(conn: &Connection, uri: &str) -> String {
-    if conn.execute("attach ? as 'other'", &[uri]).is_ok() {
-        match conn.execute_batch("BEGIN;
-        insert into main.note (uuid4, title, url, tags, description, comments, annotations, created_at, is_public)
-        select uuid4, title, url, tags, description, comments, annotations, created_at, is_public
-from other.note
-        where not exists (
-            select 1 from main.note
-            where
-            main.note.uuid4 = other.note.uuid4
-        ) order by created_at;
-
-        insert into other.note (uuid4, title, url, tags, description, comments, annotations, created_at, is_public)
-        select uuid4, title, url, tags, description, comments, annotations, created_at, is_public
-from main.note
-        where not exists (
-            select 1 from other.note
-            where
-            other.note.uuid4 = main.note.uuid4
-        ) order by created_at;
+// notes go both ways through merge_notes, so versions, edits and conflicts
+// are handled as in any other sync, then the attachments missing on either
+// side by note and content hash; a non empty filter is a search query only
+// the notes sent have to match
+pub fn sync_via_attach(conn: &Connection, uri: &str, filter: &str) -> String {
+    if let Err(err) = merge_attached(conn, uri, filter) {
+        eprintln!("Err {:?}", err);
+        return format!(
+            r#"{{"error": "{}. sync-via-attach: version may not match, upgrade both to latest version and try again."}}"#,
+            err
+        );
+    }
+    if let Err(err) = sync_filter::stage_attach(conn, uri, filter) {
+        return format!(r#"{{"error": "sync filter: {}"}}"#, err);
+    }
+    if conn
+        .execute(crate::encryption::attach_plaintext_sql(), [uri])
+        .is_ok()
+    {
+        match conn.execute_batch(&format!(
+            "BEGIN;
+        {}
+        {}
         COMMIT;
         detach database other;
-        "){
+        ",
+            copy_attachments_sql(filter, "other", "main"),
+            copy_attachments_sql(filter, "main", "other")
+        )) {
             Ok(_) => {
                 format!(r#"{{"sync-via-attach-done": "{}"}}"#, uri)
             }
             Err(err) => {
                 eprintln!("Err {:?}", err);
-                format!(r#"{{"error": "{}. sync-via-attach: version may not match, upgrade both to latest version and try again."}}"#, err)
+                format!(
+                    r#"{{"error": "{}. sync-via-attach: version may not match, upgrade both to latest version and try again."}}"#,
+                    err
+                )
             }
         }
     } else {
@@ -65,6 +88,50 @@ from main.note
     }
 }
 
+// the file's notes merged here first, so when this side's notes go back
+// every conflict is already settled by this device's policy
+fn merge_attached(conn: &Connection, uri: &str, filter: &str) -> anyhow::Result<()> {
+    let other = sync::open_attached(uri, OpenFlags::SQLITE_OPEN_READ_WRITE)?;
+    let mine = sync_filter::SyncFilter::load(conn, filter)?;
+    let theirs = sync_filter::SyncFilter::load(&other, filter)?;
+    let mut pulled = sync::MergeSummary::default();
+    sync::notes_behind(&other, conn, &theirs, |notes| {
+        pulled += sync::merge_notes(conn, notes)?;
+        Ok(())
+    })?;
+    let mut pushed = sync::MergeSummary::default();
+    sync::notes_behind(conn, &other, &mine, |notes| {
+        pushed += sync::merge_notes(&other, notes)?;
+        Ok(())
+    })?;
+    #[cfg(not(feature = "no_print"))]
+    eprintln!("sync via attach pulled {:?}, pushed {:?}", pulled, pushed);
+    Ok(())
+}
+
+// attachments of from's notes that to holds and syncs but lacks and has not removed
+fn copy_attachments_sql(filter: &str, from: &'static str, to: &'static str) -> String {
+    format!(
+        "insert into {to}.attachment (uuid4, note_uuid4, mime, size, sha256, data, thumbnail, created_at)
+        select uuid4, note_uuid4, mime, size, sha256, data, thumbnail, created_at
+from {from}.attachment
+        where exists (
+            select 1 from {to}.note
+            where {to}.note.uuid4 = {from}.attachment.note_uuid4 and {to}.note.is_local = 0
+        ) {} and not exists (
+            select 1 from {to}.attachment
+            where {to}.attachment.note_uuid4 = {from}.attachment.note_uuid4
+            and {to}.attachment.sha256 = {from}.attachment.sha256
+            or {to}.attachment.uuid4 = {from}.attachment.uuid4
+        ) and not exists (
+            select 1 from {to}.attachment_tombstone
+            where {to}.attachment_tombstone.note_uuid4 = {from}.attachment.note_uuid4
+            and {to}.attachment_tombstone.sha256 = {from}.attachment.sha256
+        ) order by rowid;",
+        sync_filter::attach_condition(filter, from, "attachment.note_uuid4")
+    )
+}
+
 pub fn count(conn: &Connection, tbl: &str) -> anyhow::Result<i64> {
     let mut stmt = conn.prepare(&format!("select count(1) as cnt from {}", tbl))?;
     let count = stmt.query_row([], |row| row.get(0))?;
@@ -72,6 +139,18 @@ pub fn count(conn: &Connection, tbl: &str) -> anyhow::Result<i64> {
 }
 
 pub fn delete(conn: &Connection, rowid: i64) -> anyhow::Result<()> {
+    conn.execute(
+        "delete from archive where uuid4 in (select uuid4 from note where rowid = ?1)",
+        [rowid],
+    )?;
+    conn.execute(
+        "delete from link_status where uuid4 in (select uuid4 from note where rowid = ?1)",
+        [rowid],
+    )?;
+    conn.execute(
+        "delete from attachment where note_uuid4 in (select uuid4 from note where rowid = ?1)",
+        [rowid],
+    )?;
     conn.execute("delete from note where rowid = ?1", &[&rowid])?;
     Ok(())
 }
@@ -91,10 +170,17 @@ pub fn insert(note: Note) -> anyhow::Result<()> {
     let conn = &mut super::exe::get_sqlite_connection();
     let tx = conn.transaction()?;
     {
+        // notes from another device keep their version
+        let version = if note.version.device.is_empty() {
+            conflict::new_version(&tx, &note.created_at)?
+        } else {
+            note.version.clone()
+        };
+        let (clock, fields) = conflict::encode_version(&version)?;
         tx.execute(
             "
-        INSERT INTO note (uuid4, title, url, tags, description, comments, annotations, created_at, is_public)
-        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9);
+        INSERT INTO note (uuid4, title, url, tags, description, comments, annotations, created_at, is_public, is_local, updated_at, device, clock, field_clock)
+        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14);
 
         ",
             &[
@@ -107,6 +193,11 @@ pub fn insert(note: Note) -> anyhow::Result<()> {
                 &note.annotations,
                 &note.created_at,
                 &note.is_public as &dyn ToSql,
+                &note.is_local,
+                &version.updated_at,
+                &version.device,
+                &clock,
+                &fields,
             ],
         )?;
     }
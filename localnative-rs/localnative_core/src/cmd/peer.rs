@@ -0,0 +1,136 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+// devices paired for sync, keyed by their certificate fingerprint
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Default)]
+pub struct Peer {
+    pub fingerprint: String,
+    // address the peer was paired from, for display only
+    pub name: String,
+    pub paired_at: String,
+    // search query picking the notes synced with the peer
+    #[serde(default)]
+    pub filter: String,
+}
+
+pub fn trust_peer(conn: &Connection, fingerprint: &str, name: &str) -> anyhow::Result<Peer> {
+    if fingerprint.is_empty() {
+        return Err(anyhow::anyhow!("peer has no fingerprint"));
+    }
+    let peer = Peer {
+        fingerprint: fingerprint.to_string(),
+        name: name.to_string(),
+        paired_at: crate::exe::created_time(),
+        filter: filter(conn, fingerprint)?,
+    };
+    // pairing again keeps the filter
+    conn.execute(
+        "INSERT INTO peer (fingerprint, name, paired_at) VALUES (?1, ?2, ?3)
+        ON CONFLICT(fingerprint) DO UPDATE SET name = ?2, paired_at = ?3",
+        params![peer.fingerprint, peer.name, peer.paired_at],
+    )?;
+    Ok(peer)
+}
+
+pub fn is_trusted(conn: &Connection, fingerprint: &str) -> anyhow::Result<bool> {
+    let trusted = conn
+        .prepare("select 1 FROM peer where fingerprint = ?")?
+        .exists([fingerprint])?;
+    Ok(trusted)
+}
+
+pub fn list_peers(conn: &Connection) -> anyhow::Result<Vec<Peer>> {
+    let mut stmt = conn.prepare(
+        "SELECT fingerprint, name, paired_at, filter
+        FROM peer order by paired_at",
+    )?;
+    let iter = stmt.query_map([], |row| {
+        Ok(Peer {
+            fingerprint: row.get(0)?,
+            name: row.get(1)?,
+            paired_at: row.get(2)?,
+            filter: row.get(3)?,
+        })
+    })?;
+    let mut r = Vec::new();
+    for p in iter {
+        r.push(p?);
+    }
+    Ok(r)
+}
+
+// false if the peer is not paired
+pub fn set_filter(conn: &Connection, fingerprint: &str, filter: &str) -> anyhow::Result<bool> {
+    let n = conn.execute(
+        "update peer set filter = ?2 where fingerprint = ?1",
+        params![fingerprint, filter.trim()],
+    )?;
+    Ok(n > 0)
+}
+
+// empty for a peer without one, or one reached over a transport that
+// vouches for it without pairing
+pub fn filter(conn: &Connection, fingerprint: &str) -> anyhow::Result<String> {
+    let filter = conn
+        .query_row(
+            "select filter FROM peer where fingerprint = ?1",
+            [fingerprint],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(filter.unwrap_or_default())
+}
+
+fn last_sync_key(fingerprint: &str) -> String {
+    format!("peer_sync:{}", fingerprint)
+}
+
+// when the peer last synced with this device as its server
+pub fn set_last_sync(conn: &Connection, fingerprint: &str, time: &str) -> anyhow::Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO meta (meta_key, meta_value) VALUES (?1, ?2)",
+        params![last_sync_key(fingerprint), time],
+    )?;
+    Ok(())
+}
+
+// empty if it never did
+pub fn last_sync(conn: &Connection, fingerprint: &str) -> anyhow::Result<String> {
+    let time = conn
+        .query_row(
+            "select meta_value FROM meta where meta_key = ?1",
+            [last_sync_key(fingerprint)],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(time.unwrap_or_default())
+}
+
+pub fn revoke_peer(conn: &Connection, fingerprint: &str) -> anyhow::Result<bool> {
+    let n = conn.execute("delete from peer where fingerprint = ?1", [fingerprint])?;
+    conn.execute(
+        "delete from meta where meta_key = ?1",
+        [last_sync_key(fingerprint)],
+    )?;
+    // pairing again starts with a full sync
+    super::sync::clear_high_water(conn, fingerprint)?;
+    super::sync_filter::forget(conn, fingerprint)?;
+    Ok(n > 0)
+}
@@ -0,0 +1,143 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+// dry run of a sync: which notes would go each way and which would clash,
+// worked out from reads alone so nothing changes on either side
+use super::bundle::{deleted_by_tombstones, Tombstone};
+use super::conflict::{compare, get_note, Order};
+use super::sync::{notes_behind, open_attached};
+use super::sync_filter::SyncFilter;
+use crate::rpc::progress::note_bytes;
+use crate::Note;
+use rusqlite::{Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct PreviewNote {
+    pub uuid4: String,
+    pub title: String,
+    pub url: String,
+}
+
+impl From<&Note> for PreviewNote {
+    fn from(note: &Note) -> Self {
+        PreviewNote {
+            uuid4: note.uuid4.clone(),
+            title: note.title.clone(),
+            url: note.url.clone(),
+        }
+    }
+}
+
+// a note already here changes by an update, a conflict or, with a peer
+// synced without a filter, a delete made there
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Default)]
+pub struct SyncPreview {
+    // notes the peer lacks or holds an older version of
+    pub push: Vec<PreviewNote>,
+    // notes new to this device
+    pub pull: Vec<PreviewNote>,
+    // notes here a newer version from the peer would replace
+    pub update: Vec<PreviewNote>,
+    // edited on both sides since they last agreed, settled by the conflict policy
+    pub conflicts: Vec<PreviewNote>,
+    // notes here the peer deleted and no one edited since
+    pub delete: Vec<PreviewNote>,
+    // deletes made here the peer is sent, it keeps notes edited there since
+    pub tombstones: usize,
+    // note payload both ways
+    pub bytes: u64,
+}
+
+impl SyncPreview {
+    pub fn is_empty(&self) -> bool {
+        self.push.is_empty()
+            && self.pull.is_empty()
+            && self.update.is_empty()
+            && self.conflicts.is_empty()
+            && self.delete.is_empty()
+            && self.tombstones == 0
+    }
+
+    fn add_conflict(&mut self, note: &Note) {
+        if !self.conflicts.iter().any(|c| c.uuid4 == note.uuid4) {
+            self.conflicts.push(note.into());
+        }
+    }
+
+    // notes to send, theirs are the peer's copies of them where it has one
+    pub fn add_push(&mut self, notes: &[Note], theirs: &[Note]) {
+        for note in notes {
+            let clash = theirs.iter().any(|their| {
+                their.uuid4 == note.uuid4
+                    && compare(&their.version.clock, &note.version.clock) == Order::Concurrent
+            });
+            if clash {
+                self.add_conflict(note);
+            }
+            self.push.push(note.into());
+        }
+        self.bytes += note_bytes(notes);
+    }
+
+    // notes fetched from the peer, sorted the way merge_notes would take them
+    pub fn add_pull(&mut self, conn: &Connection, notes: &[Note]) -> anyhow::Result<()> {
+        for note in notes.iter().filter(|note| !note.is_local) {
+            match get_note(conn, &note.uuid4)? {
+                None => self.pull.push(note.into()),
+                Some(local) if local.is_local => {}
+                Some(local) => match compare(&local.version.clock, &note.version.clock) {
+                    Order::Equal | Order::After => {}
+                    Order::Before => self.update.push(note.into()),
+                    Order::Concurrent => self.add_conflict(note),
+                },
+            }
+        }
+        self.bytes += note_bytes(notes);
+        Ok(())
+    }
+
+    // tombstones fetched from the peer
+    pub fn add_tombstones(
+        &mut self,
+        conn: &Connection,
+        tombstones: &[Tombstone],
+    ) -> anyhow::Result<()> {
+        for note in deleted_by_tombstones(conn, tombstones)? {
+            self.delete.push((&note).into());
+        }
+        Ok(())
+    }
+}
+
+// what sync_via_attach would merge each way, from the same comparisons
+pub fn preview_attach(conn: &Connection, uri: &str, filter: &str) -> anyhow::Result<SyncPreview> {
+    let other = open_attached(uri, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let mine = SyncFilter::load(conn, filter)?;
+    let theirs = SyncFilter::load(&other, filter)?;
+    let mut preview = SyncPreview::default();
+    notes_behind(&other, conn, &theirs, |notes| preview.add_pull(conn, notes))?;
+    notes_behind(conn, &other, &mine, |notes| {
+        let mut copies = Vec::new();
+        for note in notes {
+            copies.extend(get_note(&other, &note.uuid4)?);
+        }
+        preview.add_push(notes, &copies);
+        Ok(())
+    })?;
+    Ok(preview)
+}
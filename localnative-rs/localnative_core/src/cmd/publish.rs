@@ -0,0 +1,396 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+// renders public notes into a static site: a paginated index, a page per tag,
+// a page per month and a permalink per note
+use minijinja::{context, Environment};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_PER_PAGE: usize = 20;
+// left in the output directory so a later publish knows it may clear it
+const MARKER_FILE: &str = ".localnative-site";
+// generated sub directories, removed before every publish
+const NOTE_DIR: &str = "note";
+const TAG_DIR: &str = "tag";
+const MONTH_DIR: &str = "archive";
+
+// bundled templates, a file with the same name in the template directory wins
+const TEMPLATES: &[(&str, &str)] = &[
+    (
+        "layout.html",
+        include_str!("../../templates/publish/layout.html"),
+    ),
+    (
+        "macros.html",
+        include_str!("../../templates/publish/macros.html"),
+    ),
+    (
+        "list.html",
+        include_str!("../../templates/publish/list.html"),
+    ),
+    (
+        "note.html",
+        include_str!("../../templates/publish/note.html"),
+    ),
+];
+const STYLE: (&str, &str) = (
+    "style.css",
+    include_str!("../../templates/publish/style.css"),
+);
+
+#[derive(Debug, Clone)]
+pub struct PublishOptions {
+    pub out_dir: PathBuf,
+    pub template_dir: Option<PathBuf>,
+    pub per_page: usize,
+    pub title: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+pub struct PublishSummary {
+    pub notes: usize,
+    pub pages: usize,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct Link {
+    name: String,
+    href: String,
+    count: usize,
+}
+
+#[derive(Serialize, Debug, Clone)]
+struct PageNote {
+    uuid4: String,
+    title: String,
+    url: String,
+    // only http(s) urls are rendered as links
+    link: bool,
+    description: String,
+    comments: String,
+    created_at: String,
+    date: String,
+    href: String,
+    tags: Vec<Link>,
+}
+
+#[derive(Serialize, Debug)]
+struct Site {
+    title: String,
+    tags: Vec<Link>,
+    months: Vec<Link>,
+}
+
+#[derive(Serialize, Debug)]
+struct Pagination {
+    page: usize,
+    pages: usize,
+    prev: Option<String>,
+    next: Option<String>,
+}
+
+// public notes, newest first; local only notes are never published
+pub fn public_notes(conn: &Connection) -> anyhow::Result<Vec<crate::Note>> {
+    let mut stmt = conn.prepare(
+        "SELECT rowid, uuid4, title, url, tags, description, comments, annotations, created_at
+        FROM note where is_public = 1 and is_local = 0
+        order by created_at desc",
+    )?;
+    let iter = stmt.query_map([], |row| {
+        Ok(crate::Note {
+            rowid: row.get(0)?,
+            uuid4: row.get(1)?,
+            title: row.get(2)?,
+            url: row.get(3)?,
+            tags: row.get(4)?,
+            description: row.get(5)?,
+            comments: row.get(6)?,
+            annotations: row.get(7)?,
+            created_at: row.get(8)?,
+            is_public: true,
+            is_local: false,
+            version: Default::default(),
+        })
+    })?;
+    let mut r = Vec::new();
+    for note in iter {
+        r.push(note?);
+    }
+    Ok(r)
+}
+
+// file name for a tag, anything but letters, digits, '-' and '_' becomes '_'
+pub fn tag_slug(tag: &str) -> String {
+    tag.chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+// the page of each tag; tags whose slugs clash, even only in case, get a
+// numbered suffix rather than share a page
+fn tag_slugs<'a>(tags: impl Iterator<Item = &'a str>) -> HashMap<String, String> {
+    let mut taken = HashSet::new();
+    let mut slugs = HashMap::new();
+    for tag in tags.collect::<BTreeSet<_>>() {
+        let base = tag_slug(tag);
+        let mut slug = base.clone();
+        let mut n = 1;
+        while !taken.insert(slug.to_lowercase()) {
+            n += 1;
+            slug = format!("{}-{}", base, n);
+        }
+        slugs.insert(tag.to_string(), slug);
+    }
+    slugs
+}
+
+fn note_tags(note: &crate::Note) -> impl Iterator<Item = &str> {
+    note.tags.split(',').filter(|t| !t.is_empty())
+}
+
+// `YYYY-MM` of created_at, which names the archive page; none for anything
+// else a peer or an import put there
+fn note_month(created_at: &str) -> Option<&str> {
+    let month = created_at.get(..7)?;
+    let valid = month.bytes().enumerate().all(|(i, c)| {
+        if i == 4 {
+            c == b'-'
+        } else {
+            c.is_ascii_digit()
+        }
+    });
+    valid.then_some(month)
+}
+
+fn page_href(page: usize) -> String {
+    if page == 1 {
+        "index.html".to_string()
+    } else {
+        format!("page-{}.html", page)
+    }
+}
+
+fn to_page_note(note: &crate::Note, uuid: uuid::Uuid, slugs: &HashMap<String, String>) -> PageNote {
+    let tags = note_tags(note)
+        .map(|t| Link {
+            name: t.to_string(),
+            href: format!("{}/{}.html", TAG_DIR, slugs[t]),
+            count: 0,
+        })
+        .collect();
+    PageNote {
+        uuid4: note.uuid4.clone(),
+        title: note.title.clone(),
+        url: note.url.clone(),
+        link: note.url.starts_with("http://") || note.url.starts_with("https://"),
+        description: note.description.clone(),
+        comments: note.comments.clone(),
+        created_at: note.created_at.clone(),
+        date: note.created_at.chars().take(10).collect(),
+        href: format!("{}/{}.html", NOTE_DIR, uuid),
+        tags,
+    }
+}
+
+fn load_templates(template_dir: Option<&Path>) -> anyhow::Result<Environment<'static>> {
+    let mut env = Environment::new();
+    for (name, bundled) in TEMPLATES {
+        let source = match template_dir.map(|dir| dir.join(name)) {
+            Some(path) if path.exists() => fs::read_to_string(path)?,
+            _ => bundled.to_string(),
+        };
+        env.add_template_owned(*name, source)?;
+    }
+    Ok(env)
+}
+
+// refuses to touch a non-empty directory that an earlier publish did not create
+fn prepare_out_dir(out_dir: &Path) -> anyhow::Result<()> {
+    if out_dir.exists() {
+        let is_empty = fs::read_dir(out_dir)?.next().is_none();
+        if !is_empty && !out_dir.join(MARKER_FILE).exists() {
+            return Err(anyhow::anyhow!(
+                "{} is not empty and was not created by publish",
+                out_dir.display()
+            ));
+        }
+        for dir in [NOTE_DIR, TAG_DIR, MONTH_DIR] {
+            let dir = out_dir.join(dir);
+            if dir.exists() {
+                fs::remove_dir_all(dir)?;
+            }
+        }
+        for entry in fs::read_dir(out_dir)? {
+            let path = entry?.path();
+            let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+            if name.starts_with("page-") && name.ends_with(".html") {
+                fs::remove_file(path)?;
+            }
+        }
+    }
+    for dir in [NOTE_DIR, TAG_DIR, MONTH_DIR] {
+        fs::create_dir_all(out_dir.join(dir))?;
+    }
+    fs::write(out_dir.join(MARKER_FILE), "")?;
+    Ok(())
+}
+
+pub fn publish(conn: &Connection, options: &PublishOptions) -> anyhow::Result<PublishSummary> {
+    // uuid4 names the note's file, anything but a uuid could point outside out_dir
+    let public: Vec<(crate::Note, uuid::Uuid)> = public_notes(conn)?
+        .into_iter()
+        .filter_map(|note| match uuid::Uuid::parse_str(&note.uuid4) {
+            Ok(uuid) => Some((note, uuid)),
+            Err(_err) => {
+                #[cfg(not(feature = "no_print"))]
+                eprintln!("publish skips note, uuid4 is not a uuid: {:?}", note.uuid4);
+                None
+            }
+        })
+        .collect();
+    let slugs = tag_slugs(public.iter().flat_map(|(note, _)| note_tags(note)));
+    let notes: Vec<PageNote> = public
+        .iter()
+        .map(|(note, uuid)| to_page_note(note, *uuid, &slugs))
+        .collect();
+    let env = load_templates(options.template_dir.as_deref())?;
+    prepare_out_dir(&options.out_dir)?;
+
+    let mut by_tag: BTreeMap<String, (String, Vec<PageNote>)> = BTreeMap::new();
+    let mut by_month: BTreeMap<String, Vec<PageNote>> = BTreeMap::new();
+    for note in &notes {
+        for tag in &note.tags {
+            by_tag
+                .entry(slugs[&tag.name].clone())
+                .or_insert_with(|| (tag.name.clone(), Vec::new()))
+                .1
+                .push(note.clone());
+        }
+        match note_month(&note.created_at) {
+            Some(month) => by_month
+                .entry(month.to_string())
+                .or_default()
+                .push(note.clone()),
+            None => {
+                #[cfg(not(feature = "no_print"))]
+                eprintln!(
+                    "publish leaves note {} out of the archive, created_at is {:?}",
+                    note.uuid4, note.created_at
+                );
+            }
+        }
+    }
+    let site = Site {
+        title: options.title.clone(),
+        tags: by_tag
+            .iter()
+            .map(|(slug, (name, notes))| Link {
+                name: name.clone(),
+                href: format!("{}/{}.html", TAG_DIR, slug),
+                count: notes.len(),
+            })
+            .collect(),
+        months: by_month
+            .iter()
+            .rev()
+            .map(|(month, notes)| Link {
+                name: month.clone(),
+                href: format!("{}/{}.html", MONTH_DIR, month),
+                count: notes.len(),
+            })
+            .collect(),
+    };
+
+    let mut pages = 0;
+    let mut write = |path: PathBuf, html: String| -> anyhow::Result<()> {
+        fs::write(path, html)?;
+        pages += 1;
+        Ok(())
+    };
+
+    let list = env.get_template("list.html")?;
+    let per_page = options.per_page.max(1);
+    let total = notes.len().div_ceil(per_page).max(1);
+    for page in 1..=total {
+        let start = (page - 1) * per_page;
+        let chunk = &notes[start.min(notes.len())..(start + per_page).min(notes.len())];
+        let pagination = Pagination {
+            page,
+            pages: total,
+            prev: (page > 1).then(|| page_href(page - 1)),
+            next: (page < total).then(|| page_href(page + 1)),
+        };
+        let html = list.render(context! {
+            site => &site, root => "", notes => chunk, pagination => pagination,
+        })?;
+        write(options.out_dir.join(page_href(page)), html)?;
+    }
+    for (slug, (name, tag_notes)) in &by_tag {
+        let html = list.render(context! {
+            site => &site, root => "../", notes => tag_notes, heading => name,
+        })?;
+        write(
+            options.out_dir.join(TAG_DIR).join(format!("{}.html", slug)),
+            html,
+        )?;
+    }
+    for (month, month_notes) in &by_month {
+        let html = list.render(context! {
+            site => &site, root => "../", notes => month_notes, heading => month,
+        })?;
+        write(
+            options
+                .out_dir
+                .join(MONTH_DIR)
+                .join(format!("{}.html", month)),
+            html,
+        )?;
+    }
+    let note_template = env.get_template("note.html")?;
+    for note in &notes {
+        let html = note_template.render(context! {
+            site => &site, root => "../", note => note,
+        })?;
+        write(options.out_dir.join(&note.href), html)?;
+    }
+
+    let (style_name, bundled_style) = STYLE;
+    let style = match options
+        .template_dir
+        .as_ref()
+        .map(|dir| dir.join(style_name))
+    {
+        Some(path) if path.exists() => fs::read(path)?,
+        _ => bundled_style.as_bytes().to_vec(),
+    };
+    fs::write(options.out_dir.join(style_name), style)?;
+
+    Ok(PublishSummary {
+        notes: notes.len(),
+        pages,
+    })
+}
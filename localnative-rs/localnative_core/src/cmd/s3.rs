@@ -0,0 +1,433 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+// segments kept in an s3 compatible bucket (aws, minio, r2, b2 and the like),
+// so devices never online at the same time still converge. every object is
+// sealed with a key derived from a passphrase all devices share, the storage
+// provider only ever sees device ids and seqs in the object names:
+//
+// <bucket>/<prefix>/<device id>/<until>.lnseg
+use super::segment::{sync_store, Segment, SegmentStore, SegmentSummary};
+use crate::exe::sqlite3_db_location;
+use reqwest::{Method, Url};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::{hmac, pbkdf2};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use time::macros::format_description;
+use tokio::runtime::Runtime;
+
+pub const CONFIG_FILE: &str = "s3_sync.json";
+const EXTENSION: &str = "lnseg";
+const PBKDF2_ITERATIONS: u32 = 100_000;
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct S3Config {
+    // e.g. https://s3.us-east-1.amazonaws.com or http://127.0.0.1:9000
+    pub endpoint: String,
+    #[serde(default = "default_region")]
+    pub region: String,
+    pub bucket: String,
+    // the bucket can be shared with other data
+    #[serde(default = "default_prefix")]
+    pub prefix: String,
+    pub access_key: String,
+    pub secret_key: String,
+    // the same on every device, never sent anywhere
+    pub passphrase: String,
+}
+
+fn default_region() -> String {
+    "us-east-1".to_string()
+}
+
+fn default_prefix() -> String {
+    "localnative".to_string()
+}
+
+// next to the database, like the sync daemon config
+pub fn config_path() -> PathBuf {
+    let location = sqlite3_db_location();
+    Path::new(&location)
+        .parent()
+        .map_or_else(|| PathBuf::from(CONFIG_FILE), |dir| dir.join(CONFIG_FILE))
+}
+
+pub fn load_config(path: &Path) -> anyhow::Result<S3Config> {
+    let config: S3Config = serde_json::from_str(&fs::read_to_string(path)?)?;
+    Url::parse(&config.endpoint)
+        .map_err(|err| anyhow::anyhow!("endpoint {} invalid: {}", config.endpoint, err))?;
+    if config.bucket.is_empty() {
+        return Err(anyhow::anyhow!("bucket is empty"));
+    }
+    if config.passphrase.is_empty() {
+        return Err(anyhow::anyhow!("passphrase is empty"));
+    }
+    Ok(config)
+}
+
+pub struct Credentials {
+    pub access_key: String,
+    pub secret_key: String,
+    pub region: String,
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+// everything but the unreserved characters, '/' kept in paths
+fn uri_encode(s: &str, keep_slash: bool) -> String {
+    let mut r = String::new();
+    for b in s.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                r.push(b as char)
+            }
+            b'/' if keep_slash => r.push('/'),
+            _ => r.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    r
+}
+
+fn hmac_sha256(key: &[u8], data: &str) -> Vec<u8> {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, key);
+    hmac::sign(&key, data.as_bytes()).as_ref().to_vec()
+}
+
+// authorization header of aws signature version 4. headers are lower case and
+// must include host, x-amz-date and x-amz-content-sha256, all of them signed
+pub fn authorization(
+    creds: &Credentials,
+    method: &str,
+    path: &str,
+    query: &[(String, String)],
+    headers: &[(String, String)],
+) -> String {
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|(n, _)| n == name)
+            .map_or("", |(_, v)| v.as_str())
+    };
+    let amz_date = header("x-amz-date");
+    let scope = format!(
+        "{}/{}/s3/aws4_request",
+        &amz_date[..amz_date.len().min(8)],
+        creds.region
+    );
+
+    let mut query: Vec<String> = query
+        .iter()
+        .map(|(k, v)| format!("{}={}", uri_encode(k, false), uri_encode(v, false)))
+        .collect();
+    query.sort();
+    let mut headers: Vec<(String, String)> = headers
+        .iter()
+        .map(|(n, v)| (n.to_lowercase(), v.trim().to_string()))
+        .collect();
+    headers.sort();
+    let signed = headers
+        .iter()
+        .map(|(n, _)| n.as_str())
+        .collect::<Vec<_>>()
+        .join(";");
+    let canonical = format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        method,
+        uri_encode(path, true),
+        query.join("&"),
+        headers
+            .iter()
+            .map(|(n, v)| format!("{}:{}\n", n, v))
+            .collect::<String>(),
+        signed,
+        header("x-amz-content-sha256")
+    );
+    let to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        sha256_hex(canonical.as_bytes())
+    );
+
+    let mut key = format!("AWS4{}", creds.secret_key).into_bytes();
+    for part in scope.split('/') {
+        key = hmac_sha256(&key, part);
+    }
+    format!(
+        "AWS4-HMAC-SHA256 Credential={}/{},SignedHeaders={},Signature={}",
+        creds.access_key,
+        scope,
+        signed,
+        hex::encode(hmac_sha256(&key, &to_sign))
+    )
+}
+
+// the text of every <tag> in a response, entities decoded
+fn xml_values(body: &str, tag: &str) -> Vec<String> {
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+    let mut r = Vec::new();
+    let mut rest = body;
+    while let Some(start) = rest.find(&open) {
+        rest = &rest[start + open.len()..];
+        let Some(end) = rest.find(&close) else {
+            break;
+        };
+        r.push(
+            rest[..end]
+                .replace("&lt;", "<")
+                .replace("&gt;", ">")
+                .replace("&quot;", "\"")
+                .replace("&apos;", "'")
+                .replace("&amp;", "&"),
+        );
+        rest = &rest[end + close.len()..];
+    }
+    r
+}
+
+pub struct S3Store {
+    config: S3Config,
+    endpoint: Url,
+    key: LessSafeKey,
+    client: reqwest::Client,
+    rt: Runtime,
+}
+
+impl S3Store {
+    pub fn open(config: &S3Config) -> anyhow::Result<Self> {
+        let endpoint = Url::parse(&config.endpoint)?;
+        let mut key = [0u8; 32];
+        pbkdf2::derive(
+            pbkdf2::PBKDF2_HMAC_SHA256,
+            NonZeroU32::new(PBKDF2_ITERATIONS).unwrap(),
+            format!("localnative-s3:{}/{}", config.bucket, config.prefix).as_bytes(),
+            config.passphrase.as_bytes(),
+            &mut key,
+        );
+        let key = LessSafeKey::new(
+            UnboundKey::new(&CHACHA20_POLY1305, &key)
+                .map_err(|_| anyhow::anyhow!("cannot derive segment key"))?,
+        );
+        Ok(S3Store {
+            config: config.clone(),
+            endpoint,
+            key,
+            client: reqwest::Client::builder().timeout(TIMEOUT).build()?,
+            rt: Runtime::new()?,
+        })
+    }
+
+    fn object_key(&self, device: &str, until: i64) -> String {
+        format!(
+            "{}/{}/{:020}.{}",
+            self.config.prefix, device, until, EXTENSION
+        )
+    }
+
+    fn parse_key(&self, key: &str) -> Option<Segment> {
+        let rest = key.strip_prefix(&self.config.prefix)?.strip_prefix('/')?;
+        let (device, name) = rest.split_once('/')?;
+        let until = name
+            .strip_suffix(EXTENSION)?
+            .strip_suffix('.')?
+            .parse()
+            .ok()?;
+        Some(Segment {
+            device: device.to_string(),
+            until,
+            name: key.to_string(),
+        })
+    }
+
+    // path style, virtual hosted buckets need dns most stand-ins lack
+    fn request(
+        &self,
+        method: Method,
+        key: Option<&str>,
+        query: &[(String, String)],
+        body: Vec<u8>,
+    ) -> anyhow::Result<Vec<u8>> {
+        let mut path = format!(
+            "{}/{}",
+            self.endpoint.path().trim_end_matches('/'),
+            self.config.bucket
+        );
+        if let Some(key) = key {
+            path = format!("{}/{}", path, key);
+        }
+        let mut url = self.endpoint.clone();
+        url.set_path(&uri_encode(&path, true));
+        if !query.is_empty() {
+            url.set_query(Some(
+                &query
+                    .iter()
+                    .map(|(k, v)| format!("{}={}", uri_encode(k, false), uri_encode(v, false)))
+                    .collect::<Vec<_>>()
+                    .join("&"),
+            ));
+        }
+        let host = match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            (None, _) => return Err(anyhow::anyhow!("endpoint has no host")),
+        };
+        let amz_date = time::OffsetDateTime::now_utc().format(&format_description!(
+            "[year][month][day]T[hour][minute][second]Z"
+        ))?;
+        let headers = vec![
+            ("host".to_string(), host),
+            ("x-amz-content-sha256".to_string(), sha256_hex(&body)),
+            ("x-amz-date".to_string(), amz_date),
+        ];
+        let creds = Credentials {
+            access_key: self.config.access_key.clone(),
+            secret_key: self.config.secret_key.clone(),
+            region: self.config.region.clone(),
+        };
+        let authorization = authorization(&creds, method.as_str(), &path, query, &headers);
+
+        // reqwest adds the host header itself
+        let mut request = self
+            .client
+            .request(method, url)
+            .header("authorization", authorization);
+        for (name, value) in &headers[1..] {
+            request = request.header(name, value);
+        }
+        self.rt.block_on(async {
+            let response = request.body(body).send().await?;
+            let status = response.status();
+            let bytes = response.bytes().await?;
+            if !status.is_success() {
+                return Err(anyhow::anyhow!(
+                    "{} {}",
+                    status,
+                    xml_values(&String::from_utf8_lossy(&bytes), "Message").join(" ")
+                ));
+            }
+            Ok(bytes.to_vec())
+        })
+    }
+
+    fn seal(&self, object_key: &str, bundle: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let mut nonce = [0u8; NONCE_LEN];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .map_err(|_| anyhow::anyhow!("no randomness for a nonce"))?;
+        let mut sealed = bundle.to_vec();
+        // the object name is bound in, a segment cannot pose as another
+        self.key
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(object_key.as_bytes()),
+                &mut sealed,
+            )
+            .map_err(|_| anyhow::anyhow!("cannot seal segment"))?;
+        let mut r = nonce.to_vec();
+        r.append(&mut sealed);
+        Ok(r)
+    }
+
+    fn open_sealed(&self, object_key: &str, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+        if data.len() < NONCE_LEN {
+            return Err(anyhow::anyhow!("segment truncated"));
+        }
+        let (nonce, sealed) = data.split_at(NONCE_LEN);
+        let mut sealed = sealed.to_vec();
+        let bundle = self
+            .key
+            .open_in_place(
+                Nonce::try_assume_unique_for_key(nonce)
+                    .map_err(|_| anyhow::anyhow!("segment truncated"))?,
+                Aad::from(object_key.as_bytes()),
+                &mut sealed,
+            )
+            .map_err(|_| anyhow::anyhow!("cannot decrypt segment, passphrase differs?"))?;
+        Ok(bundle.to_vec())
+    }
+}
+
+impl SegmentStore for S3Store {
+    fn kind(&self) -> &str {
+        "s3"
+    }
+
+    fn location(&self) -> String {
+        format!(
+            "{}/{}/{}",
+            self.config.endpoint.trim_end_matches('/'),
+            self.config.bucket,
+            self.config.prefix
+        )
+    }
+
+    // ListObjectsV2, a page at a time
+    fn list(&self) -> anyhow::Result<Vec<Segment>> {
+        let mut r = Vec::new();
+        let mut token: Option<String> = None;
+        loop {
+            let mut query = vec![
+                ("list-type".to_string(), "2".to_string()),
+                ("prefix".to_string(), format!("{}/", self.config.prefix)),
+            ];
+            if let Some(token) = token.take() {
+                query.push(("continuation-token".to_string(), token));
+            }
+            let body = self.request(Method::GET, None, &query, Vec::new())?;
+            let body = String::from_utf8_lossy(&body);
+            r.extend(
+                xml_values(&body, "Key")
+                    .iter()
+                    .filter_map(|key| self.parse_key(key)),
+            );
+            if xml_values(&body, "IsTruncated").first().map(|t| t.as_str()) != Some("true") {
+                return Ok(r);
+            }
+            match xml_values(&body, "NextContinuationToken").pop() {
+                Some(next) => token = Some(next),
+                None => return Err(anyhow::anyhow!("truncated listing without a token")),
+            }
+        }
+    }
+
+    fn get(&self, segment: &Segment) -> anyhow::Result<Vec<u8>> {
+        let data = self.request(Method::GET, Some(&segment.name), &[], Vec::new())?;
+        self.open_sealed(&segment.name, &data)
+    }
+
+    fn put(&self, device: &str, until: i64, bundle: &[u8]) -> anyhow::Result<String> {
+        let key = self.object_key(device, until);
+        let sealed = self.seal(&key, bundle)?;
+        self.request(Method::PUT, Some(&key), &[], sealed)?;
+        Ok(key)
+    }
+}
+
+pub fn sync_s3(conn: &Connection, config: &S3Config) -> anyhow::Result<SegmentSummary> {
+    sync_store(conn, &S3Store::open(config)?)
+}
@@ -17,6 +17,7 @@
 */
 use super::make_tags;
 use super::select::{select, select_by_day, select_by_tag, select_count};
+use super::utils::split_filters;
 use crate::{KVStringI64, Note, Tags};
 use regex::Regex;
 use rusqlite::types::ToSql;
@@ -24,12 +25,14 @@ use rusqlite::Connection;
 use std::collections::HashMap;
 
 pub fn search_by_tag(conn: &Connection, query: &str) -> anyhow::Result<String> {
-    if query.is_empty() {
+    let (query, filter_clauses) = split_filters(query);
+    if query.is_empty() && filter_clauses.is_empty() {
         return select_by_tag(conn);
     }
-    let words = make_words(query);
+    let words = make_words(&query);
     let num_words = words.len();
-    let r: Vec<String> = where_vec(num_words);
+    let mut r: Vec<String> = where_vec(num_words);
+    r.extend(filter_clauses.iter().map(|c| c.to_string()));
     let sql = format!(
         "SELECT tags
         FROM note where
@@ -84,13 +87,15 @@ pub fn search_by_tag(conn: &Connection, query: &str) -> anyhow::Result<String> {
 }
 
 pub fn search_by_day(conn: &Connection, query: &str) -> anyhow::Result<String> {
-    if query.is_empty() {
+    let (query, filter_clauses) = split_filters(query);
+    if query.is_empty() && filter_clauses.is_empty() {
         return select_by_day(conn);
     }
-    let words = make_words(query);
+    let words = make_words(&query);
 
     let num_words = words.len();
-    let r: Vec<String> = where_vec(num_words);
+    let mut r: Vec<String> = where_vec(num_words);
+    r.extend(filter_clauses.iter().map(|c| c.to_string()));
     let sql = format!(
         "SELECT substr(created_at, 0, 11) as dt, count(1) as n
         FROM note where
@@ -135,16 +140,18 @@ pub fn search_by_day(conn: &Connection, query: &str) -> anyhow::Result<String> {
 }
 
 pub fn search_count(conn: &Connection, query: &str) -> anyhow::Result<u32> {
-    if query.is_empty() {
+    let (query, filter_clauses) = split_filters(query);
+    if query.is_empty() && filter_clauses.is_empty() {
         return select_count(conn);
     }
 
-    let words = make_words(query);
+    let words = make_words(&query);
     let num_words = words.len();
     #[cfg(not(feature = "no_print"))]
     eprintln!("{} words {:?}", num_words, words);
 
-    let r: Vec<String> = where_vec(num_words);
+    let mut r: Vec<String> = where_vec(num_words);
+    r.extend(filter_clauses.iter().map(|c| c.to_string()));
     let sql = format!(
         "SELECT count(1)
         FROM note where
@@ -187,20 +194,24 @@ pub fn search_count(conn: &Connection, query: &str) -> anyhow::Result<u32> {
 }
 
 pub fn search(conn: &Connection, query: &str, limit: u32, offset: u32) -> anyhow::Result<String> {
-    if query.is_empty() {
+    let (query, filter_clauses) = split_filters(query);
+    if query.is_empty() && filter_clauses.is_empty() {
         return select(conn, limit, offset);
     }
 
-    let words = make_words(query);
+    let words = make_words(&query);
     let num_words = words.len();
     #[cfg(not(feature = "no_print"))]
     eprintln!("{} words {:?}", num_words, words);
 
-    let r: Vec<String> = where_vec(num_words);
+    let mut r: Vec<String> = where_vec(num_words);
+    r.extend(filter_clauses.iter().map(|c| c.to_string()));
     let sql = format!(
         "SELECT rowid, uuid4, title, url, tags, description, comments
-        , hex(annotations)
-        , created_at, is_public
+        , (select thumbnail from attachment
+            where attachment.note_uuid4 = note.uuid4 and thumbnail is not null
+            order by attachment.rowid limit 1)
+        , created_at, is_public, is_local
         FROM note where
         {}
         order by created_at desc limit :limit offset :offset",
@@ -241,6 +252,8 @@ pub fn search(conn: &Connection, query: &str, limit: u32, offset: u32) -> anyhow
             annotations: super::utils::make_data_url(row).unwrap_or_else(|_| "".into()),
             created_at: row.get(8)?,
             is_public: row.get(9)?,
+            is_local: row.get(10)?,
+            version: Default::default(),
         })
     })?;
 
@@ -258,7 +271,7 @@ pub fn search(conn: &Connection, query: &str, limit: u32, offset: u32) -> anyhow
     Ok(j)
 }
 
-fn make_words(query: &str) -> Vec<String> {
+pub(super) fn make_words(query: &str) -> Vec<String> {
     let re1 = Regex::new(r"\s+").unwrap();
     let s1 = re1.replace_all(query, " ");
     s1.trim()
@@ -267,13 +280,13 @@ fn make_words(query: &str) -> Vec<String> {
         .collect::<Vec<String>>()
 }
 
-fn make_keys(num_words: usize) -> Vec<String> {
+pub(super) fn make_keys(num_words: usize) -> Vec<String> {
     (0..num_words)
         .map(|i| ":w".to_string() + &i.to_string())
         .collect()
 }
 
-fn where_vec(num_words: usize) -> Vec<String> {
+pub(super) fn where_vec(num_words: usize) -> Vec<String> {
     (0..num_words)
         .map(|i| {
             format!(
@@ -282,6 +295,7 @@ fn where_vec(num_words: usize) -> Vec<String> {
         or url like :w{i}
         or tags like :w{i}
         or description like :w{i}
+        or uuid4 in (select uuid4 from archive where text like :w{i})
         )"
             )
         })
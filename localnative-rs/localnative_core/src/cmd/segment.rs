@@ -0,0 +1,128 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+// sync through storage no two devices need to reach at the same time. each
+// device only ever adds segments of its own changes, named by the change log
+// seq they end at, and merges everyone else's in order. a segment is a bundle,
+// so it carries the same clocks and tombstones the rpc sync merges by
+use super::bundle::{encode_bundle, import_bundle_bytes};
+use super::conflict::device_id;
+use super::sync::{last_change_seq, MergeSummary};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct Segment {
+    pub device: String,
+    // change log seq of the writing device the segment ends at
+    pub until: i64,
+    // what the store finds it by
+    pub name: String,
+}
+
+pub trait SegmentStore {
+    // meta keys of the store start with it
+    fn kind(&self) -> &str;
+    // where the segments go, a new location starts over with everything
+    fn location(&self) -> String;
+    // segments of every device
+    fn list(&self) -> anyhow::Result<Vec<Segment>>;
+    fn get(&self, segment: &Segment) -> anyhow::Result<Vec<u8>>;
+    // returns the name of the segment written
+    fn put(&self, device: &str, until: i64, bundle: &[u8]) -> anyhow::Result<String>;
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Default)]
+pub struct SegmentSummary {
+    // segment written for this device's changes, if there were any
+    pub written: Option<String>,
+    pub read: usize,
+    pub merged: MergeSummary,
+    pub deleted: usize,
+    // segments left for the next pass, most likely still being copied in
+    pub skipped: Vec<String>,
+}
+
+fn get_seq(conn: &Connection, key: &str) -> anyhow::Result<i64> {
+    let seq = conn
+        .query_row(
+            "select meta_value FROM meta where meta_key = ?1",
+            [key],
+            |row| row.get::<_, String>(0),
+        )
+        .optional()?;
+    Ok(seq.and_then(|s| s.parse().ok()).unwrap_or(0))
+}
+
+fn set_seq(conn: &Connection, key: &str, seq: i64) -> anyhow::Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO meta (meta_key, meta_value) VALUES (?1, ?2)",
+        params![key, seq.to_string()],
+    )?;
+    Ok(())
+}
+
+// writes this device's new changes, then merges what the others wrote since
+// the last pass
+pub fn sync_store(conn: &Connection, store: &impl SegmentStore) -> anyhow::Result<SegmentSummary> {
+    let device = device_id(conn)?;
+    let export_key = format!("{}_export:{}", store.kind(), store.location());
+    let mut summary = SegmentSummary::default();
+
+    let written = get_seq(conn, &export_key)?;
+    let last_seq = last_change_seq(conn)?;
+    if last_seq > written {
+        let (_, bundle) = encode_bundle(conn, written)?;
+        summary.written = Some(store.put(&device, last_seq, &bundle)?);
+        set_seq(conn, &export_key, last_seq)?;
+    }
+
+    let mut segments = store.list()?;
+    segments.retain(|segment| segment.device != device);
+    segments.sort_by(|a, b| (&a.device, a.until).cmp(&(&b.device, b.until)));
+    // later segments of a device wait for one that failed
+    let mut failed: Option<&str> = None;
+    for segment in &segments {
+        if failed == Some(segment.device.as_str()) {
+            continue;
+        }
+        let key = format!("{}_import:{}", store.kind(), segment.device);
+        if segment.until <= get_seq(conn, &key)? {
+            continue;
+        }
+        match store
+            .get(segment)
+            .and_then(|bytes| import_bundle_bytes(conn, &bytes))
+        {
+            Ok(imported) => {
+                summary.read += 1;
+                summary.merged += imported.merged;
+                summary.deleted += imported.deleted;
+                set_seq(conn, &key, segment.until)?;
+            }
+            Err(err) => {
+                eprintln!("{} sync {}: {}", store.kind(), segment.name, err);
+                summary.skipped.push(segment.name.clone());
+                failed = Some(&segment.device);
+            }
+        }
+    }
+
+    // what the merges above changed here, conflicts settled by this device's
+    // policy among them, goes out with the next segment
+    Ok(summary)
+}
@@ -103,8 +103,10 @@ pub fn select_count(conn: &Connection) -> anyhow::Result<u32> {
 pub fn select(conn: &Connection, limit: u32, offset: u32) -> anyhow::Result<String> {
     let mut stmt = conn.prepare(
         "SELECT rowid, uuid4, title, url, tags, description, comments
-        , hex(annotations)
-        , created_at, is_public
+        , (select thumbnail from attachment
+            where attachment.note_uuid4 = note.uuid4 and thumbnail is not null
+            order by attachment.rowid limit 1)
+        , created_at, is_public, is_local
         FROM note
         order by created_at desc limit :limit offset :offset",
     )?;
@@ -125,6 +127,8 @@ pub fn select(conn: &Connection, limit: u32, offset: u32) -> anyhow::Result<Stri
                 annotations: super::utils::make_data_url(row).unwrap_or_else(|_| "".into()),
                 created_at: row.get(8)?,
                 is_public: row.get(9)?,
+                is_local: row.get(10)?,
+                version: Default::default(),
             })
         },
     )?;
@@ -16,35 +16,25 @@
     along with this program.  If not, see <https://www.gnu.org/licenses/>.
 */
 
+use super::conflict::{compare, get_note, get_policy, merge_note, Clock, Merge, Order};
+use super::sync_filter::SyncFilter;
 use crate::{Note, OneString};
-use rusqlite::Connection;
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
+use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 
 //client
+// local only notes are never handed out, to the server or from it
 pub fn get_note_by_uuid4(conn: &Connection, uuid4: &str) -> anyhow::Result<Note> {
-    let mut stmt = conn.prepare(
-"select uuid4, title, url, tags, description, comments, annotations, created_at FROM note where uuid4 = ? "
-    )?;
-    let note = stmt.query_row(&[uuid4], |row| {
-        Ok(Note {
-            rowid: 0,
-            uuid4: row.get(0)?,
-            title: row.get(1)?,
-            url: row.get(2)?,
-            tags: row.get(3)?,
-            description: row.get(4)?,
-            comments: row.get(5)?,
-            annotations: row.get(6)?,
-            created_at: row.get(7)?,
-            is_public: false,
-        })
-    })?;
-    Ok(note)
+    match get_note(conn, uuid4)? {
+        Some(note) if !note.is_local => Ok(Note { rowid: 0, ..note }),
+        _ => Err(anyhow::anyhow!("no syncable note {}", uuid4)),
+    }
 }
 
 pub fn next_uuid4_candidates(conn: &Connection) -> anyhow::Result<Vec<String>> {
     let mut r = Vec::new();
-    let mut stmt = conn.prepare("select uuid4 FROM note order by rowid")?;
+    let mut stmt = conn.prepare("select uuid4 FROM note where is_local = 0 order by rowid")?;
     let iter = stmt.query_map([], |row| Ok(OneString { s: row.get(0)? }))?;
     for uuid4 in iter.flatten() {
         r.push(uuid4.s);
@@ -74,7 +64,7 @@ pub fn diff_uuid4_from_server(
 ) -> anyhow::Result<Vec<String>> {
     let candidates: HashSet<_> = candidates.iter().collect();
     let mut r = Vec::new();
-    let mut stmt = conn.prepare("select uuid4 FROM note")?;
+    let mut stmt = conn.prepare("select uuid4 FROM note where is_local = 0")?;
     let iter = stmt.query_map([], |row| Ok(OneString { s: row.get(0)? }))?;
 
     for uuid4 in iter.flatten() {
@@ -84,3 +74,269 @@ pub fn diff_uuid4_from_server(
     }
     Ok(r)
 }
+
+// batched protocol: candidate sets travel in chunks and notes in bounded batches,
+// so neither side holds the whole uuid4 list or pays a round trip per note
+pub const CANDIDATE_CHUNK: u32 = 1000;
+pub const NOTE_BATCH: usize = 200;
+
+// syncable uuid4s after the cursor in uuid4 order, an empty cursor starts at the beginning
+pub fn uuid4_page(conn: &Connection, after: &str, limit: u32) -> anyhow::Result<Vec<String>> {
+    let mut r = Vec::new();
+    let mut stmt = conn.prepare(
+        "select uuid4 FROM note where is_local = 0 and uuid4 > ?1 order by uuid4 limit ?2",
+    )?;
+    let iter = stmt.query_map(params![after, limit], |row| {
+        Ok(OneString { s: row.get(0)? })
+    })?;
+    for uuid4 in iter {
+        r.push(uuid4?.s);
+    }
+    Ok(r)
+}
+
+// missing and local only notes are skipped
+pub fn get_notes_by_uuid4s(conn: &Connection, uuid4s: &[String]) -> anyhow::Result<Vec<Note>> {
+    let mut r = Vec::with_capacity(uuid4s.len());
+    for uuid4 in uuid4s {
+        match get_note_by_uuid4(conn, uuid4) {
+            Ok(note) => r.push(note),
+            Err(err) => eprintln!("skip note {}: {}", uuid4, err),
+        }
+    }
+    Ok(r)
+}
+
+// candidates whose version here is missing or older, local only notes excluded
+pub fn diff_versions(
+    conn: &Connection,
+    candidates: Vec<(String, Clock)>,
+) -> anyhow::Result<Vec<String>> {
+    let mut r = Vec::new();
+    for (uuid4, clock) in candidates {
+        match get_note(conn, &uuid4)? {
+            None => r.push(uuid4),
+            Some(note) if note.is_local => {}
+            Some(note) => {
+                if matches!(
+                    compare(&note.version.clock, &clock),
+                    Order::Before | Order::Concurrent
+                ) {
+                    r.push(uuid4)
+                }
+            }
+        }
+    }
+    Ok(r)
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Default)]
+pub struct MergeSummary {
+    pub inserted: usize,
+    pub updated: usize,
+    pub conflicts: usize,
+}
+
+impl std::ops::AddAssign for MergeSummary {
+    fn add_assign(&mut self, other: Self) {
+        self.inserted += other.inserted;
+        self.updated += other.updated;
+        self.conflicts += other.conflicts;
+    }
+}
+
+// one transaction per batch, local only notes are never taken;
+// concurrent edits are settled by this device's conflict policy
+pub fn merge_notes(conn: &Connection, notes: &[Note]) -> anyhow::Result<MergeSummary> {
+    let policy = get_policy(conn)?;
+    let tx = conn.unchecked_transaction()?;
+    let mut summary = MergeSummary::default();
+    for note in notes.iter().filter(|note| !note.is_local) {
+        match merge_note(&tx, note, policy)? {
+            Merge::Inserted => summary.inserted += 1,
+            Merge::Updated => summary.updated += 1,
+            Merge::Conflict => summary.conflicts += 1,
+            Merge::Unchanged => {}
+        }
+    }
+    tx.commit()?;
+    Ok(summary)
+}
+
+// incremental sync: each device numbers its own changes in change_log,
+// peers remember the last seq they exchanged in meta
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum HighWater {
+    // last seq of the peer's log pulled from it
+    Pull,
+    // last seq of this device's log pushed to the peer
+    Push,
+}
+
+fn high_water_key(direction: HighWater, fingerprint: &str) -> String {
+    match direction {
+        HighWater::Pull => format!("sync_pull:{}", fingerprint),
+        HighWater::Push => format!("sync_push:{}", fingerprint),
+    }
+}
+
+// 0 means never synced, the whole log is exchanged
+pub fn get_high_water(
+    conn: &Connection,
+    direction: HighWater,
+    fingerprint: &str,
+) -> anyhow::Result<i64> {
+    let seq = conn
+        .query_row(
+            "select meta_value FROM meta where meta_key = ?1",
+            [high_water_key(direction, fingerprint)],
+            |row| row.get::<_, String>(0),
+        )
+        .optional()?;
+    Ok(seq.and_then(|s| s.parse().ok()).unwrap_or(0))
+}
+
+pub fn set_high_water(
+    conn: &Connection,
+    direction: HighWater,
+    fingerprint: &str,
+    seq: i64,
+) -> anyhow::Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO meta (meta_key, meta_value) VALUES (?1, ?2)",
+        params![high_water_key(direction, fingerprint), seq.to_string()],
+    )?;
+    Ok(())
+}
+
+pub fn clear_high_water(conn: &Connection, fingerprint: &str) -> anyhow::Result<()> {
+    for direction in [HighWater::Pull, HighWater::Push] {
+        conn.execute(
+            "delete from meta where meta_key = ?1",
+            [high_water_key(direction, fingerprint)],
+        )?;
+    }
+    Ok(())
+}
+
+pub fn last_change_seq(conn: &Connection) -> anyhow::Result<i64> {
+    let seq = conn.query_row("select ifnull(max(seq), 0) FROM change_log", [], |row| {
+        row.get(0)
+    })?;
+    Ok(seq)
+}
+
+// notes offered to a peer without a change log
+pub fn sync_note_count(conn: &Connection) -> anyhow::Result<i64> {
+    let count = conn.query_row("select count(1) FROM note where is_local = 0", [], |row| {
+        row.get(0)
+    })?;
+    Ok(count)
+}
+
+// where a pass over this device's notes continues from: a change log seq,
+// or a uuid4 when the peer has no change log
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Cursor {
+    Seq(i64),
+    Uuid4(String),
+}
+
+// next chunk of (uuid4, clock) to offer a peer, empty when done
+pub fn next_candidates(
+    conn: &Connection,
+    cursor: &mut Cursor,
+) -> anyhow::Result<Vec<(String, Clock)>> {
+    match cursor {
+        Cursor::Seq(after) => {
+            let changes = changes_since(conn, *after, CANDIDATE_CHUNK)?;
+            if let Some(last) = changes.last() {
+                *after = last.seq;
+            }
+            Ok(changes
+                .into_iter()
+                .map(|change| (change.uuid4, change.clock))
+                .collect())
+        }
+        Cursor::Uuid4(after) => {
+            let mut r: Vec<(String, Clock)> = Vec::new();
+            let mut stmt = conn.prepare(
+                "select uuid4, clock FROM note where is_local = 0 and uuid4 > ?1 order by uuid4 limit ?2",
+            )?;
+            let iter = stmt.query_map(params![after.as_str(), CANDIDATE_CHUNK], |row| {
+                let clock: String = row.get(1)?;
+                Ok((
+                    row.get(0)?,
+                    serde_json::from_str(&clock).unwrap_or_default(),
+                ))
+            })?;
+            for candidate in iter {
+                r.push(candidate?);
+            }
+            if let Some((last, _)) = r.last() {
+                *after = last.clone();
+            }
+            Ok(r)
+        }
+    }
+}
+
+// the database file sync_via_attach works with, it is never created here
+pub fn open_attached(uri: &str, flags: OpenFlags) -> anyhow::Result<Connection> {
+    Connection::open_with_flags(uri, flags | OpenFlags::SQLITE_OPEN_URI)
+        .map_err(|err| anyhow::anyhow!("can not open {}: {}", uri, err))
+}
+
+// notes of from the filter lets through that to lacks or holds an older or
+// a concurrent version of, a chunk at a time: what merge_notes on to takes
+pub fn notes_behind(
+    from: &Connection,
+    to: &Connection,
+    filter: &SyncFilter,
+    mut each: impl FnMut(&[Note]) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let mut cursor = Cursor::Uuid4(String::new());
+    loop {
+        let candidates = next_candidates(from, &mut cursor)?;
+        if candidates.is_empty() {
+            return Ok(());
+        }
+        let candidates = candidates
+            .into_iter()
+            .filter(|(uuid4, _)| filter.allows(uuid4))
+            .collect();
+        let behind = diff_versions(to, candidates)?;
+        if !behind.is_empty() {
+            each(&get_notes_by_uuid4s(from, &behind)?)?;
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct Change {
+    pub seq: i64,
+    pub uuid4: String,
+    // lets the other side skip notes it already has
+    pub clock: Clock,
+}
+
+// changes after the given seq, notes since made local are left out
+pub fn changes_since(conn: &Connection, after: i64, limit: u32) -> anyhow::Result<Vec<Change>> {
+    let mut r = Vec::new();
+    let mut stmt = conn.prepare(
+        "select c.seq, c.uuid4, n.clock FROM change_log c join note n on n.uuid4 = c.uuid4
+        where c.seq > ?1 and n.is_local = 0 order by c.seq limit ?2",
+    )?;
+    let iter = stmt.query_map(params![after, limit], |row| {
+        let clock: String = row.get(2)?;
+        Ok(Change {
+            seq: row.get(0)?,
+            uuid4: row.get(1)?,
+            clock: serde_json::from_str(&clock).unwrap_or_default(),
+        })
+    })?;
+    for change in iter {
+        r.push(change?);
+    }
+    Ok(r)
+}
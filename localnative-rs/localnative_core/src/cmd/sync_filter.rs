@@ -0,0 +1,240 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+// selective sync: a search query such as `tag:work` or `since:365d` picks
+// the notes exchanged with a peer, each side evaluates it on the notes it
+// holds so what is left out is neither offered nor asked for
+use super::attachment::{self, AttachmentKey};
+use super::search::{make_keys, make_words, where_vec};
+use super::sync::{self, clear_high_water, Change};
+use super::utils::{resolve_dates, split_filters};
+use rusqlite::types::ToSql;
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
+use std::collections::HashSet;
+
+// no filter, every syncable note goes
+#[derive(Debug, Clone, Default)]
+pub struct SyncFilter {
+    matching: Option<HashSet<String>>,
+}
+
+pub fn is_empty(query: &str) -> bool {
+    query.trim().is_empty()
+}
+
+// both filters have to match, like two queries typed into search together
+pub fn combine(mine: &str, theirs: &str) -> String {
+    mine.split_whitespace()
+        .chain(theirs.split_whitespace())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+impl SyncFilter {
+    // the syncable notes here that match the query right now
+    pub fn load(conn: &Connection, query: &str) -> anyhow::Result<Self> {
+        let (query, clauses) = split_filters(query);
+        if query.is_empty() && clauses.is_empty() {
+            return Ok(SyncFilter::default());
+        }
+        let words = if query.is_empty() {
+            Vec::new()
+        } else {
+            make_words(&query)
+        };
+        let mut r = where_vec(words.len());
+        r.extend(clauses);
+        let mut stmt = conn.prepare(&format!(
+            "select uuid4 FROM note where is_local = 0 and {}",
+            r.join(" and ")
+        ))?;
+        let keys = make_keys(words.len());
+        let params: Vec<(&str, &dyn ToSql)> = keys
+            .iter()
+            .map(|k| k.as_str())
+            .zip(words.iter().map(|w| w as &dyn ToSql))
+            .collect();
+        let iter = stmt.query_map(&params[..], |row| row.get(0))?;
+        let mut matching = HashSet::new();
+        for uuid4 in iter {
+            matching.insert(uuid4?);
+        }
+        Ok(SyncFilter {
+            matching: Some(matching),
+        })
+    }
+
+    // a filtered peer is told of no deletes: whether it ever got a deleted
+    // note can not be told once the note is gone
+    pub fn is_all(&self) -> bool {
+        self.matching.is_none()
+    }
+
+    pub fn allows(&self, uuid4: &str) -> bool {
+        match &self.matching {
+            Some(matching) => matching.contains(uuid4),
+            None => true,
+        }
+    }
+
+    // sync::changes_since with the notes left out dropped, reads on past
+    // pages that match nothing so an empty result still means the log is done
+    pub fn changes_since(
+        &self,
+        conn: &Connection,
+        mut after: i64,
+        limit: u32,
+    ) -> anyhow::Result<Vec<Change>> {
+        loop {
+            let changes = sync::changes_since(conn, after, limit)?;
+            match changes.last() {
+                Some(last) => after = last.seq,
+                None => return Ok(changes),
+            }
+            let changes: Vec<_> = changes
+                .into_iter()
+                .filter(|change| self.allows(&change.uuid4))
+                .collect();
+            if !changes.is_empty() {
+                return Ok(changes);
+            }
+        }
+    }
+
+    // sync::uuid4_page the same way
+    pub fn uuid4_page(
+        &self,
+        conn: &Connection,
+        after: &str,
+        limit: u32,
+    ) -> anyhow::Result<Vec<String>> {
+        let mut after = after.to_string();
+        loop {
+            let page = sync::uuid4_page(conn, &after, limit)?;
+            match page.last() {
+                Some(last) => after = last.clone(),
+                None => return Ok(page),
+            }
+            let page: Vec<_> = page
+                .into_iter()
+                .filter(|uuid4| self.allows(uuid4))
+                .collect();
+            if !page.is_empty() {
+                return Ok(page);
+            }
+        }
+    }
+
+    // attachment::attachment_key_page the same way, by the note they are on
+    pub fn attachment_key_page(
+        &self,
+        conn: &Connection,
+        after: &AttachmentKey,
+        limit: u32,
+    ) -> anyhow::Result<Vec<AttachmentKey>> {
+        let mut after = after.clone();
+        loop {
+            let page = attachment::attachment_key_page(conn, &after, limit)?;
+            match page.last() {
+                Some(last) => after = last.clone(),
+                None => return Ok(page),
+            }
+            let page: Vec<_> = page
+                .into_iter()
+                .filter(|key| self.allows(&key.note_uuid4))
+                .collect();
+            if !page.is_empty() {
+                return Ok(page);
+            }
+        }
+    }
+}
+
+fn last_filter_key(fingerprint: &str) -> String {
+    format!("sync_filter:{}", fingerprint)
+}
+
+// whether the filter differs from the one of the last sync with the peer.
+// relative dates are compared as the dates they resolve to, so once the
+// window of `since:365d` moves on a day the next sync is a full one
+pub fn changed(conn: &Connection, fingerprint: &str, query: &str) -> anyhow::Result<bool> {
+    let last: Option<String> = conn
+        .query_row(
+            "select meta_value FROM meta where meta_key = ?1",
+            [last_filter_key(fingerprint)],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(last.as_deref().unwrap_or_default() != resolve_dates(query))
+}
+
+// a changed filter starts over with a full exchange, so notes it used to
+// leave out are offered too
+pub fn track(conn: &Connection, fingerprint: &str, query: &str) -> anyhow::Result<()> {
+    if changed(conn, fingerprint, query)? {
+        eprintln!("sync filter now {:?}, full sync", query);
+        clear_high_water(conn, fingerprint)?;
+        conn.execute(
+            "INSERT OR REPLACE INTO meta (meta_key, meta_value) VALUES (?1, ?2)",
+            params![last_filter_key(fingerprint), resolve_dates(query)],
+        )?;
+    }
+    Ok(())
+}
+
+pub fn forget(conn: &Connection, fingerprint: &str) -> anyhow::Result<()> {
+    conn.execute(
+        "delete from meta where meta_key = ?1",
+        [last_filter_key(fingerprint)],
+    )?;
+    Ok(())
+}
+
+// sync_via_attach copies attachments in sql across both databases, so the
+// notes matching in each are listed in a temp table first; the attached file
+// is searched on its own connection for the query to see that file's archive
+// and link status
+pub fn stage_attach(conn: &Connection, uri: &str, query: &str) -> anyhow::Result<()> {
+    conn.execute_batch(
+        "drop table if exists temp.sync_filter;
+        create temp table sync_filter (db TEXT NOT NULL, uuid4 TEXT NOT NULL);",
+    )?;
+    if is_empty(query) {
+        return Ok(());
+    }
+    let other = Connection::open_with_flags(uri, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|err| anyhow::anyhow!("can not open {}: {}", uri, err))?;
+    let mut stmt = conn.prepare("insert into temp.sync_filter (db, uuid4) values (?1, ?2)")?;
+    for (db, filter) in [
+        ("main", SyncFilter::load(conn, query)?),
+        ("other", SyncFilter::load(&other, query)?),
+    ] {
+        for uuid4 in filter.matching.iter().flatten() {
+            stmt.execute(params![db, uuid4])?;
+        }
+    }
+    Ok(())
+}
+
+// condition on a note uuid4 column of db for sync_via_attach, after stage_attach
+pub fn attach_condition(query: &str, db: &'static str, column: &str) -> String {
+    if is_empty(query) {
+        String::new()
+    } else {
+        format!("and {db}.{column} in (select uuid4 from temp.sync_filter where db = '{db}')")
+    }
+}
@@ -15,20 +15,85 @@
     You should have received a copy of the GNU Affero General Public License
     along with this program.  If not, see <https://www.gnu.org/licenses/>.
 */
-use base64::{Engine as _, engine::general_purpose};
+use super::attachment::{to_data_url, THUMBNAIL_MIME};
+use super::link_check;
+use super::visibility;
+use rusqlite::types::ValueRef;
+use std::time::Duration;
+use time::macros::format_description;
+use time::{Date, OffsetDateTime};
 
 pub fn make_data_url(row: &rusqlite::Row) -> anyhow::Result<String> {
     let url = row.get::<_, String>(3)?;
     #[cfg(not(feature = "no_print"))]
     eprintln!("url: {}", url);
-    if url == "mime://image/png" {
-        let hex_string = row.get::<_, String>(7)?;
-        let bytes = hex::decode(&hex_string)?;
-        let result = general_purpose::STANDARD.encode(&bytes);
-        let mut r = "data:image/png;base64,".to_owned();
-        r.push_str(&result);
-        Ok(r)
-    } else {
-        Ok("".to_string())
+    // column 7 is the thumbnail of the first image attachment, the original is fetched separately
+    match (url.starts_with("mime://"), row.get_ref(7)?) {
+        (true, ValueRef::Blob(thumbnail)) => Ok(to_data_url(THUMBNAIL_MIME, thumbnail)),
+        _ => Ok("".to_string()),
     }
 }
+
+// pull `is:<name>`, `tag:<name>` and `since:<date>` filters out of a search
+// query, returns the remaining query text and the matching sql conditions
+pub fn split_filters(query: &str) -> (String, Vec<String>) {
+    let mut words = Vec::new();
+    let mut clauses = Vec::new();
+    for w in query.split_whitespace() {
+        match filter_clause(w) {
+            Some(clause) => clauses.push(clause),
+            None => words.push(w),
+        }
+    }
+    (words.join(" "), clauses)
+}
+
+// the query with each relative `since:` written as the date it stands for today
+pub fn resolve_dates(query: &str) -> String {
+    query
+        .split_whitespace()
+        .map(|w| match w.strip_prefix("since:").and_then(since_date) {
+            Some(date) => format!("since:{}", date),
+            None => w.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn filter_clause(word: &str) -> Option<String> {
+    if let Some(name) = word.strip_prefix("is:") {
+        return link_check::is_filter_clause(name)
+            .or_else(|| visibility::is_filter_clause(name))
+            .map(String::from);
+    }
+    if let Some(tag) = word.strip_prefix("tag:").filter(|tag| !tag.is_empty()) {
+        // tags are stored comma separated, match a whole one
+        let tag = format!(",{},", tag.to_lowercase()).replace('\'', "''");
+        return Some(format!("instr(',' || lower(tags) || ',', '{}') > 0", tag));
+    }
+    word.strip_prefix("since:")
+        .and_then(since_date)
+        .map(|date| format!("created_at >= '{}'", date))
+}
+
+// `since:2024-01-31`, or `since:365d` for the last 365 days
+fn since_date(value: &str) -> Option<Date> {
+    match value.strip_suffix('d') {
+        Some(days) => {
+            let days: u32 = days.parse().ok()?;
+            OffsetDateTime::now_utc()
+                .date()
+                .checked_sub(time::Duration::days(days.into()))
+        }
+        None => Date::parse(value, format_description!("[year]-[month]-[day]")).ok(),
+    }
+}
+
+// shared by link checking and archiving
+pub fn http_client(timeout: Duration) -> reqwest::Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .timeout(timeout)
+        .redirect(reqwest::redirect::Policy::limited(10))
+        .user_agent(concat!("localnative/", env!("CARGO_PKG_VERSION")))
+        .build()
+}
@@ -0,0 +1,50 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+// is_public travels with the note, is_local keeps it off every other device
+use rusqlite::{params, Connection};
+
+// returns false when there is no such note
+pub fn set_visibility(
+    conn: &Connection,
+    uuid4: &str,
+    is_public: bool,
+    is_local: bool,
+) -> anyhow::Result<bool> {
+    // is_public is an edit other devices pick up, is_local is not
+    if !super::conflict::edit_note(conn, uuid4, |note| note.is_public = is_public)? {
+        return Ok(false);
+    }
+    set_local(conn, uuid4, is_local)
+}
+
+pub fn set_local(conn: &Connection, uuid4: &str, is_local: bool) -> anyhow::Result<bool> {
+    let n = conn.execute(
+        "UPDATE note SET is_local = ?1 WHERE uuid4 = ?2",
+        params![is_local, uuid4],
+    )?;
+    Ok(n > 0)
+}
+
+// sql condition for `is:public` and `is:local` in search queries
+pub fn is_filter_clause(name: &str) -> Option<&'static str> {
+    match name {
+        "public" => Some("is_public = 1"),
+        "local" => Some("is_local = 1"),
+        _ => None,
+    }
+}
@@ -0,0 +1,143 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+// at-rest encryption of the whole database file with sqlcipher,
+// which derives the page key from the passphrase itself (PBKDF2-HMAC-SHA512)
+use rusqlite::{params, Connection};
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+use std::sync::Mutex;
+
+// passphrase of the unlocked database, only ever held in memory
+static PASSPHRASE: Mutex<Option<String>> = Mutex::new(None);
+
+const SQLITE_HEADER: &[u8; 16] = b"SQLite format 3\0";
+
+fn require_sqlcipher() -> anyhow::Result<()> {
+    if cfg!(feature = "sqlcipher") {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!("built without sqlcipher support"))
+    }
+}
+
+fn require_passphrase(passphrase: &str) -> anyhow::Result<()> {
+    if passphrase.is_empty() {
+        Err(anyhow::anyhow!("passphrase is empty"))
+    } else {
+        Ok(())
+    }
+}
+
+// a plaintext sqlite file starts with a fixed header, sqlcipher files look random
+pub fn is_encrypted(path: &Path) -> bool {
+    let mut header = [0u8; 16];
+    match File::open(path).and_then(|mut f| f.read_exact(&mut header)) {
+        Ok(()) => &header != SQLITE_HEADER,
+        // missing or empty file, sqlite creates it as plaintext
+        Err(_) => false,
+    }
+}
+
+pub fn is_unlocked() -> bool {
+    PASSPHRASE.lock().unwrap().is_some()
+}
+
+pub fn is_locked(path: &Path) -> bool {
+    is_encrypted(path) && !is_unlocked()
+}
+
+// must run before anything else on a new connection
+pub fn apply_key(conn: &Connection) -> rusqlite::Result<()> {
+    if let Some(passphrase) = &*PASSPHRASE.lock().unwrap() {
+        conn.pragma_update(None, "key", passphrase)?;
+    }
+    Ok(())
+}
+
+fn check_key(conn: &Connection) -> anyhow::Result<()> {
+    conn.query_row("SELECT count(*) FROM sqlite_master", [], |row| {
+        row.get::<_, i64>(0)
+    })
+    .map_err(|_| anyhow::anyhow!("wrong passphrase"))?;
+    Ok(())
+}
+
+pub fn unlock(path: &Path, passphrase: &str) -> anyhow::Result<()> {
+    require_sqlcipher()?;
+    if !is_encrypted(path) {
+        return Err(anyhow::anyhow!("database is not encrypted"));
+    }
+    let conn = Connection::open(path)?;
+    conn.pragma_update(None, "key", passphrase)?;
+    check_key(&conn)?;
+    *PASSPHRASE.lock().unwrap() = Some(passphrase.to_string());
+    Ok(())
+}
+
+pub fn lock() {
+    *PASSPHRASE.lock().unwrap() = None;
+}
+
+// conn must come from the unlocked database
+pub fn change_key(conn: &Connection, passphrase: &str) -> anyhow::Result<()> {
+    require_sqlcipher()?;
+    require_passphrase(passphrase)?;
+    if !is_unlocked() {
+        return Err(anyhow::anyhow!("database is not unlocked"));
+    }
+    conn.pragma_update(None, "rekey", passphrase)?;
+    *PASSPHRASE.lock().unwrap() = Some(passphrase.to_string());
+    Ok(())
+}
+
+// rewrites a plaintext database encrypted and replaces the file in place,
+// the database stays unlocked with passphrase afterwards
+pub fn encrypt(path: &Path, passphrase: &str) -> anyhow::Result<()> {
+    require_sqlcipher()?;
+    require_passphrase(passphrase)?;
+    if is_encrypted(path) {
+        return Err(anyhow::anyhow!("database is already encrypted"));
+    }
+    let tmp = path.with_extension("sqlite3.encrypting");
+    if tmp.exists() {
+        fs::remove_file(&tmp)?;
+    }
+    {
+        let conn = Connection::open(path)?;
+        conn.execute(
+            "ATTACH DATABASE ?1 AS encrypted KEY ?2",
+            params![tmp.to_string_lossy(), passphrase],
+        )?;
+        conn.query_row("SELECT sqlcipher_export('encrypted')", [], |_| Ok(()))?;
+        conn.execute_batch("DETACH DATABASE encrypted;")?;
+    }
+    fs::rename(&tmp, path)?;
+    *PASSPHRASE.lock().unwrap() = Some(passphrase.to_string());
+    Ok(())
+}
+
+// sql to attach another, plaintext, database file as `other`
+pub fn attach_plaintext_sql() -> &'static str {
+    if is_unlocked() {
+        // without an explicit empty key sqlcipher reuses the main database key
+        "attach ? as 'other' key ''"
+    } else {
+        "attach ? as 'other'"
+    }
+}
@@ -21,12 +21,15 @@ use crate::cmd::{
     search_by_tag, search_count, select, select_by_day, select_by_tag, select_count,
     sync_via_attach,
 };
+use crate::encryption;
+use crate::rpc::stream::Endpoint;
 use crate::upgrade;
 use crate::Cmd;
 use crate::Note;
 use rusqlite::Connection;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use time::macros::format_description;
 use uuid::Uuid;
 
@@ -46,6 +49,8 @@ pub enum ProcessError {
     ClientSyncFailure(String),
     #[error("client-stop-server error: {0}")]
     ClientStopServerFailure(String),
+    #[error("database is locked")]
+    Locked,
     #[error("unknown error: {0} from {1}")]
     Unknown(String, String),
 }
@@ -55,6 +60,7 @@ pub fn get_sqlite_connection() -> Connection {
     let path = Path::new(&p);
     #[allow(clippy::let_and_return)]
     let conn = Connection::open(path).unwrap();
+    encryption::apply_key(&conn).unwrap();
     // .execSQL("PRAGMA temp_store_directory = '/data/data/com.cmp.pkg/databases/main'")
     #[cfg(target_os = "android")]
     conn.execute(
@@ -66,7 +72,7 @@ pub fn get_sqlite_connection() -> Connection {
     conn
 }
 
-fn sqlite3_db_location() -> String {
+pub fn sqlite3_db_location() -> String {
     if cfg!(target_os = "android") {
         fs::create_dir_all("/data/data/app.localnative/files").unwrap();
         return "/data/data/app.localnative/files/localnative.sqlite3".into();
@@ -97,8 +103,17 @@ pub fn run(text: &str) -> String {
     }
 }
 
-fn process(cmd: Cmd) -> anyhow::Result<String, ProcessError> {
-    eprintln!("process cmd {:?}", cmd);
+// the database ready for any command, what callers outside the json api
+// such as the sync cli run first too
+pub fn prepare_db() -> anyhow::Result<Connection, ProcessError> {
+    let db_location = sqlite3_db_location();
+    open_upgraded(Path::new(&db_location))
+}
+
+fn open_upgraded(db_path: &Path) -> anyhow::Result<Connection, ProcessError> {
+    if encryption::is_locked(db_path) {
+        return Err(ProcessError::Locked);
+    }
     let conn = get_sqlite_connection();
     create(&conn).map_err(|err| ProcessError::Unknown(err.to_string(), "create conn".into()))?;
 
@@ -108,30 +123,266 @@ fn process(cmd: Cmd) -> anyhow::Result<String, ProcessError> {
     } else {
         return Err(ProcessError::UpgradeFailure("init".into()));
     }
+    Ok(conn)
+}
+
+fn process(cmd: Cmd) -> anyhow::Result<String, ProcessError> {
+    eprintln!("process cmd {:?}", cmd);
+    let db_location = sqlite3_db_location();
+    let db_path = Path::new(&db_location);
+    // these work on the database file before a connection is usable
+    match &cmd {
+        Cmd::Unlock(u) => {
+            return encryption::unlock(db_path, &u.passphrase)
+                .map(|_| r#"{"unlock": "done"}"#.to_string())
+                .map_err(|err| ProcessError::Unknown(err.to_string(), "unlock".into()));
+        }
+        Cmd::Lock => {
+            encryption::lock();
+            return Ok(r#"{"lock": "done"}"#.to_string());
+        }
+        Cmd::Encrypt(e) => {
+            return encryption::encrypt(db_path, &e.passphrase)
+                .map(|_| r#"{"encrypt": "done"}"#.to_string())
+                .map_err(|err| ProcessError::Unknown(err.to_string(), "encrypt".into()));
+        }
+        Cmd::EncryptionStatus => {
+            return Ok(format!(
+                r#"{{"encrypted": {}, "locked": {}}}"#,
+                encryption::is_encrypted(db_path),
+                encryption::is_locked(db_path)
+            ));
+        }
+        _ => {}
+    }
+    let conn = open_upgraded(db_path)?;
 
     match cmd {
-        Cmd::Server(s) => match crate::rpc::server::start(&s.addr) {
-            Ok(_) => Ok(r#"{"server": "started"}"#.to_string()),
-            Err(e) => Err(ProcessError::StartServerFailure(e.into())),
-        },
+        Cmd::Server(s) => {
+            let config = s.config.as_ref().map(PathBuf::from);
+            crate::rpc::policy::resolve_config(config.as_deref(), s.settings.as_ref())
+                .and_then(|config| crate::rpc::server::start(&s.addr, config))
+                .map(|_| r#"{"server": "started"}"#.to_string())
+                .map_err(|err| ProcessError::StartServerFailure(err.to_string()))
+        }
+        // stdout carries the sync stream, nothing else may be written there
+        Cmd::SyncStdio => crate::rpc::server::serve_stdio()
+            .map(|_| String::new())
+            .map_err(|err| ProcessError::Unknown(err.to_string(), "sync stdio".into())),
         Cmd::ClientSync(s) => {
             eprintln!(r#"{{"client": "starting"}}"#);
-            match crate::rpc::client::sync(&s.addr) {
+            match Endpoint::parse_untrusted(&s.addr).and_then(|e| crate::rpc::client::sync(&e)) {
                 Ok(resp) => Ok(format!(r#"{{"client-sync": "{}"}}"#, resp)),
                 Err(err) => Err(ProcessError::ClientSyncFailure(err.to_string())),
             }
         }
+        Cmd::ClientSyncPreview(s) => Endpoint::parse_untrusted(&s.addr)
+            .and_then(|e| crate::rpc::client::preview(&e))
+            .map(|preview| {
+                format!(
+                    r#"{{"client-sync-preview": {}}}"#,
+                    serde_json::to_string(&preview).unwrap()
+                )
+            })
+            .map_err(|err| ProcessError::ClientSyncFailure(err.to_string())),
+        Cmd::ClientInfo(s) => Endpoint::parse_untrusted(&s.addr)
+            .and_then(|e| crate::rpc::client::info(&e))
+            .map(|info| {
+                format!(
+                    r#"{{"client-info": {}}}"#,
+                    serde_json::to_string(&info).unwrap()
+                )
+            })
+            .map_err(|err| ProcessError::ClientSyncFailure(err.to_string())),
+        Cmd::SyncProgress => Ok(format!(
+            r#"{{"sync-progress": {}}}"#,
+            serde_json::to_string(&crate::rpc::progress::current().map(|t| t.progress())).unwrap()
+        )),
+        Cmd::CancelSync => {
+            let current = crate::rpc::progress::current();
+            if let Some(tracker) = &current {
+                tracker.cancel();
+            }
+            Ok(format!(r#"{{"cancel-sync": {}}}"#, current.is_some()))
+        }
         Cmd::ClientStopServer(s) => {
             eprintln!(r#"{{"client": "starting"}}"#);
-            match crate::rpc::client::stop_server(&s.addr) {
+            match Endpoint::parse_untrusted(&s.addr)
+                .and_then(|e| crate::rpc::client::stop_server(&e))
+            {
                 Ok(resp) => Ok(format!(r#"{{"client-stop-server": "{}"}}"#, resp)),
                 Err(err) => Err(ProcessError::ClientStopServerFailure(err.to_string())),
             }
         }
+        Cmd::ClientPair(p) => Endpoint::parse_untrusted(&p.addr)
+            .and_then(|e| crate::rpc::client::pair(&e, &p.code))
+            .map(|fingerprint| format!(r#"{{"client-pair": "{}"}}"#, fingerprint))
+            .map_err(|err| ProcessError::Unknown(err.to_string(), "client pair".into())),
+        Cmd::PairingCode => Ok(format!(
+            r#"{{"pairing-code": "{}"}}"#,
+            crate::rpc::pairing::new_code()
+        )),
+        Cmd::SyncDaemon(d) => crate::rpc::daemon::start(d.config.as_deref(), d.once)
+            .map(|synced| {
+                format!(
+                    r#"{{"sync-daemon": {}}}"#,
+                    serde_json::to_string(&synced).unwrap()
+                )
+            })
+            .map_err(|err| ProcessError::ClientSyncFailure(err.to_string())),
+        Cmd::SyncStatus => crate::rpc::daemon::list_status(&conn)
+            .map(|status| {
+                format!(
+                    r#"{{"sync-status": {}}}"#,
+                    serde_json::to_string(&status).unwrap()
+                )
+            })
+            .map_err(|err| ProcessError::Unknown(err.to_string(), "sync status".into())),
+        Cmd::ListPeers => cmd::peer::list_peers(&conn)
+            .map(|peers| format!(r#"{{"peers": {}}}"#, serde_json::to_string(&peers).unwrap()))
+            .map_err(|err| ProcessError::Unknown(err.to_string(), "list peers".into())),
+        Cmd::Discover(d) => crate::rpc::discovery::browse(d.timeout_secs.map_or(
+            crate::rpc::discovery::DEFAULT_BROWSE_TIMEOUT,
+            Duration::from_secs,
+        ))
+        .map(|peers| {
+            format!(
+                r#"{{"discovered": {}}}"#,
+                serde_json::to_string(&peers).unwrap()
+            )
+        })
+        .map_err(|err| ProcessError::Unknown(err.to_string(), "discover".into())),
+        Cmd::RevokePeer(r) => cmd::peer::revoke_peer(&conn, &r.fingerprint)
+            .map(|revoked| format!(r#"{{"revoked": {}}}"#, revoked))
+            .map_err(|err| ProcessError::Unknown(err.to_string(), "revoke peer".into())),
+        Cmd::SetPeerFilter(f) => cmd::peer::set_filter(&conn, &f.fingerprint, &f.filter)
+            .map(|updated| format!(r#"{{"updated": {}}}"#, updated))
+            .map_err(|err| ProcessError::Unknown(err.to_string(), "set peer filter".into())),
+        Cmd::SetVisibility(v) => {
+            cmd::visibility::set_visibility(&conn, &v.uuid4, v.is_public, v.is_local)
+                .map(|updated| format!(r#"{{"updated": {}}}"#, updated))
+                .map_err(|err| ProcessError::Unknown(err.to_string(), "set visibility".into()))
+        }
+        Cmd::Publish(p) => cmd::publish::publish(
+            &conn,
+            &cmd::publish::PublishOptions {
+                out_dir: p.dir.into(),
+                template_dir: p.template_dir.map(Into::into),
+                per_page: p.per_page.unwrap_or(cmd::publish::DEFAULT_PER_PAGE),
+                title: p.title.unwrap_or_else(|| "Local Native".to_string()),
+            },
+        )
+        .map(|summary| {
+            format!(
+                r#"{{"publish": {}}}"#,
+                serde_json::to_string(&summary).unwrap()
+            )
+        })
+        .map_err(|err| ProcessError::Unknown(err.to_string(), "publish".into())),
+        Cmd::Feed(f) => f
+            .format
+            .parse()
+            .and_then(|format| {
+                cmd::feed::feed(
+                    &conn,
+                    format,
+                    f.tag.as_deref(),
+                    f.limit.unwrap_or(cmd::feed::DEFAULT_LIMIT),
+                )
+            })
+            .map(|xml| format!(r#"{{"feed": {}}}"#, serde_json::to_string(&xml).unwrap()))
+            .map_err(|err| ProcessError::Unknown(err.to_string(), "feed".into())),
+        Cmd::Update(u) => cmd::conflict::edit_note(&conn, &u.uuid4, |note| {
+            note.title = u.title;
+            note.url = u.url;
+            note.tags = u.tags;
+            note.description = u.description;
+            note.comments = u.comments;
+            note.annotations = u.annotations;
+            note.is_public = u.is_public;
+        })
+        .map(|updated| format!(r#"{{"updated": {}}}"#, updated))
+        .map_err(|err| ProcessError::Unknown(err.to_string(), "update".into())),
+        Cmd::ConflictPolicy(p) => p
+            .policy
+            .map_or(Ok(()), |policy| {
+                cmd::conflict::set_policy(&conn, policy.parse()?)
+            })
+            .and_then(|_| cmd::conflict::get_policy(&conn))
+            .map(|policy| format!(r#"{{"conflict-policy": "{}"}}"#, policy.as_str()))
+            .map_err(|err| ProcessError::Unknown(err.to_string(), "conflict policy".into())),
+        Cmd::ListConflicts => cmd::conflict::list_conflicts(&conn)
+            .map(|conflicts| {
+                format!(
+                    r#"{{"conflicts": {}}}"#,
+                    serde_json::to_string(&conflicts).unwrap()
+                )
+            })
+            .map_err(|err| ProcessError::Unknown(err.to_string(), "list conflicts".into())),
+        Cmd::ResolveConflict(r) => r
+            .resolution
+            .parse()
+            .and_then(|resolution| cmd::conflict::resolve_conflict(&conn, &r.uuid4, resolution))
+            .map(|resolved| format!(r#"{{"resolved": {}}}"#, resolved))
+            .map_err(|err| ProcessError::Unknown(err.to_string(), "resolve conflict".into())),
         Cmd::Upgrade => upgrade::upgrade(&conn)
             .map(|version| format!(r#"{{"upgrade-done": "{}"}}"#, version))
             .map_err(|err| ProcessError::UpgradeFailure(err.to_string())),
-        Cmd::SyncViaAttach(s) => Ok(sync_via_attach(&conn, &s.uri)),
+        Cmd::SyncViaAttach(s) => Ok(sync_via_attach(&conn, &s.uri, &s.filter)),
+        Cmd::SyncViaAttachPreview(s) => cmd::preview::preview_attach(&conn, &s.uri, &s.filter)
+            .map(|preview| {
+                format!(
+                    r#"{{"sync-via-attach-preview": {}}}"#,
+                    serde_json::to_string(&preview).unwrap()
+                )
+            })
+            .map_err(|err| {
+                ProcessError::Unknown(err.to_string(), "sync via attach preview".into())
+            }),
+        Cmd::ExportBundle(b) => cmd::bundle::export_bundle(&conn, Path::new(&b.path), b.since)
+            .map(|manifest| {
+                format!(
+                    r#"{{"export-bundle": {}}}"#,
+                    serde_json::to_string(&manifest).unwrap()
+                )
+            })
+            .map_err(|err| ProcessError::Unknown(err.to_string(), "export bundle".into())),
+        Cmd::ImportBundle(b) => cmd::bundle::import_bundle(&conn, Path::new(&b.path))
+            .map(|summary| {
+                format!(
+                    r#"{{"import-bundle": {}}}"#,
+                    serde_json::to_string(&summary).unwrap()
+                )
+            })
+            .map_err(|err| ProcessError::Unknown(err.to_string(), "import bundle".into())),
+        Cmd::FolderSync(f) => {
+            let dir = Path::new(&f.dir);
+            let result = if f.watch {
+                cmd::folder::watch(dir, get_sqlite_connection)
+                    .map(|_| r#"{"folder-sync": null}"#.to_string())
+            } else {
+                cmd::folder::sync_folder(&conn, dir).map(|summary| {
+                    format!(
+                        r#"{{"folder-sync": {}}}"#,
+                        serde_json::to_string(&summary).unwrap()
+                    )
+                })
+            };
+            result.map_err(|err| ProcessError::Unknown(err.to_string(), "folder sync".into()))
+        }
+        Cmd::S3Sync(s) => {
+            let path = s.config.map_or_else(cmd::s3::config_path, PathBuf::from);
+            cmd::s3::load_config(&path)
+                .map_err(|err| anyhow::anyhow!("s3 sync config {:?}: {}", path, err))
+                .and_then(|config| cmd::s3::sync_s3(&conn, &config))
+                .map(|summary| {
+                    format!(
+                        r#"{{"s3-sync": {}}}"#,
+                        serde_json::to_string(&summary).unwrap()
+                    )
+                })
+                .map_err(|err| ProcessError::Unknown(err.to_string(), "s3 sync".into()))
+        }
         Cmd::InsertImage(i) => {
             let created_at = created_time();
             let note = Note {
@@ -145,8 +396,10 @@ fn process(cmd: Cmd) -> anyhow::Result<String, ProcessError> {
                 annotations: i.annotations,
                 created_at,
                 is_public: i.is_public,
+                is_local: i.is_local,
+                version: Default::default(),
             };
-            cmd::image::insert_image(note)
+            cmd::image::insert_image(&conn, note)
                 .map_err(|err| ProcessError::Unknown(err.to_string(), "insert image op".into()))?;
             if i.is_public {
                 eprintln!("is_public")
@@ -168,12 +421,27 @@ fn process(cmd: Cmd) -> anyhow::Result<String, ProcessError> {
                 annotations: i.annotations,
                 created_at,
                 is_public: i.is_public,
+                is_local: i.is_local,
+                version: Default::default(),
             };
+            let uuid4 = note.uuid4.clone();
+            let url = note.url.clone();
             insert(note)
                 .map_err(|err| ProcessError::Unknown(err.to_string(), "insert op".into()))?;
             if i.is_public {
                 eprintln!("is_public")
             }
+            if i.archive && (url.starts_with("http://") || url.starts_with("https://")) {
+                // a failed snapshot should not fail the insert
+                if let Err(err) = cmd::archive::archive_note(
+                    &conn,
+                    &uuid4,
+                    &url,
+                    Duration::from_secs(cmd::link_check::DEFAULT_TIMEOUT_SECS),
+                ) {
+                    eprintln!("archive on insert error: {:?}", err);
+                }
+            }
             do_select(&conn, i.limit, i.offset)
                 .map_err(|err| ProcessError::Unknown(err.to_string(), "insert".into()))
         }
@@ -189,10 +457,93 @@ fn process(cmd: Cmd) -> anyhow::Result<String, ProcessError> {
             .map_err(|err| ProcessError::Unknown(err.to_string(), "search".into())),
         Cmd::Filter(s) => do_filter(&conn, &s.query, s.limit, s.offset, &s.from, &s.to)
             .map_err(|err| ProcessError::Unknown(err.to_string(), "filter".into())),
+        Cmd::CheckLinks(c) => cmd::link_check::check_links(
+            &conn,
+            c.limit,
+            c.concurrency,
+            Duration::from_secs(c.timeout_secs),
+        )
+        .map(|summary| {
+            format!(
+                r#"{{"check-links": {}}}"#,
+                serde_json::to_string(&summary).unwrap()
+            )
+        })
+        .map_err(|err| ProcessError::Unknown(err.to_string(), "check links".into())),
+        Cmd::Archive(a) => cmd::archive::archive_notes(
+            &conn,
+            a.limit,
+            a.concurrency,
+            Duration::from_secs(a.timeout_secs),
+        )
+        .map(|summary| {
+            format!(
+                r#"{{"archive": {}}}"#,
+                serde_json::to_string(&summary).unwrap()
+            )
+        })
+        .map_err(|err| ProcessError::Unknown(err.to_string(), "archive".into())),
+        Cmd::GetArchive(a) => cmd::archive::get_archive(&conn, &a.uuid4)
+            .map(|archive| {
+                format!(
+                    r#"{{"archive": {}}}"#,
+                    serde_json::to_string(&archive).unwrap()
+                )
+            })
+            .map_err(|err| ProcessError::Unknown(err.to_string(), "get archive".into())),
+        Cmd::AddAttachment(a) => cmd::attachment::parse_data_url(&a.data)
+            .and_then(|(_, data)| cmd::attachment::add_attachment(&conn, &a.note_uuid4, &data))
+            .map(|attachment| {
+                format!(
+                    r#"{{"attachment": {}}}"#,
+                    serde_json::to_string(&attachment).unwrap()
+                )
+            })
+            .map_err(|err| ProcessError::Unknown(err.to_string(), "add attachment".into())),
+        Cmd::ListAttachments(a) => cmd::attachment::list_attachments(&conn, &a.note_uuid4)
+            .map(|attachments| {
+                format!(
+                    r#"{{"attachments": {}}}"#,
+                    serde_json::to_string(&attachments).unwrap()
+                )
+            })
+            .map_err(|err| ProcessError::Unknown(err.to_string(), "list attachments".into())),
+        Cmd::GetAttachment(a) => cmd::attachment::get_attachment(&conn, &a.uuid4)
+            .map(|attachment| match attachment {
+                Some((attachment, data)) => format!(
+                    r#"{{"attachment": {}, "data": {}}}"#,
+                    serde_json::to_string(&attachment).unwrap(),
+                    serde_json::to_string(&cmd::attachment::to_data_url(&attachment.mime, &data))
+                        .unwrap()
+                ),
+                None => r#"{"attachment": null}"#.to_string(),
+            })
+            .map_err(|err| ProcessError::Unknown(err.to_string(), "get attachment".into())),
+        Cmd::RemoveAttachment(a) => cmd::attachment::remove_attachment(&conn, &a.uuid4)
+            .map(|removed| format!(r#"{{"removed": {}}}"#, removed))
+            .map_err(|err| ProcessError::Unknown(err.to_string(), "remove attachment".into())),
+        Cmd::ChangeKey(c) => encryption::change_key(&conn, &c.passphrase)
+            .map(|_| r#"{"change-key": "done"}"#.to_string())
+            .map_err(|err| ProcessError::Unknown(err.to_string(), "change key".into())),
+        // answered above, before the database is opened
+        Cmd::Unlock(_) | Cmd::Lock | Cmd::Encrypt(_) | Cmd::EncryptionStatus => Err(
+            ProcessError::Unknown("not a database command".into(), "encryption".into()),
+        ),
+        Cmd::GetImage(i) => cmd::attachment::get_image(&conn, &i.note_uuid4)
+            .map(|image| {
+                format!(
+                    r#"{{"image": {}}}"#,
+                    serde_json::to_string(
+                        &image.map(|(mime, data)| cmd::attachment::to_data_url(&mime, &data))
+                    )
+                    .unwrap()
+                )
+            })
+            .map_err(|err| ProcessError::Unknown(err.to_string(), "get image".into())),
     }
 }
 
-fn created_time() -> String {
+pub fn created_time() -> String {
     let created_at = time::OffsetDateTime::now_utc();
     created_at
         .format(&format_description!(
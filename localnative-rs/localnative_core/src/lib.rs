@@ -23,6 +23,7 @@ pub extern crate rusqlite;
 pub extern crate serde_json;
 
 pub mod cmd;
+pub mod encryption;
 pub mod exe;
 pub mod upgrade;
 
@@ -84,6 +85,7 @@ pub extern "C" fn localnative_free(s: *mut c_char) {
     }
 }
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 #[allow(clippy::upper_case_acronyms)]
 #[derive(Serialize, Deserialize, Debug)]
 pub struct KVStringI64 {
@@ -144,6 +146,23 @@ pub struct Note {
     pub annotations: String,
     pub created_at: String,
     pub is_public: bool,
+    // never leaves this device through sync
+    #[serde(default)]
+    pub is_local: bool,
+    #[serde(default)]
+    pub version: NoteVersion,
+}
+
+// sync tells an update from a concurrent edit by comparing clocks
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Default)]
+pub struct NoteVersion {
+    pub updated_at: String,
+    // device the last edit came from
+    pub device: String,
+    // edits seen from each device
+    pub clock: BTreeMap<String, u64>,
+    // (device, edit) that last changed each field, absent means never edited
+    pub fields: BTreeMap<String, (String, u64)>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -152,15 +171,57 @@ pub struct Note {
 pub enum Cmd {
     Server(CmdRpcServer),
     ClientSync(CmdRpcClient),
+    // what client-sync would do, nothing written on either side
+    ClientSyncPreview(CmdRpcClient),
+    SyncProgress,
+    CancelSync,
     ClientStopServer(CmdRpcClient),
+    // what the server says about itself before a sync
+    ClientInfo(CmdRpcClient),
+    ClientPair(CmdClientPair),
+    // a new one-time code for the server running in this process to show
+    PairingCode,
+    // serves one sync client on stdin and stdout, e.g. run by ssh
+    SyncStdio,
+    SyncDaemon(CmdSyncDaemon),
+    SyncStatus,
+    ListPeers,
+    Discover(CmdDiscover),
+    RevokePeer(CmdRevokePeer),
+    SetPeerFilter(CmdSetPeerFilter),
+    SetVisibility(CmdSetVisibility),
+    Publish(CmdPublish),
+    Feed(CmdFeed),
+    Update(CmdUpdate),
+    ConflictPolicy(CmdConflictPolicy),
+    ListConflicts,
+    ResolveConflict(CmdResolveConflict),
     Upgrade,
     SyncViaAttach(CmdSyncViaAttach),
+    SyncViaAttachPreview(CmdSyncViaAttach),
+    ExportBundle(CmdExportBundle),
+    ImportBundle(CmdImportBundle),
+    FolderSync(CmdFolderSync),
+    S3Sync(CmdS3Sync),
     InsertImage(CmdInsert),
     Insert(CmdInsert),
     Delete(CmdDelete),
     Select(CmdSelect),
     Search(CmdSearch),
     Filter(CmdFilter),
+    CheckLinks(CmdCheckLinks),
+    Archive(CmdArchive),
+    GetArchive(CmdGetArchive),
+    AddAttachment(CmdAddAttachment),
+    ListAttachments(CmdListAttachments),
+    GetAttachment(CmdGetAttachment),
+    RemoveAttachment(CmdGetAttachment),
+    GetImage(CmdGetImage),
+    Unlock(CmdPassphrase),
+    Lock,
+    ChangeKey(CmdPassphrase),
+    Encrypt(CmdPassphrase),
+    EncryptionStatus,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -175,11 +236,48 @@ pub struct CmdInsert {
     pub limit: u32,
     pub offset: u32,
     pub is_public: bool,
+    #[serde(default)]
+    pub is_local: bool,
+    // fetch a snapshot of url right after insert
+    #[serde(default)]
+    pub archive: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct CmdSyncViaAttach {
     pub uri: String,
+    // search query the copied notes have to match
+    #[serde(default)]
+    pub filter: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CmdExportBundle {
+    pub path: String,
+    // change log seq to start after, the until of the last bundle; 0 for everything
+    #[serde(default)]
+    pub since: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CmdImportBundle {
+    pub path: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CmdFolderSync {
+    // shared by a file sync tool between the devices
+    pub dir: String,
+    // keep syncing on folder changes and local edits instead of returning
+    #[serde(default)]
+    pub watch: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CmdS3Sync {
+    // s3_sync.json next to the database when omitted
+    #[serde(default)]
+    pub config: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -215,14 +313,165 @@ pub struct CmdSelect {
     pub offset: u32,
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CmdCheckLinks {
+    pub limit: u32,
+    pub concurrency: usize,
+    pub timeout_secs: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CmdArchive {
+    pub limit: u32,
+    pub concurrency: usize,
+    pub timeout_secs: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CmdGetArchive {
+    pub uuid4: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CmdAddAttachment {
+    pub note_uuid4: String,
+    // data:<mime>;base64,<data>
+    pub data: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CmdListAttachments {
+    pub note_uuid4: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CmdGetAttachment {
+    pub uuid4: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CmdSetVisibility {
+    pub uuid4: String,
+    pub is_public: bool,
+    pub is_local: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CmdPublish {
+    // output directory of the static site
+    pub dir: String,
+    #[serde(default)]
+    pub template_dir: Option<String>,
+    #[serde(default)]
+    pub per_page: Option<usize>,
+    #[serde(default)]
+    pub title: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CmdFeed {
+    // atom or rss
+    pub format: String,
+    #[serde(default)]
+    pub tag: Option<String>,
+    #[serde(default)]
+    pub limit: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CmdUpdate {
+    pub uuid4: String,
+    pub title: String,
+    pub url: String,
+    pub tags: String,
+    pub description: String,
+    pub comments: String,
+    pub annotations: String,
+    pub is_public: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CmdSyncDaemon {
+    // sync_daemon.json next to the database when omitted
+    #[serde(default)]
+    pub config: Option<String>,
+    // sync the peers that are due once and return instead of looping
+    #[serde(default)]
+    pub once: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CmdDiscover {
+    // how long to listen for servers on the lan, 3 seconds when omitted
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CmdConflictPolicy {
+    // last-writer-wins, merge-fields or keep-both, omitted only reads it
+    #[serde(default)]
+    pub policy: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CmdResolveConflict {
+    pub uuid4: String,
+    // keep-note, keep-copy or keep-both
+    pub resolution: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CmdGetImage {
+    pub note_uuid4: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CmdPassphrase {
+    pub passphrase: String,
+}
+
+// keep passphrases out of the "process cmd" log line
+impl std::fmt::Debug for CmdPassphrase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("CmdPassphrase { .. }")
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct CmdRpcClient {
     pub addr: String,
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CmdClientPair {
+    pub addr: String,
+    // one-time code shown by the server
+    pub code: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CmdRevokePeer {
+    pub fingerprint: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct CmdSetPeerFilter {
+    pub fingerprint: String,
+    // search query the notes synced with the peer have to match, empty syncs all
+    #[serde(default)]
+    pub filter: String,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct CmdRpcServer {
     pub addr: String,
+    // sync_server.json next to the database when not given
+    #[serde(default)]
+    pub config: Option<String>,
+    // fields that replace the ones in the config file
+    #[serde(default)]
+    pub settings: Option<serde_json::Value>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
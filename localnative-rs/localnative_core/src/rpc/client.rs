@@ -15,124 +15,645 @@
     You should have received a copy of the GNU Affero General Public License
     along with this program.  If not, see <https://www.gnu.org/licenses/>.
 */
+use super::handshake::{
+    hello, negotiate, Hello, ATTACHMENTS, CHANGE_LOG, INFO, NOTE_VERSIONS, NOT_PAIRED, READ_ONLY,
+    SYNC_FILTER, TOMBSTONES,
+};
+use super::info::ServerInfo;
+use super::progress::{self, note_bytes, Phase, Tracker};
+use super::stream::{self, Endpoint};
+use super::tls;
 use super::LnClient;
-use crate::cmd::insert;
-use crate::cmd::sync::get_note_by_uuid4;
-use crate::cmd::sync::next_uuid4_candidates;
+use crate::cmd::attachment::{
+    attachment_bytes, get_attachments_by_keys, missing_attachments, save_attachments,
+    AttachmentKey, ATTACHMENT_BATCH,
+};
+use crate::cmd::bundle::{delete_by_tombstones, tombstones_since};
+use crate::cmd::conflict::Clock;
+use crate::cmd::peer;
+use crate::cmd::preview::SyncPreview;
+use crate::cmd::sync::{
+    clear_high_water, diff_versions, get_high_water, get_notes_by_uuid4s, last_change_seq,
+    merge_notes, next_candidates, set_high_water, sync_note_count, Cursor, HighWater, MergeSummary,
+    CANDIDATE_CHUNK, NOTE_BATCH,
+};
+use crate::cmd::sync_filter::{self, SyncFilter};
 use crate::exe::get_sqlite_connection;
-use crate::upgrade::get_meta_version;
-use std::net::SocketAddr;
+use rusqlite::Connection;
 use tarpc::{client, context};
+use thiserror::Error;
 use tokio::runtime::Runtime;
-use tokio_serde::formats::Bincode;
 
-pub async fn run_sync_to_server(addr: &SocketAddr) -> anyhow::Result<()> {
-    let transport = tarpc::serde_transport::tcp::connect(addr, Bincode::default).await?;
-    let client = LnClient::new(client::Config::default(), transport).spawn();
-    let conn = get_sqlite_connection();
-
-    // check version
-    let version = get_meta_version(&conn)?;
-    let is_version_match = client.is_version_match(context::current(), version).await?;
-    eprintln!("is_version_match: {}", is_version_match);
-    if !is_version_match {
-        return Err(anyhow::anyhow!("version not match"));
+// tls connection to the server, returns the client and the server's certificate fingerprint
+pub async fn connect(endpoint: &Endpoint) -> anyhow::Result<(LnClient, String)> {
+    let identity = tls::device_identity()?;
+    let stream = tls::connect_stream(stream::open(endpoint).await?, &identity).await?;
+    let fingerprint = tls::client_peer_fingerprint(&stream);
+    // what is kept per server is keyed by it, whatever the transport
+    if fingerprint.is_empty() {
+        return Err(anyhow::anyhow!("server {} sent no certificate", endpoint));
     }
+    eprintln!("server {} fingerprint {}", endpoint, fingerprint);
+    let client = LnClient::new(client::Config::default(), tls::transport(stream)).spawn();
+    Ok((client, fingerprint))
+}
 
-    let candidates = next_uuid4_candidates(&conn)?;
-    // diff uuid4
-    let diff_uuid4 = client
-        .diff_uuid4_to_server(context::current(), candidates)
-        .await?;
-    eprintln!("diff_uuid4_to_server len: {:?}", diff_uuid4.len());
+#[derive(Error, Debug)]
+#[error("not paired with server {0}, pair with the code it shows first")]
+pub struct NotPaired(pub Endpoint);
+
+// connection to a server both sides have paired with, or reached over a
+// transport that vouches for it
+pub async fn connect_paired(endpoint: &Endpoint) -> anyhow::Result<(LnClient, String)> {
+    let (client, fingerprint) = connect(endpoint).await?;
+    if !(endpoint.is_authenticated() || peer::is_trusted(&get_sqlite_connection(), &fingerprint)?)
+        || !client.is_paired(context::current()).await?
+    {
+        return Err(NotPaired(endpoint.clone()).into());
+    }
+    Ok((client, fingerprint))
+}
 
-    // send one by one
-    for u in diff_uuid4 {
-        let uuid4 = get_note_by_uuid4(&conn, &u)?;
-        client.send_note(context::current(), uuid4).await?;
+// presents the code shown by the server, on success each side trusts the other
+pub async fn run_pair(endpoint: &Endpoint, code: &str) -> anyhow::Result<String> {
+    let (client, fingerprint) = connect(endpoint).await?;
+    if !client.pair(context::current(), code.to_string()).await? {
+        return Err(anyhow::anyhow!("pairing code rejected"));
     }
-    eprintln!("send_note done");
+    peer::trust_peer(&get_sqlite_connection(), &fingerprint, &endpoint.name())?;
+    Ok(fingerprint)
+}
+
+pub fn pair(endpoint: &Endpoint, code: &str) -> anyhow::Result<String> {
+    let rt = Runtime::new()?;
+    rt.block_on(run_pair(endpoint, code))
+}
 
+// a server whose log is behind what was pulled from it lost its database,
+// start over with a full exchange
+fn forget_reset_server(conn: &Connection, fingerprint: &str, last_seq: i64) -> anyhow::Result<()> {
+    if get_high_water(conn, HighWater::Pull, fingerprint)? > last_seq {
+        eprintln!("server change log was reset, full sync");
+        clear_high_water(conn, fingerprint)?;
+    }
     Ok(())
 }
 
-pub async fn run_sync_from_server(addr: &SocketAddr) -> anyhow::Result<()> {
-    let transport = tarpc::serde_transport::tcp::connect(addr, Bincode::default).await?;
-    let client = LnClient::new(client::Config::default(), transport).spawn();
-    let conn = get_sqlite_connection();
+// exchanges hellos, returns the capabilities both sides have and the server's hello
+async fn handshake(
+    client: &LnClient,
+    endpoint: &Endpoint,
+    local: Hello,
+) -> anyhow::Result<(Vec<String>, Hello)> {
+    let remote = client.hello(context::current(), local.clone()).await?;
+    eprintln!("server hello {:?}", remote);
+    if remote.has(NOT_PAIRED) {
+        return Err(NotPaired(endpoint.clone()).into());
+    }
+    Ok((negotiate(&local, &remote)?, remote))
+}
 
-    // check version
-    let version = get_meta_version(&conn)?;
-    let is_version_match = client.is_version_match(context::current(), version).await?;
-    eprintln!("is_version_match: {}", is_version_match);
-    if !is_version_match {
-        return Err(anyhow::anyhow!("version not match"));
+// the filter kept here for the server and the one it keeps for this device
+// both apply, each side to the notes it holds; the server remembers this
+// device's for the rest of the connection
+async fn exchange_filter(
+    client: &LnClient,
+    conn: &Connection,
+    fingerprint: &str,
+    capabilities: &[String],
+) -> anyhow::Result<String> {
+    let mine = peer::filter(conn, fingerprint)?;
+    if !capabilities.iter().any(|c| c == SYNC_FILTER) {
+        if !sync_filter::is_empty(&mine) {
+            return Err(anyhow::anyhow!(
+                "server is too old to filter what it sends, upgrade Local Native there"
+            ));
+        }
+        return Ok(mine);
     }
+    let theirs = client.sync_filter(context::current(), mine.clone()).await?;
+    Ok(sync_filter::combine(&mine, &theirs))
+}
 
-    let candidates = next_uuid4_candidates(&conn)?;
-    // diff uuid4
-    let diff_uuid4 = client
-        .diff_uuid4_from_server(context::current(), candidates)
-        .await?;
-    eprintln!("diff_uuid4_from_server len: {:?}", diff_uuid4.len());
+// attachments of the notes the filter lets through that the server lacks, by
+// note and content hash; sent after the notes so the server has them to go on
+async fn push_attachments(
+    client: &LnClient,
+    conn: &Connection,
+    filter: &SyncFilter,
+    tracker: &Tracker,
+    done: u64,
+) -> anyhow::Result<u32> {
+    let mut after = AttachmentKey::default();
+    let mut sent = 0;
+    while !tracker.is_cancelled() {
+        let page = filter.attachment_key_page(conn, &after, CANDIDATE_CHUNK)?;
+        match page.last() {
+            Some(last) => after = last.clone(),
+            None => break,
+        }
+        let missing = client.diff_attachments(context::current(), page).await?;
+        for batch in missing.chunks(ATTACHMENT_BATCH) {
+            if tracker.is_cancelled() {
+                break;
+            }
+            let attachments = get_attachments_by_keys(conn, batch)?;
+            let bytes = attachment_bytes(&attachments);
+            sent += client
+                .send_attachments(context::current(), attachments)
+                .await?;
+            tracker.advance(done, bytes);
+        }
+    }
+    Ok(sent)
+}
 
-    // send one by one
-    for u in diff_uuid4 {
-        let note = client.receive_note(context::current(), u).await?;
-        insert(note)?;
+// attachments on the server missing here, after the notes were pulled
+async fn pull_attachments(
+    client: &LnClient,
+    conn: &Connection,
+    tracker: &Tracker,
+    done: u64,
+) -> anyhow::Result<u32> {
+    let mut after = AttachmentKey::default();
+    let mut saved = 0;
+    while !tracker.is_cancelled() {
+        let page = client
+            .attachment_keys(context::current(), after.clone(), CANDIDATE_CHUNK)
+            .await?;
+        match page.last() {
+            Some(last) => after = last.clone(),
+            None => break,
+        }
+        for batch in missing_attachments(conn, page)?.chunks(ATTACHMENT_BATCH) {
+            if tracker.is_cancelled() {
+                break;
+            }
+            let attachments = client
+                .receive_attachments(context::current(), batch.to_vec())
+                .await?;
+            tracker.advance(done, attachment_bytes(&attachments));
+            saved += save_attachments(conn, &attachments)?;
+        }
     }
-    eprintln!("receive_note done");
+    Ok(saved)
+}
 
-    Ok(())
+// where a pass over a change log starts; a preview leaves a reset server or
+// a changed filter for the sync to forget and starts over as it would
+fn start_seq(
+    conn: &Connection,
+    direction: HighWater,
+    fingerprint: &str,
+    server_seq: i64,
+    query: &str,
+    dry_run: bool,
+) -> anyhow::Result<i64> {
+    if dry_run {
+        if get_high_water(conn, HighWater::Pull, fingerprint)? > server_seq
+            || sync_filter::changed(conn, fingerprint, query)?
+        {
+            return Ok(0);
+        }
+    } else {
+        forget_reset_server(conn, fingerprint, server_seq)?;
+    }
+    get_high_water(conn, direction, fingerprint)
 }
 
-pub fn sync(addr: &str) -> anyhow::Result<String> {
-    let server_addr = addr
-        .parse()
-        .unwrap_or_else(|e| panic!(r#"server_addr {} invalid: {}"#, addr, e));
-    let rt = Runtime::new()?;
+// deletes made here after one seq up to another; sent again when a push is
+// cut short, the server skips notes already gone. a preview counts them
+async fn push_tombstones(
+    client: &LnClient,
+    conn: &Connection,
+    mut after: i64,
+    until: i64,
+    mut preview: Option<&mut SyncPreview>,
+) -> anyhow::Result<u32> {
+    let mut deleted = 0;
+    loop {
+        let mut page = tombstones_since(conn, after, NOTE_BATCH as u32)?;
+        page.retain(|tombstone| tombstone.seq <= until);
+        match page.last() {
+            Some(last) => after = last.seq,
+            None => return Ok(deleted),
+        }
+        match preview.as_deref_mut() {
+            Some(preview) => preview.tombstones += page.len(),
+            None => deleted += client.send_tombstones(context::current(), page).await?,
+        }
+    }
+}
 
-    rt.block_on(async {
-        if let Err(e) = run_sync_to_server(&server_addr).await {
-            eprintln!("sync to server error:{:?}", e);
+// deletes made on the server after one of its seqs up to another, a
+// preview lists the notes they would remove
+async fn pull_tombstones(
+    client: &LnClient,
+    conn: &Connection,
+    mut after: i64,
+    until: i64,
+    mut preview: Option<&mut SyncPreview>,
+) -> anyhow::Result<usize> {
+    let mut deleted = 0;
+    loop {
+        let mut page = client
+            .tombstones_since(context::current(), after, CANDIDATE_CHUNK)
+            .await?;
+        page.retain(|tombstone| tombstone.seq <= until);
+        match page.last() {
+            Some(last) => after = last.seq,
+            None => return Ok(deleted),
+        }
+        match preview.as_deref_mut() {
+            Some(preview) => preview.add_tombstones(conn, &page)?,
+            None => {
+                let tx = conn.unchecked_transaction()?;
+                deleted += delete_by_tombstones(&tx, &page)?;
+                tx.commit()?;
+            }
+        }
+    }
+}
+
+// offers local changes since the last push, or every note to a server
+// without a change log, a chunk at a time; sends what it lacks in batches.
+// with a preview nothing is sent or recorded, the notes go into it instead
+async fn push(
+    client: &LnClient,
+    conn: &Connection,
+    fingerprint: &str,
+    capabilities: &[String],
+    query: &str,
+    tracker: &Tracker,
+    mut preview: Option<&mut SyncPreview>,
+) -> anyhow::Result<()> {
+    let has = |capability| capabilities.iter().any(|c| c == capability);
+    let dry_run = preview.is_some();
+    let filter = SyncFilter::load(conn, query)?;
+    let last_seq = last_change_seq(conn)?;
+    let mut cursor = if has(CHANGE_LOG) {
+        let server_seq = client.last_change_seq(context::current()).await?;
+        let after = start_seq(
+            conn,
+            HighWater::Push,
+            fingerprint,
+            server_seq,
+            query,
+            dry_run,
+        )?;
+        // a local log that was reset offers everything again
+        Cursor::Seq(if after > last_seq { 0 } else { after })
+    } else {
+        Cursor::Uuid4(String::new())
+    };
+    let (start, total) = match cursor {
+        Cursor::Seq(after) => (after, last_seq - after),
+        Cursor::Uuid4(_) => (0, sync_note_count(conn)?),
+    };
+    // deletes only go along the change log and to a peer that gets every note
+    if let Cursor::Seq(after) = cursor {
+        if has(TOMBSTONES) && sync_filter::is_empty(query) {
+            let deleted =
+                push_tombstones(client, conn, after, last_seq, preview.as_deref_mut()).await?;
+            eprintln!("send_tombstones done: {}", deleted);
+        }
+    }
+    tracker.phase(Phase::Pushing, total as u64);
+    // where the last chunk that was sent in full ended
+    let mut pushed = cursor.clone();
+    let mut done = 0;
+    let mut sent = 0;
+    'chunks: while !tracker.is_cancelled() {
+        let mut chunk = next_candidates(conn, &mut cursor)?;
+        if chunk.is_empty() {
+            break;
+        }
+        let compared = chunk.len() as u64;
+        chunk.retain(|(uuid4, _)| filter.allows(uuid4));
+        let missing = if chunk.is_empty() {
+            Vec::new()
+        } else if has(NOTE_VERSIONS) {
+            client.diff_versions(context::current(), chunk).await?
         } else {
-            eprintln!("sync to server done");
+            let uuid4s = chunk.into_iter().map(|(uuid4, _)| uuid4).collect();
+            client
+                .diff_uuid4_to_server(context::current(), uuid4s)
+                .await?
+        };
+        for batch in missing.chunks(NOTE_BATCH) {
+            if tracker.is_cancelled() {
+                break 'chunks;
+            }
+            let notes = get_notes_by_uuid4s(conn, batch)?;
+            match preview.as_deref_mut() {
+                Some(preview) => {
+                    // the server's copies, to tell which would clash there
+                    let theirs = if has(NOTE_VERSIONS) {
+                        client
+                            .receive_notes(context::current(), batch.to_vec())
+                            .await?
+                    } else {
+                        Vec::new()
+                    };
+                    preview.add_push(&notes, &theirs);
+                }
+                None => {
+                    let bytes = note_bytes(&notes);
+                    sent += client.send_notes(context::current(), notes).await?;
+                    tracker.advance(done, bytes);
+                }
+            }
         }
-    });
-    let rt2 = Runtime::new()?;
-    rt2.block_on(async {
-        if let Err(e) = run_sync_from_server(&server_addr).await {
-            eprintln!("sync from server error:{:?}", e);
+        pushed = cursor.clone();
+        done = match cursor {
+            Cursor::Seq(after) => (after - start) as u64,
+            Cursor::Uuid4(_) => done + compared,
+        };
+        tracker.advance(done, 0);
+    }
+    if dry_run {
+        return Ok(());
+    }
+    if let Cursor::Seq(after) = pushed {
+        // a cancelled push keeps the chunks it got through
+        let after = if tracker.is_cancelled() {
+            after
+        } else {
+            after.max(last_seq)
+        };
+        set_high_water(conn, HighWater::Push, fingerprint, after)?;
+    }
+    eprintln!("send_notes done: {}", sent);
+    if has(ATTACHMENTS) {
+        let sent = push_attachments(client, conn, &filter, tracker, done).await?;
+        eprintln!("send_attachments done: {}", sent);
+    }
+    Ok(())
+}
+
+// pages through the server's changes since the last pull, or all of its
+// uuid4s without a change log; fetches what is missing here in batches.
+// with a preview nothing is merged or recorded, the notes go into it instead
+async fn pull(
+    client: &LnClient,
+    conn: &Connection,
+    fingerprint: &str,
+    capabilities: &[String],
+    query: &str,
+    tracker: &Tracker,
+    mut preview: Option<&mut SyncPreview>,
+) -> anyhow::Result<()> {
+    let has = |capability| capabilities.iter().any(|c| c == capability);
+    let dry_run = preview.is_some();
+    let incremental = has(CHANGE_LOG);
+    let mut last_seq = 0;
+    let mut after = 0;
+    if incremental {
+        last_seq = client.last_change_seq(context::current()).await?;
+        after = start_seq(conn, HighWater::Pull, fingerprint, last_seq, query, dry_run)?;
+        if has(TOMBSTONES) && sync_filter::is_empty(query) {
+            let deleted =
+                pull_tombstones(client, conn, after, last_seq, preview.as_deref_mut()).await?;
+            eprintln!("receive_tombstones done: {}", deleted);
+        }
+    }
+    // a server without a change log does not say how many notes it has
+    let start = after;
+    tracker.phase(Phase::Pulling, (last_seq - after).max(0) as u64);
+    // last seq merged in full
+    let mut pulled = after;
+    let mut after_uuid4 = String::new();
+    let mut done = 0;
+    let mut received = MergeSummary::default();
+    'chunks: while !tracker.is_cancelled() {
+        let chunk: Vec<(String, Clock)> = if incremental {
+            let changes = client
+                .changes_since(context::current(), after, CANDIDATE_CHUNK)
+                .await?;
+            if let Some(last) = changes.last() {
+                after = last.seq;
+            }
+            changes
+                .into_iter()
+                .map(|change| (change.uuid4, change.clock))
+                .collect()
         } else {
-            eprintln!("sync from server done");
+            let uuid4s = client
+                .list_uuid4s(context::current(), after_uuid4.clone(), CANDIDATE_CHUNK)
+                .await?;
+            if let Some(last) = uuid4s.last() {
+                after_uuid4 = last.clone();
+            }
+            // no clock only brings over notes missing here
+            uuid4s
+                .into_iter()
+                .map(|uuid4| (uuid4, Clock::new()))
+                .collect()
+        };
+        if chunk.is_empty() {
+            break;
         }
-    });
-    Ok("sync ok".to_string())
+        let listed = chunk.len() as u64;
+        // local only notes here are never overwritten either
+        let missing = diff_versions(conn, chunk)?;
+        for batch in missing.chunks(NOTE_BATCH) {
+            if tracker.is_cancelled() {
+                break 'chunks;
+            }
+            let notes = client
+                .receive_notes(context::current(), batch.to_vec())
+                .await?;
+            tracker.advance(done, note_bytes(&notes));
+            match preview.as_deref_mut() {
+                Some(preview) => preview.add_pull(conn, &notes)?,
+                None => received += merge_notes(conn, &notes)?,
+            }
+        }
+        pulled = after;
+        done = if incremental {
+            (after - start) as u64
+        } else {
+            done + listed
+        };
+        tracker.advance(done, 0);
+    }
+    if dry_run {
+        return Ok(());
+    }
+    if incremental {
+        // a cancelled pull keeps the chunks it got through
+        let after = if tracker.is_cancelled() {
+            pulled
+        } else {
+            pulled.max(last_seq)
+        };
+        set_high_water(conn, HighWater::Pull, fingerprint, after)?;
+    }
+    eprintln!("receive_notes done: {:?}", received);
+    if has(ATTACHMENTS) {
+        let saved = pull_attachments(client, conn, tracker, done).await?;
+        eprintln!("receive_attachments done: {}", saved);
+    }
+    Ok(())
+}
+
+// returns the server fingerprint for the user to verify
+pub async fn run_sync_to_server(endpoint: &Endpoint, tracker: &Tracker) -> anyhow::Result<String> {
+    tracker.check()?;
+    let (client, fingerprint) = connect_paired(endpoint).await?;
+    let conn = get_sqlite_connection();
+    let local = hello(&conn)?;
+    let (capabilities, remote) = handshake(&client, endpoint, local).await?;
+    // nothing is pushed, the high water stays for when the server takes notes again
+    if remote.has(READ_ONLY) {
+        eprintln!("server {} is read-only, skip sending notes", endpoint);
+        return Ok(fingerprint);
+    }
+    let query = exchange_filter(&client, &conn, &fingerprint, &capabilities).await?;
+    sync_filter::track(&conn, &fingerprint, &query)?;
+    push(
+        &client,
+        &conn,
+        &fingerprint,
+        &capabilities,
+        &query,
+        tracker,
+        None,
+    )
+    .await?;
+    tracker.check()?;
+
+    Ok(fingerprint)
 }
 
-pub async fn run_stop_server(addr: &SocketAddr) -> anyhow::Result<()> {
-    let transport = tarpc::serde_transport::tcp::connect(addr, Bincode::default).await?;
-    let client = LnClient::new(client::Config::default(), transport).spawn();
+// returns the server fingerprint for the user to verify
+pub async fn run_sync_from_server(
+    endpoint: &Endpoint,
+    tracker: &Tracker,
+) -> anyhow::Result<String> {
+    tracker.check()?;
+    let (client, fingerprint) = connect_paired(endpoint).await?;
     let conn = get_sqlite_connection();
+    let local = hello(&conn)?;
+    let (capabilities, _) = handshake(&client, endpoint, local).await?;
+    // the server leaves out what the filter does not match
+    let query = exchange_filter(&client, &conn, &fingerprint, &capabilities).await?;
+    sync_filter::track(&conn, &fingerprint, &query)?;
+    pull(
+        &client,
+        &conn,
+        &fingerprint,
+        &capabilities,
+        &query,
+        tracker,
+        None,
+    )
+    .await?;
+    tracker.check()?;
+
+    Ok(fingerprint)
+}
 
-    // check version
-    let version = get_meta_version(&conn)?;
-    let is_version_match = client.is_version_match(context::current(), version).await?;
-    eprintln!("is_version_match: {}", is_version_match);
-    if !is_version_match {
-        return Err(anyhow::anyhow!("version not match"));
+// pushes then pulls, the tracker sees both phases and how it ended
+pub async fn run_sync(endpoint: &Endpoint, tracker: &Tracker) -> anyhow::Result<String> {
+    tracker.phase(Phase::Connecting, 0);
+    let result = match run_sync_to_server(endpoint, tracker).await {
+        Ok(_) => run_sync_from_server(endpoint, tracker).await,
+        Err(err) => Err(err),
+    };
+    tracker.finish(&result);
+    result
+}
+
+// what run_sync would do, the same passes without sending or merging anything
+pub async fn run_preview(endpoint: &Endpoint) -> anyhow::Result<SyncPreview> {
+    let (client, fingerprint) = connect_paired(endpoint).await?;
+    let conn = get_sqlite_connection();
+    let local = hello(&conn)?;
+    let (capabilities, remote) = handshake(&client, endpoint, local).await?;
+    let query = exchange_filter(&client, &conn, &fingerprint, &capabilities).await?;
+    let tracker = Tracker::new();
+    let mut preview = SyncPreview::default();
+    // a read-only server would refuse every push
+    if !remote.has(READ_ONLY) {
+        push(
+            &client,
+            &conn,
+            &fingerprint,
+            &capabilities,
+            &query,
+            &tracker,
+            Some(&mut preview),
+        )
+        .await?;
     }
+    pull(
+        &client,
+        &conn,
+        &fingerprint,
+        &capabilities,
+        &query,
+        &tracker,
+        Some(&mut preview),
+    )
+    .await?;
+    Ok(preview)
+}
+
+pub fn preview(endpoint: &Endpoint) -> anyhow::Result<SyncPreview> {
+    let rt = Runtime::new()?;
+    rt.block_on(run_preview(endpoint))
+}
+
+// the json api polls and cancels this one through progress::current
+pub fn sync(endpoint: &Endpoint) -> anyhow::Result<String> {
+    let tracker = Tracker::new();
+    progress::set_current(&tracker);
+    let rt = Runtime::new()?;
+    rt.block_on(run_sync(endpoint, &tracker))?;
+    Ok("sync ok".to_string())
+}
+
+// who the server is, what it holds and when this device last synced with it
+pub async fn run_info(endpoint: &Endpoint) -> anyhow::Result<ServerInfo> {
+    let (client, _) = connect_paired(endpoint).await?;
+    let local = hello(&get_sqlite_connection())?;
+    let (capabilities, _) = handshake(&client, endpoint, local).await?;
+    if !capabilities.iter().any(|c| c == INFO) {
+        return Err(anyhow::anyhow!(
+            "server {} is too old to describe itself, upgrade Local Native there",
+            endpoint
+        ));
+    }
+    let info = client.info(context::current()).await?;
+    if info.is_refused() {
+        return Err(anyhow::anyhow!(
+            "server {} did not describe itself, see its log",
+            endpoint
+        ));
+    }
+    Ok(info)
+}
+
+pub fn info(endpoint: &Endpoint) -> anyhow::Result<ServerInfo> {
+    let rt = Runtime::new()?;
+    rt.block_on(run_info(endpoint))
+}
+
+pub async fn run_stop_server(endpoint: &Endpoint) -> anyhow::Result<()> {
+    let (client, _) = connect_paired(endpoint).await?;
+    let local = hello(&get_sqlite_connection())?;
+    handshake(&client, endpoint, local).await?;
 
     // diff uuid4
     client.stop(context::current()).await?;
     Ok(())
 }
 
-pub fn stop_server(addr: &str) -> anyhow::Result<String> {
-    let server_addr: SocketAddr = addr.parse()?;
+pub fn stop_server(endpoint: &Endpoint) -> anyhow::Result<String> {
     let rt = Runtime::new()?;
     rt.block_on(async {
-        if let Err(e) = run_stop_server(&server_addr).await {
+        if let Err(e) = run_stop_server(endpoint).await {
             eprintln!("stop server error:{:?}", e);
         }
     });
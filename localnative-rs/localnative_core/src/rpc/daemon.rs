@@ -0,0 +1,263 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+// background sync with the peers listed in a config file, each on an interval
+// and soon after local changes, backing off while it fails. the last result
+// per peer is kept in meta so the guis can show it
+use super::client::run_sync;
+use super::progress::Tracker;
+use super::stream::Endpoint;
+use crate::cmd::sync::last_change_seq;
+use crate::exe::{created_time, get_sqlite_connection, sqlite3_db_location};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::runtime::Runtime;
+
+pub const CONFIG_FILE: &str = "sync_daemon.json";
+pub const DEFAULT_INTERVAL_SECS: u64 = 15 * 60;
+// how often the change log is checked for local edits
+const POLL: Duration = Duration::from_secs(5);
+const RETRY_BASE_SECS: u64 = 30;
+const MAX_BACKOFF_SECS: u64 = 60 * 60;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct DaemonConfig {
+    #[serde(default = "default_interval")]
+    pub interval_secs: u64,
+    // sync with every healthy peer once local notes change
+    #[serde(default = "default_sync_on_change")]
+    pub sync_on_change: bool,
+    pub peers: Vec<PeerConfig>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct PeerConfig {
+    // ip:port of a paired sync server, or unix:, ssh: or exec: of one
+    // reached over another stream
+    pub addr: String,
+    #[serde(default)]
+    pub name: String,
+}
+
+fn default_interval() -> u64 {
+    DEFAULT_INTERVAL_SECS
+}
+
+fn default_sync_on_change() -> bool {
+    true
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Default)]
+pub struct SyncStatus {
+    pub addr: String,
+    pub name: String,
+    // of the server, known after the first successful sync
+    pub fingerprint: String,
+    pub last_attempt: String,
+    pub last_success: String,
+    // "ok" or the error of the last attempt
+    pub last_result: String,
+    // failed attempts in a row
+    pub failures: u32,
+    // unix seconds
+    pub next_attempt: i64,
+}
+
+// next to the database, like the device certificate
+pub fn config_path() -> PathBuf {
+    let location = sqlite3_db_location();
+    Path::new(&location)
+        .parent()
+        .map_or_else(|| PathBuf::from(CONFIG_FILE), |dir| dir.join(CONFIG_FILE))
+}
+
+pub fn load_config(path: &Path) -> anyhow::Result<DaemonConfig> {
+    let config: DaemonConfig = serde_json::from_str(&fs::read_to_string(path)?)?;
+    if config.interval_secs == 0 {
+        return Err(anyhow::anyhow!("interval_secs must be at least 1"));
+    }
+    for peer in &config.peers {
+        peer.addr.parse::<Endpoint>()?;
+    }
+    Ok(config)
+}
+
+fn status_key(addr: &str) -> String {
+    format!("sync_status:{}", addr)
+}
+
+pub fn get_status(conn: &Connection, addr: &str) -> anyhow::Result<Option<SyncStatus>> {
+    let status = conn
+        .query_row(
+            "select meta_value FROM meta where meta_key = ?1",
+            [status_key(addr)],
+            |row| row.get::<_, String>(0),
+        )
+        .optional()?;
+    Ok(status.and_then(|s| serde_json::from_str(&s).ok()))
+}
+
+pub fn set_status(conn: &Connection, status: &SyncStatus) -> anyhow::Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO meta (meta_key, meta_value) VALUES (?1, ?2)",
+        params![status_key(&status.addr), serde_json::to_string(status)?],
+    )?;
+    Ok(())
+}
+
+pub fn list_status(conn: &Connection) -> anyhow::Result<Vec<SyncStatus>> {
+    let mut stmt = conn.prepare(
+        "select meta_value FROM meta where meta_key like 'sync_status:%' order by meta_key",
+    )?;
+    let iter = stmt.query_map([], |row| row.get::<_, String>(0))?;
+    let mut r = Vec::new();
+    for status in iter {
+        if let Ok(status) = serde_json::from_str(&status?) {
+            r.push(status);
+        }
+    }
+    Ok(r)
+}
+
+// 30s after the first failure, doubling up to an hour
+pub fn backoff_secs(failures: u32) -> u64 {
+    let doublings = failures.saturating_sub(1).min(16);
+    (RETRY_BASE_SECS << doublings).min(MAX_BACKOFF_SECS)
+}
+
+// peers never synced are due at once, a failing peer waits out its backoff
+// even when notes change
+pub fn is_due(status: Option<&SyncStatus>, now: i64, changed: bool) -> bool {
+    match status {
+        None => true,
+        Some(status) => now >= status.next_attempt || (changed && status.failures == 0),
+    }
+}
+
+pub fn record(
+    status: &mut SyncStatus,
+    result: &anyhow::Result<String>,
+    now: i64,
+    interval_secs: u64,
+) {
+    status.last_attempt = created_time();
+    match result {
+        Ok(fingerprint) => {
+            status.fingerprint = fingerprint.clone();
+            status.last_success = status.last_attempt.clone();
+            status.last_result = "ok".to_string();
+            status.failures = 0;
+            status.next_attempt = now + interval_secs as i64;
+        }
+        Err(err) => {
+            status.last_result = err.to_string();
+            status.failures += 1;
+            status.next_attempt = now + backoff_secs(status.failures) as i64;
+        }
+    }
+}
+
+fn now() -> i64 {
+    time::OffsetDateTime::now_utc().unix_timestamp()
+}
+
+async fn sync_peer(addr: &str) -> anyhow::Result<String> {
+    run_sync(&addr.parse()?, &Tracker::new()).await
+}
+
+// one pass over the peers that are due, returns the status of each one synced
+pub async fn run_once(config: &DaemonConfig, changed: bool) -> anyhow::Result<Vec<SyncStatus>> {
+    let mut synced = Vec::new();
+    for peer in &config.peers {
+        let status = get_status(&get_sqlite_connection(), &peer.addr)?;
+        if !is_due(status.as_ref(), now(), changed) {
+            continue;
+        }
+        let mut status = status.unwrap_or_else(|| SyncStatus {
+            addr: peer.addr.clone(),
+            ..Default::default()
+        });
+        status.name = peer.name.clone();
+        let result = sync_peer(&peer.addr).await;
+        if let Err(ref err) = result {
+            eprintln!("sync daemon {} error: {:?}", peer.addr, err);
+        }
+        record(&mut status, &result, now(), config.interval_secs);
+        set_status(&get_sqlite_connection(), &status)?;
+        synced.push(status);
+    }
+    Ok(synced)
+}
+
+// syncs the peers that are due and notes where the change log got to
+async fn poll(config: &DaemonConfig, seen: &mut i64) -> anyhow::Result<()> {
+    let seq = last_change_seq(&get_sqlite_connection())?;
+    let changed = config.sync_on_change && seq > *seen;
+    for status in run_once(config, changed).await? {
+        eprintln!(
+            "sync daemon {} {}, next in {}s",
+            status.addr,
+            status.last_result,
+            status.next_attempt - now()
+        );
+    }
+    // notes pulled in above reach the other peers on their interval,
+    // only edits made here wake the daemon early
+    *seen = last_change_seq(&get_sqlite_connection())?;
+    Ok(())
+}
+
+// a failing database, locked or on a disk gone for a while, is waited out
+// like a failing peer rather than ending the daemon
+pub async fn run(config: DaemonConfig) -> anyhow::Result<()> {
+    let mut seen = last_change_seq(&get_sqlite_connection())?;
+    let mut failures = 0;
+    loop {
+        match poll(&config, &mut seen).await {
+            Ok(()) => {
+                failures = 0;
+                tokio::time::sleep(POLL).await;
+            }
+            Err(err) => {
+                failures += 1;
+                let wait = backoff_secs(failures);
+                eprintln!("sync daemon error: {:?}, retry in {}s", err, wait);
+                tokio::time::sleep(Duration::from_secs(wait)).await;
+            }
+        }
+    }
+}
+
+pub fn start(config: Option<&str>, once: bool) -> anyhow::Result<Vec<SyncStatus>> {
+    let path = config.map_or_else(config_path, PathBuf::from);
+    let config = load_config(&path)
+        .map_err(|err| anyhow::anyhow!("sync daemon config {:?}: {}", path, err))?;
+    let rt = Runtime::new()?;
+    if once {
+        return rt.block_on(run_once(&config, true));
+    }
+    eprintln!(
+        "sync daemon with {} peers every {}s",
+        config.peers.len(),
+        config.interval_secs
+    );
+    rt.block_on(run(config))?;
+    Ok(Vec::new())
+}
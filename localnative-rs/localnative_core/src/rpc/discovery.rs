@@ -0,0 +1,154 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+// lan discovery of sync servers over mdns/dns-sd, the txt record carries the
+// device name and certificate fingerprint so users can pick a peer and check
+// it against what the other device shows before pairing
+use super::handshake::PROTOCOL_VERSION;
+use mdns_sd::{IfKind, ServiceDaemon, ServiceEvent, ServiceInfo};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+pub const SERVICE_TYPE: &str = "_localnative._tcp.local.";
+pub const DEFAULT_BROWSE_TIMEOUT: Duration = Duration::from_secs(3);
+
+const NAME: &str = "name";
+const FINGERPRINT: &str = "fingerprint";
+const PROTOCOL: &str = "protocol";
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct DiscoveredPeer {
+    pub name: String,
+    pub addr: SocketAddr,
+    pub fingerprint: String,
+    pub protocol: u32,
+}
+
+// the server stays advertised until this is dropped
+pub struct Advertisement {
+    daemon: ServiceDaemon,
+    fullname: String,
+}
+
+impl Drop for Advertisement {
+    fn drop(&mut self) {
+        // wait for the goodbye so browsers drop the peer right away
+        if let Ok(status) = self.daemon.unregister(&self.fullname) {
+            let _ = status.recv_timeout(Duration::from_secs(1));
+        }
+        let _ = self.daemon.shutdown();
+    }
+}
+
+pub fn device_name() -> String {
+    gethostname::gethostname()
+        .to_string_lossy()
+        .trim_end_matches(".local")
+        .to_string()
+}
+
+// loopback is off by default in mdns-sd, browsing always listens there so
+// servers on this host show up too
+fn daemon(loopback: bool) -> anyhow::Result<ServiceDaemon> {
+    let daemon = ServiceDaemon::new()?;
+    if loopback {
+        daemon.enable_interface(IfKind::LoopbackV4)?;
+    }
+    Ok(daemon)
+}
+
+pub fn advertise(addr: SocketAddr, name: &str, fingerprint: &str) -> anyhow::Result<Advertisement> {
+    // a server bound to loopback is only reachable from this host
+    let daemon = daemon(addr.ip().is_loopback())?;
+    // the fingerprint keeps instance names apart when host names clash
+    let id: String = fingerprint.replace(' ', "").chars().take(8).collect();
+    let instance = format!("{} {}", name, id);
+    let host = format!("localnative-{}.local.", id.to_lowercase());
+    let protocol = PROTOCOL_VERSION.to_string();
+    let properties = [
+        (NAME, name),
+        (FINGERPRINT, fingerprint),
+        (PROTOCOL, protocol.as_str()),
+    ];
+    let service = if addr.ip().is_unspecified() {
+        // every interface, kept current as addresses come and go
+        ServiceInfo::new(
+            SERVICE_TYPE,
+            &instance,
+            &host,
+            "",
+            addr.port(),
+            &properties[..],
+        )?
+        .enable_addr_auto()
+    } else {
+        ServiceInfo::new(
+            SERVICE_TYPE,
+            &instance,
+            &host,
+            addr.ip(),
+            addr.port(),
+            &properties[..],
+        )?
+    };
+    let fullname = service.get_fullname().to_string();
+    daemon.register(service)?;
+    eprintln!("advertising {} on {}", fullname, addr);
+    Ok(Advertisement { daemon, fullname })
+}
+
+fn to_peer(service: &ServiceInfo) -> Option<DiscoveredPeer> {
+    // lan addresses before loopback, v4 before v6
+    let ip = service
+        .get_addresses()
+        .iter()
+        .min_by_key(|ip| (ip.is_loopback(), ip.is_ipv6(), **ip))
+        .copied()?;
+    Some(DiscoveredPeer {
+        name: service.get_property_val_str(NAME)?.to_string(),
+        addr: SocketAddr::new(ip, service.get_port()),
+        fingerprint: service.get_property_val_str(FINGERPRINT)?.to_string(),
+        protocol: service
+            .get_property_val_str(PROTOCOL)
+            .and_then(|p| p.parse().ok())
+            .unwrap_or(0),
+    })
+}
+
+// listens for the given time, returns every server that answered sorted by name
+pub fn browse(timeout: Duration) -> anyhow::Result<Vec<DiscoveredPeer>> {
+    let daemon = daemon(true)?;
+    let events = daemon.browse(SERVICE_TYPE)?;
+    let deadline = Instant::now() + timeout;
+    let mut peers: Vec<DiscoveredPeer> = Vec::new();
+    while let Some(left) = deadline.checked_duration_since(Instant::now()) {
+        match events.recv_timeout(left) {
+            Ok(ServiceEvent::ServiceResolved(service)) => {
+                if let Some(peer) = to_peer(&service) {
+                    peers.retain(|p| p.fingerprint != peer.fingerprint);
+                    peers.push(peer);
+                }
+            }
+            Ok(_) => {}
+            Err(_) => break,
+        }
+    }
+    let _ = daemon.shutdown();
+    peers.sort_by(|a, b| a.name.cmp(&b.name).then(a.addr.cmp(&b.addr)));
+    Ok(peers)
+}
@@ -0,0 +1,192 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+// plain http next to the sync server so feed readers can subscribe, when the
+// server config gives it a port. it only ever serves public notes: /atom.xml
+// and /rss.xml, with optional ?tag= and ?limit=
+use super::policy::Policy;
+use crate::cmd::feed::{self, FeedFormat};
+use crate::exe::get_sqlite_connection;
+use futures::prelude::*;
+use reqwest::Url;
+use rusqlite::Connection;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_REQUEST_BYTES: usize = 8 * 1024;
+const CONCURRENCY: usize = 8;
+
+pub struct Response {
+    pub status: &'static str,
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+impl Response {
+    fn text(status: &'static str, body: &str) -> Self {
+        Response {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body: body.to_string(),
+        }
+    }
+}
+
+// the sync address with the feed port, None while feeds are off
+pub fn feed_addr(sync_addr: SocketAddr, feed_port: Option<u16>) -> Option<SocketAddr> {
+    feed_port.map(|port| SocketAddr::new(sync_addr.ip(), port))
+}
+
+// the ?limit= asked for, capped so one request cannot render every note
+pub fn feed_limit(value: &str) -> u32 {
+    value
+        .parse()
+        .unwrap_or(feed::DEFAULT_LIMIT)
+        .min(feed::MAX_LIMIT)
+}
+
+pub fn respond(conn: &Connection, method: &str, target: &str) -> Response {
+    if method != "GET" && method != "HEAD" {
+        return Response::text("405 Method Not Allowed", "method not allowed");
+    }
+    let url = match Url::parse(&format!("http://localhost{}", target)) {
+        Ok(url) => url,
+        Err(_) => return Response::text("400 Bad Request", "bad request"),
+    };
+    let format = match url.path() {
+        "/atom.xml" => FeedFormat::Atom,
+        "/rss.xml" => FeedFormat::Rss,
+        _ => return Response::text("404 Not Found", "feeds are at /atom.xml and /rss.xml"),
+    };
+    let mut tag = None;
+    let mut limit = feed::DEFAULT_LIMIT;
+    for (key, value) in url.query_pairs() {
+        match key.as_ref() {
+            "tag" if !value.is_empty() => tag = Some(value.to_string()),
+            "limit" => limit = feed_limit(&value),
+            _ => {}
+        }
+    }
+    match feed::feed(conn, format, tag.as_deref(), limit) {
+        Ok(body) => Response {
+            status: "200 OK",
+            content_type: format.content_type(),
+            body,
+        },
+        Err(err) => {
+            eprintln!("feed error: {:?}", err);
+            Response::text("500 Internal Server Error", "feed error")
+        }
+    }
+}
+
+async fn read_request_head(stream: &mut TcpStream) -> io::Result<String> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 1024];
+    while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+        if buf.len() > MAX_REQUEST_BYTES {
+            return Err(io::Error::other("request too large"));
+        }
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            break;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+    Ok(String::from_utf8_lossy(&buf).to_string())
+}
+
+// each request counts against the peer's rate limit like an rpc does
+async fn handle(mut stream: TcpStream, peer: SocketAddr, policy: &Policy) -> io::Result<()> {
+    let head = tokio::time::timeout(REQUEST_TIMEOUT, read_request_head(&mut stream))
+        .await
+        .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "request timeout"))??;
+    let mut parts = head.lines().next().unwrap_or("").split_whitespace();
+    let method = parts.next().unwrap_or("");
+    let target = parts.next().unwrap_or("/");
+    let resp = if policy.take(&peer.ip().to_string()) {
+        policy.log(
+            "feed",
+            &peer.to_string(),
+            "",
+            &format!("{} {}", method, target),
+        );
+        respond(&get_sqlite_connection(), method, target)
+    } else {
+        policy.log(
+            "rate-limit",
+            &peer.to_string(),
+            "",
+            &format!("{} a minute", policy.config.rate_limit),
+        );
+        Response::text("429 Too Many Requests", "too many requests")
+    };
+    let mut out = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        resp.status,
+        resp.content_type,
+        resp.body.len()
+    )
+    .into_bytes();
+    if method != "HEAD" {
+        out.extend_from_slice(resp.body.as_bytes());
+    }
+    stream.write_all(&out).await?;
+    stream.shutdown().await
+}
+
+// admit turns away addresses outside the allowlist before anything is read
+pub async fn listen<F>(
+    addr: SocketAddr,
+    admit: F,
+) -> io::Result<impl Stream<Item = (TcpStream, SocketAddr)>>
+where
+    F: Fn(SocketAddr) -> bool,
+{
+    let listener = TcpListener::bind(addr).await?;
+    eprintln!("feeds at http://{}/atom.xml and /rss.xml", addr);
+    Ok(stream::unfold(listener, |listener| async move {
+        let accepted = listener.accept().await;
+        Some((accepted, listener))
+    })
+    .filter_map(move |accepted| {
+        future::ready(
+            accepted
+                .map_err(|err| eprintln!("feed accept error: {:?}", err))
+                .ok()
+                .filter(|(_, peer)| admit(*peer)),
+        )
+    }))
+}
+
+pub async fn serve(incoming: impl Stream<Item = (TcpStream, SocketAddr)>, policy: Arc<Policy>) {
+    incoming
+        .for_each_concurrent(CONCURRENCY, |(stream, peer)| {
+            let policy = policy.clone();
+            async move {
+                if let Err(err) = handle(stream, peer, &policy).await {
+                    eprintln!("feed request error: {:?}", err);
+                }
+            }
+        })
+        .await
+}
@@ -0,0 +1,114 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+// what each side speaks, exchanged before any sync rpc so devices on
+// different releases can still sync
+use crate::upgrade::get_meta_version;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+// bumped when a change to the rpc trait breaks older peers
+pub const PROTOCOL_VERSION: u32 = 2;
+// oldest peer protocol this build still syncs with, 1 only knew is_version_match
+pub const MIN_PROTOCOL_VERSION: u32 = 2;
+
+// optional parts of the protocol, a peer without one gets the older path
+pub const CHANGE_LOG: &str = "change-log";
+pub const NOTE_VERSIONS: &str = "note-versions";
+pub const INFO: &str = "info";
+pub const SYNC_FILTER: &str = "sync-filter";
+pub const ATTACHMENTS: &str = "attachments";
+pub const TOMBSTONES: &str = "tombstones";
+pub const CAPABILITIES: [&str; 6] = [
+    CHANGE_LOG,
+    NOTE_VERSIONS,
+    INFO,
+    SYNC_FILTER,
+    ATTACHMENTS,
+    TOMBSTONES,
+];
+// only a server says this, its clients pull and skip the push
+pub const READ_ONLY: &str = "read-only";
+// all a server says to a peer it does not trust, which has to pair first
+pub const NOT_PAIRED: &str = "not-paired";
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Default)]
+pub struct Hello {
+    pub protocol: u32,
+    pub min_protocol: u32,
+    // meta version of the database, for the user only
+    pub schema: String,
+    pub capabilities: Vec<String>,
+}
+
+impl Hello {
+    pub fn has(&self, capability: &str) -> bool {
+        self.capabilities.iter().any(|c| c == capability)
+    }
+
+    pub fn not_paired() -> Hello {
+        Hello {
+            capabilities: vec![NOT_PAIRED.to_string()],
+            ..Default::default()
+        }
+    }
+}
+
+pub fn hello(conn: &Connection) -> anyhow::Result<Hello> {
+    Ok(Hello {
+        protocol: PROTOCOL_VERSION,
+        min_protocol: MIN_PROTOCOL_VERSION,
+        schema: get_meta_version(conn)?,
+        capabilities: CAPABILITIES.iter().map(|c| c.to_string()).collect(),
+    })
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum Incompatible {
+    #[error("the server speaks sync protocol {server} (schema {schema}), this device needs at least {min}: upgrade Local Native on the server")]
+    UpgradeServer {
+        server: u32,
+        min: u32,
+        schema: String,
+    },
+    #[error("the server needs sync protocol {min} or newer, this device speaks {client}: upgrade Local Native on this device")]
+    UpgradeClient { client: u32, min: u32 },
+}
+
+// capabilities both sides have, or which side has to upgrade
+pub fn negotiate(local: &Hello, remote: &Hello) -> Result<Vec<String>, Incompatible> {
+    if remote.protocol < local.min_protocol {
+        return Err(Incompatible::UpgradeServer {
+            server: remote.protocol,
+            min: local.min_protocol,
+            schema: remote.schema.clone(),
+        });
+    }
+    if local.protocol < remote.min_protocol {
+        return Err(Incompatible::UpgradeClient {
+            client: local.protocol,
+            min: remote.min_protocol,
+        });
+    }
+    Ok(local
+        .capabilities
+        .iter()
+        .filter(|c| remote.has(c))
+        .cloned()
+        .collect())
+}
@@ -0,0 +1,72 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+// what a server tells a paired client about itself, shown before syncing
+use super::discovery::device_name;
+use super::handshake::Hello;
+use crate::cmd::peer;
+use crate::cmd::sync::sync_note_count;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Default)]
+pub struct ServerInfo {
+    pub name: String,
+    // fingerprint of the server's certificate
+    pub device_id: String,
+    pub app_version: String,
+    // meta version of the database
+    pub schema: String,
+    // notes a client can sync, local only ones are not counted
+    pub note_count: i64,
+    // of the newest of those notes, empty without any
+    pub last_modified: String,
+    pub capabilities: Vec<String>,
+    // of the asking device with this server, empty if it never synced
+    pub last_sync: String,
+}
+
+impl ServerInfo {
+    // what a server answers when it refuses or fails to describe itself, a
+    // real one always carries the server's fingerprint
+    pub fn is_refused(&self) -> bool {
+        self.device_id.is_empty()
+    }
+}
+
+pub fn server_info(
+    conn: &Connection,
+    hello: Hello,
+    device_id: &str,
+    caller: &str,
+) -> anyhow::Result<ServerInfo> {
+    let last_modified = conn.query_row(
+        "select ifnull(max(updated_at), '') FROM note where is_local = 0",
+        [],
+        |row| row.get(0),
+    )?;
+    Ok(ServerInfo {
+        name: device_name(),
+        device_id: device_id.to_string(),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        schema: hello.schema,
+        note_count: sync_note_count(conn)?,
+        last_modified,
+        capabilities: hello.capabilities,
+        last_sync: peer::last_sync(conn, caller)?,
+    })
+}
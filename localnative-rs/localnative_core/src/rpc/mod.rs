@@ -16,9 +16,25 @@
     along with this program.  If not, see <https://www.gnu.org/licenses/>.
 */
 pub mod client;
+pub mod daemon;
+pub mod discovery;
+pub mod feed;
+pub mod handshake;
+pub mod info;
+pub mod pairing;
+pub mod policy;
+pub mod progress;
 pub mod server;
+pub mod stream;
+pub mod tls;
 
+use crate::cmd::attachment::{AttachmentData, AttachmentKey};
+use crate::cmd::bundle::Tombstone;
+use crate::cmd::conflict::Clock;
+use crate::cmd::sync::Change;
 use crate::Note;
+use handshake::Hello;
+use info::ServerInfo;
 
 #[tarpc::service]
 pub trait LocalNative {
@@ -28,5 +44,35 @@ pub trait LocalNative {
     async fn send_note(note: Note) -> bool;
     async fn receive_note(uuid4: String) -> Note;
     async fn stop() -> ();
+    // batched sync, appended so older clients keep their request ids
+    async fn list_uuid4s(after: String, limit: u32) -> Vec<String>;
+    async fn send_notes(notes: Vec<Note>) -> u32;
+    async fn receive_notes(uuid4s: Vec<String>) -> Vec<Note>;
+    // incremental sync over the server's change log
+    async fn last_change_seq() -> i64;
+    async fn changes_since(after: i64, limit: u32) -> Vec<Change>;
+    // uuid4s the server lacks or holds an older version of
+    async fn diff_versions(candidates: Vec<(String, Clock)>) -> Vec<String>;
+    // protocol versions and capabilities, replaces is_version_match
+    async fn hello(client: Hello) -> Hello;
+    // who the server is and what it holds, for the user
+    async fn info() -> ServerInfo;
+    // the search query the client wants its notes filtered by, returns the
+    // one the server keeps for the client
+    async fn sync_filter(query: String) -> String;
+    // attachments go by note and content hash, after the notes they are on
+    async fn attachment_keys(after: AttachmentKey, limit: u32) -> Vec<AttachmentKey>;
+    // keys the server lacks, for notes it holds
+    async fn diff_attachments(candidates: Vec<AttachmentKey>) -> Vec<AttachmentKey>;
+    async fn send_attachments(attachments: Vec<AttachmentData>) -> u32;
+    async fn receive_attachments(keys: Vec<AttachmentKey>) -> Vec<AttachmentData>;
+    // notes deleted, by the change log seq of the delete; a note edited
+    // since outlives its tombstone
+    async fn tombstones_since(after: i64, limit: u32) -> Vec<Tombstone>;
+    async fn send_tombstones(tombstones: Vec<Tombstone>) -> u32;
+    // presents the code shown on the server once, the server then trusts this
+    // device; last like every rpc since the first six
+    async fn pair(code: String) -> bool;
+    async fn is_paired() -> bool;
 }
 pub type LnClient = LocalNativeClient;
@@ -0,0 +1,92 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+// one-time code the server shows and a new client presents once to get paired
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+pub const CODE_TTL: Duration = Duration::from_secs(5 * 60);
+// wrong guesses before the code is thrown away
+const MAX_ATTEMPTS: u32 = 5;
+
+struct PairingCode {
+    code: String,
+    created: Instant,
+    attempts: u32,
+}
+
+static CODE: Mutex<Option<PairingCode>> = Mutex::new(None);
+
+// replaces any previous code
+pub fn new_code() -> String {
+    let code = format!("{:06}", Uuid::new_v4().as_u128() % 1_000_000);
+    *CODE.lock().unwrap() = Some(PairingCode {
+        code: code.clone(),
+        created: Instant::now(),
+        attempts: 0,
+    });
+    code
+}
+
+// the code still valid, or a new one
+pub fn current_code() -> String {
+    let current = CODE.lock().unwrap();
+    if let Some(pairing) = current.as_ref() {
+        if pairing.created.elapsed() <= CODE_TTL {
+            return pairing.code.clone();
+        }
+    }
+    drop(current);
+    new_code()
+}
+
+// a new code once the shown one was used, burnt by wrong guesses or
+// expired, so a server that shows its code once keeps taking pairings
+pub fn renew(shown: &str) -> Option<String> {
+    let current = CODE.lock().unwrap();
+    let valid = current
+        .as_ref()
+        .is_some_and(|pairing| pairing.code == shown && pairing.created.elapsed() <= CODE_TTL);
+    drop(current);
+    (!valid).then(new_code)
+}
+
+pub fn clear_code() {
+    *CODE.lock().unwrap() = None;
+}
+
+// a matching code is used up, so are too many wrong ones
+pub fn redeem(code: &str) -> bool {
+    let mut current = CODE.lock().unwrap();
+    let Some(pairing) = current.as_mut() else {
+        return false;
+    };
+    if pairing.created.elapsed() > CODE_TTL {
+        *current = None;
+        return false;
+    }
+    if pairing.code == code {
+        *current = None;
+        return true;
+    }
+    pairing.attempts += 1;
+    if pairing.attempts >= MAX_ATTEMPTS {
+        *current = None;
+    }
+    false
+}
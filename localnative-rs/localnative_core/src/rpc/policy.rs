@@ -0,0 +1,292 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+// who may use the sync server and how much, read from a file next to the
+// database. every connection is logged as a line of json
+use crate::exe::{created_time, sqlite3_db_location};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+pub const CONFIG_FILE: &str = "sync_server.json";
+// what the transport's frames hold at most anyway
+pub const DEFAULT_MAX_PAYLOAD_BYTES: u64 = 8 * 1024 * 1024;
+pub const DEFAULT_IDLE_TIMEOUT_SECS: u64 = 5 * 60;
+// buckets of peers quiet this long are dropped once there are many
+const MAX_BUCKETS: usize = 1024;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct ServerConfig {
+    // ip addresses and cidr ranges that may connect, empty lets anyone try
+    #[serde(default)]
+    pub allow: Vec<String>,
+    // clients can pull but every note they send is refused
+    #[serde(default)]
+    pub read_only: bool,
+    // rpcs a minute from one peer, 0 for no limit
+    #[serde(default)]
+    pub rate_limit: u32,
+    // of the notes in one rpc, 0 for no limit
+    #[serde(default = "default_max_payload")]
+    pub max_payload_bytes: u64,
+    // whether a paired client may stop the server
+    #[serde(default = "default_remote_stop")]
+    pub remote_stop: bool,
+    // 0 keeps quiet connections open
+    #[serde(default = "default_idle_timeout")]
+    pub idle_timeout_secs: u64,
+    // connection logs are appended here as well as written to stderr
+    #[serde(default)]
+    pub log_file: Option<String>,
+    // plain http feeds of public notes on this port of the sync address, off unless set
+    #[serde(default)]
+    pub feed_port: Option<u16>,
+}
+
+fn default_max_payload() -> u64 {
+    DEFAULT_MAX_PAYLOAD_BYTES
+}
+
+fn default_remote_stop() -> bool {
+    true
+}
+
+fn default_idle_timeout() -> u64 {
+    DEFAULT_IDLE_TIMEOUT_SECS
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            allow: Vec::new(),
+            read_only: false,
+            rate_limit: 0,
+            max_payload_bytes: DEFAULT_MAX_PAYLOAD_BYTES,
+            remote_stop: true,
+            idle_timeout_secs: DEFAULT_IDLE_TIMEOUT_SECS,
+            log_file: None,
+            feed_port: None,
+        }
+    }
+}
+
+// next to the database, like the device certificate
+pub fn config_path() -> PathBuf {
+    let location = sqlite3_db_location();
+    Path::new(&location)
+        .parent()
+        .map_or_else(|| PathBuf::from(CONFIG_FILE), |dir| dir.join(CONFIG_FILE))
+}
+
+fn validate(config: &ServerConfig) -> anyhow::Result<()> {
+    for allow in &config.allow {
+        allow.parse::<Cidr>()?;
+    }
+    if config.feed_port == Some(0) {
+        return Err(anyhow::anyhow!("feed_port 0 is not a port"));
+    }
+    Ok(())
+}
+
+pub fn load_config(path: &Path) -> anyhow::Result<ServerConfig> {
+    let config: ServerConfig = serde_json::from_str(&fs::read_to_string(path)?)?;
+    validate(&config)?;
+    Ok(config)
+}
+
+pub fn save_config(path: &Path, config: &ServerConfig) -> anyhow::Result<()> {
+    validate(config)?;
+    fs::write(path, serde_json::to_string_pretty(config)?)?;
+    Ok(())
+}
+
+// the given file, or the default one when it exists, with the fields of
+// overrides replacing what the file says
+pub fn resolve_config(
+    path: Option<&Path>,
+    overrides: Option<&serde_json::Value>,
+) -> anyhow::Result<ServerConfig> {
+    let default_path = config_path();
+    let path = match path {
+        Some(path) => Some(path),
+        None if default_path.exists() => Some(default_path.as_path()),
+        None => None,
+    };
+    let mut value = match path {
+        Some(path) => serde_json::from_str(&fs::read_to_string(path)?)?,
+        None => serde_json::json!({}),
+    };
+    if let (Some(fields), Some(overrides)) = (
+        value.as_object_mut(),
+        overrides.and_then(serde_json::Value::as_object),
+    ) {
+        for (key, field) in overrides {
+            fields.insert(key.clone(), field.clone());
+        }
+    }
+    let config: ServerConfig = serde_json::from_value(value)?;
+    validate(&config)?;
+    Ok(config)
+}
+
+// an address with a prefix length, a bare address is a range of one
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Cidr {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+fn bits(ip: IpAddr) -> (u128, u8) {
+    match ip.to_canonical() {
+        IpAddr::V4(ip) => (u32::from(ip) as u128, 32),
+        IpAddr::V6(ip) => (u128::from(ip), 128),
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let (addr, prefix) = match s.trim().split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (s.trim(), None),
+        };
+        let addr: IpAddr = addr
+            .parse()
+            .map_err(|_| anyhow::anyhow!("{} is not an ip address or cidr range", s))?;
+        let max = bits(addr).1;
+        let prefix = match prefix {
+            Some(prefix) => prefix
+                .parse()
+                .ok()
+                .filter(|prefix| *prefix <= max)
+                .ok_or_else(|| anyhow::anyhow!("{} has a prefix longer than {}", s, max))?,
+            None => max,
+        };
+        Ok(Cidr { addr, prefix })
+    }
+}
+
+impl Cidr {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let (net, len) = bits(self.addr);
+        let (ip, ip_len) = bits(ip);
+        if len != ip_len {
+            return false;
+        }
+        // a /0 shifts every bit out, both sides are then None
+        let shift = u32::from(len - self.prefix);
+        net.checked_shr(shift) == ip.checked_shr(shift)
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+pub struct Policy {
+    pub config: ServerConfig,
+    allow: Vec<Cidr>,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl Policy {
+    pub fn new(config: ServerConfig) -> anyhow::Result<Self> {
+        let allow = config
+            .allow
+            .iter()
+            .map(|allow| allow.parse())
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Policy {
+            config,
+            allow,
+            buckets: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn allows(&self, ip: IpAddr) -> bool {
+        self.allow.is_empty() || self.allow.iter().any(|cidr| cidr.contains(ip))
+    }
+
+    // one rpc from the peer, false once it used up its minute
+    pub fn take(&self, peer: &str) -> bool {
+        self.take_at(peer, Instant::now())
+    }
+
+    // a full bucket holds a minute of rpcs and refills at the same rate
+    pub fn take_at(&self, peer: &str, now: Instant) -> bool {
+        if self.config.rate_limit == 0 {
+            return true;
+        }
+        let rate = f64::from(self.config.rate_limit);
+        let mut buckets = self.buckets.lock().unwrap_or_else(|err| err.into_inner());
+        if buckets.len() > MAX_BUCKETS {
+            buckets
+                .retain(|_, bucket| now.duration_since(bucket.updated) < Duration::from_secs(60));
+        }
+        let bucket = buckets.entry(peer.to_string()).or_insert(Bucket {
+            tokens: rate,
+            updated: now,
+        });
+        let refill = now.duration_since(bucket.updated).as_secs_f64() * rate / 60.0;
+        bucket.tokens = (bucket.tokens + refill).min(rate);
+        bucket.updated = now;
+        if bucket.tokens < 1.0 {
+            return false;
+        }
+        bucket.tokens -= 1.0;
+        true
+    }
+
+    pub fn idle_timeout(&self) -> Option<Duration> {
+        Some(Duration::from_secs(self.config.idle_timeout_secs)).filter(|d| !d.is_zero())
+    }
+
+    // over the limit, or none is set
+    pub fn too_large(&self, bytes: u64) -> bool {
+        self.config.max_payload_bytes != 0 && bytes > self.config.max_payload_bytes
+    }
+
+    // connect, reject, refuse, close and the like, one json object a line
+    pub fn log(&self, event: &str, peer: &str, fingerprint: &str, detail: &str) {
+        let line = serde_json::json!({
+            "time": created_time(),
+            "event": event,
+            "peer": peer,
+            "fingerprint": fingerprint,
+            "detail": detail,
+        })
+        .to_string();
+        eprintln!("{}", line);
+        if let Some(ref log_file) = self.config.log_file {
+            let written = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(log_file)
+                .and_then(|mut file| writeln!(file, "{}", line));
+            if let Err(err) = written {
+                eprintln!("connection log {} error: {:?}", log_file, err);
+            }
+        }
+    }
+}
@@ -0,0 +1,174 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+// progress events and cancellation for a client sync. guis holding the
+// tracker get events pushed to them, the json api polls the one started last
+use crate::Note;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use thiserror::Error;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum Phase {
+    #[default]
+    Connecting,
+    Pushing,
+    Pulling,
+    Done,
+    Cancelled,
+    Failed,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Default)]
+pub struct Progress {
+    pub phase: Phase,
+    // candidates compared so far in this phase
+    pub done: u64,
+    // 0 while the peer cannot tell how many there are
+    pub total: u64,
+    // note payload sent and received over the whole sync
+    pub bytes: u64,
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+#[error("sync cancelled")]
+pub struct Cancelled;
+
+type Observer = Arc<dyn Fn(&Progress) + Send + Sync>;
+
+// cloned into the sync, cancel() from anywhere stops it before its next rpc
+#[derive(Clone, Default)]
+pub struct Tracker {
+    cancelled: Arc<AtomicBool>,
+    progress: Arc<Mutex<Progress>>,
+    observer: Option<Observer>,
+}
+
+impl std::fmt::Debug for Tracker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Tracker")
+            .field("cancelled", &self.is_cancelled())
+            .field("progress", &self.progress())
+            .finish()
+    }
+}
+
+impl Tracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // called on every event, from the thread running the sync
+    pub fn with_observer(observer: impl Fn(&Progress) + Send + Sync + 'static) -> Self {
+        Tracker {
+            observer: Some(Arc::new(observer)),
+            ..Default::default()
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    pub fn check(&self) -> Result<(), Cancelled> {
+        if self.is_cancelled() {
+            Err(Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn progress(&self) -> Progress {
+        self.progress.lock().unwrap().clone()
+    }
+
+    fn update(&self, f: impl FnOnce(&mut Progress)) {
+        let progress = {
+            let mut progress = self.progress.lock().unwrap();
+            f(&mut progress);
+            progress.clone()
+        };
+        if let Some(observer) = &self.observer {
+            observer(&progress);
+        }
+    }
+
+    // a new phase starts counting from 0, bytes carry over
+    pub fn phase(&self, phase: Phase, total: u64) {
+        self.update(|p| {
+            p.phase = phase;
+            p.done = 0;
+            p.total = total;
+        });
+    }
+
+    pub fn advance(&self, done: u64, bytes: u64) {
+        self.update(|p| {
+            p.done = done;
+            p.bytes += bytes;
+        });
+    }
+
+    // done, cancelled or failed, whichever the result says
+    pub fn finish<T>(&self, result: &anyhow::Result<T>) {
+        let phase = match result {
+            Ok(_) => Phase::Done,
+            Err(err) if err.is::<Cancelled>() => Phase::Cancelled,
+            Err(_) => Phase::Failed,
+        };
+        self.update(|p| {
+            if phase == Phase::Done {
+                p.done = p.total;
+            }
+            p.phase = phase;
+        });
+    }
+}
+
+// size of what a note puts on the wire, near enough for a progress bar
+pub fn note_bytes(notes: &[Note]) -> u64 {
+    notes
+        .iter()
+        .map(|note| {
+            note.uuid4.len()
+                + note.title.len()
+                + note.url.len()
+                + note.tags.len()
+                + note.description.len()
+                + note.comments.len()
+                + note.annotations.len()
+                + note.created_at.len()
+        })
+        .sum::<usize>() as u64
+}
+
+static CURRENT: Mutex<Option<Tracker>> = Mutex::new(None);
+
+// the sync the json api reports on and cancels
+pub fn set_current(tracker: &Tracker) {
+    *CURRENT.lock().unwrap() = Some(tracker.clone());
+}
+
+pub fn current() -> Option<Tracker> {
+    CURRENT.lock().unwrap().clone()
+}
@@ -16,33 +16,292 @@
     along with this program.  If not, see <https://www.gnu.org/licenses/>.
 */
 
-use crate::cmd::insert;
-use crate::cmd::sync::{diff_uuid4_from_server, diff_uuid4_to_server, get_note_by_uuid4};
-use crate::exe::get_sqlite_connection;
+use crate::cmd::attachment::{
+    attachment_bytes, get_attachments_by_keys, missing_attachments, save_attachments,
+    AttachmentData, AttachmentKey, ATTACHMENT_BATCH,
+};
+use crate::cmd::bundle::{delete_by_tombstones, tombstones_since, Tombstone};
+use crate::cmd::conflict::Clock;
+use crate::cmd::peer;
+use crate::cmd::sync::{
+    diff_uuid4_from_server, diff_uuid4_to_server, diff_versions, get_note_by_uuid4,
+    get_notes_by_uuid4s, last_change_seq, merge_notes, Change, MergeSummary, CANDIDATE_CHUNK,
+    NOTE_BATCH,
+};
+use crate::cmd::sync_filter::{self, SyncFilter};
+use crate::exe::{created_time, get_sqlite_connection};
 use crate::upgrade::get_meta_version;
 use crate::Note;
-use std::process;
+use rusqlite::Connection;
 
+use super::discovery;
+use super::feed;
+use super::handshake::{self, Hello, READ_ONLY};
+use super::info::{self, ServerInfo};
+use super::pairing;
+use super::policy::{self, Policy, ServerConfig};
+use super::progress::note_bytes;
+use super::stream::{self, Endpoint};
+use super::tls;
 use super::LocalNative;
 use futures::{
     future::{self, Ready},
     prelude::*,
 };
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use std::{io, net::SocketAddr};
 use stream_cancel::Trigger;
 use tarpc::server::incoming::Incoming;
 use tarpc::server::Channel;
 use tarpc::{context, server};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpStream;
 use tokio::runtime::Runtime;
-use tokio_serde::formats::Bincode;
+use tokio::sync::Notify;
+use tokio::time::Instant;
+use tokio_rustls::server::TlsStream;
+
+// how soon a used, burnt or expired pairing code is replaced
+const PAIRING_CHECK: Duration = Duration::from_secs(5);
+
+// one connection, closed by the server or once it idles for too long
+struct Session {
+    started: Instant,
+    // millis after started
+    last_active: AtomicU64,
+    reason: Mutex<Option<String>>,
+    closed: Notify,
+    // the peer synced, or previewed a sync
+    synced: AtomicBool,
+    // search query the peer asked its notes to match
+    filter: Mutex<String>,
+    // the notes that filter and the one kept for the peer let through, as of
+    // a change log seq; loaded again once the log moved on
+    loaded: Mutex<Option<(i64, Arc<SyncFilter>)>>,
+}
+
+impl Session {
+    fn new() -> Arc<Self> {
+        Arc::new(Session {
+            started: Instant::now(),
+            last_active: AtomicU64::new(0),
+            reason: Mutex::new(None),
+            closed: Notify::new(),
+            synced: AtomicBool::new(false),
+            filter: Mutex::new(String::new()),
+            loaded: Mutex::new(None),
+        })
+    }
+
+    fn touch(&self) {
+        let millis = self.started.elapsed().as_millis() as u64;
+        self.last_active.store(millis, Ordering::Relaxed);
+    }
+
+    fn close(&self, reason: &str) {
+        let mut closing = self.reason.lock().unwrap_or_else(|err| err.into_inner());
+        closing.get_or_insert_with(|| reason.to_string());
+        self.closed.notify_one();
+    }
+
+    // why the connection has to end
+    async fn ended(&self, idle: Option<Duration>) -> String {
+        loop {
+            let last_active = self.last_active.load(Ordering::Relaxed);
+            let deadline =
+                self.started + Duration::from_millis(last_active) + idle.unwrap_or_default();
+            tokio::select! {
+                _ = self.closed.notified() => {
+                    let reason = self.reason.lock().unwrap_or_else(|err| err.into_inner());
+                    return reason.clone().unwrap_or_default();
+                }
+                // an rpc in the meantime moved the deadline, wait again
+                _ = tokio::time::sleep_until(deadline), if idle.is_some() => {
+                    if self.last_active.load(Ordering::Relaxed) == last_active {
+                        return "idle timeout".to_string();
+                    }
+                }
+            }
+        }
+    }
+}
 
 #[derive(Clone)]
-struct LocalNativeServer(SocketAddr, Option<tokio::sync::mpsc::Sender<()>>);
+struct LocalNativeServer {
+    // for the logs
+    peer: String,
+    // what pairing lists the peer as
+    name: String,
+    exit_tx: Option<tokio::sync::mpsc::Sender<()>>,
+    // of the peer's certificate
+    fingerprint: String,
+    // the transport already vouched for the peer, like ssh or a unix socket
+    // only this user can open
+    authenticated: bool,
+    policy: Arc<Policy>,
+    session: Arc<Session>,
+}
+
+impl LocalNativeServer {
+    fn tcp(
+        stream: &TlsStream<TcpStream>,
+        exit_tx: Option<tokio::sync::mpsc::Sender<()>>,
+        policy: &Arc<Policy>,
+    ) -> Self {
+        let addr = peer_addr(stream);
+        LocalNativeServer {
+            peer: addr.to_string(),
+            name: addr.ip().to_string(),
+            exit_tx,
+            fingerprint: tls::server_peer_fingerprint(stream),
+            authenticated: false,
+            policy: policy.clone(),
+            session: Session::new(),
+        }
+    }
+
+    fn log(&self, event: &str, detail: &str) {
+        self.policy
+            .log(event, &self.peer, &self.fingerprint, detail);
+    }
+
+    // every rpc counts against the peer's rate limit and keeps the
+    // connection from idling out, a peer over the limit is disconnected
+    fn limit(&self) -> bool {
+        self.session.touch();
+        if self.policy.take(&self.name) {
+            return true;
+        }
+        self.log(
+            "rate-limit",
+            &format!("{} a minute", self.policy.config.rate_limit),
+        );
+        self.session.close("rate limit");
+        false
+    }
+
+    fn admit(&self) -> bool {
+        self.limit() && self.is_trusted()
+    }
+
+    // the peer's last sync is recorded once it hangs up
+    fn admit_sync(&self) -> bool {
+        let admitted = self.admit();
+        if admitted {
+            self.session.synced.store(true, Ordering::Relaxed);
+        }
+        admitted
+    }
+
+    // notes the peer gets, by the filter kept for it here and the one it
+    // asked for; what it sends is filtered on its side
+    fn note_filter(&self, conn: &Connection) -> anyhow::Result<Arc<SyncFilter>> {
+        let seq = last_change_seq(conn)?;
+        let mut loaded = self
+            .session
+            .loaded
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        if let Some((at, filter)) = loaded.as_ref() {
+            if *at == seq {
+                return Ok(filter.clone());
+            }
+        }
+        let asked = self
+            .session
+            .filter
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .clone();
+        let query = sync_filter::combine(&peer::filter(conn, &self.fingerprint)?, &asked);
+        let filter = Arc::new(SyncFilter::load(conn, &query)?);
+        *loaded = Some((seq, filter.clone()));
+        Ok(filter)
+    }
+
+    // capabilities of the server as configured
+    fn announce(&self, mut hello: Hello) -> Hello {
+        if self.policy.config.read_only {
+            hello.capabilities.push(READ_ONLY.to_string());
+        }
+        hello
+    }
+
+    // a read-only server refuses notes, as does one sent more than it takes
+    fn accepts(&self, notes: &[Note]) -> bool {
+        self.accepts_bytes(note_bytes(notes), "notes")
+    }
+
+    fn accepts_bytes(&self, bytes: u64, what: &str) -> bool {
+        if self.policy.config.read_only {
+            self.log("refuse", "read-only");
+            return false;
+        }
+        if self.policy.too_large(bytes) {
+            self.log(
+                "refuse",
+                &format!(
+                    "{} bytes of {}, the limit is {}",
+                    bytes, what, self.policy.config.max_payload_bytes
+                ),
+            );
+            return false;
+        }
+        true
+    }
+
+    // every rpc except pairing needs the peer in the trust store
+    fn is_trusted(&self) -> bool {
+        if self.authenticated {
+            return true;
+        }
+        let conn = get_sqlite_connection();
+        match peer::is_trusted(&conn, &self.fingerprint) {
+            Ok(true) => true,
+            Ok(false) => {
+                self.log("refuse", "not paired");
+                false
+            }
+            Err(err) => {
+                eprintln!("trust store error: {:?}", err);
+                false
+            }
+        }
+    }
+}
 
 impl LocalNative for LocalNativeServer {
+    type PairFut = Ready<bool>;
+    fn pair(self, _: context::Context, code: String) -> Self::PairFut {
+        if !self.limit() || !pairing::redeem(&code) {
+            eprintln!("pairing code rejected for {}", self.peer);
+            return future::ready(false);
+        }
+        let conn = get_sqlite_connection();
+        match peer::trust_peer(&conn, &self.fingerprint, &self.name) {
+            Ok(_) => {
+                eprintln!("paired {} fingerprint {}", self.peer, self.fingerprint);
+                future::ready(true)
+            }
+            Err(err) => {
+                eprintln!("pair error: {:?}", err);
+                future::ready(false)
+            }
+        }
+    }
+    type IsPairedFut = Ready<bool>;
+    fn is_paired(self, _: context::Context) -> Self::IsPairedFut {
+        future::ready(self.admit())
+    }
     type IsVersionMatchFut = Ready<bool>;
     #[allow(clippy::wrong_self_convention)]
     fn is_version_match(self, _: context::Context, version: String) -> Self::IsVersionMatchFut {
+        if !self.admit() {
+            return future::ready(false);
+        }
         let conn = get_sqlite_connection();
         let meta_version = get_meta_version(&conn).unwrap_or_else(|_| "0.3.10".into());
         if version == meta_version {
@@ -57,11 +316,14 @@ impl LocalNative for LocalNativeServer {
         _: context::Context,
         candidates: Vec<String>,
     ) -> Self::DiffUuid4ToServerFut {
+        if !self.admit_sync() {
+            return future::ready(Vec::new());
+        }
         let conn = get_sqlite_connection();
         match diff_uuid4_to_server(&conn, candidates) {
             Ok(diff) => future::ready(diff),
             Err(err) => {
-                println!("diff_uuid4_to_server error: {}", err);
+                eprintln!("diff_uuid4_to_server error: {}", err);
                 future::ready(Vec::new())
             }
         }
@@ -72,112 +334,685 @@ impl LocalNative for LocalNativeServer {
         _: context::Context,
         candidates: Vec<String>,
     ) -> Self::DiffUuid4FromServerFut {
+        if !self.admit_sync() {
+            return future::ready(Vec::new());
+        }
         let conn = get_sqlite_connection();
-        match diff_uuid4_from_server(&conn, candidates) {
+        let diff = self.note_filter(&conn).and_then(|filter| {
+            let mut diff = diff_uuid4_from_server(&conn, candidates)?;
+            diff.retain(|uuid4| filter.allows(uuid4));
+            Ok(diff)
+        });
+        match diff {
             Ok(diff) => future::ready(diff),
             Err(err) => {
-                println!("diff_uuid4_from_server error: {}", err);
+                eprintln!("diff_uuid4_from_server error: {}", err);
                 future::ready(Vec::new())
             }
         }
     }
     type SendNoteFut = Ready<bool>;
     fn send_note(self, _: context::Context, note: Note) -> Self::SendNoteFut {
-        eprintln!("upsert note {:?}", note);
-        let res = match insert(note) {
-            Ok(_) => true,
+        if !self.admit_sync() || !self.accepts(std::slice::from_ref(&note)) {
+            return future::ready(false);
+        }
+        eprintln!("upsert note {}", note.uuid4);
+        if note.is_local {
+            eprintln!("refuse local only note {}", note.uuid4);
+            return future::ready(false);
+        }
+        let conn = get_sqlite_connection();
+        match merge_notes(&conn, std::slice::from_ref(&note)) {
+            Ok(summary) => {
+                eprintln!("merged {:?}", summary);
+                future::ready(true)
+            }
             Err(err) => {
                 eprintln!("insert note error: {:?}", err);
-                false
+                future::ready(false)
             }
-        };
-        future::ready(res)
+        }
     }
     type ReceiveNoteFut = Ready<Note>;
     fn receive_note(self, _: context::Context, uuid4: String) -> Self::ReceiveNoteFut {
+        if !self.admit_sync() {
+            return future::ready(Note::default());
+        }
         eprintln!("receive note {:?}", uuid4);
         let conn = get_sqlite_connection();
-        match get_note_by_uuid4(&conn, &uuid4) {
+        let note = self.note_filter(&conn).and_then(|filter| {
+            if !filter.allows(&uuid4) {
+                return Err(anyhow::anyhow!("{} is filtered out", uuid4));
+            }
+            get_note_by_uuid4(&conn, &uuid4)
+        });
+        match note {
             Ok(note) => future::ready(note),
             Err(err) => {
-                println!("receive note error: {}", err);
+                eprintln!("receive note error: {}", err);
                 future::ready(Note::default())
             }
         }
     }
+    type ListUuid4sFut = Ready<Vec<String>>;
+    fn list_uuid4s(self, _: context::Context, after: String, limit: u32) -> Self::ListUuid4sFut {
+        if !self.admit_sync() {
+            return future::ready(Vec::new());
+        }
+        let conn = get_sqlite_connection();
+        let page = self
+            .note_filter(&conn)
+            .and_then(|filter| filter.uuid4_page(&conn, &after, limit.min(CANDIDATE_CHUNK)));
+        match page {
+            Ok(page) => future::ready(page),
+            Err(err) => {
+                eprintln!("list_uuid4s error: {}", err);
+                future::ready(Vec::new())
+            }
+        }
+    }
+    type SendNotesFut = Ready<u32>;
+    fn send_notes(self, _: context::Context, notes: Vec<Note>) -> Self::SendNotesFut {
+        if !self.admit_sync() || !self.accepts(&notes) {
+            return future::ready(0);
+        }
+        eprintln!("upsert {} notes", notes.len());
+        let conn = get_sqlite_connection();
+        // a client may send more than a batch, merged a batch per transaction
+        let merged = notes.chunks(NOTE_BATCH).try_fold(
+            MergeSummary::default(),
+            |mut summary, batch| -> anyhow::Result<MergeSummary> {
+                summary += merge_notes(&conn, batch)?;
+                Ok(summary)
+            },
+        );
+        match merged {
+            Ok(summary) => {
+                eprintln!("merged {:?}", summary);
+                future::ready((summary.inserted + summary.updated + summary.conflicts) as u32)
+            }
+            Err(err) => {
+                eprintln!("insert notes error: {:?}", err);
+                future::ready(0)
+            }
+        }
+    }
+    type ReceiveNotesFut = Ready<Vec<Note>>;
+    fn receive_notes(self, _: context::Context, uuid4s: Vec<String>) -> Self::ReceiveNotesFut {
+        if !self.admit_sync() {
+            return future::ready(Vec::new());
+        }
+        eprintln!("receive {} notes", uuid4s.len());
+        let conn = get_sqlite_connection();
+        let uuid4s = &uuid4s[..uuid4s.len().min(NOTE_BATCH)];
+        let notes = self.note_filter(&conn).and_then(|filter| {
+            let mut notes = get_notes_by_uuid4s(&conn, uuid4s)?;
+            notes.retain(|note| filter.allows(&note.uuid4));
+            Ok(notes)
+        });
+        match notes {
+            Ok(notes) => future::ready(notes),
+            Err(err) => {
+                eprintln!("receive notes error: {}", err);
+                future::ready(Vec::new())
+            }
+        }
+    }
+    type LastChangeSeqFut = Ready<i64>;
+    fn last_change_seq(self, _: context::Context) -> Self::LastChangeSeqFut {
+        if !self.admit_sync() {
+            return future::ready(0);
+        }
+        let conn = get_sqlite_connection();
+        match last_change_seq(&conn) {
+            Ok(seq) => future::ready(seq),
+            Err(err) => {
+                eprintln!("last_change_seq error: {}", err);
+                future::ready(0)
+            }
+        }
+    }
+    type ChangesSinceFut = Ready<Vec<Change>>;
+    fn changes_since(self, _: context::Context, after: i64, limit: u32) -> Self::ChangesSinceFut {
+        if !self.admit_sync() {
+            return future::ready(Vec::new());
+        }
+        let conn = get_sqlite_connection();
+        let changes = self
+            .note_filter(&conn)
+            .and_then(|filter| filter.changes_since(&conn, after, limit.min(CANDIDATE_CHUNK)));
+        match changes {
+            Ok(changes) => future::ready(changes),
+            Err(err) => {
+                eprintln!("changes_since error: {}", err);
+                future::ready(Vec::new())
+            }
+        }
+    }
+    type DiffVersionsFut = Ready<Vec<String>>;
+    fn diff_versions(
+        self,
+        _: context::Context,
+        candidates: Vec<(String, Clock)>,
+    ) -> Self::DiffVersionsFut {
+        if !self.admit_sync() {
+            return future::ready(Vec::new());
+        }
+        let conn = get_sqlite_connection();
+        match diff_versions(&conn, candidates) {
+            Ok(diff) => future::ready(diff),
+            Err(err) => {
+                eprintln!("diff_versions error: {}", err);
+                future::ready(Vec::new())
+            }
+        }
+    }
+    type HelloFut = Ready<Hello>;
+    fn hello(self, _: context::Context, client: Hello) -> Self::HelloFut {
+        if !self.limit() {
+            return future::ready(Hello::default());
+        }
+        // told apart from a server too old to answer
+        if !self.is_trusted() {
+            return future::ready(Hello::not_paired());
+        }
+        let conn = get_sqlite_connection();
+        match handshake::hello(&conn) {
+            Ok(hello) => {
+                if let Err(err) = handshake::negotiate(&hello, &client) {
+                    eprintln!("client {} {:?}: {}", self.peer, client, err);
+                }
+                future::ready(self.announce(hello))
+            }
+            Err(err) => {
+                eprintln!("hello error: {}", err);
+                future::ready(Hello::default())
+            }
+        }
+    }
+    type InfoFut = Ready<ServerInfo>;
+    fn info(self, _: context::Context) -> Self::InfoFut {
+        if !self.admit() {
+            return future::ready(ServerInfo::default());
+        }
+        let conn = get_sqlite_connection();
+        let info = handshake::hello(&conn).and_then(|hello| {
+            let device_id = tls::device_identity()?.fingerprint();
+            info::server_info(&conn, self.announce(hello), &device_id, &self.fingerprint)
+        });
+        match info {
+            Ok(info) => future::ready(info),
+            Err(err) => {
+                eprintln!("info error: {}", err);
+                future::ready(ServerInfo::default())
+            }
+        }
+    }
+    type SyncFilterFut = Ready<String>;
+    fn sync_filter(self, _: context::Context, query: String) -> Self::SyncFilterFut {
+        if !self.admit_sync() {
+            return future::ready(String::new());
+        }
+        self.log("sync-filter", &query);
+        *self
+            .session
+            .filter
+            .lock()
+            .unwrap_or_else(|err| err.into_inner()) = query;
+        *self
+            .session
+            .loaded
+            .lock()
+            .unwrap_or_else(|err| err.into_inner()) = None;
+        let conn = get_sqlite_connection();
+        // loaded once here rather than on the rpcs that follow
+        if let Err(err) = self.note_filter(&conn) {
+            eprintln!("sync_filter error: {}", err);
+        }
+        match peer::filter(&conn, &self.fingerprint) {
+            Ok(filter) => future::ready(filter),
+            Err(err) => {
+                eprintln!("sync_filter error: {}", err);
+                future::ready(String::new())
+            }
+        }
+    }
+    type AttachmentKeysFut = Ready<Vec<AttachmentKey>>;
+    fn attachment_keys(
+        self,
+        _: context::Context,
+        after: AttachmentKey,
+        limit: u32,
+    ) -> Self::AttachmentKeysFut {
+        if !self.admit_sync() {
+            return future::ready(Vec::new());
+        }
+        let conn = get_sqlite_connection();
+        let page = self.note_filter(&conn).and_then(|filter| {
+            filter.attachment_key_page(&conn, &after, limit.min(CANDIDATE_CHUNK))
+        });
+        match page {
+            Ok(page) => future::ready(page),
+            Err(err) => {
+                eprintln!("attachment_keys error: {}", err);
+                future::ready(Vec::new())
+            }
+        }
+    }
+    type DiffAttachmentsFut = Ready<Vec<AttachmentKey>>;
+    fn diff_attachments(
+        self,
+        _: context::Context,
+        candidates: Vec<AttachmentKey>,
+    ) -> Self::DiffAttachmentsFut {
+        if !self.admit_sync() {
+            return future::ready(Vec::new());
+        }
+        let conn = get_sqlite_connection();
+        match missing_attachments(&conn, candidates) {
+            Ok(diff) => future::ready(diff),
+            Err(err) => {
+                eprintln!("diff_attachments error: {}", err);
+                future::ready(Vec::new())
+            }
+        }
+    }
+    type SendAttachmentsFut = Ready<u32>;
+    fn send_attachments(
+        self,
+        _: context::Context,
+        attachments: Vec<AttachmentData>,
+    ) -> Self::SendAttachmentsFut {
+        // bounded by the payload limit, not by count
+        if !self.admit_sync() || !self.accepts_bytes(attachment_bytes(&attachments), "attachments")
+        {
+            return future::ready(0);
+        }
+        eprintln!("save {} attachments", attachments.len());
+        let conn = get_sqlite_connection();
+        match save_attachments(&conn, &attachments) {
+            Ok(saved) => future::ready(saved),
+            Err(err) => {
+                eprintln!("save attachments error: {:?}", err);
+                future::ready(0)
+            }
+        }
+    }
+    type ReceiveAttachmentsFut = Ready<Vec<AttachmentData>>;
+    fn receive_attachments(
+        self,
+        _: context::Context,
+        keys: Vec<AttachmentKey>,
+    ) -> Self::ReceiveAttachmentsFut {
+        if !self.admit_sync() {
+            return future::ready(Vec::new());
+        }
+        let conn = get_sqlite_connection();
+        let keys = &keys[..keys.len().min(ATTACHMENT_BATCH)];
+        let attachments = self.note_filter(&conn).and_then(|filter| {
+            let mut attachments = get_attachments_by_keys(&conn, keys)?;
+            attachments.retain(|a| filter.allows(&a.attachment.note_uuid4));
+            Ok(attachments)
+        });
+        match attachments {
+            Ok(attachments) => future::ready(attachments),
+            Err(err) => {
+                eprintln!("receive attachments error: {}", err);
+                future::ready(Vec::new())
+            }
+        }
+    }
+    type TombstonesSinceFut = Ready<Vec<Tombstone>>;
+    fn tombstones_since(
+        self,
+        _: context::Context,
+        after: i64,
+        limit: u32,
+    ) -> Self::TombstonesSinceFut {
+        if !self.admit_sync() {
+            return future::ready(Vec::new());
+        }
+        let conn = get_sqlite_connection();
+        let tombstones = self.note_filter(&conn).and_then(|filter| {
+            if !filter.is_all() {
+                return Ok(Vec::new());
+            }
+            tombstones_since(&conn, after, limit.min(CANDIDATE_CHUNK))
+        });
+        match tombstones {
+            Ok(tombstones) => future::ready(tombstones),
+            Err(err) => {
+                eprintln!("tombstones_since error: {}", err);
+                future::ready(Vec::new())
+            }
+        }
+    }
+    type SendTombstonesFut = Ready<u32>;
+    fn send_tombstones(
+        self,
+        _: context::Context,
+        tombstones: Vec<Tombstone>,
+    ) -> Self::SendTombstonesFut {
+        if !self.admit_sync() || !self.accepts_bytes(0, "tombstones") {
+            return future::ready(0);
+        }
+        let conn = get_sqlite_connection();
+        let deleted = self.note_filter(&conn).and_then(|filter| {
+            if !filter.is_all() {
+                self.log("refuse", "tombstones through a sync filter");
+                return Ok(0);
+            }
+            let tx = conn.unchecked_transaction()?;
+            let deleted = delete_by_tombstones(&tx, &tombstones)?;
+            tx.commit()?;
+            Ok(deleted)
+        });
+        match deleted {
+            Ok(deleted) => {
+                eprintln!("deleted {} of {} tombstones", deleted, tombstones.len());
+                future::ready(deleted as u32)
+            }
+            Err(err) => {
+                eprintln!("send tombstones error: {:?}", err);
+                future::ready(0)
+            }
+        }
+    }
     type StopFut = Ready<()>;
-    #[allow(unreachable_code)]
     fn stop(self, _: context::Context) -> Self::StopFut {
-        eprintln!("server stopping");
-        if let Some(ref exit_tx) = self.1 {
-            let _ = exit_tx.try_send(());
-        } else {
-            process::exit(0);
+        if !self.admit() {
+            return future::ready(());
+        }
+        if !self.policy.config.remote_stop {
+            self.log("refuse", "remote stop is disabled");
+            return future::ready(());
+        }
+        self.log("stop", "");
+        match self.exit_tx {
+            Some(ref exit_tx) => {
+                let _ = exit_tx.try_send(());
+            }
+            // a server on stdio has only this connection to stop
+            None => self.session.close("stopped"),
         }
         future::ready(())
     }
 }
 
-async fn start_server(addr: SocketAddr, exit_tx: tokio::sync::mpsc::Sender<()>) -> io::Result<()> {
-    tarpc::serde_transport::tcp::listen(addr, Bincode::default)
-        .await?
-        .filter_map(|r| future::ready(r.ok()))
+// only with a feed port configured; one that is taken should not keep sync
+// from starting
+async fn listen_feed(
+    addr: SocketAddr,
+    policy: &Arc<Policy>,
+) -> Option<impl Stream<Item = (TcpStream, SocketAddr)>> {
+    let feed_addr = feed::feed_addr(addr, policy.config.feed_port)?;
+    feed::listen(feed_addr, admit_addr(policy))
+        .await
+        .map_err(|err| eprintln!("feed server {} error: {:?}", feed_addr, err))
+        .ok()
+}
+
+// discovery only saves typing the address, the server runs without it
+fn advertise(addr: SocketAddr, identity: &tls::DeviceIdentity) -> Option<discovery::Advertisement> {
+    discovery::advertise(addr, &discovery::device_name(), &identity.fingerprint())
+        .map_err(|err| eprintln!("mdns advertise {} error: {:?}", addr, err))
+        .ok()
+}
+
+// turns away addresses outside the allowlist before the tls handshake
+fn admit_addr(policy: &Arc<Policy>) -> impl Fn(SocketAddr) -> bool {
+    let policy = policy.clone();
+    move |peer| {
+        let allowed = policy.allows(peer.ip());
+        if !allowed {
+            policy.log("reject", &peer.to_string(), "", "not in the allowlist");
+        }
+        allowed
+    }
+}
+
+// where the stream came from, unspecified when the socket is already gone
+fn peer_addr(stream: &TlsStream<TcpStream>) -> SocketAddr {
+    tls::server_peer_addr(stream).unwrap_or_else(|_| SocketAddr::from(([0, 0, 0, 0], 0)))
+}
+
+// serves a connection until the client hangs up, it idles out or the
+// server closes it
+async fn run_session(server: LocalNativeServer, execute: impl Future<Output = ()>) {
+    server.log("connect", "");
+    let reason = tokio::select! {
+        _ = execute => "client closed".to_string(),
+        reason = server.session.ended(server.policy.idle_timeout()) => reason,
+    };
+    server.log("close", &reason);
+    if server.session.synced.load(Ordering::Relaxed) {
+        let conn = get_sqlite_connection();
+        if let Err(err) = peer::set_last_sync(&conn, &server.fingerprint, &created_time()) {
+            eprintln!("last sync error: {:?}", err);
+        }
+    }
+}
+
+async fn serve_tcp(
+    incoming: impl Stream<Item = TlsStream<TcpStream>>,
+    exit_tx: tokio::sync::mpsc::Sender<()>,
+    policy: Arc<Policy>,
+) {
+    incoming
+        .map(tls::transport)
         .map(server::BaseChannel::with_defaults)
         // Limit channels to 2 per IP.
-        .max_channels_per_key(2, |t| t.as_ref().peer_addr().unwrap().ip())
+        .max_channels_per_key(2, |t| peer_addr(t.as_ref().get_ref()).ip())
         .map(move |channel| {
-            let server = LocalNativeServer(
-                channel.as_ref().as_ref().peer_addr().unwrap(),
-                Some(exit_tx.clone()),
-            );
-            channel.execute(server.serve())
+            let stream = channel.as_ref().as_ref().get_ref();
+            let server = LocalNativeServer::tcp(stream, Some(exit_tx.clone()), &policy);
+            let execute = channel.execute(server.clone().serve());
+            run_session(server, execute)
         })
         // Max 10 channels.
         .buffer_unordered(10)
         .for_each(|_| async {})
         .await;
+}
+
+async fn start_server(
+    addr: SocketAddr,
+    exit_tx: tokio::sync::mpsc::Sender<()>,
+    policy: Arc<Policy>,
+) -> io::Result<()> {
+    let identity = tls::device_identity()?;
+    eprintln!("server device fingerprint: {}", identity.fingerprint());
+    let _advertisement = advertise(addr, &identity);
+    if let Some(incoming) = listen_feed(addr, &policy).await {
+        tokio::spawn(feed::serve(incoming, policy.clone()));
+    }
+    let incoming = tls::listen_with(addr, &identity, admit_addr(&policy)).await?;
+    serve_tcp(incoming, exit_tx, policy).await;
+    Ok(())
+}
 
+// a single connection over a stream whose transport authenticated the peer
+async fn serve_stream<S>(
+    stream: S,
+    peer: String,
+    exit_tx: Option<tokio::sync::mpsc::Sender<()>>,
+    policy: Arc<Policy>,
+) -> io::Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let identity = tls::device_identity()?;
+    let stream = tls::accept(stream, &identity).await?;
+    // the transport vouches for the peer but does not say which device it
+    // is, high water marks, sync filters and the last sync are kept by
+    // certificate fingerprint so each device on the socket gets its own
+    let fingerprint = tls::server_peer_fingerprint(&stream);
+    if fingerprint.is_empty() {
+        policy.log("reject", &peer, "", "no certificate");
+        return Ok(());
+    }
+    let server = LocalNativeServer {
+        name: peer.clone(),
+        peer,
+        exit_tx,
+        fingerprint,
+        authenticated: true,
+        policy,
+        session: Session::new(),
+    };
+    let execute =
+        server::BaseChannel::with_defaults(tls::transport(stream)).execute(server.clone().serve());
+    run_session(server, execute).await;
     Ok(())
 }
+
+// the socket is made private to this user, and a peer running as anyone
+// else is turned away as well
+#[cfg(unix)]
+async fn start_unix_server(
+    path: &Path,
+    exit_tx: tokio::sync::mpsc::Sender<()>,
+    policy: Arc<Policy>,
+) -> io::Result<()> {
+    use std::fs;
+    use std::os::unix::fs::{FileTypeExt, MetadataExt, PermissionsExt};
+    // a socket left behind by a server that did not stop cleanly, anything
+    // else at that path is not ours to remove
+    match fs::symlink_metadata(path) {
+        Ok(meta) if meta.file_type().is_socket() => fs::remove_file(path)?,
+        Ok(_) => {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} exists and is not a socket", path.display()),
+            ))
+        }
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+        Err(err) => return Err(err),
+    }
+    let listener = tokio::net::UnixListener::bind(path)?;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+    let uid = fs::metadata(path)?.uid();
+    eprintln!("server listening on unix:{}", path.display());
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let peer = format!("unix:{}", path.display());
+        match stream.peer_cred() {
+            Ok(cred) if cred.uid() == uid => {}
+            other => {
+                policy.log("reject", &peer, "", &format!("{:?}", other));
+                continue;
+            }
+        }
+        let exit_tx = exit_tx.clone();
+        let policy = policy.clone();
+        tokio::spawn(async move {
+            if let Err(err) = serve_stream(stream, peer, Some(exit_tx), policy).await {
+                eprintln!("unix peer error: {:?}", err);
+            }
+        });
+    }
+}
+
+#[cfg(not(unix))]
+async fn start_unix_server(
+    _: &Path,
+    _: tokio::sync::mpsc::Sender<()>,
+    _: Arc<Policy>,
+) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "unix sockets need a unix system",
+    ))
+}
+
+// serves one client on stdin and stdout until it hangs up, what ssh runs
+// for a client syncing with ssh:<host>; the default server config applies
+pub fn serve_stdio() -> anyhow::Result<()> {
+    let policy = Arc::new(Policy::new(policy::resolve_config(None, None)?)?);
+    let rt = Runtime::new()?;
+    Ok(rt.block_on(serve_stream(
+        stream::stdio(),
+        "stdio".to_string(),
+        None,
+        policy,
+    ))?)
+}
+
 use tokio::sync::oneshot::Receiver;
 pub type Stop = Receiver<Trigger>;
-pub async fn iced_start_server(addr: SocketAddr) -> io::Result<Stop> {
-    use stream_cancel::Valved;
+pub async fn iced_start_server(addr: SocketAddr, config: ServerConfig) -> io::Result<Stop> {
+    use stream_cancel::Valve;
+    let policy = Policy::new(config)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err.to_string()))?;
+    let policy = Arc::new(policy);
     let (exit_sender, exit_receiver) = tokio::sync::oneshot::channel();
-    let listener = tarpc::serde_transport::tcp::listen(addr, Bincode::default).await?;
+    let identity = tls::device_identity()?;
+    eprintln!("server device fingerprint: {}", identity.fingerprint());
+    let listener = tls::listen_with(addr, &identity, admit_addr(&policy)).await?;
+    let feed_listener = listen_feed(addr, &policy).await;
+    let advertisement = advertise(addr, &identity);
 
     tokio::spawn(async move {
-        let (exit, incoming) = Valved::new(listener);
+        // withdrawn once the server stops
+        let _advertisement = advertisement;
+        // one trigger closes both the sync and the feed listener
+        let (exit, valve) = Valve::new();
         exit_sender.send(exit).unwrap();
-        incoming
-            .filter_map(|r| future::ready(r.ok()))
-            .map(server::BaseChannel::with_defaults)
-            // Limit channels to 2 per IP.
-            .max_channels_per_key(2, |t| t.as_ref().peer_addr().unwrap().ip())
-            .map(|channel| {
-                let server =
-                    LocalNativeServer(channel.as_ref().as_ref().peer_addr().unwrap(), None);
-                channel.execute(server.serve())
-            })
-            // Max 10 channels.
-            .buffer_unordered(10)
-            .for_each(|_| async {})
-            .await
+        if let Some(feed_listener) = feed_listener {
+            tokio::spawn(feed::serve(valve.wrap(feed_listener), policy.clone()));
+        }
+        let (exit_tx, mut exit_rx) = tokio::sync::mpsc::channel(1);
+        tokio::select! {
+            _ = serve_tcp(valve.wrap(listener), exit_tx, policy) => {}
+            // a client stopped sync, the feed stays up until the server is closed here
+            _ = exit_rx.recv() => eprintln!("server task exit"),
+        }
     });
 
     Ok(exit_receiver)
 }
 
-pub fn start(addr: &str) -> Result<(), &'static str> {
-    let server_addr: SocketAddr = addr
+// the code is printed once, and again each time it had to be replaced
+async fn show_pairing_codes() {
+    // a front end may have asked for a code to show already
+    let mut shown = pairing::current_code();
+    eprintln!("pairing code: {}", shown);
+    loop {
+        tokio::time::sleep(PAIRING_CHECK).await;
+        if let Some(code) = pairing::renew(&shown) {
+            eprintln!("pairing code: {}", code);
+            shown = code;
+        }
+    }
+}
+
+pub fn start(addr: &str, config: ServerConfig) -> anyhow::Result<()> {
+    let endpoint: Endpoint = addr
         .parse()
-        .unwrap_or_else(|e| panic!(r#"server_addr {} invalid: {}"#, addr, e));
-    let rt = Runtime::new().unwrap();
+        .map_err(|err| anyhow::anyhow!("server_addr {} invalid: {}", addr, err))?;
+    let policy = Arc::new(Policy::new(config)?);
+    let rt = Runtime::new()?;
     rt.block_on(async {
+        if !endpoint.is_authenticated() {
+            tokio::spawn(show_pairing_codes());
+        }
         let (exit_tx, mut exit_rx) = tokio::sync::mpsc::channel(1);
+        let serve = async {
+            let result = match endpoint {
+                Endpoint::Tcp(addr) => start_server(addr, exit_tx, policy).await,
+                Endpoint::Unix(ref path) => start_unix_server(path, exit_tx, policy).await,
+                _ => Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "serve commands with localnative-sync-stdio",
+                )),
+            };
+            if let Err(err) = result {
+                eprintln!("server {} error: {:?}", endpoint, err);
+            }
+        };
 
         tokio::select! {
-            _ = start_server(server_addr, exit_tx) => {},
+            _ = serve => {},
             _ = exit_rx.recv() => {
                 eprintln!("server task exit");
             }
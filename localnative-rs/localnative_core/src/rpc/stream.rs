@@ -0,0 +1,258 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+// where a sync peer is reached. besides ip:port over tcp the LocalNative
+// service runs over any byte stream, a unix socket or the stdin and stdout of
+// a command like ssh, so no port has to be opened:
+//
+// unix:/run/user/1000/localnative.sock
+// ssh:myserver                   runs localnative-sync-stdio on myserver
+// exec:ssh -p 2222 me@myserver localnative-sync-stdio
+//
+// tls still runs on top, the certificate fingerprints keep naming the peers
+use futures::executor::block_on;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::process::Stdio;
+use std::str::FromStr;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, DuplexStream, ReadBuf};
+use tokio::net::TcpStream;
+use tokio::process::Command;
+
+// what sync-stdio is called on the far side of ssh
+pub const STDIO_COMMAND: &str = "localnative-sync-stdio";
+const BUFFER: usize = 64 * 1024;
+
+pub trait ByteStream: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<S: AsyncRead + AsyncWrite + Unpin + Send> ByteStream for S {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Endpoint {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+    Ssh(String),
+    Exec(String),
+}
+
+impl Endpoint {
+    // ssh or the file permissions of the socket already vouch for the other
+    // side, only tcp peers need pairing
+    pub fn is_authenticated(&self) -> bool {
+        !matches!(self, Endpoint::Tcp(_))
+    }
+
+    // an address from the json api, which a scanned qr code or another app
+    // reaches too. ssh: and exec: run a command on this device, only the
+    // command line and the daemon config may name one
+    pub fn parse_untrusted(s: &str) -> anyhow::Result<Self> {
+        match s.parse()? {
+            endpoint @ (Endpoint::Ssh(_) | Endpoint::Exec(_)) => Err(anyhow::anyhow!(
+                "{} runs a command, give it on the command line or in the daemon config",
+                endpoint
+            )),
+            endpoint => Ok(endpoint),
+        }
+    }
+
+    // how the peer is listed in the trust store
+    pub fn name(&self) -> String {
+        match self {
+            Endpoint::Tcp(addr) => addr.ip().to_string(),
+            other => other.to_string(),
+        }
+    }
+}
+
+impl From<SocketAddr> for Endpoint {
+    fn from(addr: SocketAddr) -> Self {
+        Endpoint::Tcp(addr)
+    }
+}
+
+impl FromStr for Endpoint {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let nonempty = |rest: &str, what: &str| {
+            if rest.trim().is_empty() {
+                Err(anyhow::anyhow!("{} missing in {}", what, s))
+            } else {
+                Ok(rest.trim().to_string())
+            }
+        };
+        if let Some(path) = s.strip_prefix("unix:") {
+            Ok(Endpoint::Unix(PathBuf::from(nonempty(
+                path,
+                "socket path",
+            )?)))
+        } else if let Some(host) = s.strip_prefix("ssh:") {
+            let host = nonempty(host, "host")?;
+            // ssh would read a leading dash as an option like -oProxyCommand
+            if host.starts_with('-') {
+                return Err(anyhow::anyhow!("host {} invalid", host));
+            }
+            Ok(Endpoint::Ssh(host))
+        } else if let Some(command) = s.strip_prefix("exec:") {
+            Ok(Endpoint::Exec(nonempty(command, "command")?))
+        } else {
+            s.parse()
+                .map(Endpoint::Tcp)
+                .map_err(|err| anyhow::anyhow!("addr {} invalid: {}", s, err))
+        }
+    }
+}
+
+impl fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Endpoint::Tcp(addr) => write!(f, "{}", addr),
+            Endpoint::Unix(path) => write!(f, "unix:{}", path.display()),
+            Endpoint::Ssh(host) => write!(f, "ssh:{}", host),
+            Endpoint::Exec(command) => write!(f, "exec:{}", command),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(not(unix))]
+fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+// reads from one half and writes to the other, what a child's stdout and
+// stdin need to pass for a socket
+struct Joined<R, W> {
+    reader: R,
+    writer: W,
+}
+
+impl<R: AsyncRead + Unpin, W: Unpin> AsyncRead for Joined<R, W> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.reader).poll_read(cx, buf)
+    }
+}
+
+impl<R: Unpin, W: AsyncWrite + Unpin> AsyncWrite for Joined<R, W> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.writer).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.writer).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.writer).poll_shutdown(cx)
+    }
+}
+
+// the command's stdout and stdin as one stream, its stderr goes to ours so
+// ssh prompts and remote errors are seen
+fn spawn(mut command: Command) -> io::Result<Box<dyn ByteStream>> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()?;
+    let stdin = child.stdin.take().expect("piped stdin");
+    let stdout = child.stdout.take().expect("piped stdout");
+    // the command ends once the connection drops its stdin
+    tokio::spawn(async move {
+        match child.wait().await {
+            Ok(status) if !status.success() => eprintln!("sync command {}", status),
+            Err(err) => eprintln!("sync command error: {:?}", err),
+            Ok(_) => {}
+        }
+    });
+    Ok(Box::new(Joined {
+        reader: stdout,
+        writer: stdin,
+    }))
+}
+
+// this process's stdin and stdout as one stream. plain threads move the
+// bytes, a handshake over tokio's own stdout could stall on a write whose
+// wakeup got lost
+pub fn stdio() -> DuplexStream {
+    let (near, far) = tokio::io::duplex(BUFFER);
+    let (mut from_far, mut to_far) = tokio::io::split(far);
+    std::thread::spawn(move || {
+        let mut stdin = io::stdin().lock();
+        let mut buf = vec![0; BUFFER];
+        while let Ok(n) = stdin.read(&mut buf) {
+            if n == 0 || block_on(to_far.write_all(&buf[..n])).is_err() {
+                break;
+            }
+        }
+        let _ = block_on(to_far.shutdown());
+    });
+    std::thread::spawn(move || {
+        let mut stdout = io::stdout().lock();
+        let mut buf = vec![0; BUFFER];
+        while let Ok(n) = block_on(from_far.read(&mut buf)) {
+            if n == 0
+                || stdout
+                    .write_all(&buf[..n])
+                    .and_then(|_| stdout.flush())
+                    .is_err()
+            {
+                break;
+            }
+        }
+    });
+    near
+}
+
+pub async fn open(endpoint: &Endpoint) -> io::Result<Box<dyn ByteStream>> {
+    match endpoint {
+        Endpoint::Tcp(addr) => Ok(Box::new(TcpStream::connect(addr).await?)),
+        #[cfg(unix)]
+        Endpoint::Unix(path) => Ok(Box::new(tokio::net::UnixStream::connect(path).await?)),
+        #[cfg(not(unix))]
+        Endpoint::Unix(_) => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "unix sockets need a unix system",
+        )),
+        Endpoint::Ssh(host) => {
+            let mut ssh = Command::new("ssh");
+            ssh.arg("--").arg(host).arg(STDIO_COMMAND);
+            spawn(ssh)
+        }
+        Endpoint::Exec(command) => spawn(shell(command)),
+    }
+}
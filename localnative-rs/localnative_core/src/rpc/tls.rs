@@ -0,0 +1,290 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+// sync runs over mutual tls, each device has a self-signed certificate created on first run;
+// there is no CA, a peer is identified by the fingerprint of its certificate
+use futures::prelude::*;
+use rustls::client::{ServerCertVerified, ServerCertVerifier};
+use rustls::server::{ClientCertVerified, ClientCertVerifier};
+use rustls::{Certificate, ClientConfig, DistinguishedName, PrivateKey, ServerConfig, ServerName};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tarpc::serde_transport::Transport;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_rustls::{client, server, TlsAcceptor, TlsConnector};
+use tokio_serde::formats::Bincode;
+
+const CERT_FILE: &str = "device-cert.der";
+const KEY_FILE: &str = "device-key.der";
+// every certificate carries this name, the verifiers below never check it
+const SERVER_NAME: &str = "localnative";
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+const HANDSHAKE_CONCURRENCY: usize = 8;
+
+pub struct DeviceIdentity {
+    pub cert_der: Vec<u8>,
+    pub key_der: Vec<u8>,
+}
+
+impl DeviceIdentity {
+    pub fn fingerprint(&self) -> String {
+        fingerprint(&self.cert_der)
+    }
+}
+
+// sha256 of the certificate, upper case hex in groups of four
+pub fn fingerprint(cert_der: &[u8]) -> String {
+    hex::encode_upper(Sha256::digest(cert_der))
+        .as_bytes()
+        .chunks(4)
+        .map(|c| String::from_utf8_lossy(c).to_string())
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn to_io_error<E: std::fmt::Display>(err: E) -> io::Error {
+    io::Error::other(err.to_string())
+}
+
+pub fn load_or_create_identity(dir: &Path) -> io::Result<DeviceIdentity> {
+    let cert_path = dir.join(CERT_FILE);
+    let key_path = dir.join(KEY_FILE);
+    if cert_path.exists() && key_path.exists() {
+        return Ok(DeviceIdentity {
+            cert_der: fs::read(cert_path)?,
+            key_der: fs::read(key_path)?,
+        });
+    }
+    eprintln!("create device identity in {:?}", dir);
+    let cert =
+        rcgen::generate_simple_self_signed(vec![SERVER_NAME.to_string()]).map_err(to_io_error)?;
+    let identity = DeviceIdentity {
+        cert_der: cert.serialize_der().map_err(to_io_error)?,
+        key_der: cert.serialize_private_key_der(),
+    };
+    fs::create_dir_all(dir)?;
+    write_private(&key_path, &identity.key_der)?;
+    fs::write(cert_path, &identity.cert_der)?;
+    Ok(identity)
+}
+
+#[cfg(unix)]
+fn write_private(path: &Path, data: &[u8]) -> io::Result<()> {
+    use std::io::Write;
+    use std::os::unix::fs::OpenOptionsExt;
+    fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)?
+        .write_all(data)
+}
+
+#[cfg(not(unix))]
+fn write_private(path: &Path, data: &[u8]) -> io::Result<()> {
+    fs::write(path, data)
+}
+
+// the identity of this device, kept next to the database
+pub fn device_identity() -> io::Result<DeviceIdentity> {
+    let location = crate::exe::sqlite3_db_location();
+    let dir = Path::new(&location)
+        .parent()
+        .ok_or_else(|| to_io_error("database has no parent directory"))?;
+    load_or_create_identity(dir)
+}
+
+// accepts any certificate, the handshake still proves the peer holds its private key
+struct AnyCert;
+
+impl ServerCertVerifier for AnyCert {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &Certificate,
+        _intermediates: &[Certificate],
+        _server_name: &ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+}
+
+impl ClientCertVerifier for AnyCert {
+    fn client_auth_root_subjects(&self) -> &[DistinguishedName] {
+        &[]
+    }
+    // the certificate is what tells peers apart, on a unix socket or over
+    // ssh as much as on tcp, so a client without one is not served
+    fn client_auth_mandatory(&self) -> bool {
+        true
+    }
+    fn verify_client_cert(
+        &self,
+        _end_entity: &Certificate,
+        _intermediates: &[Certificate],
+        _now: SystemTime,
+    ) -> Result<ClientCertVerified, rustls::Error> {
+        Ok(ClientCertVerified::assertion())
+    }
+}
+
+fn server_config(identity: &DeviceIdentity) -> io::Result<Arc<ServerConfig>> {
+    let config = ServerConfig::builder()
+        .with_safe_defaults()
+        .with_client_cert_verifier(Arc::new(AnyCert))
+        .with_single_cert(
+            vec![Certificate(identity.cert_der.clone())],
+            PrivateKey(identity.key_der.clone()),
+        )
+        .map_err(to_io_error)?;
+    Ok(Arc::new(config))
+}
+
+fn client_config(identity: &DeviceIdentity) -> io::Result<Arc<ClientConfig>> {
+    let config = ClientConfig::builder()
+        .with_safe_defaults()
+        .with_custom_certificate_verifier(Arc::new(AnyCert))
+        .with_client_auth_cert(
+            vec![Certificate(identity.cert_der.clone())],
+            PrivateKey(identity.key_der.clone()),
+        )
+        .map_err(to_io_error)?;
+    Ok(Arc::new(config))
+}
+
+fn peer_fingerprint(certs: Option<&[Certificate]>) -> String {
+    certs
+        .and_then(|certs| certs.first())
+        .map(|cert| fingerprint(&cert.0))
+        .unwrap_or_default()
+}
+
+pub fn server_peer_fingerprint<S>(stream: &server::TlsStream<S>) -> String {
+    peer_fingerprint(stream.get_ref().1.peer_certificates())
+}
+
+pub fn client_peer_fingerprint<S>(stream: &client::TlsStream<S>) -> String {
+    peer_fingerprint(stream.get_ref().1.peer_certificates())
+}
+
+pub fn server_peer_addr(stream: &server::TlsStream<TcpStream>) -> io::Result<SocketAddr> {
+    stream.get_ref().0.peer_addr()
+}
+
+// accepted connections that completed the tls handshake, failed handshakes are dropped
+pub async fn listen(
+    addr: SocketAddr,
+    identity: &DeviceIdentity,
+) -> io::Result<impl Stream<Item = server::TlsStream<TcpStream>>> {
+    listen_with(addr, identity, |_| true).await
+}
+
+// as listen, connections from addresses admit turns down are closed before
+// the handshake
+pub async fn listen_with<F>(
+    addr: SocketAddr,
+    identity: &DeviceIdentity,
+    admit: F,
+) -> io::Result<impl Stream<Item = server::TlsStream<TcpStream>>>
+where
+    F: Fn(SocketAddr) -> bool,
+{
+    let acceptor = TlsAcceptor::from(server_config(identity)?);
+    let listener = TcpListener::bind(addr).await?;
+    let incoming = stream::unfold(listener, |listener| async move {
+        let accepted = listener.accept().await;
+        Some((accepted, listener))
+    })
+    .filter_map(move |accepted| {
+        future::ready(
+            accepted
+                .map_err(|err| eprintln!("accept error: {:?}", err))
+                .ok()
+                .filter(|(_, peer)| admit(*peer)),
+        )
+    })
+    .map(move |(tcp, _)| tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(tcp)))
+    .buffer_unordered(HANDSHAKE_CONCURRENCY)
+    .filter_map(|handshake| {
+        future::ready(match handshake {
+            Ok(Ok(stream)) => Some(stream),
+            Ok(Err(err)) => {
+                eprintln!("tls handshake error: {:?}", err);
+                None
+            }
+            Err(_) => {
+                eprintln!("tls handshake timeout");
+                None
+            }
+        })
+    });
+    Ok(incoming)
+}
+
+// the server side of a stream some other transport opened
+pub async fn accept<S>(stream: S, identity: &DeviceIdentity) -> io::Result<server::TlsStream<S>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let acceptor = TlsAcceptor::from(server_config(identity)?);
+    tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream))
+        .await
+        .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "tls handshake timeout"))?
+}
+
+pub async fn connect(
+    addr: &SocketAddr,
+    identity: &DeviceIdentity,
+) -> io::Result<client::TlsStream<TcpStream>> {
+    connect_stream(TcpStream::connect(addr).await?, identity).await
+}
+
+pub async fn connect_stream<S>(
+    stream: S,
+    identity: &DeviceIdentity,
+) -> io::Result<client::TlsStream<S>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let connector = TlsConnector::from(client_config(identity)?);
+    let name = ServerName::try_from(SERVER_NAME).map_err(to_io_error)?;
+    tokio::time::timeout(HANDSHAKE_TIMEOUT, connector.connect(name, stream))
+        .await
+        .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "tls handshake timeout"))?
+}
+
+// length-delimited bincode frames over the tls stream, as tarpc's tcp transport does
+pub fn transport<S, Item, SinkItem>(
+    stream: S,
+) -> Transport<S, Item, SinkItem, Bincode<Item, SinkItem>>
+where
+    S: AsyncRead + AsyncWrite,
+    Item: for<'de> Deserialize<'de>,
+    SinkItem: Serialize,
+{
+    Transport::from((stream, Bincode::default()))
+}
@@ -23,9 +23,10 @@ extern crate uuid;
 use self::semver::Version;
 use rusqlite::Connection;
 // version to upgrade to
-const VERSION: &str = "0.5.1";
+const VERSION: &str = "0.6.10";
 mod to_0_4_0;
 mod to_0_5_0;
+mod to_0_6_0;
 mod utils;
 use crate::OneString;
 
@@ -59,7 +60,52 @@ pub fn upgrade(conn: &Connection) -> anyhow::Result<&str> {
             set_meta_version(conn, "0.5.0")?;
         }
         if Version::parse(&get_meta_version(conn)?)? == Version::parse("0.5.0")? {
-            set_meta_version(conn, VERSION)?;
+            set_meta_version(conn, "0.5.1")?;
+        }
+        if Version::parse(&get_meta_version(conn)?)? == Version::parse("0.5.1")? {
+            to_0_6_0::create_link_status_table(conn)?;
+            set_meta_version(conn, "0.6.0")?;
+        }
+        if Version::parse(&get_meta_version(conn)?)? == Version::parse("0.6.0")? {
+            to_0_6_0::create_archive_table(conn)?;
+            set_meta_version(conn, "0.6.1")?;
+        }
+        if Version::parse(&get_meta_version(conn)?)? == Version::parse("0.6.1")? {
+            to_0_6_0::create_attachment_table(conn)?;
+            to_0_6_0::migrate_image_notes(conn)?;
+            set_meta_version(conn, "0.6.2")?;
+        }
+        if Version::parse(&get_meta_version(conn)?)? == Version::parse("0.6.2")? {
+            to_0_6_0::add_attachment_thumbnail(conn)?;
+            set_meta_version(conn, "0.6.3")?;
+        }
+        if Version::parse(&get_meta_version(conn)?)? == Version::parse("0.6.3")? {
+            to_0_6_0::create_peer_table(conn)?;
+            set_meta_version(conn, "0.6.4")?;
+        }
+        if Version::parse(&get_meta_version(conn)?)? == Version::parse("0.6.4")? {
+            to_0_6_0::add_note_is_local(conn)?;
+            set_meta_version(conn, "0.6.5")?;
+        }
+        if Version::parse(&get_meta_version(conn)?)? == Version::parse("0.6.5")? {
+            to_0_6_0::create_change_log(conn)?;
+            set_meta_version(conn, "0.6.6")?;
+        }
+        if Version::parse(&get_meta_version(conn)?)? == Version::parse("0.6.6")? {
+            to_0_6_0::add_note_version(conn)?;
+            set_meta_version(conn, "0.6.7")?;
+        }
+        if Version::parse(&get_meta_version(conn)?)? == Version::parse("0.6.7")? {
+            to_0_6_0::create_tombstone_table(conn)?;
+            set_meta_version(conn, "0.6.8")?;
+        }
+        if Version::parse(&get_meta_version(conn)?)? == Version::parse("0.6.8")? {
+            to_0_6_0::add_peer_filter(conn)?;
+            set_meta_version(conn, "0.6.9")?;
+        }
+        if Version::parse(&get_meta_version(conn)?)? == Version::parse("0.6.9")? {
+            to_0_6_0::create_attachment_tombstone_table(conn)?;
+            set_meta_version(conn, "0.6.10")?;
         }
         eprintln!("upgraded to {}", VERSION);
         Ok(VERSION)
@@ -0,0 +1,281 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use crate::cmd::attachment::{make_thumbnail, sha256_hex, sniff_mime};
+use rusqlite::types::ValueRef;
+use rusqlite::{params, Connection};
+use uuid::Uuid;
+
+// last http status, redirect target and check time per note, keyed by uuid4
+// status 0 means the request itself failed, see error
+pub fn create_link_status_table(conn: &Connection) -> anyhow::Result<()> {
+    eprintln!("to_0_6_0 create_link_status_table");
+    conn.execute_batch(
+        "BEGIN;
+        CREATE TABLE IF NOT EXISTS link_status (
+         uuid4          TEXT PRIMARY KEY,
+         status         INTEGER NOT NULL,
+         redirect_url   TEXT NOT NULL default '',
+         error          TEXT NOT NULL default '',
+         checked_at     TEXT NOT NULL
+         );
+        COMMIT;",
+    )?;
+    Ok(())
+}
+
+// page snapshot per note, keyed by uuid4
+pub fn create_archive_table(conn: &Connection) -> anyhow::Result<()> {
+    eprintln!("to_0_6_0 create_archive_table");
+    conn.execute_batch(
+        "BEGIN;
+        CREATE TABLE IF NOT EXISTS archive (
+         uuid4          TEXT PRIMARY KEY,
+         url            TEXT NOT NULL,
+         html           TEXT NOT NULL,
+         text           TEXT NOT NULL,
+         archived_at    TEXT NOT NULL
+         );
+        COMMIT;",
+    )?;
+    Ok(())
+}
+
+// blobs per note with mime type, size and content hash
+pub fn create_attachment_table(conn: &Connection) -> anyhow::Result<()> {
+    eprintln!("to_0_6_0 create_attachment_table");
+    conn.execute_batch(
+        "BEGIN;
+        CREATE TABLE IF NOT EXISTS attachment (
+         rowid          INTEGER PRIMARY KEY AUTOINCREMENT,
+         uuid4          TEXT NOT NULL UNIQUE,
+         note_uuid4     TEXT NOT NULL,
+         mime           TEXT NOT NULL,
+         size           INTEGER NOT NULL,
+         sha256         TEXT NOT NULL,
+         data           BLOB NOT NULL,
+         created_at     TEXT NOT NULL
+         );
+        CREATE INDEX IF NOT EXISTS attachment_note_uuid4 ON attachment (note_uuid4);
+        COMMIT;",
+    )?;
+    Ok(())
+}
+
+// move image bytes stored in note.annotations into the attachment table
+pub fn migrate_image_notes(conn: &Connection) -> anyhow::Result<()> {
+    eprintln!("to_0_6_0 migrate_image_notes");
+    let mut images = Vec::new();
+    {
+        let mut stmt = conn.prepare(
+            "SELECT uuid4, annotations FROM note
+            where url like 'mime://%' and length(annotations) > 0",
+        )?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let data = match row.get_ref(1)? {
+                ValueRef::Blob(b) => b.to_vec(),
+                ValueRef::Text(t) => t.to_vec(),
+                _ => continue,
+            };
+            images.push((row.get::<_, String>(0)?, data));
+        }
+    }
+    let tx = conn.unchecked_transaction()?;
+    for (uuid4, data) in images {
+        if let Some(mime) = sniff_mime(&data) {
+            // columns as of 0.6.2, thumbnails are filled in by the next step
+            tx.execute(
+                "INSERT INTO attachment (uuid4, note_uuid4, mime, size, sha256, data, created_at)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    Uuid::new_v4().to_string(),
+                    uuid4,
+                    mime,
+                    data.len() as i64,
+                    sha256_hex(&data),
+                    data,
+                    crate::exe::created_time()
+                ],
+            )?;
+            tx.execute(
+                "UPDATE note SET annotations = '', url = ?1 WHERE uuid4 = ?2",
+                [&format!("mime://{}", mime), &uuid4],
+            )?;
+        } else {
+            eprintln!("to_0_6_0 skip unrecognised image note {}", uuid4);
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+// smaller image for result pages, generated for attachments added before 0.6.3
+pub fn add_attachment_thumbnail(conn: &Connection) -> anyhow::Result<()> {
+    eprintln!("to_0_6_0 add_attachment_thumbnail");
+    conn.execute_batch("ALTER TABLE attachment ADD COLUMN thumbnail BLOB;")?;
+    let mut images = Vec::new();
+    {
+        let mut stmt =
+            conn.prepare("SELECT rowid, data FROM attachment where mime like 'image/%'")?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            images.push((row.get::<_, i64>(0)?, row.get::<_, Vec<u8>>(1)?));
+        }
+    }
+    let tx = conn.unchecked_transaction()?;
+    for (rowid, data) in images {
+        if let Some(thumbnail) = make_thumbnail(&data) {
+            tx.execute(
+                "UPDATE attachment SET thumbnail = ?1 WHERE rowid = ?2",
+                params![thumbnail, rowid],
+            )?;
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+// devices allowed to sync, keyed by certificate fingerprint
+pub fn create_peer_table(conn: &Connection) -> anyhow::Result<()> {
+    eprintln!("to_0_6_0 create_peer_table");
+    conn.execute_batch(
+        "BEGIN;
+        CREATE TABLE IF NOT EXISTS peer (
+         fingerprint    TEXT PRIMARY KEY,
+         name           TEXT NOT NULL,
+         paired_at      TEXT NOT NULL
+         );
+        COMMIT;",
+    )?;
+    Ok(())
+}
+
+// notes that stay on this device and are never offered to sync
+pub fn add_note_is_local(conn: &Connection) -> anyhow::Result<()> {
+    eprintln!("to_0_6_0 add_note_is_local");
+    conn.execute_batch("ALTER TABLE note ADD COLUMN is_local BOOLEAN NOT NULL default 0;")?;
+    Ok(())
+}
+
+// logs edits of syncable notes, see create_change_log
+const NOTE_UPDATE_CHANGE_LOG: &str = "
+        CREATE TRIGGER IF NOT EXISTS note_update_change_log AFTER UPDATE ON note
+        WHEN new.is_local = 0
+        BEGIN
+         INSERT INTO change_log (uuid4) VALUES (new.uuid4);
+        END;";
+
+// append only log of syncable changes, seq orders this device's changes
+// so a peer can ask for everything after the last seq it saw
+pub fn create_change_log(conn: &Connection) -> anyhow::Result<()> {
+    eprintln!("to_0_6_0 create_change_log");
+    conn.execute_batch(&format!(
+        "BEGIN;
+        CREATE TABLE IF NOT EXISTS change_log (
+         seq            INTEGER PRIMARY KEY AUTOINCREMENT,
+         uuid4          TEXT NOT NULL
+         );
+        CREATE TRIGGER IF NOT EXISTS note_insert_change_log AFTER INSERT ON note
+        WHEN new.is_local = 0
+        BEGIN
+         INSERT INTO change_log (uuid4) VALUES (new.uuid4);
+        END;
+        {}
+        INSERT INTO change_log (uuid4)
+        SELECT uuid4 FROM note WHERE is_local = 0 ORDER BY rowid;
+        COMMIT;",
+        NOTE_UPDATE_CHANGE_LOG
+    ))?;
+    Ok(())
+}
+
+// version of each note for conflict detection, existing notes start unedited
+pub fn add_note_version(conn: &Connection) -> anyhow::Result<()> {
+    eprintln!("to_0_6_0 add_note_version");
+    // the backfill is not an edit, the change log trigger sits it out
+    conn.execute_batch(&format!(
+        "BEGIN;
+        ALTER TABLE note ADD COLUMN updated_at TEXT NOT NULL default '';
+        ALTER TABLE note ADD COLUMN device TEXT NOT NULL default '';
+        ALTER TABLE note ADD COLUMN clock TEXT NOT NULL default '{{}}';
+        ALTER TABLE note ADD COLUMN field_clock TEXT NOT NULL default '{{}}';
+        DROP TRIGGER IF EXISTS note_update_change_log;
+        UPDATE note SET updated_at = created_at;
+        {}
+        CREATE TABLE IF NOT EXISTS conflict (
+         uuid4          TEXT PRIMARY KEY,
+         copy_uuid4     TEXT NOT NULL,
+         detected_at    TEXT NOT NULL
+         );
+        COMMIT;",
+        NOTE_UPDATE_CHANGE_LOG
+    ))?;
+    Ok(())
+}
+
+// deleting a syncable note leaves its uuid4 and last clock behind, logged
+// like any other change, so bundles can carry the delete to other devices
+pub fn create_tombstone_table(conn: &Connection) -> anyhow::Result<()> {
+    eprintln!("to_0_6_0 create_tombstone_table");
+    conn.execute_batch(
+        "BEGIN;
+        CREATE TABLE IF NOT EXISTS tombstone (
+         uuid4          TEXT PRIMARY KEY,
+         clock          TEXT NOT NULL default '{}',
+         seq            INTEGER NOT NULL
+         );
+        CREATE TRIGGER IF NOT EXISTS note_delete_tombstone AFTER DELETE ON note
+        WHEN old.is_local = 0
+        BEGIN
+         INSERT INTO change_log (uuid4) VALUES (old.uuid4);
+         INSERT OR REPLACE INTO tombstone (uuid4, clock, seq)
+         VALUES (old.uuid4, old.clock, (SELECT max(seq) FROM change_log));
+        END;
+        CREATE TRIGGER IF NOT EXISTS note_insert_tombstone AFTER INSERT ON note
+        BEGIN
+         DELETE FROM tombstone WHERE uuid4 = new.uuid4;
+        END;
+        COMMIT;",
+    )?;
+    Ok(())
+}
+
+// search query picking the notes synced with the peer, empty syncs them all
+pub fn add_peer_filter(conn: &Connection) -> anyhow::Result<()> {
+    eprintln!("to_0_6_0 add_peer_filter");
+    conn.execute_batch("ALTER TABLE peer ADD COLUMN filter TEXT NOT NULL default '';")?;
+    Ok(())
+}
+
+// removing an attachment leaves its note and content hash behind, so sync
+// does not bring the same file back from a device that still has it
+pub fn create_attachment_tombstone_table(conn: &Connection) -> anyhow::Result<()> {
+    eprintln!("to_0_6_0 create_attachment_tombstone_table");
+    conn.execute_batch(
+        "BEGIN;
+        CREATE TABLE IF NOT EXISTS attachment_tombstone (
+         note_uuid4     TEXT NOT NULL,
+         sha256         TEXT NOT NULL,
+         removed_at     TEXT NOT NULL,
+         PRIMARY KEY (note_uuid4, sha256)
+         );
+        COMMIT;",
+    )?;
+    Ok(())
+}
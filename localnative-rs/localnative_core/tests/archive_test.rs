@@ -0,0 +1,77 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+extern crate localnative_core;
+mod common;
+use common::{insert_note, open_db, start_http_server, Response};
+use localnative_core::cmd::archive::{archive_notes, extract_text, get_archive};
+use localnative_core::cmd::search_count;
+use std::time::Duration;
+
+const PAGE: &str = r#"<html><head><title>Fixture page</title>
+<link rel="stylesheet" href="/style.css">
+<script>var secret = "scriptword";</script>
+</head><body>
+<h1>Heading</h1>
+<p>The quokka &amp; the wombat.</p>
+<img src="pixel.png" alt="pixel">
+</body></html>"#;
+
+fn handler(_method: &str, path: &str) -> Response {
+    match path {
+        "/page" => Response::new("200 OK", PAGE.as_bytes()).header("Content-Type", "text/html"),
+        "/style.css" => {
+            Response::new("200 OK", b"body { color: teal; }").header("Content-Type", "text/css")
+        }
+        "/pixel.png" => {
+            Response::new("200 OK", b"\x89PNG\r\n\x1a\n").header("Content-Type", "image/png")
+        }
+        _ => Response::new("404 Not Found", b""),
+    }
+}
+
+#[test]
+fn test_extract_text() {
+    let text = extract_text(PAGE);
+    eprintln!("{:?}", text);
+    assert_eq!("Fixture page\nHeading\nThe quokka & the wombat.", text);
+}
+
+#[test]
+fn test_archive_notes() {
+    let base = start_http_server(handler);
+    let conn = open_db();
+    insert_note(&conn, "page", &format!("{}/page", base));
+    insert_note(&conn, "gone", &format!("{}/gone", base));
+
+    let summary = archive_notes(&conn, 100, 2, Duration::from_secs(2)).unwrap();
+    assert_eq!(1, summary.archived);
+    assert_eq!(1, summary.failed);
+
+    let archive = get_archive(&conn, "page").unwrap().unwrap();
+    assert!(archive.html.contains("body { color: teal; }"));
+    assert!(archive.html.contains(r#"src="data:image/png;base64,iVBORw0KGgo=""#));
+    assert!(!archive.html.contains("scriptword"));
+    assert!(get_archive(&conn, "gone").unwrap().is_none());
+
+    assert_eq!(1, search_count(&conn, "wombat").unwrap());
+    assert_eq!(0, search_count(&conn, "scriptword").unwrap());
+
+    // already archived notes are skipped
+    let summary = archive_notes(&conn, 100, 2, Duration::from_secs(2)).unwrap();
+    assert_eq!(0, summary.archived);
+}
@@ -0,0 +1,319 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+extern crate localnative_core;
+mod common;
+use common::{insert_note, open_db};
+use localnative_core::cmd::attachment::{
+    add_attachment, attachment_key_page, get_attachment, get_attachments_by_keys, get_image,
+    is_removed, list_attachments, make_thumbnail, missing_attachments, parse_data_url,
+    remove_attachment, save_attachments, sha256_hex, sniff_mime, to_data_url, AttachmentKey,
+    THUMBNAIL_MAX_SIDE, THUMBNAIL_MIME,
+};
+use localnative_core::cmd::bundle::{encode_bundle, import_bundle_bytes};
+use localnative_core::cmd::image::insert_image;
+use localnative_core::cmd::sync_filter::SyncFilter;
+use localnative_core::cmd::visibility::set_visibility;
+use localnative_core::cmd::{delete, search, select, sync_via_attach};
+use localnative_core::rusqlite::Connection;
+use localnative_core::Note;
+
+const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+const PDF: &[u8] = b"%PDF-1.4\n%fixture";
+
+fn real_png(width: u32, height: u32) -> Vec<u8> {
+    let img = image::RgbImage::from_pixel(width, height, image::Rgb([10, 120, 200]));
+    let mut buf = std::io::Cursor::new(Vec::new());
+    img.write_to(&mut buf, image::ImageOutputFormat::Png)
+        .unwrap();
+    buf.into_inner()
+}
+
+#[test]
+fn test_sniff_mime() {
+    assert_eq!(sniff_mime(PNG), Some("image/png"));
+    assert_eq!(sniff_mime(b"\xff\xd8\xff\xe0"), Some("image/jpeg"));
+    assert_eq!(sniff_mime(b"RIFF\0\0\0\0WEBPVP8 "), Some("image/webp"));
+    assert_eq!(sniff_mime(b"GIF89a"), Some("image/gif"));
+    assert_eq!(sniff_mime(PDF), Some("application/pdf"));
+    assert_eq!(sniff_mime(b"<html>"), None);
+}
+
+#[test]
+fn test_add_list_get_remove() {
+    let conn = open_db();
+    insert_note(&conn, "note-1", "https://example.com");
+
+    assert!(add_attachment(&conn, "note-1", b"plain text").is_err());
+    assert!(add_attachment(&conn, "missing", PNG).is_err());
+
+    let png = add_attachment(&conn, "note-1", PNG).unwrap();
+    assert_eq!(png.mime, "image/png");
+    assert_eq!(png.size, PNG.len() as i64);
+    assert_eq!(png.sha256, sha256_hex(PNG));
+    // same content on the same note is stored once
+    let again = add_attachment(&conn, "note-1", PNG).unwrap();
+    assert_eq!(again.uuid4, png.uuid4);
+    let pdf = add_attachment(&conn, "note-1", PDF).unwrap();
+
+    let list = list_attachments(&conn, "note-1").unwrap();
+    assert_eq!(list, vec![png.clone(), pdf.clone()]);
+
+    let (meta, data) = get_attachment(&conn, &pdf.uuid4).unwrap().unwrap();
+    assert_eq!(meta, pdf);
+    assert_eq!(data, PDF);
+
+    assert!(remove_attachment(&conn, &pdf.uuid4).unwrap());
+    assert!(!remove_attachment(&conn, &pdf.uuid4).unwrap());
+    assert!(get_attachment(&conn, &pdf.uuid4).unwrap().is_none());
+    assert_eq!(list_attachments(&conn, "note-1").unwrap(), vec![png]);
+}
+
+#[test]
+fn test_make_thumbnail() {
+    let thumbnail = make_thumbnail(&real_png(600, 300)).unwrap();
+    let img = image::load_from_memory(&thumbnail).unwrap();
+    assert_eq!((img.width(), img.height()), (256, 128));
+    // not decodable or not an image
+    assert!(make_thumbnail(PNG).is_none());
+    assert!(make_thumbnail(PDF).is_none());
+    // past the decode limits the image is kept without one
+    let wide = real_png(THUMBNAIL_MAX_SIDE + 1, 1);
+    assert!(make_thumbnail(&wide).is_none());
+    let conn = open_db();
+    insert_note(&conn, "note-1", "https://example.com");
+    let a = add_attachment(&conn, "note-1", &wide).unwrap();
+    let thumbnail: Option<Vec<u8>> = conn
+        .query_row(
+            "select thumbnail from attachment where uuid4 = ?1",
+            [&a.uuid4],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert!(thumbnail.is_none());
+}
+
+#[test]
+fn test_insert_image() {
+    let conn = open_db();
+    let png = real_png(600, 300);
+    let note = Note {
+        rowid: 0,
+        uuid4: "image-1".to_string(),
+        title: "Screenshot".to_string(),
+        url: "mime://image/png".to_string(),
+        tags: "screenshot".to_string(),
+        description: "".to_string(),
+        comments: "".to_string(),
+        annotations: to_data_url("image/png", &png),
+        created_at: "2026-01-01 00:00:00:0 UTC".to_string(),
+        is_public: false,
+        is_local: false,
+        version: Default::default(),
+    };
+    insert_image(&conn, note).unwrap();
+
+    let annotations: String = conn
+        .query_row(
+            "select annotations from note where uuid4 = 'image-1'",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(annotations, "");
+    let list = list_attachments(&conn, "image-1").unwrap();
+    assert_eq!(list.len(), 1);
+    assert_eq!(list[0].sha256, sha256_hex(&png));
+
+    // result pages carry the thumbnail, the original is a separate fetch
+    let thumbnail = to_data_url(THUMBNAIL_MIME, &make_thumbnail(&png).unwrap());
+    let original = to_data_url("image/png", &png);
+    let searched = search(&conn, "screenshot", 10, 0).unwrap();
+    assert!(searched.contains(&thumbnail));
+    assert!(!searched.contains(&original));
+    assert!(select(&conn, 10, 0).unwrap().contains(&thumbnail));
+    let (mime, data) = get_image(&conn, "image-1").unwrap().unwrap();
+    assert_eq!(mime, "image/png");
+    assert_eq!(data, png);
+    assert_eq!(parse_data_url(&original).unwrap(), (mime, data));
+    assert!(get_image(&conn, "missing").unwrap().is_none());
+
+    let rowid: i64 = conn
+        .query_row(
+            "select rowid from note where uuid4 = 'image-1'",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+    delete(&conn, rowid).unwrap();
+    assert!(list_attachments(&conn, "image-1").unwrap().is_empty());
+}
+
+// what sync does over rpc, between two databases: page the keys, ask the
+// other side which it lacks, send those
+fn push_attachments(from: &Connection, to: &Connection, filter: &SyncFilter) -> u32 {
+    let mut after = AttachmentKey::default();
+    let mut saved = 0;
+    loop {
+        let page = filter.attachment_key_page(from, &after, 1).unwrap();
+        match page.last() {
+            Some(last) => after = last.clone(),
+            None => return saved,
+        }
+        let missing = missing_attachments(to, page).unwrap();
+        let attachments = get_attachments_by_keys(from, &missing).unwrap();
+        saved += save_attachments(to, &attachments).unwrap();
+    }
+}
+
+#[test]
+fn test_sync_attachments() {
+    let server = open_db();
+    let client = open_db();
+    for conn in [&server, &client] {
+        insert_note(conn, "note-1", "https://example.com");
+        insert_note(conn, "note-2", "https://example.com");
+    }
+    insert_note(&server, "server-only", "https://example.com");
+    insert_note(&server, "local-only", "https://example.com");
+    set_visibility(&server, "local-only", false, true).unwrap();
+    let png = real_png(8, 8);
+    let a = add_attachment(&server, "note-1", &png).unwrap();
+    add_attachment(&server, "note-2", PDF).unwrap();
+    add_attachment(&server, "server-only", PDF).unwrap();
+    add_attachment(&server, "local-only", PDF).unwrap();
+    // already here under its own uuid4, the content is what counts
+    add_attachment(&client, "note-2", PDF).unwrap();
+
+    let keys = attachment_key_page(&server, &AttachmentKey::default(), 10).unwrap();
+    assert_eq!(keys.len(), 3);
+    assert!(!keys.iter().any(|key| key.note_uuid4 == "local-only"));
+    assert_eq!(missing_attachments(&client, keys).unwrap(), vec![a.key()]);
+
+    assert_eq!(
+        push_attachments(&server, &client, &SyncFilter::default()),
+        1
+    );
+    let list = list_attachments(&client, "note-1").unwrap();
+    assert_eq!(list, vec![a.clone()]);
+    let (_, data) = get_attachment(&client, &a.uuid4).unwrap().unwrap();
+    assert_eq!(data, png);
+    // thumbnails are made on arrival
+    let thumbnail: Vec<u8> = client
+        .query_row(
+            "select thumbnail from attachment where uuid4 = ?1",
+            [&a.uuid4],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(thumbnail, make_thumbnail(&png).unwrap());
+    assert_eq!(
+        push_attachments(&server, &client, &SyncFilter::default()),
+        0
+    );
+    assert_eq!(
+        push_attachments(&client, &server, &SyncFilter::default()),
+        0
+    );
+
+    // bytes that do not hash to the key are refused
+    insert_note(&client, "note-3", "https://example.com");
+    let mut tampered = get_attachments_by_keys(&server, &[a.key()]).unwrap();
+    tampered[0].attachment.note_uuid4 = "note-3".to_string();
+    tampered[0].data.push(0);
+    assert_eq!(save_attachments(&client, &tampered).unwrap(), 0);
+    assert!(list_attachments(&client, "note-3").unwrap().is_empty());
+}
+
+#[test]
+fn test_sync_via_attach_attachments() {
+    let path = std::env::temp_dir().join(format!(
+        "localnative-attachment-sync-{}.sqlite3",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+    let png = real_png(8, 8);
+    {
+        let other = open_db();
+        insert_note(&other, "other-note", "https://example.com");
+        insert_note(&other, "shared", "https://example.com");
+        add_attachment(&other, "other-note", &png).unwrap();
+        add_attachment(&other, "shared", PDF).unwrap();
+        other
+            .execute("vacuum into ?1", [path.to_str().unwrap()])
+            .unwrap();
+    }
+    let conn = open_db();
+    insert_note(&conn, "main-note", "https://example.com");
+    insert_note(&conn, "shared", "https://example.com");
+    add_attachment(&conn, "main-note", PDF).unwrap();
+    add_attachment(&conn, "shared", PDF).unwrap();
+
+    let res = sync_via_attach(&conn, path.to_str().unwrap(), "");
+    assert!(res.contains("sync-via-attach-done"), "{}", res);
+    let other = Connection::open(&path).unwrap();
+    for db in [&conn, &other] {
+        for uuid4 in ["other-note", "main-note", "shared"] {
+            assert_eq!(list_attachments(db, uuid4).unwrap().len(), 1, "{}", uuid4);
+        }
+    }
+    let copied = &list_attachments(&conn, "other-note").unwrap()[0];
+    assert_eq!(
+        get_attachment(&conn, &copied.uuid4).unwrap().unwrap().1,
+        png
+    );
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn test_removed_attachment_stays_gone() {
+    let path = std::env::temp_dir().join(format!(
+        "localnative-attachment-removed-{}.sqlite3",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+    let conn = open_db();
+    let other = open_db();
+    for db in [&conn, &other] {
+        insert_note(db, "note-1", "https://example.com");
+        add_attachment(db, "note-1", PDF).unwrap();
+    }
+    let a = list_attachments(&conn, "note-1").unwrap().remove(0);
+    assert!(remove_attachment(&conn, &a.uuid4).unwrap());
+    assert!(is_removed(&conn, &a.key()).unwrap());
+
+    // over rpc it is neither asked for nor saved when sent anyway
+    assert_eq!(push_attachments(&other, &conn, &SyncFilter::default()), 0);
+    let sent = get_attachments_by_keys(&other, &[a.key()]).unwrap();
+    assert_eq!(save_attachments(&conn, &sent).unwrap(), 0);
+    // nor from a bundle, which folder and s3 sync import
+    let (_, bytes) = encode_bundle(&other, 0).unwrap();
+    assert_eq!(import_bundle_bytes(&conn, &bytes).unwrap().attachments, 0);
+    // nor by sync via attach
+    other
+        .execute("vacuum into ?1", [path.to_str().unwrap()])
+        .unwrap();
+    let res = sync_via_attach(&conn, path.to_str().unwrap(), "");
+    assert!(res.contains("sync-via-attach-done"), "{}", res);
+    assert!(list_attachments(&conn, "note-1").unwrap().is_empty());
+
+    // adding it again by hand brings it back
+    add_attachment(&conn, "note-1", PDF).unwrap();
+    assert!(!is_removed(&conn, &a.key()).unwrap());
+    assert_eq!(list_attachments(&conn, "note-1").unwrap().len(), 1);
+    let _ = std::fs::remove_file(&path);
+}
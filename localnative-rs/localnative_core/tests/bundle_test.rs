@@ -0,0 +1,184 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+mod common;
+use common::{insert_note, open_db};
+use localnative_core::cmd::attachment::{add_attachment, list_attachments};
+use localnative_core::cmd::bundle::{
+    delete_by_tombstones, deleted_by_tombstones, export_bundle, import_bundle, list_tombstones,
+    read_manifest, tombstones_since, FORMAT,
+};
+use localnative_core::cmd::conflict::{edit_note, get_note};
+use localnative_core::cmd::visibility::set_visibility;
+use localnative_core::cmd::{count, delete};
+use localnative_core::rusqlite::Connection;
+use std::path::PathBuf;
+
+fn bundle_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "localnative-bundle-{}-{}.lnbundle",
+        name,
+        std::process::id()
+    ))
+}
+
+fn remove(conn: &Connection, uuid4: &str) {
+    delete(conn, get_note(conn, uuid4).unwrap().unwrap().rowid).unwrap();
+}
+
+#[test]
+fn test_round_trip() {
+    let a = open_db();
+    insert_note(&a, "n1", "https://example.com/1");
+    insert_note(&a, "n2", "https://example.com/2");
+    insert_note(&a, "local", "https://example.com/local");
+    set_visibility(&a, "local", false, true).unwrap();
+    add_attachment(&a, "n1", b"%PDF-1.4 bundle").unwrap();
+
+    let path = bundle_path("round-trip");
+    let manifest = export_bundle(&a, &path, 0).unwrap();
+    assert_eq!(manifest.format, FORMAT);
+    assert_eq!((manifest.notes, manifest.attachments), (2, 1));
+    assert_eq!(manifest.since, 0);
+    assert!(manifest.until > 0);
+    assert_eq!(read_manifest(&path).unwrap(), manifest);
+
+    let b = open_db();
+    let summary = import_bundle(&b, &path).unwrap();
+    assert_eq!(summary.manifest, manifest);
+    assert_eq!(summary.merged.inserted, 2);
+    assert_eq!(summary.attachments, 1);
+    assert!(get_note(&b, "local").unwrap().is_none());
+    assert_eq!(list_attachments(&b, "n1").unwrap()[0].size, 15);
+
+    // a second import changes nothing
+    let again = import_bundle(&b, &path).unwrap();
+    assert_eq!(again.merged.inserted + again.merged.updated, 0);
+    assert_eq!(again.attachments, 0);
+    assert_eq!(count(&b, "note").unwrap(), 2);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn test_incremental_and_tombstones() {
+    let a = open_db();
+    let b = open_db();
+    for uuid4 in ["keep", "gone", "edited"] {
+        insert_note(&a, uuid4, "https://example.com");
+    }
+    let path = bundle_path("incremental");
+    let first = export_bundle(&a, &path, 0).unwrap();
+    import_bundle(&b, &path).unwrap();
+
+    edit_note(&a, "keep", |note| note.title = "from a".to_string()).unwrap();
+    remove(&a, "gone");
+    remove(&a, "edited");
+    assert_eq!(list_tombstones(&a, 0).unwrap().len(), 2);
+    // b edits a note a deleted meanwhile, the edit outlives the delete
+    edit_note(&b, "edited", |note| note.title = "from b".to_string()).unwrap();
+
+    let second = export_bundle(&a, &path, first.until).unwrap();
+    assert_eq!(second.since, first.until);
+    assert_eq!((second.notes, second.tombstones), (1, 2));
+    let summary = import_bundle(&b, &path).unwrap();
+    assert_eq!(summary.merged.updated, 1);
+    assert_eq!(summary.deleted, 1);
+    assert_eq!(get_note(&b, "keep").unwrap().unwrap().title, "from a");
+    assert!(get_note(&b, "gone").unwrap().is_none());
+    assert_eq!(get_note(&b, "edited").unwrap().unwrap().title, "from b");
+    // the delete goes on from b in its own bundles
+    assert_eq!(list_tombstones(&b, 0).unwrap()[0].uuid4, "gone");
+
+    // notes coming back clear their tombstone
+    insert_note(&a, "gone", "https://example.com");
+    assert_eq!(list_tombstones(&a, 0).unwrap().len(), 1);
+    let _ = std::fs::remove_file(&path);
+}
+
+// what sync does over rpc: page through the tombstones after a seq and
+// delete on the other side what was not edited there since
+#[test]
+fn test_tombstones_by_page() {
+    let a = open_db();
+    let b = open_db();
+    for conn in [&a, &b] {
+        for uuid4 in ["one", "two", "edited"] {
+            insert_note(conn, uuid4, "https://example.com");
+        }
+    }
+    remove(&a, "one");
+    remove(&a, "two");
+    remove(&a, "edited");
+    edit_note(&b, "edited", |note| note.title = "from b".to_string()).unwrap();
+
+    let first = tombstones_since(&a, 0, 2).unwrap();
+    assert_eq!(first.len(), 2);
+    let rest = tombstones_since(&a, first[1].seq, 2).unwrap();
+    assert_eq!(rest.len(), 1);
+    assert!(tombstones_since(&a, rest[0].seq, 2).unwrap().is_empty());
+
+    let gone: Vec<_> = deleted_by_tombstones(&b, &first)
+        .unwrap()
+        .into_iter()
+        .map(|note| note.uuid4)
+        .collect();
+    assert_eq!(gone, vec!["one", "two"]);
+    assert!(deleted_by_tombstones(&b, &rest).unwrap().is_empty());
+    assert_eq!(delete_by_tombstones(&b, &first).unwrap(), 2);
+    assert_eq!(delete_by_tombstones(&b, &rest).unwrap(), 0);
+    assert_eq!(count(&b, "note").unwrap(), 1);
+    // sent again, nothing is left to delete
+    assert_eq!(delete_by_tombstones(&b, &first).unwrap(), 0);
+}
+
+#[test]
+fn test_local_notes_leave_no_tombstone() {
+    let conn = open_db();
+    insert_note(&conn, "local", "https://example.com");
+    set_visibility(&conn, "local", false, true).unwrap();
+    remove(&conn, "local");
+    assert!(list_tombstones(&conn, 0).unwrap().is_empty());
+}
+
+#[test]
+fn test_damaged_bundles() {
+    let a = open_db();
+    insert_note(&a, "n1", "https://example.com/1");
+    let path = bundle_path("damaged");
+    export_bundle(&a, &path, 0).unwrap();
+    let b = open_db();
+
+    let mut bytes = std::fs::read(&path).unwrap();
+    let last = bytes.len() - 1;
+    bytes[last] ^= 0xff;
+    std::fs::write(&path, &bytes).unwrap();
+    let err = import_bundle(&b, &path).unwrap_err();
+    assert!(err.to_string().contains("checksum"), "{}", err);
+
+    std::fs::write(&path, b"SQLite format 3\0").unwrap();
+    assert!(import_bundle(&b, &path).is_err());
+
+    let newer = format!(
+        "LNBUNDLE\n{{\"format\":{},\"schema\":\"\",\"device\":\"\",\"created_at\":\"\",\"since\":0,\"until\":0,\"notes\":0,\"tombstones\":0,\"attachments\":0,\"sha256\":\"\"}}\n",
+        FORMAT + 1
+    );
+    std::fs::write(&path, newer).unwrap();
+    let err = import_bundle(&b, &path).unwrap_err();
+    assert!(err.to_string().contains("upgrade"), "{}", err);
+    assert_eq!(count(&b, "note").unwrap(), 0);
+    let _ = std::fs::remove_file(&path);
+}
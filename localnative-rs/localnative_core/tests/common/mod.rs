@@ -0,0 +1,98 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+#![allow(dead_code)]
+use localnative_core::cmd::create;
+use localnative_core::rusqlite::Connection;
+use localnative_core::upgrade;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::thread;
+
+pub struct Response {
+    pub status: &'static str,
+    pub headers: Vec<(&'static str, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn new(status: &'static str, body: &[u8]) -> Self {
+        Response {
+            status,
+            headers: Vec::new(),
+            body: body.to_vec(),
+        }
+    }
+    pub fn header(mut self, name: &'static str, value: &str) -> Self {
+        self.headers.push((name, value.to_string()));
+        self
+    }
+}
+
+// minimal http stand-in on loopback, handler gets (method, path),
+// returns the base url
+pub fn start_http_server(handler: fn(&str, &str) -> Response) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            thread::spawn(move || {
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap_or(0) > 2 {
+                    line.clear();
+                }
+                let mut parts = request_line.split_whitespace();
+                let method = parts.next().unwrap_or("");
+                let path = parts.next().unwrap_or("");
+                let resp = handler(method, path);
+                let mut head = format!("HTTP/1.1 {}\r\n", resp.status);
+                for (name, value) in &resp.headers {
+                    head.push_str(&format!("{}: {}\r\n", name, value));
+                }
+                head.push_str(&format!(
+                    "Content-Length: {}\r\nConnection: close\r\n\r\n",
+                    resp.body.len()
+                ));
+                let mut stream = stream;
+                let _ = stream.write_all(head.as_bytes());
+                if method != "HEAD" {
+                    let _ = stream.write_all(&resp.body);
+                }
+            });
+        }
+    });
+    format!("http://{}", addr)
+}
+
+pub fn open_db() -> Connection {
+    let conn = Connection::open_in_memory().unwrap();
+    create(&conn).unwrap();
+    upgrade::upgrade(&conn).unwrap();
+    conn
+}
+
+pub fn insert_note(conn: &Connection, uuid4: &str, url: &str) {
+    conn.execute(
+        "INSERT INTO note (uuid4, title, url, tags, description, comments, annotations, created_at, is_public)
+        VALUES (?1, '', ?2, '', '', '', '', '2026-01-01 00:00:00:0 UTC', 0)",
+        [uuid4, url],
+    )
+    .unwrap();
+}
@@ -0,0 +1,216 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+extern crate localnative_core;
+mod common;
+use common::{insert_note, open_db};
+use localnative_core::cmd::conflict::{
+    compare, edit_note, get_note, get_policy, list_conflicts, resolve_conflict, set_policy, Clock,
+    ConflictPolicy, Order, Resolution,
+};
+use localnative_core::cmd::count;
+use localnative_core::cmd::sync::{get_note_by_uuid4, merge_notes, MergeSummary};
+use localnative_core::rusqlite::Connection;
+use std::thread::sleep;
+use std::time::Duration;
+
+fn clock(entries: &[(&str, u64)]) -> Clock {
+    entries.iter().map(|(d, c)| (d.to_string(), *c)).collect()
+}
+
+// what a sync of one note from one device to the other does
+fn share(from: &Connection, to: &Connection, uuid4: &str) -> MergeSummary {
+    merge_notes(to, &[get_note_by_uuid4(from, uuid4).unwrap()]).unwrap()
+}
+
+// the same note on two devices, as after a first sync
+fn two_devices() -> (Connection, Connection) {
+    let a = open_db();
+    let b = open_db();
+    insert_note(&a, "n", "https://example.com");
+    edit_note(&a, "n", |note| note.title = "start".to_string()).unwrap();
+    assert_eq!(share(&a, &b, "n").inserted, 1);
+    (a, b)
+}
+
+fn edit_title(conn: &Connection, title: &str) {
+    // keeps updated_at apart between the devices
+    sleep(Duration::from_millis(5));
+    edit_note(conn, "n", |note| note.title = title.to_string()).unwrap();
+}
+
+#[test]
+fn test_compare() {
+    let a = clock(&[("a", 2), ("b", 1)]);
+    assert_eq!(compare(&a, &a), Order::Equal);
+    assert_eq!(compare(&clock(&[("a", 1)]), &a), Order::Before);
+    assert_eq!(compare(&a, &clock(&[("a", 1)])), Order::After);
+    assert_eq!(compare(&a, &clock(&[("b", 2)])), Order::Concurrent);
+    assert_eq!(compare(&Clock::new(), &Clock::new()), Order::Equal);
+}
+
+#[test]
+fn test_edit_note() {
+    let conn = open_db();
+    insert_note(&conn, "n", "https://example.com");
+    assert!(!edit_note(&conn, "missing", |_| {}).unwrap());
+
+    assert!(edit_note(&conn, "n", |note| note.tags = "a a,b".to_string()).unwrap());
+    let note = get_note(&conn, "n").unwrap().unwrap();
+    assert_eq!(note.tags, "a,b");
+    let device = note.version.device.clone();
+    assert!(!device.is_empty());
+    assert_eq!(note.version.clock, clock(&[(&device, 1)]));
+    assert_eq!(note.version.fields["tags"], (device.clone(), 1));
+    assert!(!note.version.fields.contains_key("title"));
+
+    // no change, no new version
+    edit_note(&conn, "n", |note| note.tags = "a,b".to_string()).unwrap();
+    assert_eq!(get_note(&conn, "n").unwrap().unwrap().version, note.version);
+}
+
+#[test]
+fn test_fast_forward() {
+    let (a, b) = two_devices();
+    edit_title(&a, "from a");
+    let summary = share(&a, &b, "n");
+    assert_eq!(summary.updated, 1);
+    assert_eq!(get_note(&b, "n").unwrap().unwrap().title, "from a");
+    // nothing new either way
+    assert_eq!(share(&a, &b, "n"), MergeSummary::default());
+    assert_eq!(share(&b, &a, "n"), MergeSummary::default());
+    assert!(list_conflicts(&b).unwrap().is_empty());
+}
+
+#[test]
+fn test_last_writer_wins() {
+    let (a, b) = two_devices();
+    assert_eq!(get_policy(&a).unwrap(), ConflictPolicy::LastWriterWins);
+    edit_title(&a, "older");
+    edit_title(&b, "newer");
+    assert_eq!(share(&b, &a, "n").updated, 1);
+    assert_eq!(get_note(&a, "n").unwrap().unwrap().title, "newer");
+    // the result covers both edits, b takes it as it is
+    share(&a, &b, "n");
+    let (na, nb) = (
+        get_note(&a, "n").unwrap().unwrap(),
+        get_note(&b, "n").unwrap().unwrap(),
+    );
+    assert_eq!(na.title, nb.title);
+    assert_eq!(na.version.clock, nb.version.clock);
+    assert!(list_conflicts(&a).unwrap().is_empty());
+}
+
+#[test]
+fn test_merge_fields() {
+    let (a, b) = two_devices();
+    set_policy(&a, "merge-fields".parse().unwrap()).unwrap();
+    edit_note(&a, "n", |note| note.description = "from a".to_string()).unwrap();
+    edit_title(&b, "from b");
+    assert_eq!(share(&b, &a, "n").updated, 1);
+    let note = get_note(&a, "n").unwrap().unwrap();
+    assert_eq!(note.title, "from b");
+    assert_eq!(note.description, "from a");
+    share(&a, &b, "n");
+    let merged = get_note(&b, "n").unwrap().unwrap();
+    assert_eq!(
+        (merged.title, merged.description),
+        (note.title, note.description)
+    );
+
+    // both edit the title, the newer one stays and the other becomes a copy
+    edit_title(&a, "title a");
+    edit_title(&b, "title b");
+    let summary = share(&b, &a, "n");
+    assert_eq!(summary.conflicts, 1);
+    assert_eq!(get_note(&a, "n").unwrap().unwrap().title, "title b");
+    let conflicts = list_conflicts(&a).unwrap();
+    assert_eq!(conflicts.len(), 1);
+    assert_eq!(conflicts[0].note.uuid4, "n");
+    assert_eq!(conflicts[0].copy.title, "title a");
+    assert_eq!(conflicts[0].copy.description, "from a");
+}
+
+#[test]
+fn test_keep_both_and_resolve() {
+    let (a, b) = two_devices();
+    set_policy(&a, ConflictPolicy::KeepBoth).unwrap();
+    edit_title(&b, "title b");
+    edit_title(&a, "title a");
+    assert_eq!(share(&b, &a, "n").conflicts, 1);
+    assert_eq!(get_note(&a, "n").unwrap().unwrap().title, "title a");
+    assert_eq!(count(&a, "note").unwrap(), 2);
+    let copy = list_conflicts(&a).unwrap()[0].copy.clone();
+    assert_eq!(copy.title, "title b");
+
+    // the kept note now covers b's edit, so b takes it without a conflict
+    assert_eq!(share(&a, &b, "n").updated, 1);
+    assert_eq!(get_note(&b, "n").unwrap().unwrap().title, "title a");
+
+    assert!(!resolve_conflict(&a, "missing", Resolution::KeepNote).unwrap());
+    assert!(resolve_conflict(&a, "n", Resolution::KeepCopy).unwrap());
+    assert!(list_conflicts(&a).unwrap().is_empty());
+    assert_eq!(count(&a, "note").unwrap(), 1);
+    assert_eq!(get_note(&a, "n").unwrap().unwrap().title, "title b");
+    assert!(get_note(&a, &copy.uuid4).unwrap().is_none());
+    // picking the copy is an edit b picks up
+    assert_eq!(share(&a, &b, "n").updated, 1);
+    assert_eq!(get_note(&b, "n").unwrap().unwrap().title, "title b");
+}
+
+#[test]
+fn test_resolve_keep_note_and_both() {
+    let (a, b) = two_devices();
+    set_policy(&a, ConflictPolicy::KeepBoth).unwrap();
+    edit_title(&a, "title a");
+    edit_title(&b, "title b");
+    share(&b, &a, "n");
+    let copy = list_conflicts(&a).unwrap()[0].copy.uuid4.clone();
+    assert!(resolve_conflict(&a, "n", Resolution::KeepBoth).unwrap());
+    assert!(get_note(&a, &copy).unwrap().is_some());
+    assert!(list_conflicts(&a).unwrap().is_empty());
+
+    edit_title(&a, "again a");
+    edit_title(&b, "again b");
+    share(&b, &a, "n");
+    // b's edit is the later one, so it is the note and a's the copy
+    assert_eq!(list_conflicts(&a).unwrap()[0].copy.title, "again a");
+    assert!(resolve_conflict(&a, "n", Resolution::KeepNote).unwrap());
+    assert_eq!(get_note(&a, "n").unwrap().unwrap().title, "again b");
+    assert_eq!(count(&a, "note").unwrap(), 2);
+}
+
+#[test]
+fn test_keep_both_converges() {
+    let (a, b) = two_devices();
+    set_policy(&a, ConflictPolicy::KeepBoth).unwrap();
+    set_policy(&b, ConflictPolicy::KeepBoth).unwrap();
+    edit_title(&a, "title a");
+    edit_title(&b, "title b");
+    // each side takes the other's edit before either pushes its result
+    let from_a = get_note_by_uuid4(&a, "n").unwrap();
+    let from_b = get_note_by_uuid4(&b, "n").unwrap();
+    assert_eq!(merge_notes(&a, &[from_b]).unwrap().conflicts, 1);
+    assert_eq!(merge_notes(&b, &[from_a]).unwrap().conflicts, 1);
+    for conn in [&a, &b] {
+        let note = get_note(conn, "n").unwrap().unwrap();
+        assert_eq!(note.title, "title b");
+        assert_eq!(list_conflicts(conn).unwrap()[0].copy.title, "title a");
+    }
+    assert_eq!(share(&a, &b, "n"), MergeSummary::default());
+    assert_eq!(share(&b, &a, "n"), MergeSummary::default());
+}
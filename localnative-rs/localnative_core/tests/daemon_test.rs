@@ -0,0 +1,113 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+mod common;
+
+use common::open_db;
+use localnative_core::rpc::daemon::{
+    backoff_secs, get_status, is_due, list_status, load_config, record, set_status, SyncStatus,
+    DEFAULT_INTERVAL_SECS,
+};
+use std::fs;
+
+#[test]
+fn test_backoff() {
+    assert_eq!(backoff_secs(1), 30);
+    assert_eq!(backoff_secs(2), 60);
+    assert_eq!(backoff_secs(3), 120);
+    assert_eq!(backoff_secs(8), 3600);
+    assert_eq!(backoff_secs(u32::MAX), 3600);
+}
+
+#[test]
+fn test_is_due() {
+    assert!(is_due(None, 0, false));
+    let mut status = SyncStatus {
+        next_attempt: 100,
+        ..Default::default()
+    };
+    assert!(!is_due(Some(&status), 99, false));
+    assert!(is_due(Some(&status), 100, false));
+    // local changes sync early
+    assert!(is_due(Some(&status), 50, true));
+    // unless the peer is backing off
+    status.failures = 1;
+    assert!(!is_due(Some(&status), 50, true));
+}
+
+#[test]
+fn test_record() {
+    let mut status = SyncStatus::default();
+    record(&mut status, &Err(anyhow::anyhow!("refused")), 1000, 60);
+    record(&mut status, &Err(anyhow::anyhow!("refused")), 1000, 60);
+    assert_eq!(status.failures, 2);
+    assert_eq!(status.next_attempt, 1060);
+    assert_eq!(status.last_result, "refused");
+    assert!(status.last_success.is_empty());
+
+    record(&mut status, &Ok("AAAA BBBB".to_string()), 2000, 60);
+    assert_eq!(status.failures, 0);
+    assert_eq!(status.next_attempt, 2060);
+    assert_eq!(status.last_result, "ok");
+    assert_eq!(status.fingerprint, "AAAA BBBB");
+    assert_eq!(status.last_success, status.last_attempt);
+}
+
+#[test]
+fn test_status_round_trip() {
+    let conn = open_db();
+    assert_eq!(get_status(&conn, "10.0.0.2:2345").unwrap(), None);
+    for addr in ["10.0.0.3:2345", "10.0.0.2:2345"] {
+        let status = SyncStatus {
+            addr: addr.to_string(),
+            last_result: "ok".to_string(),
+            ..Default::default()
+        };
+        set_status(&conn, &status).unwrap();
+    }
+    let status = get_status(&conn, "10.0.0.2:2345").unwrap().unwrap();
+    assert_eq!(status.last_result, "ok");
+    let addrs: Vec<String> = list_status(&conn)
+        .unwrap()
+        .into_iter()
+        .map(|s| s.addr)
+        .collect();
+    assert_eq!(addrs, ["10.0.0.2:2345", "10.0.0.3:2345"]);
+}
+
+#[test]
+fn test_load_config() {
+    let dir = std::env::temp_dir().join(format!("ln-daemon-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("sync_daemon.json");
+
+    fs::write(
+        &path,
+        r#"{"peers": [{"addr": "10.0.0.2:2345", "name": "laptop"}]}"#,
+    )
+    .unwrap();
+    let config = load_config(&path).unwrap();
+    assert_eq!(config.interval_secs, DEFAULT_INTERVAL_SECS);
+    assert!(config.sync_on_change);
+    assert_eq!(config.peers[0].name, "laptop");
+
+    fs::write(&path, r#"{"peers": [{"addr": "laptop"}]}"#).unwrap();
+    assert!(load_config(&path).is_err());
+    fs::write(&path, r#"{"interval_secs": 0, "peers": []}"#).unwrap();
+    assert!(load_config(&path).is_err());
+    fs::remove_dir_all(&dir).unwrap();
+}
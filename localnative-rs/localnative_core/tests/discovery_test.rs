@@ -0,0 +1,57 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+use localnative_core::rpc::discovery::{advertise, browse};
+use localnative_core::rpc::handshake::PROTOCOL_VERSION;
+use std::net::{SocketAddr, TcpListener};
+use std::time::Duration;
+
+// advertised on loopback, found by a browser on the same host; skipped where
+// no multicast socket can be opened
+#[test]
+fn test_advertise_browse_loopback() {
+    // held so the port stays ours
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr: SocketAddr = listener.local_addr().unwrap();
+    let fingerprint = "ABCD 1234 EF56 7890";
+    let advertisement = match advertise(addr, "test device", fingerprint) {
+        Ok(advertisement) => advertisement,
+        Err(err) => {
+            eprintln!("skipped, no multicast: {}", err);
+            return;
+        }
+    };
+
+    let peers = match browse(Duration::from_secs(5)) {
+        Ok(peers) => peers,
+        Err(err) => {
+            eprintln!("skipped, no multicast: {}", err);
+            return;
+        }
+    };
+    let peer = peers
+        .iter()
+        .find(|p| p.fingerprint == fingerprint)
+        .expect("advertised peer");
+    assert_eq!(peer.name, "test device");
+    assert_eq!(peer.addr, addr);
+    assert_eq!(peer.protocol, PROTOCOL_VERSION);
+
+    drop(advertisement);
+    let peers = browse(Duration::from_secs(2)).unwrap();
+    assert!(peers.iter().all(|p| p.fingerprint != fingerprint));
+}
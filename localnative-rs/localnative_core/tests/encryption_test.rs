@@ -0,0 +1,115 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+extern crate localnative_core;
+mod common;
+use common::insert_note;
+use localnative_core::cmd::create;
+#[cfg(feature = "sqlcipher")]
+use localnative_core::cmd::sync_via_attach;
+#[cfg(feature = "sqlcipher")]
+use localnative_core::encryption::{apply_key, is_locked, is_unlocked, lock};
+use localnative_core::encryption::{change_key, encrypt, is_encrypted, unlock};
+use localnative_core::rusqlite::Connection;
+use localnative_core::upgrade;
+use std::path::{Path, PathBuf};
+
+fn temp_db(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("localnative-encryption-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(format!("{}.sqlite3", name));
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+fn plaintext_db(path: &Path, uuid4: &str) {
+    let conn = Connection::open(path).unwrap();
+    create(&conn).unwrap();
+    upgrade::upgrade(&conn).unwrap();
+    insert_note(&conn, uuid4, "https://example.com");
+}
+
+#[cfg(feature = "sqlcipher")]
+fn open(path: &Path) -> Connection {
+    let conn = Connection::open(path).unwrap();
+    apply_key(&conn).unwrap();
+    conn
+}
+
+#[cfg(feature = "sqlcipher")]
+fn note_count(conn: &Connection) -> localnative_core::rusqlite::Result<i64> {
+    conn.query_row("select count(*) from note", [], |row| row.get(0))
+}
+
+// one test, the unlocked passphrase is process wide
+#[cfg(feature = "sqlcipher")]
+#[test]
+fn test_encryption() {
+    let path = temp_db("main");
+    plaintext_db(&path, "note-1");
+    assert!(!is_encrypted(&path));
+    assert!(unlock(&path, "secret").is_err());
+    assert!(encrypt(&path, "").is_err());
+
+    // existing plaintext database is encrypted in place
+    encrypt(&path, "secret").unwrap();
+    assert!(is_encrypted(&path));
+    assert!(is_unlocked());
+    assert!(encrypt(&path, "secret").is_err());
+    assert_eq!(note_count(&open(&path)).unwrap(), 1);
+
+    lock();
+    assert!(is_locked(&path));
+    assert!(note_count(&open(&path)).is_err());
+    assert!(unlock(&path, "wrong").is_err());
+    assert!(is_locked(&path));
+    unlock(&path, "secret").unwrap();
+    assert!(!is_locked(&path));
+
+    change_key(&open(&path), "changed").unwrap();
+    lock();
+    assert!(unlock(&path, "secret").is_err());
+    unlock(&path, "changed").unwrap();
+    assert_eq!(note_count(&open(&path)).unwrap(), 1);
+
+    // plaintext databases still attach for sync
+    let other = temp_db("other");
+    plaintext_db(&other, "note-2");
+    let conn = open(&path);
+    let resp = sync_via_attach(&conn, &other.to_string_lossy(), "");
+    assert!(resp.contains("sync-via-attach-done"), "{}", resp);
+    assert_eq!(note_count(&conn).unwrap(), 2);
+    assert!(!is_encrypted(&other));
+    assert_eq!(note_count(&Connection::open(&other).unwrap()).unwrap(), 2);
+
+    lock();
+}
+
+#[cfg(not(feature = "sqlcipher"))]
+#[test]
+fn test_without_sqlcipher() {
+    let path = temp_db("plain");
+    plaintext_db(&path, "note-1");
+    for err in [
+        encrypt(&path, "secret").unwrap_err(),
+        unlock(&path, "secret").unwrap_err(),
+        change_key(&Connection::open(&path).unwrap(), "secret").unwrap_err(),
+    ] {
+        assert_eq!(err.to_string(), "built without sqlcipher support");
+    }
+    assert!(!is_encrypted(&path));
+}
@@ -0,0 +1,170 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+extern crate localnative_core;
+mod common;
+use common::{insert_note, open_db};
+use localnative_core::cmd::feed::{
+    entry_id, feed, feed_notes, xml_escape, FeedFormat, DEFAULT_LIMIT, MAX_LIMIT,
+};
+use localnative_core::cmd::visibility::set_visibility;
+use localnative_core::rpc::feed::{feed_addr, feed_limit, listen, respond, serve};
+use localnative_core::rpc::policy::{Policy, ServerConfig};
+use localnative_core::rusqlite::{params, Connection};
+
+fn public_note(conn: &Connection, uuid4: &str, title: &str, tags: &str, created_at: &str) {
+    insert_note(
+        conn,
+        uuid4,
+        &format!("https://example.com/{}?a=1&b=2", uuid4),
+    );
+    conn.execute(
+        "update note set title = ?1, tags = ?2, created_at = ?3, description = 'desc' where uuid4 = ?4",
+        params![title, tags, created_at, uuid4],
+    )
+    .unwrap();
+    set_visibility(conn, uuid4, true, false).unwrap();
+}
+
+fn fixture() -> Connection {
+    let conn = open_db();
+    public_note(
+        &conn,
+        "n1",
+        "Rust & <XML>",
+        "rust,web",
+        "2026-01-05 08:30:00:123 UTC",
+    );
+    public_note(
+        &conn,
+        "n2",
+        "Second",
+        "rust_lang",
+        "2026-02-01 00:00:00:0 UTC",
+    );
+    insert_note(&conn, "private", "https://example.com/private");
+    insert_note(&conn, "local", "https://example.com/local");
+    set_visibility(&conn, "local", true, true).unwrap();
+    conn
+}
+
+#[test]
+fn test_feed_notes() {
+    let conn = fixture();
+    let all: Vec<String> = feed_notes(&conn, None, 10)
+        .unwrap()
+        .into_iter()
+        .map(|n| n.uuid4)
+        .collect();
+    assert_eq!(all, vec!["n2", "n1"]);
+    // whole tags only
+    let rust: Vec<String> = feed_notes(&conn, Some("rust"), 10)
+        .unwrap()
+        .into_iter()
+        .map(|n| n.uuid4)
+        .collect();
+    assert_eq!(rust, vec!["n1"]);
+    assert!(feed_notes(&conn, Some("rust_"), 10).unwrap().is_empty());
+    assert_eq!(feed_notes(&conn, None, 1).unwrap().len(), 1);
+}
+
+#[test]
+fn test_atom_and_rss() {
+    let conn = fixture();
+    let atom = feed(&conn, FeedFormat::Atom, None, 10).unwrap();
+    assert!(atom.contains(r#"<feed xmlns="http://www.w3.org/2005/Atom">"#));
+    assert!(atom.contains("<id>urn:uuid:n1</id>"));
+    assert!(atom.contains("<title>Rust &amp; &lt;XML&gt;</title>"));
+    assert!(atom.contains(r#"<link href="https://example.com/n1?a=1&amp;b=2"/>"#));
+    assert!(atom.contains("<updated>2026-01-05T08:30:00Z</updated>"));
+    assert!(atom.contains("<updated>2026-02-01T00:00:00Z</updated>"));
+    assert!(atom.contains(r#"<category term="web"/>"#));
+    assert!(!atom.contains("example.com/private"));
+    assert!(!atom.contains("example.com/local"));
+
+    let rss = feed(&conn, FeedFormat::Rss, Some("web"), 10).unwrap();
+    assert!(rss.contains(r#"<rss version="2.0">"#));
+    assert!(rss.contains(r#"<guid isPermaLink="false">urn:uuid:n1</guid>"#));
+    assert!(rss.contains("<pubDate>Mon, 05 Jan 2026 08:30:00 +0000</pubDate>"));
+    assert!(rss.contains("<title>Local Native - web</title>"));
+    assert!(!rss.contains("urn:uuid:n2"));
+
+    assert_eq!(xml_escape("a\u{1}'\""), "a&apos;&quot;");
+    let note = feed_notes(&conn, None, 1).unwrap().pop().unwrap();
+    assert_eq!(entry_id(&note), "urn:uuid:n2");
+    assert!("json".parse::<FeedFormat>().is_err());
+}
+
+#[test]
+fn test_feed_endpoint() {
+    let conn = fixture();
+    let resp = respond(&conn, "GET", "/rss.xml?tag=rust%5Flang&limit=5");
+    assert_eq!(resp.status, "200 OK");
+    assert_eq!(resp.content_type, "application/rss+xml; charset=utf-8");
+    assert!(resp.body.contains("urn:uuid:n2"));
+    assert!(!resp.body.contains("urn:uuid:n1"));
+
+    let resp = respond(&conn, "GET", "/atom.xml");
+    assert_eq!(resp.content_type, "application/atom+xml; charset=utf-8");
+    assert!(resp.body.contains("urn:uuid:n1"));
+    assert_eq!(respond(&conn, "GET", "/notes").status, "404 Not Found");
+    assert_eq!(
+        respond(&conn, "POST", "/atom.xml").status,
+        "405 Method Not Allowed"
+    );
+
+    assert_eq!(feed_limit("5"), 5);
+    assert_eq!(feed_limit("1000000"), MAX_LIMIT);
+    assert_eq!(feed_limit("all"), DEFAULT_LIMIT);
+
+    // feeds are off until the config gives them a port
+    assert_eq!(
+        feed_addr("127.0.0.1:2345".parse().unwrap(), Some(8080)),
+        Some("127.0.0.1:8080".parse().unwrap())
+    );
+    assert_eq!(feed_addr("127.0.0.1:2345".parse().unwrap(), None), None);
+    assert_eq!(ServerConfig::default().feed_port, None);
+}
+
+// the feed port answers the allowlist like the sync port does
+#[tokio::test]
+async fn test_feed_allowlist() {
+    use std::sync::Arc;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    let addr = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+    let policy = Arc::new(
+        Policy::new(ServerConfig {
+            allow: vec!["10.0.0.0/8".to_string()],
+            ..ServerConfig::default()
+        })
+        .unwrap(),
+    );
+    let admit = {
+        let policy = policy.clone();
+        move |peer: std::net::SocketAddr| policy.allows(peer.ip())
+    };
+    let incoming = listen(addr, admit).await.unwrap();
+    tokio::spawn(serve(incoming, policy));
+    let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+    let _ = stream.write_all(b"GET /atom.xml HTTP/1.1\r\n\r\n").await;
+    let mut buf = Vec::new();
+    let _ = stream.read_to_end(&mut buf).await;
+    assert!(buf.is_empty());
+}
@@ -0,0 +1,109 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+mod common;
+use common::{insert_note, open_db};
+use localnative_core::cmd::conflict::{device_id, edit_note, get_note};
+use localnative_core::cmd::folder::sync_folder;
+use localnative_core::cmd::sync::MergeSummary;
+use localnative_core::cmd::{count, delete};
+use std::fs;
+use std::path::PathBuf;
+
+fn shared_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "localnative-folder-{}-{}",
+        name,
+        std::process::id()
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+#[test]
+fn test_two_devices() {
+    let dir = shared_dir("two");
+    let a = open_db();
+    let b = open_db();
+    insert_note(&a, "n1", "https://example.com/1");
+    insert_note(&a, "n2", "https://example.com/2");
+
+    let first = sync_folder(&a, &dir).unwrap();
+    assert!(first.written.is_some());
+    assert_eq!(first.read, 0);
+    assert!(dir.join(device_id(&a).unwrap()).is_dir());
+
+    let pulled = sync_folder(&b, &dir).unwrap();
+    assert_eq!(pulled.read, 1);
+    assert_eq!(pulled.merged.inserted, 2);
+    // what b merged is not written back
+    assert!(pulled.written.is_none());
+
+    edit_note(&b, "n1", |note| note.title = "from b".to_string()).unwrap();
+    delete(&b, get_note(&b, "n2").unwrap().unwrap().rowid).unwrap();
+    assert!(sync_folder(&b, &dir).unwrap().written.is_some());
+
+    let merged = sync_folder(&a, &dir).unwrap();
+    assert_eq!(
+        (merged.read, merged.merged.updated, merged.deleted),
+        (1, 1, 1)
+    );
+    assert_eq!(get_note(&a, "n1").unwrap().unwrap().title, "from b");
+    assert_eq!(count(&a, "note").unwrap(), 1);
+
+    // what a merged goes out once more and changes nothing at b
+    assert!(sync_folder(&a, &dir).unwrap().written.is_some());
+    let echo = sync_folder(&b, &dir).unwrap();
+    assert_eq!(echo.read, 1);
+    assert_eq!((echo.merged, echo.deleted), (MergeSummary::default(), 0));
+    assert!(echo.written.is_none());
+
+    // settled, nothing more either way
+    for conn in [&a, &b] {
+        let again = sync_folder(conn, &dir).unwrap();
+        assert!(again.written.is_none());
+        assert_eq!(again.read, 0);
+    }
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_partial_bundle_is_read_later() {
+    let dir = shared_dir("partial");
+    let a = open_db();
+    let b = open_db();
+    insert_note(&a, "n1", "https://example.com/1");
+    let written = sync_folder(&a, &dir).unwrap().written.unwrap();
+    let path = dir.join(device_id(&a).unwrap()).join(&written);
+
+    // as a file sync tool has it halfway through the copy
+    let full = fs::read(&path).unwrap();
+    fs::write(&path, &full[..full.len() / 2]).unwrap();
+    // its temp files are not bundles
+    fs::write(path.with_extension("lnbundle.tmp"), b"partial").unwrap();
+    let skipped = sync_folder(&b, &dir).unwrap();
+    assert_eq!(skipped.read, 0);
+    assert_eq!(skipped.skipped.len(), 1);
+    assert_eq!(count(&b, "note").unwrap(), 0);
+
+    fs::write(&path, &full).unwrap();
+    let done = sync_folder(&b, &dir).unwrap();
+    assert_eq!(done.read, 1);
+    assert!(done.skipped.is_empty());
+    assert_eq!(count(&b, "note").unwrap(), 1);
+    let _ = fs::remove_dir_all(&dir);
+}
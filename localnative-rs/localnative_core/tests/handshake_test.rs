@@ -0,0 +1,127 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+extern crate localnative_core;
+mod common;
+use common::{insert_note, open_db};
+use localnative_core::cmd::peer::set_last_sync;
+use localnative_core::rpc::handshake::{
+    hello, negotiate, Hello, Incompatible, CHANGE_LOG, INFO, MIN_PROTOCOL_VERSION, NOTE_VERSIONS,
+    NOT_PAIRED, PROTOCOL_VERSION,
+};
+use localnative_core::rpc::info::{server_info, ServerInfo};
+use localnative_core::upgrade::get_meta_version;
+
+fn peer(protocol: u32, min_protocol: u32, capabilities: &[&str]) -> Hello {
+    Hello {
+        protocol,
+        min_protocol,
+        schema: "0.6.0".to_string(),
+        capabilities: capabilities.iter().map(|c| c.to_string()).collect(),
+    }
+}
+
+#[test]
+fn test_hello() {
+    let conn = open_db();
+    let local = hello(&conn).unwrap();
+    assert_eq!(local.protocol, PROTOCOL_VERSION);
+    assert_eq!(local.min_protocol, MIN_PROTOCOL_VERSION);
+    assert_eq!(local.schema, get_meta_version(&conn).unwrap());
+    assert!(local.has(CHANGE_LOG));
+    assert!(local.has(NOTE_VERSIONS));
+    assert!(local.has(INFO));
+    assert!(!local.has("telepathy"));
+    assert!(!local.has(NOT_PAIRED));
+    // what an untrusted peer hears, which no protocol accepts
+    let refused = Hello::not_paired();
+    assert!(refused.has(NOT_PAIRED));
+    assert!(negotiate(&local, &refused).is_err());
+}
+
+#[test]
+fn test_server_info() {
+    let conn = open_db();
+    let info = server_info(&conn, hello(&conn).unwrap(), "SERV ER00", "AAAA BBBB").unwrap();
+    assert_eq!(info.device_id, "SERV ER00");
+    assert!(!info.is_refused());
+    // what an unpaired client is answered
+    assert!(ServerInfo::default().is_refused());
+    assert_eq!(info.note_count, 0);
+    assert_eq!(info.last_modified, "");
+    assert_eq!(info.last_sync, "");
+    assert!(!info.app_version.is_empty());
+    assert_eq!(info.schema, get_meta_version(&conn).unwrap());
+
+    insert_note(&conn, "u1", "http://a");
+    insert_note(&conn, "u2", "http://b");
+    insert_note(&conn, "u3", "http://c");
+    conn.execute_batch(
+        "UPDATE note SET updated_at = '2026-03-01 00:00:00:0 UTC' where uuid4 = 'u1';
+        UPDATE note SET updated_at = '2026-02-01 00:00:00:0 UTC' where uuid4 = 'u2';
+        -- local only notes are neither counted nor dated
+        UPDATE note SET updated_at = '2026-04-01 00:00:00:0 UTC', is_local = 1 where uuid4 = 'u3';",
+    )
+    .unwrap();
+    set_last_sync(&conn, "AAAA BBBB", "2026-03-02 00:00:00:0 UTC").unwrap();
+    let info = server_info(&conn, hello(&conn).unwrap(), "SERV ER00", "AAAA BBBB").unwrap();
+    assert_eq!(info.note_count, 2);
+    assert_eq!(info.last_modified, "2026-03-01 00:00:00:0 UTC");
+    assert_eq!(info.last_sync, "2026-03-02 00:00:00:0 UTC");
+    // each caller sees its own
+    let info = server_info(&conn, hello(&conn).unwrap(), "SERV ER00", "CCCC DDDD").unwrap();
+    assert_eq!(info.last_sync, "");
+}
+
+#[test]
+fn test_negotiate() {
+    let local = peer(3, 2, &[CHANGE_LOG, NOTE_VERSIONS, "new-thing"]);
+    // same protocol, different schema and capabilities still sync
+    assert_eq!(
+        negotiate(&local, &peer(3, 2, &[CHANGE_LOG, NOTE_VERSIONS])).unwrap(),
+        vec![CHANGE_LOG, NOTE_VERSIONS]
+    );
+    // an older server without a change log degrades to full passes
+    assert_eq!(
+        negotiate(&local, &peer(2, 2, &[NOTE_VERSIONS])).unwrap(),
+        vec![NOTE_VERSIONS]
+    );
+    // a newer server that still accepts this device
+    assert_eq!(
+        negotiate(&local, &peer(5, 3, &[CHANGE_LOG])).unwrap(),
+        vec![CHANGE_LOG]
+    );
+
+    let err = negotiate(&local, &peer(1, 1, &[])).unwrap_err();
+    assert_eq!(
+        err,
+        Incompatible::UpgradeServer {
+            server: 1,
+            min: 2,
+            schema: "0.6.0".to_string()
+        }
+    );
+    assert!(err
+        .to_string()
+        .contains("upgrade Local Native on the server"));
+
+    let err = negotiate(&local, &peer(6, 4, &[])).unwrap_err();
+    assert_eq!(err, Incompatible::UpgradeClient { client: 3, min: 4 });
+    assert!(err
+        .to_string()
+        .contains("upgrade Local Native on this device"));
+}
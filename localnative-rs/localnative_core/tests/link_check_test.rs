@@ -0,0 +1,90 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+extern crate localnative_core;
+mod common;
+use common::{insert_note, open_db, start_http_server, Response};
+use localnative_core::cmd::link_check::{check_links, get_link_status};
+use localnative_core::cmd::{filter_count, search_count};
+use std::thread;
+use std::time::Duration;
+
+// /ok 200, /gone 404, /moved 301 to /ok,
+// /nohead 405 on HEAD and 200 on GET, /slow outlasts the timeout
+fn handler(method: &str, path: &str) -> Response {
+    match (method, path) {
+        (_, "/ok") => Response::new("200 OK", b""),
+        (_, "/moved") => Response::new("301 Moved Permanently", b"").header("Location", "/ok"),
+        ("HEAD", "/nohead") => Response::new("405 Method Not Allowed", b""),
+        ("GET", "/nohead") => Response::new("200 OK", b""),
+        (_, "/slow") => {
+            thread::sleep(Duration::from_secs(5));
+            Response::new("200 OK", b"")
+        }
+        _ => Response::new("404 Not Found", b""),
+    }
+}
+
+#[test]
+fn test_check_links() {
+    let base = start_http_server(handler);
+    let conn = open_db();
+    insert_note(&conn, "ok", &format!("{}/ok", base));
+    insert_note(&conn, "gone", &format!("{}/gone", base));
+    insert_note(&conn, "moved", &format!("{}/moved", base));
+    insert_note(&conn, "nohead", &format!("{}/nohead", base));
+    insert_note(&conn, "slow", &format!("{}/slow", base));
+    insert_note(&conn, "image", "mime://image/png");
+
+    let summary = check_links(&conn, 100, 4, Duration::from_secs(1)).unwrap();
+    eprintln!("{:?}", summary);
+    assert_eq!(5, summary.checked);
+    assert_eq!(2, summary.dead);
+    assert_eq!(1, summary.redirected);
+
+    let uuid4s: Vec<String> = ["ok", "gone", "moved", "nohead", "slow", "image"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    let status = get_link_status(&conn, &uuid4s).unwrap();
+    assert_eq!(200, status["ok"].status);
+    assert_eq!(404, status["gone"].status);
+    assert_eq!(200, status["moved"].status);
+    assert_eq!(format!("{}/ok", base), status["moved"].redirect_url);
+    assert_eq!(200, status["nohead"].status);
+    assert_eq!(0, status["slow"].status);
+    assert!(!status["slow"].error.is_empty());
+    assert!(!status.contains_key("image"));
+
+    assert_eq!(2, search_count(&conn, "is:dead").unwrap());
+    assert_eq!(1, search_count(&conn, "is:redirected").unwrap());
+    assert_eq!(1, search_count(&conn, "is:dead gone").unwrap());
+    // the date range view keeps them with no words typed
+    assert_eq!(
+        2,
+        filter_count(&conn, "is:dead", "0000-01-01", "9999-12-31").unwrap()
+    );
+    assert_eq!(
+        0,
+        filter_count(&conn, "is:dead", "0000-01-01", "0000-12-31").unwrap()
+    );
+    // an empty query still lists every note
+    assert_eq!(
+        6,
+        filter_count(&conn, "", "0000-01-01", "0000-12-31").unwrap()
+    );
+}
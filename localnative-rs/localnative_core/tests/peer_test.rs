@@ -0,0 +1,116 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+extern crate localnative_core;
+mod common;
+use localnative_core::cmd::peer::{
+    is_trusted, last_sync, list_peers, revoke_peer, set_last_sync, trust_peer,
+};
+use localnative_core::rpc::pairing;
+
+#[test]
+fn test_trust_and_revoke() {
+    let conn = common::open_db();
+    assert!(!is_trusted(&conn, "AAAA BBBB").unwrap());
+    assert!(trust_peer(&conn, "", "10.0.0.2").is_err());
+
+    trust_peer(&conn, "AAAA BBBB", "10.0.0.2").unwrap();
+    trust_peer(&conn, "CCCC DDDD", "10.0.0.3").unwrap();
+    // pairing again replaces the record
+    trust_peer(&conn, "AAAA BBBB", "10.0.0.4").unwrap();
+    assert!(is_trusted(&conn, "AAAA BBBB").unwrap());
+    let peers = list_peers(&conn).unwrap();
+    assert_eq!(peers.len(), 2);
+    assert!(peers
+        .iter()
+        .any(|p| p.fingerprint == "AAAA BBBB" && p.name == "10.0.0.4"));
+
+    set_last_sync(&conn, "AAAA BBBB", "2026-03-02 00:00:00:0 UTC").unwrap();
+    assert_eq!(
+        last_sync(&conn, "AAAA BBBB").unwrap(),
+        "2026-03-02 00:00:00:0 UTC"
+    );
+    assert!(revoke_peer(&conn, "AAAA BBBB").unwrap());
+    assert!(!revoke_peer(&conn, "AAAA BBBB").unwrap());
+    assert_eq!(last_sync(&conn, "AAAA BBBB").unwrap(), "");
+    assert!(!is_trusted(&conn, "AAAA BBBB").unwrap());
+    assert!(is_trusted(&conn, "CCCC DDDD").unwrap());
+}
+
+// the code is process wide, so all cases run in one test
+#[test]
+fn test_pairing_code() {
+    assert!(!pairing::redeem("000000"));
+
+    let code = pairing::new_code();
+    assert_eq!(code.len(), 6);
+    assert!(code.chars().all(|c| c.is_ascii_digit()));
+    assert!(pairing::redeem(&code));
+    // single use
+    assert!(!pairing::redeem(&code));
+
+    // a new code replaces the old one
+    let old = pairing::new_code();
+    let code = loop {
+        let code = pairing::new_code();
+        if code != old {
+            break code;
+        }
+    };
+    assert!(!pairing::redeem(&old));
+    assert!(pairing::redeem(&code));
+
+    // too many wrong guesses burn the code
+    let code = pairing::new_code();
+    let wrong = if code == "000000" { "000001" } else { "000000" };
+    for _ in 0..5 {
+        assert!(!pairing::redeem(wrong));
+    }
+    assert!(!pairing::redeem(&code));
+
+    let code = pairing::new_code();
+    pairing::clear_code();
+    assert!(!pairing::redeem(&code));
+
+    // a server starting keeps the code a front end already shows
+    let code = pairing::new_code();
+    assert_eq!(pairing::current_code(), code);
+    assert!(pairing::redeem(&code));
+    let code = pairing::current_code();
+    assert!(pairing::redeem(&code));
+
+    // the code shown is replaced once used or burnt, and only then
+    let shown = pairing::new_code();
+    assert_eq!(pairing::renew(&shown), None);
+    assert!(pairing::redeem(&shown));
+    let shown = pairing::renew(&shown).unwrap();
+    assert!(!pairing::redeem(wrong_for(&shown)));
+    assert_eq!(pairing::renew(&shown), None);
+    for _ in 0..4 {
+        assert!(!pairing::redeem(wrong_for(&shown)));
+    }
+    let renewed = pairing::renew(&shown).unwrap();
+    assert!(pairing::redeem(&renewed));
+}
+
+fn wrong_for(code: &str) -> &'static str {
+    if code == "000000" {
+        "000001"
+    } else {
+        "000000"
+    }
+}
@@ -0,0 +1,177 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+use localnative_core::rpc::policy::{
+    load_config, resolve_config, save_config, Cidr, Policy, ServerConfig,
+    DEFAULT_IDLE_TIMEOUT_SECS, DEFAULT_MAX_PAYLOAD_BYTES,
+};
+use std::fs;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+fn ip(s: &str) -> IpAddr {
+    s.parse().unwrap()
+}
+
+#[test]
+fn test_cidr() {
+    let lan: Cidr = "192.168.1.0/24".parse().unwrap();
+    assert!(lan.contains(ip("192.168.1.7")));
+    assert!(!lan.contains(ip("192.168.2.7")));
+    // a v4 client on a dual stack socket
+    assert!(lan.contains(ip("::ffff:192.168.1.7")));
+    assert!(!lan.contains(ip("fe80::1")));
+
+    let host: Cidr = "10.0.0.2".parse().unwrap();
+    assert!(host.contains(ip("10.0.0.2")));
+    assert!(!host.contains(ip("10.0.0.3")));
+
+    let any: Cidr = "0.0.0.0/0".parse().unwrap();
+    assert!(any.contains(ip("8.8.8.8")));
+    let link_local: Cidr = "fe80::/10".parse().unwrap();
+    assert!(link_local.contains(ip("fe80::1")));
+    assert!(!link_local.contains(ip("2001:db8::1")));
+
+    assert!("10.0.0.0/33".parse::<Cidr>().is_err());
+    assert!("laptop".parse::<Cidr>().is_err());
+    assert!("10.0.0.0/x".parse::<Cidr>().is_err());
+}
+
+#[test]
+fn test_allowlist() {
+    let policy = Policy::new(ServerConfig::default()).unwrap();
+    assert!(policy.allows(ip("203.0.113.9")));
+
+    let policy = Policy::new(ServerConfig {
+        allow: vec!["127.0.0.1".to_string(), "192.168.0.0/16".to_string()],
+        ..Default::default()
+    })
+    .unwrap();
+    assert!(policy.allows(ip("127.0.0.1")));
+    assert!(policy.allows(ip("192.168.44.1")));
+    assert!(!policy.allows(ip("203.0.113.9")));
+}
+
+#[test]
+fn test_rate_limit() {
+    let policy = Policy::new(ServerConfig {
+        rate_limit: 60,
+        ..Default::default()
+    })
+    .unwrap();
+    let now = Instant::now();
+    for _ in 0..60 {
+        assert!(policy.take_at("10.0.0.2", now));
+    }
+    assert!(!policy.take_at("10.0.0.2", now));
+    // other peers have their own minute
+    assert!(policy.take_at("10.0.0.3", now));
+    // one a second comes back
+    assert!(policy.take_at("10.0.0.2", now + Duration::from_secs(1)));
+    assert!(!policy.take_at("10.0.0.2", now + Duration::from_secs(1)));
+
+    let unlimited = Policy::new(ServerConfig::default()).unwrap();
+    assert!((0..10_000).all(|_| unlimited.take_at("10.0.0.2", now)));
+}
+
+#[test]
+fn test_limits() {
+    let policy = Policy::new(ServerConfig {
+        max_payload_bytes: 100,
+        idle_timeout_secs: 0,
+        ..Default::default()
+    })
+    .unwrap();
+    assert!(!policy.too_large(100));
+    assert!(policy.too_large(101));
+    assert_eq!(policy.idle_timeout(), None);
+
+    let policy = Policy::new(ServerConfig {
+        max_payload_bytes: 0,
+        ..Default::default()
+    })
+    .unwrap();
+    assert!(!policy.too_large(u64::MAX));
+    assert_eq!(
+        policy.idle_timeout(),
+        Some(Duration::from_secs(DEFAULT_IDLE_TIMEOUT_SECS))
+    );
+}
+
+#[test]
+fn test_log_file() {
+    let dir = std::env::temp_dir().join(format!("ln-policy-log-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let log_file = dir.join("connections.log");
+    let policy = Policy::new(ServerConfig {
+        log_file: Some(log_file.to_string_lossy().to_string()),
+        ..Default::default()
+    })
+    .unwrap();
+    policy.log("connect", "10.0.0.2:50000", "AAAA BBBB", "");
+    policy.log("reject", "203.0.113.9:50001", "", "not in the allowlist");
+    let lines: Vec<localnative_core::serde_json::Value> = fs::read_to_string(&log_file)
+        .unwrap()
+        .lines()
+        .map(|line| localnative_core::serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0]["event"], "connect");
+    assert_eq!(lines[0]["fingerprint"], "AAAA BBBB");
+    assert_eq!(lines[1]["peer"], "203.0.113.9:50001");
+    assert_eq!(lines[1]["detail"], "not in the allowlist");
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_config() {
+    let dir = std::env::temp_dir().join(format!("ln-policy-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("sync_server.json");
+
+    fs::write(&path, r#"{"allow": ["10.0.0.0/8"], "read_only": true}"#).unwrap();
+    let config = load_config(&path).unwrap();
+    assert!(config.read_only);
+    assert!(config.remote_stop);
+    assert_eq!(config.max_payload_bytes, DEFAULT_MAX_PAYLOAD_BYTES);
+
+    // flags replace what the file says
+    let overrides = localnative_core::serde_json::json!({"read_only": false, "rate_limit": 30});
+    let config = resolve_config(Some(&path), Some(&overrides)).unwrap();
+    assert!(!config.read_only);
+    assert_eq!(config.rate_limit, 30);
+    assert_eq!(config.allow, ["10.0.0.0/8"]);
+    let overrides = localnative_core::serde_json::json!({"allow": ["laptop"]});
+    assert!(resolve_config(Some(&path), Some(&overrides)).is_err());
+
+    let config = ServerConfig {
+        remote_stop: false,
+        ..Default::default()
+    };
+    save_config(&path, &config).unwrap();
+    assert_eq!(load_config(&path).unwrap(), config);
+    let invalid = ServerConfig {
+        allow: vec!["10.0.0.0/99".to_string()],
+        ..Default::default()
+    };
+    assert!(save_config(&path, &invalid).is_err());
+    fs::write(&path, r#"{"allow": ["10.0.0.300"]}"#).unwrap();
+    assert!(load_config(&path).is_err());
+    fs::write(&path, r#"{"feed_port": 0}"#).unwrap();
+    assert!(load_config(&path).is_err());
+    fs::remove_dir_all(&dir).unwrap();
+}
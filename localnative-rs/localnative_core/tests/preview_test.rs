@@ -0,0 +1,202 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+mod common;
+use common::{insert_note, open_db};
+use localnative_core::cmd::bundle::list_tombstones;
+use localnative_core::cmd::conflict::{edit_note, get_note};
+use localnative_core::cmd::preview::{preview_attach, PreviewNote, SyncPreview};
+use localnative_core::cmd::sync::{get_note_by_uuid4, merge_notes};
+use localnative_core::cmd::visibility::set_visibility;
+use localnative_core::cmd::{count, delete, sync_via_attach};
+use localnative_core::rusqlite::Connection;
+use std::thread::sleep;
+use std::time::Duration;
+
+fn uuid4s(notes: &[PreviewNote]) -> Vec<&str> {
+    notes.iter().map(|note| note.uuid4.as_str()).collect()
+}
+
+fn edit_title(conn: &Connection, uuid4: &str, title: &str) {
+    // keeps updated_at apart between the devices
+    sleep(Duration::from_millis(5));
+    edit_note(conn, uuid4, |note| note.title = title.to_string()).unwrap();
+}
+
+// a and b after a first sync of "same", "newer" and "both"
+fn two_devices() -> (Connection, Connection) {
+    let a = open_db();
+    let b = open_db();
+    for uuid4 in ["same", "newer", "both"] {
+        insert_note(&a, uuid4, "https://example.com");
+        edit_title(&a, uuid4, "start");
+        merge_notes(&b, &[get_note_by_uuid4(&a, uuid4).unwrap()]).unwrap();
+    }
+    (a, b)
+}
+
+fn notes(conn: &Connection, uuid4s: &[&str]) -> Vec<localnative_core::Note> {
+    uuid4s
+        .iter()
+        .map(|uuid4| get_note(conn, uuid4).unwrap().unwrap())
+        .collect()
+}
+
+#[test]
+fn test_preview_pull() {
+    let (a, b) = two_devices();
+    insert_note(&b, "new", "https://example.com/new");
+    insert_note(&b, "local", "https://example.com/local");
+    set_visibility(&b, "local", false, true).unwrap();
+    edit_title(&b, "newer", "from b");
+    edit_title(&a, "both", "from a");
+    edit_title(&b, "both", "from b");
+
+    let mut preview = SyncPreview::default();
+    let fetched = notes(&b, &["same", "newer", "both", "new", "local"]);
+    preview.add_pull(&a, &fetched).unwrap();
+    assert_eq!(uuid4s(&preview.pull), vec!["new"]);
+    assert_eq!(uuid4s(&preview.update), vec!["newer"]);
+    assert_eq!(preview.update[0].title, "from b");
+    assert_eq!(uuid4s(&preview.conflicts), vec!["both"]);
+    assert!(preview.bytes > 0);
+
+    // nothing was merged
+    assert_eq!(count(&a, "note").unwrap(), 3);
+    assert_eq!(get_note(&a, "newer").unwrap().unwrap().title, "start");
+    assert_eq!(get_note(&a, "both").unwrap().unwrap().title, "from a");
+}
+
+#[test]
+fn test_preview_push() {
+    let (a, b) = two_devices();
+    edit_title(&a, "newer", "from a");
+    edit_title(&a, "both", "from a");
+    edit_title(&b, "both", "from b");
+
+    let mut preview = SyncPreview::default();
+    preview.add_push(
+        &notes(&a, &["newer", "both"]),
+        &notes(&b, &["newer", "both"]),
+    );
+    assert_eq!(uuid4s(&preview.push), vec!["newer", "both"]);
+    assert_eq!(uuid4s(&preview.conflicts), vec!["both"]);
+    assert!(preview.pull.is_empty() && preview.update.is_empty());
+
+    // the same clash seen again on the pull side is listed once
+    preview.add_pull(&a, &notes(&b, &["both"])).unwrap();
+    assert_eq!(uuid4s(&preview.conflicts), vec!["both"]);
+    assert!(!preview.is_empty());
+    assert!(SyncPreview::default().is_empty());
+}
+
+#[test]
+fn test_preview_tombstones() {
+    let (a, b) = two_devices();
+    for uuid4 in ["same", "both"] {
+        let rowid = get_note(&b, uuid4).unwrap().unwrap().rowid;
+        delete(&b, rowid).unwrap();
+    }
+    // edited here since, the delete does not take it
+    edit_title(&a, "both", "from a");
+
+    let mut preview = SyncPreview::default();
+    preview
+        .add_tombstones(&a, &list_tombstones(&b, 0).unwrap())
+        .unwrap();
+    assert_eq!(uuid4s(&preview.delete), vec!["same"]);
+    assert!(!preview.is_empty());
+    assert_eq!(count(&a, "note").unwrap(), 3);
+}
+
+#[test]
+fn test_preview_attach() {
+    let path = std::env::temp_dir().join(format!(
+        "localnative-preview-{}.sqlite3",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+    {
+        let other = Connection::open(&path).unwrap();
+        localnative_core::cmd::create(&other).unwrap();
+        localnative_core::upgrade::upgrade(&other).unwrap();
+        insert_note(&other, "other-shared", "https://example.com/a");
+        insert_note(&other, "other-local", "https://example.com/b");
+        insert_note(&other, "both", "https://example.com/both");
+        set_visibility(&other, "other-local", false, true).unwrap();
+    }
+    let uri = path.to_str().unwrap();
+
+    let conn = open_db();
+    insert_note(&conn, "main-shared", "https://example.com/c");
+    insert_note(&conn, "both", "https://example.com/both");
+
+    let preview = preview_attach(&conn, uri, "").unwrap();
+    assert_eq!(uuid4s(&preview.pull), vec!["other-shared"]);
+    assert_eq!(uuid4s(&preview.push), vec!["main-shared"]);
+    assert_eq!(preview.pull[0].url, "https://example.com/a");
+    assert!(preview.update.is_empty() && preview.conflicts.is_empty());
+    // uuid4, url and created_at of each note
+    assert_eq!(preview.bytes, (12 + 21 + 25) + (11 + 21 + 25));
+
+    // nothing copied and the file detached again, the sync does what was shown
+    assert_eq!(count(&conn, "note").unwrap(), 2);
+    assert_eq!(preview_attach(&conn, uri, "").unwrap(), preview);
+    let res = sync_via_attach(&conn, uri, "");
+    assert!(res.contains("sync-via-attach-done"), "{}", res);
+    assert_eq!(count(&conn, "note").unwrap(), 3);
+    assert!(preview_attach(&conn, uri, "").unwrap().is_empty());
+
+    assert!(preview_attach(&conn, "/nonexistent/dir/x.sqlite3", "").is_err());
+    let _ = std::fs::remove_file(&path);
+}
+
+// the attach preview comes from the same comparisons the merge makes
+#[test]
+fn test_preview_attach_edits() {
+    let path = std::env::temp_dir().join(format!(
+        "localnative-preview-edits-{}.sqlite3",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+    let uri = path.to_str().unwrap();
+    let other = Connection::open(&path).unwrap();
+    localnative_core::cmd::create(&other).unwrap();
+    localnative_core::upgrade::upgrade(&other).unwrap();
+    let (a, _) = two_devices();
+    assert!(sync_via_attach(&a, uri, "").contains("sync-via-attach-done"));
+    assert!(preview_attach(&a, uri, "").unwrap().is_empty());
+
+    edit_title(&other, "newer", "edited there");
+    edit_title(&a, "both", "edited here");
+    edit_title(&other, "both", "edited there");
+    let preview = preview_attach(&a, uri, "").unwrap();
+    assert_eq!(uuid4s(&preview.update), vec!["newer"]);
+    assert_eq!(uuid4s(&preview.conflicts), vec!["both"]);
+    assert!(preview.pull.is_empty());
+    assert_eq!(uuid4s(&preview.push), vec!["both"]);
+    assert_eq!(get_note(&a, "both").unwrap().unwrap().title, "edited here");
+
+    assert!(sync_via_attach(&a, uri, "").contains("sync-via-attach-done"));
+    assert_eq!(
+        get_note(&a, "newer").unwrap().unwrap().title,
+        "edited there"
+    );
+    assert!(preview_attach(&a, uri, "").unwrap().is_empty());
+    drop(other);
+    let _ = std::fs::remove_file(&path);
+}
@@ -0,0 +1,77 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+use localnative_core::rpc::progress::{note_bytes, Cancelled, Phase, Progress, Tracker};
+use localnative_core::Note;
+use std::sync::{Arc, Mutex};
+
+#[test]
+fn test_progress_events() {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let seen = events.clone();
+    let tracker = Tracker::with_observer(move |p| seen.lock().unwrap().push(p.clone()));
+
+    tracker.phase(Phase::Pushing, 10);
+    tracker.advance(4, 100);
+    tracker.phase(Phase::Pulling, 0);
+    tracker.advance(3, 50);
+    tracker.finish(&Ok(()));
+
+    let events = events.lock().unwrap();
+    assert_eq!(events.len(), 5);
+    assert_eq!(
+        events[1],
+        Progress {
+            phase: Phase::Pushing,
+            done: 4,
+            total: 10,
+            bytes: 100
+        }
+    );
+    // a new phase counts from 0, bytes add up over the sync
+    assert_eq!(events[2].done, 0);
+    assert_eq!(events[3].bytes, 150);
+    assert_eq!(events[4].phase, Phase::Done);
+    assert_eq!(tracker.progress(), events[4]);
+}
+
+#[test]
+fn test_cancel() {
+    let tracker = Tracker::new();
+    let clone = tracker.clone();
+    assert!(tracker.check().is_ok());
+    clone.cancel();
+    assert_eq!(tracker.check(), Err(Cancelled));
+
+    tracker.phase(Phase::Pulling, 5);
+    let result: anyhow::Result<()> = Err(Cancelled.into());
+    tracker.finish(&result);
+    assert_eq!(tracker.progress().phase, Phase::Cancelled);
+    tracker.finish::<()>(&Err(anyhow::anyhow!("refused")));
+    assert_eq!(tracker.progress().phase, Phase::Failed);
+}
+
+#[test]
+fn test_note_bytes() {
+    let note = Note {
+        uuid4: "u".to_string(),
+        title: "title".to_string(),
+        annotations: "ab".to_string(),
+        ..Default::default()
+    };
+    assert_eq!(note_bytes(&[note.clone(), note]), 16);
+}
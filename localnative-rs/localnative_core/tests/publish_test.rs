@@ -0,0 +1,195 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+extern crate localnative_core;
+mod common;
+use common::{insert_note, open_db};
+use localnative_core::cmd::publish::{publish, tag_slug, PublishOptions};
+use localnative_core::cmd::visibility::set_visibility;
+use localnative_core::rusqlite::{params, Connection};
+use std::fs;
+use std::path::PathBuf;
+
+const N1: &str = "00000000-0000-4000-8000-000000000001";
+const N2: &str = "00000000-0000-4000-8000-000000000002";
+const N3: &str = "00000000-0000-4000-8000-000000000003";
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "localnative-publish-{}-{}",
+        std::process::id(),
+        name
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+fn public_note(conn: &Connection, uuid4: &str, title: &str, tags: &str, created_at: &str) {
+    insert_note(conn, uuid4, &format!("https://example.com/{}", uuid4));
+    conn.execute(
+        "update note set title = ?1, tags = ?2, created_at = ?3 where uuid4 = ?4",
+        params![title, tags, created_at, uuid4],
+    )
+    .unwrap();
+    set_visibility(conn, uuid4, true, false).unwrap();
+}
+
+fn options(out_dir: PathBuf) -> PublishOptions {
+    PublishOptions {
+        out_dir,
+        template_dir: None,
+        per_page: 2,
+        title: "Links".to_string(),
+    }
+}
+
+#[test]
+fn test_publish_site() {
+    let conn = open_db();
+    public_note(
+        &conn,
+        N1,
+        "First <b>",
+        "rust,web",
+        "2026-01-05 00:00:00:0 UTC",
+    );
+    public_note(&conn, N2, "Second", "rust", "2026-01-20 00:00:00:0 UTC");
+    public_note(&conn, N3, "Third", "c++", "2026-02-01 00:00:00:0 UTC");
+    insert_note(&conn, "private", "https://example.com/private");
+    insert_note(&conn, "local", "https://example.com/local");
+    set_visibility(&conn, "local", true, true).unwrap();
+
+    let out = temp_dir("site");
+    let summary = publish(&conn, &options(out.clone())).unwrap();
+    assert_eq!(summary.notes, 3);
+    // 2 index pages, 3 tags, 2 months, 3 notes
+    assert_eq!(summary.pages, 10);
+
+    let index = fs::read_to_string(out.join("index.html")).unwrap();
+    assert!(index.contains("Third"));
+    assert!(index.contains("Second"));
+    assert!(!index.contains("First"));
+    assert!(index.contains("page-2.html"));
+    let page2 = fs::read_to_string(out.join("page-2.html")).unwrap();
+    // escaped
+    assert!(page2.contains("First &lt;b&gt;"));
+
+    let rust = fs::read_to_string(out.join("tag/rust.html")).unwrap();
+    assert!(rust.contains("First") && rust.contains("Second") && !rust.contains("Third"));
+    assert!(out.join(format!("tag/{}.html", tag_slug("c++"))).exists());
+    let january = fs::read_to_string(out.join("archive/2026-01.html")).unwrap();
+    assert!(january.contains("Second") && !january.contains("Third"));
+    let note = fs::read_to_string(out.join(format!("note/{}.html", N3))).unwrap();
+    // minijinja also escapes '/' in html, browsers decode it in attributes
+    assert!(note.contains("example.com"));
+    assert!(note.contains("style.css"));
+    assert!(out.join("style.css").exists());
+    assert!(!out.join("note/private.html").exists());
+    assert!(!out.join("note/local.html").exists());
+
+    // a uuid4 that is not a uuid never becomes a file name
+    public_note(&conn, "../../evil", "Evil", "", "2026-02-02 00:00:00:0 UTC");
+    let summary = publish(&conn, &options(out.clone())).unwrap();
+    assert_eq!(summary.notes, 3);
+    assert!(!out.parent().unwrap().join("evil.html").exists());
+    assert!(!fs::read_to_string(out.join("index.html"))
+        .unwrap()
+        .contains("Evil"));
+
+    // a note no longer public disappears on the next publish
+    set_visibility(&conn, N1, false, false).unwrap();
+    let summary = publish(&conn, &options(out.clone())).unwrap();
+    assert_eq!(summary.notes, 2);
+    assert!(!out.join(format!("note/{}.html", N1)).exists());
+    assert!(!out.join("tag/web.html").exists());
+    assert!(!out.join("page-2.html").exists());
+}
+
+// created_at and tags come from peers and imports, neither may break the
+// site or send a page outside it
+#[test]
+fn test_publish_dates_and_tag_slugs() {
+    let conn = open_db();
+    public_note(&conn, N1, "Slash", "a+b", "2024/01/05");
+    public_note(&conn, N2, "Dots", "a_b", "../../x");
+    public_note(&conn, N3, "Case", "A_b", "2026-03-01 00:00:00:0 UTC");
+
+    let out = temp_dir("odd");
+    let summary = publish(&conn, &options(out.clone())).unwrap();
+    assert_eq!(summary.notes, 3);
+    assert!(!out.parent().unwrap().join("x.html").exists());
+    let months: Vec<_> = fs::read_dir(out.join("archive"))
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    assert_eq!(months, vec!["2026-03.html".to_string()]);
+
+    // three tags, three pages, each with only its own note
+    let mut tags: Vec<_> = fs::read_dir(out.join("tag"))
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    tags.sort();
+    assert_eq!(tags, vec!["A_b.html", "a_b-2.html", "a_b-3.html"]);
+    for (tag, title) in [("A_b", "Case"), ("a_b-2", "Slash"), ("a_b-3", "Dots")] {
+        let page = fs::read_to_string(out.join(format!("tag/{}.html", tag))).unwrap();
+        assert!(page.contains(title), "{}", tag);
+    }
+    let note = fs::read_to_string(out.join(format!("note/{}.html", N1))).unwrap();
+    assert!(note.contains("a_b-2.html"));
+}
+
+#[test]
+fn test_publish_template_override() {
+    let conn = open_db();
+    public_note(&conn, N1, "Only", "", "2026-03-01 00:00:00:0 UTC");
+    let templates = temp_dir("templates");
+    fs::create_dir_all(&templates).unwrap();
+    fs::write(
+        templates.join("note.html"),
+        "custom {{ note.title }} in {{ site.title }}",
+    )
+    .unwrap();
+    fs::write(templates.join("style.css"), "body {}").unwrap();
+
+    let out = temp_dir("override");
+    let mut options = options(out.clone());
+    options.template_dir = Some(templates);
+    publish(&conn, &options).unwrap();
+    assert_eq!(
+        fs::read_to_string(out.join(format!("note/{}.html", N1))).unwrap(),
+        "custom Only in Links"
+    );
+    assert_eq!(
+        fs::read_to_string(out.join("style.css")).unwrap(),
+        "body {}"
+    );
+    // the bundled list template is still used
+    assert!(fs::read_to_string(out.join("index.html"))
+        .unwrap()
+        .contains("Only"));
+}
+
+#[test]
+fn test_publish_refuses_foreign_dir() {
+    let conn = open_db();
+    let out = temp_dir("foreign");
+    fs::create_dir_all(&out).unwrap();
+    fs::write(out.join("keep.txt"), "mine").unwrap();
+    assert!(publish(&conn, &options(out.clone())).is_err());
+    assert_eq!(fs::read_to_string(out.join("keep.txt")).unwrap(), "mine");
+}
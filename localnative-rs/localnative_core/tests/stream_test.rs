@@ -0,0 +1,133 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+use futures::prelude::*;
+use localnative_core::rpc::stream::{open, Endpoint};
+use localnative_core::rpc::tls::{
+    accept, client_peer_fingerprint, connect_stream, load_or_create_identity,
+    server_peer_fingerprint, transport,
+};
+use std::path::PathBuf;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "localnative-stream-{}-{}",
+        std::process::id(),
+        name
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+#[test]
+fn test_parse_endpoint() {
+    for s in [
+        "10.0.0.2:2345",
+        "unix:/run/user/1000/localnative.sock",
+        "ssh:me@myserver",
+        "exec:ssh -p 2222 myserver localnative-sync-stdio",
+    ] {
+        assert_eq!(s.parse::<Endpoint>().unwrap().to_string(), s);
+    }
+    assert_eq!(
+        "ssh:myserver".parse::<Endpoint>().unwrap(),
+        Endpoint::Ssh("myserver".to_string())
+    );
+    let tcp: Endpoint = "10.0.0.2:2345".parse().unwrap();
+    assert!(!tcp.is_authenticated());
+    assert_eq!(tcp.name(), "10.0.0.2");
+    assert!("ssh:myserver"
+        .parse::<Endpoint>()
+        .unwrap()
+        .is_authenticated());
+    for s in [
+        "laptop",
+        "unix:",
+        "ssh: ",
+        "exec:",
+        "ssh:-oProxyCommand=touch x",
+    ] {
+        assert!(s.parse::<Endpoint>().is_err(), "{}", s);
+    }
+}
+
+// the json api is reached from qr codes, it never runs a command
+#[test]
+fn test_parse_untrusted_endpoint() {
+    for s in ["10.0.0.2:2345", "unix:/run/user/1000/localnative.sock"] {
+        assert_eq!(Endpoint::parse_untrusted(s).unwrap().to_string(), s);
+    }
+    for s in ["ssh:me@myserver", "exec:touch /tmp/x", "laptop"] {
+        assert!(Endpoint::parse_untrusted(s).is_err(), "{}", s);
+    }
+}
+
+// tls and the rpc framing need nothing from tcp
+#[tokio::test]
+async fn test_tls_over_any_stream() {
+    let server_identity = load_or_create_identity(&temp_dir("server")).unwrap();
+    let client_identity = load_or_create_identity(&temp_dir("client")).unwrap();
+    let (near, far) = tokio::io::duplex(4096);
+    let server = tokio::spawn(async move {
+        let stream = accept(far, &server_identity).await.unwrap();
+        let peer = server_peer_fingerprint(&stream);
+        let mut t = transport::<_, String, String>(stream);
+        let msg = t.next().await.unwrap().unwrap();
+        t.send(format!("echo {}", msg)).await.unwrap();
+        peer
+    });
+    let stream = connect_stream(near, &client_identity).await.unwrap();
+    assert_eq!(client_peer_fingerprint(&stream).split(' ').count(), 16);
+    let mut t = transport::<_, String, String>(stream);
+    t.send("hello".to_string()).await.unwrap();
+    assert_eq!(t.next().await.unwrap().unwrap(), "echo hello");
+    assert_eq!(server.await.unwrap(), client_identity.fingerprint());
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_exec_stream() {
+    // cat echoes what the client writes, as a remote sync-stdio answers
+    let mut stream = open(&"exec:cat".parse().unwrap()).await.unwrap();
+    stream.write_all(b"hello").await.unwrap();
+    let mut buf = [0u8; 5];
+    stream.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"hello");
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_unix_stream() {
+    let dir = temp_dir("unix");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("sync.sock");
+    let listener = tokio::net::UnixListener::bind(&path).unwrap();
+    let server = tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut buf = [0u8; 5];
+        stream.read_exact(&mut buf).await.unwrap();
+        stream.write_all(&buf).await.unwrap();
+    });
+    let mut stream = open(&Endpoint::Unix(path)).await.unwrap();
+    stream.write_all(b"hello").await.unwrap();
+    let mut buf = [0u8; 5];
+    stream.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"hello");
+    server.await.unwrap();
+    let _ = std::fs::remove_dir_all(&dir);
+}
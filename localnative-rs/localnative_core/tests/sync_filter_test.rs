@@ -0,0 +1,196 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+extern crate localnative_core;
+mod common;
+use common::open_db;
+use localnative_core::cmd::peer::{filter, revoke_peer, set_filter, trust_peer};
+use localnative_core::cmd::preview::preview_attach;
+use localnative_core::cmd::sync::{get_high_water, set_high_water, HighWater};
+use localnative_core::cmd::sync_filter::{changed, combine, track, SyncFilter};
+use localnative_core::cmd::visibility::set_visibility;
+use localnative_core::cmd::{filter_count, search_count, sync_via_attach};
+use localnative_core::rusqlite::Connection;
+
+fn insert_tagged(conn: &Connection, uuid4: &str, tags: &str, created_at: &str) {
+    conn.execute(
+        "INSERT INTO note (uuid4, title, url, tags, description, comments, annotations, created_at, is_public)
+        VALUES (?1, ?1, '', ?2, '', '', '', ?3, 0)",
+        [uuid4, tags, created_at],
+    )
+    .unwrap();
+}
+
+fn uuid4s(conn: &Connection) -> Vec<String> {
+    let mut stmt = conn
+        .prepare("select uuid4 from note order by uuid4")
+        .unwrap();
+    let rows = stmt.query_map([], |row| row.get(0)).unwrap();
+    rows.map(|r| r.unwrap()).collect()
+}
+
+#[test]
+fn test_filter_terms() {
+    let conn = open_db();
+    insert_tagged(&conn, "work-old", "work,rust", "2020-05-01 00:00:00:0 UTC");
+    insert_tagged(&conn, "work-new", "Work", "2099-01-01 00:00:00:0 UTC");
+    insert_tagged(&conn, "homework", "homework", "2099-01-01 00:00:00:0 UTC");
+    insert_tagged(&conn, "work-local", "work", "2099-01-01 00:00:00:0 UTC");
+    set_visibility(&conn, "work-local", false, true).unwrap();
+
+    // a whole tag, in any case
+    assert_eq!(search_count(&conn, "tag:work").unwrap(), 3);
+    assert_eq!(search_count(&conn, "tag:work since:2021-01-01").unwrap(), 2);
+    assert_eq!(search_count(&conn, "since:365d").unwrap(), 3);
+    // a quote is data, not sql
+    assert_eq!(search_count(&conn, "tag:it's").unwrap(), 0);
+    // the date range view applies them with no words typed
+    assert_eq!(
+        filter_count(&conn, "tag:work", "2020-01-01", "2099-12-31").unwrap(),
+        3
+    );
+    assert_eq!(
+        filter_count(&conn, "tag:work", "2021-01-01", "2099-12-31").unwrap(),
+        2
+    );
+
+    let filter = SyncFilter::load(&conn, "tag:work since:365d").unwrap();
+    assert!(filter.allows("work-new"));
+    assert!(!filter.allows("work-old"));
+    assert!(!filter.allows("homework"));
+    // local only notes never sync
+    assert!(!filter.allows("work-local"));
+    let filter = SyncFilter::load(&conn, "rust").unwrap();
+    assert!(filter.allows("work-old"));
+    assert!(!filter.allows("work-new"));
+    assert!(SyncFilter::load(&conn, " ").unwrap().allows("anything"));
+
+    assert_eq!(combine(" tag:work ", "since:365d"), "tag:work since:365d");
+    assert_eq!(combine("", ""), "");
+}
+
+#[test]
+fn test_filtered_pages() {
+    let conn = open_db();
+    for i in 0..5 {
+        insert_tagged(&conn, &format!("a-{}", i), "other", "2026-01-01");
+    }
+    insert_tagged(&conn, "b-work", "work", "2026-01-01");
+    insert_tagged(&conn, "c-other", "other", "2026-01-01");
+
+    // pages without a match are read past, an empty page means the end
+    let filter = SyncFilter::load(&conn, "tag:work").unwrap();
+    let changes = filter.changes_since(&conn, 0, 2).unwrap();
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].uuid4, "b-work");
+    assert!(filter
+        .changes_since(&conn, changes[0].seq, 2)
+        .unwrap()
+        .is_empty());
+    assert_eq!(filter.uuid4_page(&conn, "", 2).unwrap(), vec!["b-work"]);
+    assert!(filter.uuid4_page(&conn, "b-work", 2).unwrap().is_empty());
+}
+
+#[test]
+fn test_sync_via_attach_filter() {
+    let path = std::env::temp_dir().join(format!(
+        "localnative-sync-filter-{}.sqlite3",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+    {
+        let other = open_db();
+        insert_tagged(&other, "other-work", "work", "2026-01-01");
+        insert_tagged(&other, "other-home", "home", "2026-01-01");
+        other
+            .execute("vacuum into ?1", [path.to_str().unwrap()])
+            .unwrap();
+    }
+    let conn = open_db();
+    insert_tagged(&conn, "main-work", "work", "2026-01-01");
+    insert_tagged(&conn, "main-home", "home", "2026-01-01");
+    let uri = path.to_str().unwrap();
+
+    let preview = preview_attach(&conn, uri, "tag:work").unwrap();
+    assert_eq!(preview.pull.len(), 1);
+    assert_eq!(preview.pull[0].uuid4, "other-work");
+    assert_eq!(preview.push.len(), 1);
+    assert_eq!(preview.push[0].uuid4, "main-work");
+
+    let res = sync_via_attach(&conn, uri, "tag:work");
+    assert!(res.contains("sync-via-attach-done"), "{}", res);
+    assert_eq!(uuid4s(&conn), vec!["main-home", "main-work", "other-work"]);
+    let other = Connection::open(&path).unwrap();
+    assert_eq!(
+        uuid4s(&other),
+        vec!["main-work", "other-home", "other-work"]
+    );
+
+    // without a filter the rest follows
+    assert!(sync_via_attach(&conn, uri, "").contains("sync-via-attach-done"));
+    assert_eq!(uuid4s(&conn).len(), 4);
+    assert_eq!(uuid4s(&other).len(), 4);
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn test_peer_filter() {
+    let conn = open_db();
+    assert!(!set_filter(&conn, "AAAA BBBB", "tag:work").unwrap());
+    assert_eq!(filter(&conn, "AAAA BBBB").unwrap(), "");
+
+    trust_peer(&conn, "AAAA BBBB", "10.0.0.2").unwrap();
+    assert!(set_filter(&conn, "AAAA BBBB", " tag:work ").unwrap());
+    assert_eq!(filter(&conn, "AAAA BBBB").unwrap(), "tag:work");
+    // pairing again keeps it
+    let peer = trust_peer(&conn, "AAAA BBBB", "10.0.0.3").unwrap();
+    assert_eq!(peer.filter, "tag:work");
+    assert_eq!(filter(&conn, "AAAA BBBB").unwrap(), "tag:work");
+
+    // a filter other than last time's starts a full sync
+    set_high_water(&conn, HighWater::Pull, "AAAA BBBB", 7).unwrap();
+    assert!(!changed(&conn, "AAAA BBBB", "").unwrap());
+    assert!(changed(&conn, "AAAA BBBB", "tag:work").unwrap());
+    track(&conn, "AAAA BBBB", "tag:work").unwrap();
+    assert_eq!(
+        get_high_water(&conn, HighWater::Pull, "AAAA BBBB").unwrap(),
+        0
+    );
+    set_high_water(&conn, HighWater::Pull, "AAAA BBBB", 7).unwrap();
+    track(&conn, "AAAA BBBB", "tag:work").unwrap();
+    assert_eq!(
+        get_high_water(&conn, HighWater::Pull, "AAAA BBBB").unwrap(),
+        7
+    );
+
+    // a relative window is the date it resolves to, moving on is a new filter
+    let today = time::OffsetDateTime::now_utc().date();
+    let since = today - time::Duration::days(30);
+    track(&conn, "AAAA BBBB", "tag:work since:30d").unwrap();
+    assert!(!changed(&conn, "AAAA BBBB", &format!("tag:work since:{}", since)).unwrap());
+    track(
+        &conn,
+        "AAAA BBBB",
+        &format!("tag:work since:{}", since.previous_day().unwrap()),
+    )
+    .unwrap();
+    assert!(changed(&conn, "AAAA BBBB", "tag:work since:30d").unwrap());
+
+    assert!(revoke_peer(&conn, "AAAA BBBB").unwrap());
+    assert_eq!(filter(&conn, "AAAA BBBB").unwrap(), "");
+    assert!(!changed(&conn, "AAAA BBBB", "").unwrap());
+}
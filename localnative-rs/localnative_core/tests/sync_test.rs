@@ -0,0 +1,287 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+extern crate localnative_core;
+mod common;
+use common::{insert_note, open_db};
+use localnative_core::cmd::conflict::{edit_note, get_note};
+use localnative_core::cmd::peer::{revoke_peer, trust_peer};
+use localnative_core::cmd::sync::{
+    changes_since, diff_uuid4_to_server, diff_versions, get_high_water, get_notes_by_uuid4s,
+    last_change_seq, merge_notes, next_candidates, set_high_water, uuid4_page, Cursor, HighWater,
+    MergeSummary,
+};
+use localnative_core::cmd::visibility::set_visibility;
+use localnative_core::cmd::{count, create, sync_via_attach};
+use localnative_core::rusqlite::Connection;
+use localnative_core::upgrade;
+
+fn page_all(conn: &Connection, limit: u32) -> Vec<Vec<String>> {
+    let mut pages = Vec::new();
+    let mut after = String::new();
+    loop {
+        let page = uuid4_page(conn, &after, limit).unwrap();
+        match page.last() {
+            Some(last) => after = last.clone(),
+            None => break,
+        }
+        pages.push(page);
+    }
+    pages
+}
+
+#[test]
+fn test_uuid4_page() {
+    let conn = open_db();
+    for i in 0..7 {
+        insert_note(&conn, &format!("uuid-{}", i), "https://example.com");
+    }
+    set_visibility(&conn, "uuid-3", false, true).unwrap();
+    let pages = page_all(&conn, 3);
+    assert_eq!(pages.len(), 2);
+    assert_eq!(pages[0], vec!["uuid-0", "uuid-1", "uuid-2"]);
+    assert_eq!(pages[1], vec!["uuid-4", "uuid-5", "uuid-6"]);
+}
+
+#[test]
+fn test_batch_round_trip() {
+    let server = open_db();
+    let client = open_db();
+    for i in 0..250 {
+        insert_note(&server, &format!("note-{:03}", i), "https://example.com");
+    }
+    set_visibility(&server, "note-000", true, false).unwrap();
+    set_visibility(&server, "note-001", false, true).unwrap();
+    // already on the client, must not be duplicated or overwritten
+    insert_note(&client, "note-002", "https://client.example.com");
+
+    let mut inserted = 0;
+    for page in page_all(&server, 100) {
+        let missing = diff_uuid4_to_server(&client, page).unwrap();
+        for batch in missing.chunks(40) {
+            let notes = get_notes_by_uuid4s(&server, batch).unwrap();
+            assert!(notes.len() <= 40);
+            inserted += merge_notes(&client, &notes).unwrap().inserted;
+        }
+    }
+    assert_eq!(inserted, 248);
+    assert_eq!(count(&client, "note").unwrap(), 249);
+    let notes = get_notes_by_uuid4s(
+        &client,
+        &[
+            "note-000".to_string(),
+            "note-001".to_string(),
+            "note-002".to_string(),
+        ],
+    )
+    .unwrap();
+    assert_eq!(notes.len(), 2);
+    assert!(notes[0].is_public);
+    assert_eq!(notes[1].url, "https://client.example.com");
+
+    // a batch that is already there inserts nothing, local only notes are refused
+    let mut again = get_notes_by_uuid4s(&server, &["note-000".to_string()]).unwrap();
+    assert_eq!(
+        merge_notes(&client, &again).unwrap(),
+        MergeSummary::default()
+    );
+    again[0].uuid4 = "new-local".to_string();
+    again[0].is_local = true;
+    assert_eq!(
+        merge_notes(&client, &again).unwrap(),
+        MergeSummary::default()
+    );
+}
+
+#[test]
+fn test_change_log() {
+    let conn = open_db();
+    assert_eq!(last_change_seq(&conn).unwrap(), 0);
+    insert_note(&conn, "a", "https://example.com/a");
+    insert_note(&conn, "b", "https://example.com/b");
+    set_visibility(&conn, "b", false, true).unwrap();
+    insert_note(&conn, "c", "https://example.com/c");
+
+    let changes = changes_since(&conn, 0, 100).unwrap();
+    let uuid4s: Vec<_> = changes.iter().map(|change| change.uuid4.as_str()).collect();
+    assert_eq!(uuid4s, vec!["a", "c"]);
+    let after_c = last_change_seq(&conn).unwrap();
+    assert_eq!(changes.last().unwrap().seq, after_c);
+    assert!(changes_since(&conn, after_c, 100).unwrap().is_empty());
+
+    // sharing a local note again is a change
+    set_visibility(&conn, "b", false, false).unwrap();
+    let changes = changes_since(&conn, after_c, 100).unwrap();
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].uuid4, "b");
+
+    let first = changes_since(&conn, 0, 1).unwrap();
+    assert_eq!(first.len(), 1);
+    assert_eq!(first[0].uuid4, "a");
+}
+
+#[test]
+fn test_change_log_upgrade() {
+    let conn = Connection::open_in_memory().unwrap();
+    create(&conn).unwrap();
+    insert_note(&conn, "a", "https://example.com/a");
+    insert_note(&conn, "b", "https://example.com/b");
+    upgrade::upgrade(&conn).unwrap();
+    // logged once when the log is created, backfilling versions is no edit
+    // (the oldest upgrade hands out fresh uuid4s)
+    let changes = changes_since(&conn, 0, 100).unwrap();
+    assert_eq!(changes.len(), 2);
+    // edits are still logged
+    conn.execute(
+        "update note set title = 'edited' where uuid4 = ?1",
+        [&changes[0].uuid4],
+    )
+    .unwrap();
+    assert_eq!(changes_since(&conn, 0, 100).unwrap().len(), 3);
+}
+
+#[test]
+fn test_high_water() {
+    let conn = open_db();
+    assert_eq!(get_high_water(&conn, HighWater::Pull, "fp").unwrap(), 0);
+    set_high_water(&conn, HighWater::Pull, "fp", 42).unwrap();
+    set_high_water(&conn, HighWater::Push, "fp", 7).unwrap();
+    set_high_water(&conn, HighWater::Pull, "other", 3).unwrap();
+    assert_eq!(get_high_water(&conn, HighWater::Pull, "fp").unwrap(), 42);
+    assert_eq!(get_high_water(&conn, HighWater::Push, "fp").unwrap(), 7);
+
+    trust_peer(&conn, "fp", "127.0.0.1").unwrap();
+    assert!(revoke_peer(&conn, "fp").unwrap());
+    assert_eq!(get_high_water(&conn, HighWater::Pull, "fp").unwrap(), 0);
+    assert_eq!(get_high_water(&conn, HighWater::Push, "fp").unwrap(), 0);
+    assert_eq!(get_high_water(&conn, HighWater::Pull, "other").unwrap(), 3);
+}
+
+// what the client does on each pull, returns how many notes came over
+fn pull(server: &Connection, client: &Connection) -> usize {
+    let mut after = get_high_water(client, HighWater::Pull, "server").unwrap();
+    let mut inserted = 0;
+    loop {
+        let changes = changes_since(server, after, 2).unwrap();
+        let Some(last) = changes.last() else {
+            break;
+        };
+        after = last.seq;
+        let chunk = changes
+            .into_iter()
+            .map(|change| (change.uuid4, change.clock))
+            .collect();
+        let missing = diff_versions(client, chunk).unwrap();
+        let notes = get_notes_by_uuid4s(server, &missing).unwrap();
+        inserted += merge_notes(client, &notes).unwrap().inserted;
+    }
+    set_high_water(client, HighWater::Pull, "server", after).unwrap();
+    inserted
+}
+
+#[test]
+fn test_incremental_pull() {
+    let server = open_db();
+    let client = open_db();
+    for i in 0..5 {
+        insert_note(&server, &format!("note-{}", i), "https://example.com");
+    }
+    assert_eq!(pull(&server, &client), 5);
+    let hw = get_high_water(&client, HighWater::Pull, "server").unwrap();
+    assert_eq!(hw, last_change_seq(&server).unwrap());
+    // nothing new, nothing offered
+    assert!(changes_since(&server, hw, 100).unwrap().is_empty());
+    assert_eq!(pull(&server, &client), 0);
+
+    insert_note(&server, "note-5", "https://example.com");
+    assert_eq!(changes_since(&server, hw, 100).unwrap().len(), 1);
+    assert_eq!(pull(&server, &client), 1);
+    assert_eq!(count(&client, "note").unwrap(), 6);
+}
+
+#[test]
+fn test_next_candidates() {
+    let conn = open_db();
+    for i in 0..3 {
+        insert_note(&conn, &format!("note-{}", i), "https://example.com");
+    }
+    set_visibility(&conn, "note-1", false, true).unwrap();
+
+    let mut cursor = Cursor::Uuid4(String::new());
+    let chunk = next_candidates(&conn, &mut cursor).unwrap();
+    let uuid4s: Vec<_> = chunk.iter().map(|(uuid4, _)| uuid4.as_str()).collect();
+    assert_eq!(uuid4s, vec!["note-0", "note-2"]);
+    assert_eq!(cursor, Cursor::Uuid4("note-2".to_string()));
+    assert!(next_candidates(&conn, &mut cursor).unwrap().is_empty());
+
+    let mut cursor = Cursor::Seq(0);
+    assert_eq!(next_candidates(&conn, &mut cursor).unwrap(), chunk);
+    assert_eq!(cursor, Cursor::Seq(last_change_seq(&conn).unwrap()));
+    assert!(next_candidates(&conn, &mut cursor).unwrap().is_empty());
+}
+
+fn edit_title(conn: &Connection, uuid4: &str, title: &str) {
+    // keeps updated_at apart between the devices
+    std::thread::sleep(std::time::Duration::from_millis(5));
+    edit_note(conn, uuid4, |note| note.title = title.to_string()).unwrap();
+}
+
+// versions travel with the notes, edits on one side spread and edits on
+// both are settled, the two files end up alike
+#[test]
+fn test_sync_via_attach_merges() {
+    let path = std::env::temp_dir().join(format!(
+        "localnative-sync-attach-{}.sqlite3",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+    let uri = path.to_str().unwrap();
+    let b = Connection::open(&path).unwrap();
+    create(&b).unwrap();
+    upgrade::upgrade(&b).unwrap();
+
+    let a = open_db();
+    for uuid4 in ["one", "both"] {
+        insert_note(&a, uuid4, "https://example.com");
+        edit_title(&a, uuid4, "start");
+    }
+    assert!(sync_via_attach(&a, uri, "").contains("sync-via-attach-done"));
+    let copied = get_note(&b, "one").unwrap().unwrap();
+    assert_eq!(
+        copied.version,
+        get_note(&a, "one").unwrap().unwrap().version
+    );
+
+    edit_title(&b, "one", "edited on b");
+    edit_title(&a, "both", "edited on a");
+    edit_title(&b, "both", "edited on b");
+    assert!(sync_via_attach(&a, uri, "").contains("sync-via-attach-done"));
+    for uuid4 in ["one", "both"] {
+        let mine = get_note(&a, uuid4).unwrap().unwrap();
+        let theirs = get_note(&b, uuid4).unwrap().unwrap();
+        assert_eq!(mine.title, "edited on b", "{}", uuid4);
+        assert_eq!((mine.title, mine.version), (theirs.title, theirs.version));
+    }
+    let seq = (last_change_seq(&a).unwrap(), last_change_seq(&b).unwrap());
+    assert!(sync_via_attach(&a, uri, "").contains("sync-via-attach-done"));
+    assert_eq!(
+        (last_change_seq(&a).unwrap(), last_change_seq(&b).unwrap()),
+        seq
+    );
+    drop(b);
+    let _ = std::fs::remove_file(&path);
+}
@@ -0,0 +1,91 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+extern crate localnative_core;
+use futures::prelude::*;
+use localnative_core::rpc::tls::{
+    client_peer_fingerprint, connect, listen, load_or_create_identity, server_peer_fingerprint,
+    transport,
+};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use tokio::io::AsyncWriteExt;
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("localnative-tls-{}-{}", std::process::id(), name));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+fn free_addr() -> SocketAddr {
+    std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+}
+
+#[test]
+fn test_identity_is_persisted() {
+    let dir = temp_dir("persist");
+    let first = load_or_create_identity(&dir).unwrap();
+    let again = load_or_create_identity(&dir).unwrap();
+    assert_eq!(first.fingerprint(), again.fingerprint());
+    // 16 groups of 4 hex digits
+    assert_eq!(first.fingerprint().split(' ').count(), 16);
+    let other = load_or_create_identity(&temp_dir("other")).unwrap();
+    assert_ne!(first.fingerprint(), other.fingerprint());
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(dir.join("device-key.der"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
+
+#[tokio::test]
+async fn test_mutual_fingerprints() {
+    let server_identity = load_or_create_identity(&temp_dir("server")).unwrap();
+    let client_identity = load_or_create_identity(&temp_dir("client")).unwrap();
+    let addr = free_addr();
+    let mut incoming = Box::pin(listen(addr, &server_identity).await.unwrap());
+    let server = tokio::spawn(async move {
+        let stream = incoming.next().await.unwrap();
+        let peer = server_peer_fingerprint(&stream);
+        let mut t = transport::<_, String, String>(stream);
+        let msg = t.next().await.unwrap().unwrap();
+        t.send(format!("echo {}", msg)).await.unwrap();
+        peer
+    });
+
+    // a plain tcp client fails the handshake without stopping the listener
+    let mut plain = tokio::net::TcpStream::connect(addr).await.unwrap();
+    plain.write_all(b"GET / HTTP/1.0\r\n\r\n").await.unwrap();
+    drop(plain);
+
+    let stream = connect(&addr, &client_identity).await.unwrap();
+    assert_eq!(
+        client_peer_fingerprint(&stream),
+        server_identity.fingerprint()
+    );
+    let mut t = transport::<_, String, String>(stream);
+    t.send("hello".to_string()).await.unwrap();
+    assert_eq!(t.next().await.unwrap().unwrap(), "echo hello");
+    assert_eq!(server.await.unwrap(), client_identity.fingerprint());
+}
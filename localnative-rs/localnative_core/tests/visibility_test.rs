@@ -0,0 +1,108 @@
+/*
+    Local Native
+    Copyright (C) 2026  Yi Wang
+
+    This program is free software: you can redistribute it and/or modify
+    it under the terms of the GNU Affero General Public License as published by
+    the Free Software Foundation, either version 3 of the License, or
+    (at your option) any later version.
+
+    This program is distributed in the hope that it will be useful,
+    but WITHOUT ANY WARRANTY; without even the implied warranty of
+    MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+    GNU Affero General Public License for more details.
+
+    You should have received a copy of the GNU Affero General Public License
+    along with this program.  If not, see <https://www.gnu.org/licenses/>.
+*/
+extern crate localnative_core;
+mod common;
+use common::{insert_note, open_db};
+use localnative_core::cmd::sync::{
+    diff_uuid4_from_server, diff_uuid4_to_server, get_note_by_uuid4, next_uuid4_candidates,
+};
+use localnative_core::cmd::visibility::set_visibility;
+use localnative_core::cmd::{search, sync_via_attach};
+use localnative_core::rusqlite::Connection;
+
+fn uuid4s(conn: &Connection) -> Vec<String> {
+    let mut stmt = conn
+        .prepare("select uuid4 from note order by uuid4")
+        .unwrap();
+    let rows = stmt.query_map([], |row| row.get(0)).unwrap();
+    rows.map(|r| r.unwrap()).collect()
+}
+
+#[test]
+fn test_is_public_round_trips() {
+    let conn = open_db();
+    insert_note(&conn, "public-1", "https://example.com/1");
+    assert!(set_visibility(&conn, "public-1", true, false).unwrap());
+    assert!(!set_visibility(&conn, "missing", true, false).unwrap());
+    assert!(get_note_by_uuid4(&conn, "public-1").unwrap().is_public);
+
+    insert_note(&conn, "private-1", "https://example.com/2");
+    assert!(!get_note_by_uuid4(&conn, "private-1").unwrap().is_public);
+}
+
+#[test]
+fn test_local_notes_are_not_offered() {
+    let conn = open_db();
+    insert_note(&conn, "shared-1", "https://example.com/1");
+    insert_note(&conn, "local-1", "https://example.com/2");
+    set_visibility(&conn, "local-1", false, true).unwrap();
+
+    assert_eq!(next_uuid4_candidates(&conn).unwrap(), vec!["shared-1"]);
+    assert!(get_note_by_uuid4(&conn, "local-1").is_err());
+    // the other side has neither note, only the shared one is offered
+    assert_eq!(
+        diff_uuid4_from_server(&conn, Vec::new()).unwrap(),
+        vec!["shared-1"]
+    );
+    // a local note is never overwritten by one with the same uuid4
+    assert!(diff_uuid4_to_server(&conn, vec!["local-1".to_string()])
+        .unwrap()
+        .is_empty());
+
+    let found = search(&conn, "is:local", 10, 0).unwrap();
+    assert!(found.contains("local-1"));
+    assert!(!found.contains("shared-1"));
+}
+
+#[test]
+fn test_sync_via_attach_skips_local_notes() {
+    let path = std::env::temp_dir().join(format!(
+        "localnative-visibility-{}.sqlite3",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+    {
+        let other = Connection::open(&path).unwrap();
+        localnative_core::cmd::create(&other).unwrap();
+        localnative_core::upgrade::upgrade(&other).unwrap();
+        insert_note(&other, "other-shared", "https://example.com/a");
+        insert_note(&other, "other-local", "https://example.com/b");
+        set_visibility(&other, "other-shared", true, false).unwrap();
+        set_visibility(&other, "other-local", false, true).unwrap();
+    }
+
+    let conn = open_db();
+    insert_note(&conn, "main-shared", "https://example.com/c");
+    insert_note(&conn, "main-local", "https://example.com/d");
+    set_visibility(&conn, "main-local", false, true).unwrap();
+
+    let res = sync_via_attach(&conn, path.to_str().unwrap(), "");
+    assert!(res.contains("sync-via-attach-done"), "{}", res);
+
+    assert_eq!(
+        uuid4s(&conn),
+        vec!["main-local", "main-shared", "other-shared"]
+    );
+    assert!(get_note_by_uuid4(&conn, "other-shared").unwrap().is_public);
+    let other = Connection::open(&path).unwrap();
+    assert_eq!(
+        uuid4s(&other),
+        vec!["main-shared", "other-local", "other-shared"]
+    );
+    let _ = std::fs::remove_file(&path);
+}
@@ -13,6 +13,7 @@ mod style;
 mod sync;
 mod tags;
 mod translate;
+mod unlock;
 
 use std::cmp::Ordering;
 use std::sync::Arc;
@@ -32,6 +33,13 @@ use iced::{
 use iced_native::window;
 use iced_native::Event;
 use iced_native::{command, event::Status};
+use localnative_core::cmd::conflict::Conflict;
+use localnative_core::cmd::peer::Peer;
+use localnative_core::cmd::preview::SyncPreview;
+use localnative_core::rpc::daemon::SyncStatus;
+use localnative_core::rpc::discovery::DiscoveredPeer;
+use localnative_core::rpc::handshake::Incompatible;
+use localnative_core::rpc::info::ServerInfo;
 use localnative_core::rpc::server::Stop;
 use localnative_core::{exe::get_sqlite_connection, rusqlite::Connection};
 use middle_date::MiddleDate;
@@ -42,6 +50,7 @@ use sidebar::Sidebar;
 pub use tags::TagView;
 
 use crate::sync::SyncView;
+use crate::unlock::UnlockView;
 
 pub struct LocalNative {
     config: Config,
@@ -51,6 +60,8 @@ pub struct LocalNative {
 #[allow(clippy::large_enum_variant)]
 pub enum State {
     Loading,
+    // encrypted database waiting for its passphrase
+    Locked(UnlockView),
     Loaded(Data),
 }
 
@@ -79,12 +90,21 @@ pub enum Message {
     RequestClosed,
     ApplyLanguage(Option<()>),
     CloseWindow(Option<()>),
-    SyncResult(anyhow::Result<()>),
+    SyncResult(anyhow::Result<String>),
+    PreviewResult(anyhow::Result<(sync::PendingSync, SyncPreview)>),
+    PairResult(anyhow::Result<String>),
+    PeersLoaded(Option<Vec<Peer>>),
+    ConflictsLoaded(Option<Vec<Conflict>>),
+    Discovered(Option<Vec<DiscoveredPeer>>),
+    ServerInfoLoaded(Option<ServerInfo>),
+    SyncStatusLoaded(Option<Vec<SyncStatus>>),
     SyncOption(Option<()>),
     StartServerResult(std::io::Result<Stop>),
     ServerOption(Option<()>),
     InitHost(()),
     Receiver(Option<MiddleDate>),
+    ArchiveOpened(Option<()>),
+    UnlockMessage(unlock::Message),
 }
 
 impl iced::Application for LocalNative {
@@ -129,6 +149,11 @@ impl iced::Application for LocalNative {
         match state {
             State::Loading => match message {
                 Message::Loading(..) => {
+                    let location = localnative_core::exe::sqlite3_db_location();
+                    if localnative_core::encryption::is_locked(std::path::Path::new(&location)) {
+                        self.state = State::Locked(UnlockView::default());
+                        return Command::none();
+                    }
                     let conn = Arc::new(Mutex::new(get_sqlite_connection()));
 
                     let data = Data {
@@ -170,6 +195,17 @@ impl iced::Application for LocalNative {
                 Message::InitHost(_) => Command::none(),
                 _ => Command::none(),
             },
+            State::Locked(unlock_view) => match message {
+                Message::UnlockMessage(msg) => {
+                    if unlock_view.update(msg) {
+                        self.state = State::Loading;
+                        Command::perform(async {}, Message::Loading)
+                    } else {
+                        Command::none()
+                    }
+                }
+                _ => Command::none(),
+            },
             State::Loaded(data) => match message {
                 Message::Receiver(Some(md)) => {
                     let MiddleDate {
@@ -177,6 +213,8 @@ impl iced::Application for LocalNative {
                         notes,
                         count,
                         days,
+                        mut link_status,
+                        archived,
                     } = md;
                     data.search_page.count = count;
                     // TODO:
@@ -208,7 +246,18 @@ impl iced::Application for LocalNative {
                             Message::TagView,
                         ),
                         Command::perform(
-                            async move { notes.into_iter().map(NoteView::from).collect() },
+                            async move {
+                                notes
+                                    .into_iter()
+                                    .map(|note| {
+                                        let status = link_status.remove(&note.uuid4);
+                                        let is_archived = archived.contains(&note.uuid4);
+                                        NoteView::from(note)
+                                            .with_link_status(status)
+                                            .with_archived(is_archived)
+                                    })
+                                    .collect()
+                            },
                             Message::NoteView,
                         ),
                         {
@@ -269,7 +318,28 @@ impl iced::Application for LocalNative {
                     if matches!(smsg, sidebar::Message::ThemeChanged) {
                         data.search_page.days.chart.style = !data.search_page.days.chart.style;
                     }
-                    sidebar.update(smsg, settings, config, &mut self.theme)
+                    let load_peers = matches!(smsg, sidebar::Message::TurnSyncView);
+                    let cmd = sidebar.update(smsg, settings, config, &mut self.theme);
+                    if load_peers {
+                        Command::batch([
+                            cmd,
+                            Command::perform(
+                                sync::load_peers(data.conn.clone()),
+                                Message::PeersLoaded,
+                            ),
+                            Command::perform(
+                                sync::load_conflicts(data.conn.clone()),
+                                Message::ConflictsLoaded,
+                            ),
+                            Command::perform(sync::discover(), Message::Discovered),
+                            Command::perform(
+                                sync::load_sync_status(data.conn.clone()),
+                                Message::SyncStatusLoaded,
+                            ),
+                        ])
+                    } else {
+                        cmd
+                    }
                 }
                 Message::DeleteTipMessage(msg) => {
                     let Data {
@@ -308,26 +378,115 @@ impl iced::Application for LocalNative {
                 Message::SyncClientMessage(sync_msg) => {
                     data.sync_view.update(sync_msg, data.conn.clone())
                 }
-                Message::SyncResult(res) => {
-                    if let Err(err) = res {
-                        if let Some(io_error) = err.downcast_ref::<std::io::Error>() {
+                Message::SyncResult(res) => match res {
+                    Err(err) => {
+                        data.sync_view.stop_tracking();
+                        if err.is::<localnative_core::rpc::progress::Cancelled>() {
+                            data.sync_view
+                                .with_sync_state_mut(|state| *state = sync::SyncState::Cancelled);
+                        } else if let Some(io_error) = err.downcast_ref::<std::io::Error>() {
                             data.sync_view.with_sync_state_mut(|state| {
                                 *state = sync::SyncState::SyncError(io_error.kind())
                             });
+                        } else if err.is::<localnative_core::rpc::client::NotPaired>() {
+                            data.sync_view
+                                .with_sync_state_mut(|state| *state = sync::SyncState::NotPaired);
+                        } else if let Some(incompatible) = err.downcast_ref::<Incompatible>() {
+                            let state = match incompatible {
+                                Incompatible::UpgradeServer { .. } => {
+                                    sync::SyncState::UpgradeServer
+                                }
+                                Incompatible::UpgradeClient { .. } => {
+                                    sync::SyncState::UpgradeClient
+                                }
+                            };
+                            data.sync_view.with_sync_state_mut(|old| *old = state);
                         }
                         Command::none()
-                    } else {
+                    }
+                    Ok(fingerprint) => {
+                        data.sync_view.stop_tracking();
+                        data.sync_view
+                            .with_peer_fingerprint_mut(|peer| *peer = Some(fingerprint));
                         data.sync_view
                             .with_sync_state_mut(|state| *state = sync::SyncState::Complete);
 
-                        search_page::search(
-                            data.conn.clone(),
-                            data.search_page.search_value.clone(),
-                            config.limit,
-                            data.search_page.offset,
-                            data.search_page.range,
+                        // refreshes the notes once conflicts are loaded
+                        Command::perform(
+                            sync::load_conflicts(data.conn.clone()),
+                            Message::ConflictsLoaded,
                         )
                     }
+                },
+                Message::PreviewResult(res) => {
+                    match res {
+                        Ok(preview) => {
+                            data.sync_view.with_preview_mut(|old| *old = Some(preview));
+                            data.sync_view
+                                .with_sync_state_mut(|state| *state = sync::SyncState::Waiting);
+                        }
+                        Err(err) => {
+                            println!("sync preview fail:{:?}", err);
+                            data.sync_view.with_sync_state_mut(|state| {
+                                *state = sync::SyncState::PreviewFailed
+                            });
+                        }
+                    }
+                    Command::none()
+                }
+                Message::PairResult(res) => match res {
+                    Ok(fingerprint) => {
+                        data.sync_view
+                            .with_peer_fingerprint_mut(|peer| *peer = Some(fingerprint));
+                        data.sync_view.with_pairing_input_mut(|code| code.clear());
+                        data.sync_view
+                            .with_sync_state_mut(|state| *state = sync::SyncState::Paired);
+                        Command::perform(sync::load_peers(data.conn.clone()), Message::PeersLoaded)
+                    }
+                    Err(err) => {
+                        println!("pair fail:{:?}", err);
+                        data.sync_view
+                            .with_sync_state_mut(|state| *state = sync::SyncState::PairFailed);
+                        Command::none()
+                    }
+                },
+                Message::PeersLoaded(peers) => {
+                    if let Some(peers) = peers {
+                        data.sync_view.with_peers_mut(|old| *old = peers);
+                    }
+                    Command::none()
+                }
+                Message::Discovered(discovered) => {
+                    if let Some(discovered) = discovered {
+                        data.sync_view.with_discovered_mut(|old| *old = discovered);
+                    }
+                    Command::none()
+                }
+                Message::ServerInfoLoaded(info) => {
+                    if info.is_some() {
+                        data.sync_view.with_server_info_mut(|old| *old = info);
+                    }
+                    Command::none()
+                }
+                Message::SyncStatusLoaded(sync_status) => {
+                    if let Some(sync_status) = sync_status {
+                        data.sync_view
+                            .with_sync_status_mut(|old| *old = sync_status);
+                    }
+                    Command::none()
+                }
+                Message::ConflictsLoaded(conflicts) => {
+                    if let Some(conflicts) = conflicts {
+                        data.sync_view.with_conflicts_mut(|old| *old = conflicts);
+                    }
+                    // sync or resolving may have changed notes
+                    search_page::search(
+                        data.conn.clone(),
+                        data.search_page.search_value.clone(),
+                        config.limit,
+                        data.search_page.offset,
+                        data.search_page.range,
+                    )
                 }
                 Message::SyncOption(opt) => {
                     if opt.is_none() {
@@ -364,6 +523,9 @@ impl iced::Application for LocalNative {
                                         *state = sync::ServerState::Opened
                                     });
                                     data.sync_view.update_server_addr(addr);
+                                    data.sync_view.with_pairing_code_mut(|code| {
+                                        *code = Some(localnative_core::rpc::pairing::new_code())
+                                    });
                                     data.sync_view
                                         .with_ip_qr_code_mut(|qr_code| *qr_code = state);
                                 })
@@ -440,12 +602,22 @@ impl iced::Application for LocalNative {
                 }
                 Message::InitHost(..) => Command::none(),
                 Message::Receiver(None) => Command::none(),
+                Message::ArchiveOpened(..) => Command::none(),
             },
         }
     }
 
     fn subscription(&self) -> iced::Subscription<Self::Message> {
-        iced_native::subscription::events_with(events_handler)
+        let events = iced_native::subscription::events_with(events_handler);
+        match &self.state {
+            // the progress bar follows the running sync
+            State::Loaded(data) if data.sync_view.is_syncing() => iced::Subscription::batch([
+                events,
+                iced::time::every(std::time::Duration::from_millis(200))
+                    .map(|_| Message::SyncClientMessage(sync::Message::ProgressTick)),
+            ]),
+            _ => events,
+        }
     }
 
     fn view(&self) -> iced::Element<'_, Self::Message> {
@@ -461,6 +633,7 @@ impl iced::Application for LocalNative {
                 vertical_space(Fill),
             ]
             .into(),
+            State::Locked(unlock_view) => unlock_view.view().map(Message::UnlockMessage),
             State::Loaded(data) => {
                 let Data {
                     search_page,
@@ -1,9 +1,10 @@
 use localnative_core::{
-    cmd::{delete, insert},
+    cmd::{delete, insert, link_check::LinkStatus},
     rusqlite::Connection,
     Note,
 };
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 
 use crate::{days::Day, tags::Tag, Conn};
 
@@ -13,6 +14,10 @@ pub struct MiddleDate {
     pub notes: Vec<Note>,
     pub days: Option<Vec<Day>>,
     pub tags: Vec<Tag>,
+    #[serde(default)]
+    pub link_status: HashMap<String, LinkStatus>,
+    #[serde(default)]
+    pub archived: HashSet<String>,
 }
 
 impl MiddleDate {
@@ -86,7 +91,9 @@ impl MiddleDate {
     ) -> Option<Self> {
         let search_result = localnative_core::exe::do_search(conn, &query, limit, offset).ok()?;
 
-        serde_json::from_str::<Self>(&search_result).ok()
+        serde_json::from_str::<Self>(&search_result)
+            .ok()
+            .map(|md| md.with_note_status(conn))
     }
     fn from_filter_inner(
         conn: &Connection,
@@ -99,6 +106,18 @@ impl MiddleDate {
         let filter_result =
             localnative_core::exe::do_filter(conn, query, limit, offset, from, to).ok()?;
 
-        serde_json::from_str::<Self>(&filter_result).ok()
+        serde_json::from_str::<Self>(&filter_result)
+            .ok()
+            .map(|md| md.with_note_status(conn))
+    }
+    fn with_note_status(mut self, conn: &Connection) -> Self {
+        let uuid4s: Vec<String> = self.notes.iter().map(|note| note.uuid4.clone()).collect();
+        if let Ok(link_status) = localnative_core::cmd::link_check::get_link_status(conn, &uuid4s) {
+            self.link_status = link_status;
+        }
+        if let Ok(archived) = localnative_core::cmd::archive::archived_uuid4s(conn, &uuid4s) {
+            self.archived = archived;
+        }
+        self
     }
 }
@@ -1,18 +1,23 @@
 use iced::{
     theme,
-    widget::{button, column, container, horizontal_space, row, text, QRCode},
-    Element,
+    widget::{button, column, container, horizontal_space, image, row, text, QRCode},
+    Color, Element,
     Length::Fill,
     Length::FillPortion,
 };
-use localnative_core::Note;
+use localnative_core::{cmd::link_check::LinkStatus, Note};
 
-use crate::icons::IconItem;
+use crate::{error_handle, icons::IconItem, tr, Conn};
 #[derive(Debug)]
 pub struct NoteView {
     note: Note,
     tags: Vec<Tag>,
     qrcode: Option<iced::widget::qr_code::State>,
+    link_status: Option<LinkStatus>,
+    archived: bool,
+    // result pages only carry the thumbnail, the original is loaded on demand
+    thumbnail: Option<image::Handle>,
+    image: Option<image::Handle>,
 }
 #[derive(Debug, Clone)]
 pub struct Tag {
@@ -25,6 +30,11 @@ pub enum Message {
     Delete(i64),
     QRCode,
     Search(String),
+    OpenArchive(String),
+    LoadImage(String),
+    ImageLoaded(Option<Vec<u8>>),
+    SetLocal(String, bool),
+    LocalChanged(bool),
 }
 
 impl From<Note> for NoteView {
@@ -37,17 +47,40 @@ impl From<Note> for NoteView {
                 name: name.to_owned(),
             })
             .collect();
+        let thumbnail = localnative_core::cmd::attachment::parse_data_url(&note.annotations)
+            .ok()
+            .map(|(_, data)| image::Handle::from_memory(data));
         NoteView {
             note,
             tags,
             qrcode: None,
+            link_status: None,
+            archived: false,
+            thumbnail,
+            image: None,
         }
     }
 }
 
 impl NoteView {
+    pub fn with_link_status(mut self, link_status: Option<LinkStatus>) -> Self {
+        self.link_status = link_status;
+        self
+    }
+    pub fn with_archived(mut self, archived: bool) -> Self {
+        self.archived = archived;
+        self
+    }
     pub fn view(&self) -> Element<Message> {
-        let Self { note, tags, qrcode } = self;
+        let Self {
+            note,
+            tags,
+            qrcode,
+            link_status,
+            archived,
+            thumbnail,
+            image,
+        } = self;
         let qrcode = qrcode.as_ref().map(|state| QRCode::new(state));
         let url = button(text(&note.url))
             .style(crate::style::Url.into())
@@ -60,11 +93,20 @@ impl NoteView {
             .style(theme::Button::Text)
             .padding(0)
             .on_press(Message::QRCode);
+        let local_button = button(text(if note.is_local {
+            tr!("local-only")
+        } else {
+            tr!("shared")
+        }))
+        .style(theme::Button::Text)
+        .padding(0)
+        .on_press(Message::SetLocal(note.uuid4.clone(), !note.is_local));
         let row = row![
             text(&note.created_at),
             text(&note.uuid4),
             text(format!("rowid {}", note.rowid)),
-            qrcode_button
+            qrcode_button,
+            local_button
         ]
         .spacing(5);
         let wrap = tags
@@ -84,8 +126,53 @@ impl NoteView {
         if !note.title.is_empty() {
             column = column.push(text(&note.title));
         }
+        if let Some(image) = image {
+            column = column.push(iced::widget::image(image.clone()));
+        } else if let Some(thumbnail) = thumbnail {
+            column = column.push(
+                button(iced::widget::image(thumbnail.clone()))
+                    .style(theme::Button::Text)
+                    .padding(0)
+                    .on_press(Message::LoadImage(note.uuid4.clone())),
+            );
+        }
         if !note.url.is_empty() {
-            column = column.push(url);
+            if *archived {
+                let archive = button(text(tr!("open-archive")))
+                    .style(crate::style::Url.into())
+                    .padding(0)
+                    .on_press(Message::OpenArchive(note.uuid4.clone()));
+                column = column.push(row![url, archive].spacing(10));
+            } else {
+                column = column.push(url);
+            }
+        }
+        if let Some(status) = link_status {
+            if status.is_dead() {
+                let reason = if status.status == 0 {
+                    status.error.clone()
+                } else {
+                    status.status.to_string()
+                };
+                column = column.push(
+                    text(format!(
+                        "{} {} ({})",
+                        tr!("link-dead"),
+                        reason,
+                        status.checked_at
+                    ))
+                    .style(theme::Text::Color(Color::from_rgb8(220, 50, 47))),
+                );
+            } else if status.is_redirected() {
+                column = column.push(
+                    text(format!(
+                        "{} {}",
+                        tr!("link-redirected"),
+                        status.redirect_url
+                    ))
+                    .style(theme::Text::Color(Color::from_rgb8(203, 75, 22))),
+                );
+            }
         }
         if !note.description.is_empty() {
             column = column.push(text(&note.description));
@@ -126,9 +213,64 @@ impl NoteView {
                 // 上层处理
                 println!("search tag: {}", tag);
             }
+            Message::OpenArchive(uuid4) => {
+                // 上层处理
+                println!("open archive: {}", uuid4);
+            }
+            Message::LoadImage(uuid4) => {
+                // 上层处理
+                println!("load image: {}", uuid4);
+            }
+            Message::ImageLoaded(data) => {
+                self.image = data.map(image::Handle::from_memory);
+            }
+            Message::SetLocal(uuid4, is_local) => {
+                // 上层处理
+                println!("set local {}: {}", uuid4, is_local);
+            }
+            Message::LocalChanged(is_local) => {
+                self.note.is_local = is_local;
+            }
         }
     }
 }
+// write the snapshot to a temp file and hand it to the default browser
+pub async fn open_archive(conn: Conn, uuid4: String) -> Option<()> {
+    let archive = {
+        let conn = &*conn.lock().await;
+        localnative_core::cmd::archive::get_archive(conn, &uuid4)
+            .map_err(|err| println!("get archive fail:{:?}", err))
+            .ok()??
+    };
+    let path = std::env::temp_dir().join(format!("localnative-archive-{}.html", uuid4));
+    tokio::fs::write(&path, archive.html)
+        .await
+        .map_err(error_handle)
+        .ok()?;
+    open::that(&path).map_err(error_handle).ok()
+}
+
+pub async fn load_image(conn: Conn, uuid4: String) -> Option<Vec<u8>> {
+    let conn = &*conn.lock().await;
+    localnative_core::cmd::attachment::get_image(conn, &uuid4)
+        .map_err(|err| println!("get image fail:{:?}", err))
+        .ok()?
+        .map(|(_, data)| data)
+}
+
+// the flag as stored, unchanged when the update fails
+pub async fn set_local(conn: Conn, uuid4: String, is_local: bool) -> bool {
+    let conn = &*conn.lock().await;
+    match localnative_core::cmd::visibility::set_local(conn, &uuid4, is_local) {
+        Ok(true) => is_local,
+        Ok(false) => !is_local,
+        Err(err) => {
+            println!("set local fail:{:?}", err);
+            !is_local
+        }
+    }
+}
+
 fn open(url: &str) {
     if let Err(err) = open::that(url) {
         println!("open url fail:{:?}", err);
@@ -151,6 +293,8 @@ impl iced::Sandbox for NoteView {
             annotations: "".to_owned(),
             created_at: "2021-05-28 08:30:00:000000000 UTC".to_owned(),
             is_public: true,
+            is_local: false,
+            version: Default::default(),
         }
         .into()
     }
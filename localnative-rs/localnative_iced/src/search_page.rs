@@ -275,6 +275,27 @@ impl SearchPage {
                         Command::none()
                     }
                 }
+                crate::note::Message::OpenArchive(uuid4) => Command::perform(
+                    crate::note::open_archive(conn, uuid4),
+                    crate::Message::ArchiveOpened,
+                ),
+                crate::note::Message::LoadImage(uuid4) => {
+                    Command::perform(crate::note::load_image(conn, uuid4), move |data| {
+                        crate::Message::SearchPageMessage(Message::Note(
+                            crate::note::Message::ImageLoaded(data),
+                            idx,
+                        ))
+                    })
+                }
+                crate::note::Message::SetLocal(uuid4, is_local) => Command::perform(
+                    crate::note::set_local(conn, uuid4, is_local),
+                    move |is_local| {
+                        crate::Message::SearchPageMessage(Message::Note(
+                            crate::note::Message::LocalChanged(is_local),
+                            idx,
+                        ))
+                    },
+                ),
                 crate::note::Message::Search(s) => {
                     self.search_value = s;
                     search(
@@ -1,6 +1,7 @@
 use iced::widget::Text;
 use iced::widget::{
-    button, column, horizontal_space, qr_code, row, text, text_input, tooltip, QRCode,
+    button, checkbox, column, horizontal_space, progress_bar, qr_code, row, text, text_input,
+    tooltip, QRCode,
 };
 use iced::Command;
 use iced::Element;
@@ -19,6 +20,15 @@ use std::{
     path::PathBuf,
 };
 
+use localnative_core::cmd::conflict::{Conflict, Resolution};
+use localnative_core::cmd::peer::Peer;
+use localnative_core::cmd::preview::{PreviewNote, SyncPreview};
+use localnative_core::rpc::daemon::SyncStatus;
+use localnative_core::rpc::discovery::{self, DiscoveredPeer};
+use localnative_core::rpc::info::ServerInfo;
+use localnative_core::rpc::pairing;
+use localnative_core::rpc::policy::{self, ServerConfig};
+use localnative_core::rpc::progress::{Phase, Progress, Tracker};
 use localnative_core::rpc::server::Stop;
 use tinyfiledialogs::open_file_dialog;
 
@@ -41,6 +51,30 @@ pub struct SyncView {
     pub sync_state: SyncState,
     pub server_state: ServerState,
     pub stop: Option<Stop>,
+    // certificate fingerprints, compared by the user on both devices
+    pub device_fingerprint: String,
+    pub peer_fingerprint: Option<String>,
+    // shown while the server is open, typed in on the other device
+    pub pairing_code: Option<String>,
+    pub pairing_input: String,
+    pub peers: Vec<Peer>,
+    // notes edited on two devices at once, waiting for the user
+    pub conflicts: Vec<Conflict>,
+    // servers advertised on the lan, picking one fills in the address
+    pub discovered: Vec<DiscoveredPeer>,
+    // last result of the background sync daemon per peer
+    pub sync_status: Vec<SyncStatus>,
+    // the sync running now, polled for progress and cancelled through it
+    pub tracker: Option<Tracker>,
+    pub progress: Progress,
+    // dry run of the sync the user asked for, shown until confirmed or dropped
+    pub preview: Option<(PendingSync, SyncPreview)>,
+    // what the server synced with last said about itself
+    pub server_info: Option<ServerInfo>,
+    // saved to sync_server.json and used from the next time the server opens
+    pub server_config: ServerConfig,
+    // the allowlist as typed, comma separated
+    pub allow_input: String,
     #[borrows(server_addr)]
     #[covariant]
     pub translate: TranslateWithArgs<'this>,
@@ -56,7 +90,23 @@ pub enum SyncState {
     IpAddrParsePass,
     FilePathGetError,
     SyncFromFileUnknownError,
+    NotPaired,
+    Paired,
+    PairFailed,
+    UpgradeServer,
+    UpgradeClient,
+    Cancelled,
+    Previewing,
+    PreviewFailed,
 }
+
+// what a confirmed preview goes on to sync with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PendingSync {
+    Server(SocketAddr),
+    File(PathBuf),
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum ServerState {
     Closed,
@@ -78,6 +128,26 @@ pub enum Message {
     OpenServer,
     Waiting,
     CloseServer,
+    PairingCodeInput(String),
+    Pair,
+    NewPairingCode,
+    RevokePeer(String),
+    PeerFilterInput(String, String),
+    SavePeerFilter(String),
+    Discover,
+    SelectDiscovered(SocketAddr),
+    ResolveConflict(String, Resolution),
+    ProgressTick,
+    CancelSync,
+    PreviewSync,
+    ConfirmSync,
+    DiscardPreview,
+    ServerReadOnly(bool),
+    ServerRemoteStop(bool),
+    AllowInput(String),
+    RateLimitInput(u32),
+    IdleTimeoutInput(u64),
+    FeedPortInput(u16),
 }
 
 impl SyncView {
@@ -89,6 +159,20 @@ impl SyncView {
     fn inner_update_server_addr(self, server_addr: String) -> Self {
         let Heads {
             stop,
+            device_fingerprint,
+            peer_fingerprint,
+            pairing_code,
+            pairing_input,
+            peers,
+            conflicts,
+            discovered,
+            sync_status,
+            tracker,
+            progress,
+            preview,
+            server_info,
+            server_config,
+            allow_input,
             server_state,
             sync_state,
             ip_qr_code,
@@ -99,6 +183,20 @@ impl SyncView {
 
         SyncViewBuilder {
             stop,
+            device_fingerprint,
+            peer_fingerprint,
+            pairing_code,
+            pairing_input,
+            peers,
+            conflicts,
+            discovered,
+            sync_status,
+            tracker,
+            progress,
+            preview,
+            server_info,
+            server_config,
+            allow_input,
             server_state,
             sync_state,
             ip_qr_code,
@@ -112,6 +210,22 @@ impl SyncView {
         .build()
     }
 
+    fn start_tracking(&mut self) -> Tracker {
+        let tracker = Tracker::new();
+        self.with_progress_mut(|progress| *progress = Progress::default());
+        self.with_tracker_mut(|old| *old = Some(tracker.clone()));
+        tracker
+    }
+
+    // called with the result, the bar goes away
+    pub fn stop_tracking(&mut self) {
+        self.with_tracker_mut(|tracker| *tracker = None);
+    }
+
+    pub fn is_syncing(&self) -> bool {
+        self.borrow_tracker().is_some()
+    }
+
     pub fn view(&self) -> Element<Message> {
         let ip_input = text_input("xxx.xxx.xxx.xxx", self.borrow_ip())
             .on_input(Message::IpInput)
@@ -142,6 +256,41 @@ impl SyncView {
             horizontal_space(Fill)
         ];
 
+        let discovered = self.borrow_discovered().iter().fold(
+            column![button(text(tr!("discover")))
+                .padding(0)
+                .on_press(Message::Discover)]
+            .spacing(5)
+            .align_items(iced::Alignment::Center),
+            |discovered, peer| {
+                discovered.push(
+                    button(
+                        row![
+                            text(&peer.name),
+                            text(peer.addr.to_string()),
+                            text(&peer.fingerprint).size(14)
+                        ]
+                        .spacing(10),
+                    )
+                    .padding(0)
+                    .on_press(Message::SelectDiscovered(peer.addr)),
+                )
+            },
+        );
+
+        let pairing_input = text_input("000000", self.borrow_pairing_input())
+            .on_input(Message::PairingCodeInput)
+            .padding(0)
+            .on_submit(Message::Pair)
+            .width(iced::Length::Fixed(80.));
+        let pair_row = row![
+            text(tr!("pairing-code")),
+            pairing_input,
+            button(text(tr!("pair"))).padding(0).on_press(Message::Pair)
+        ]
+        .spacing(5)
+        .align_items(iced::Alignment::Center);
+
         let sync_from_server_button = button(row![
             IconItem::SyncFromServer,
             text(tr!("sync-from-server"))
@@ -154,6 +303,10 @@ impl SyncView {
                 .padding(0)
                 .on_press(Message::SyncToServer);
 
+        let preview_button = button(text(tr!("sync-preview")))
+            .padding(0)
+            .on_press(Message::PreviewSync);
+
         let sync_form_file_button =
             button(row![IconItem::SyncFromFile, text(tr!("sync-from-file"))])
                 .padding(0)
@@ -171,6 +324,14 @@ impl SyncView {
             SyncState::IpAddrParsePass => tr!("sync-ip-parse-complete"),
             SyncState::FilePathGetError => tr!("sync-file-path-error"),
             SyncState::SyncFromFileUnknownError => tr!("sync-from-file-unknown-error"),
+            SyncState::NotPaired => tr!("sync-not-paired"),
+            SyncState::Paired => tr!("pair-complete"),
+            SyncState::PairFailed => tr!("pair-fail"),
+            SyncState::UpgradeServer => tr!("sync-upgrade-server"),
+            SyncState::UpgradeClient => tr!("sync-upgrade-client"),
+            SyncState::Cancelled => tr!("sync-cancelled"),
+            SyncState::Previewing => tr!("sync-previewing"),
+            SyncState::PreviewFailed => tr!("sync-preview-fail"),
         };
 
         let server_button_text = match self.borrow_server_state() {
@@ -190,33 +351,245 @@ impl SyncView {
             ServerState::Opened => server_button.on_press(Message::CloseServer),
         };
 
-        let mut res = column![
-            text(content_text),
-            text(tr!("sync-client-tip")),
-            text(tr!("input-ip-tip")),
-            ip_input_row,
-            row![
-                sync_from_server_button,
-                sync_to_server_button,
-                sync_form_file_button
-            ]
-            .spacing(20)
-            .align_items(iced::Alignment::Center),
-            text(tr!("sync-server-tip")),
-            server_button
-        ]
-        .spacing(20)
+        let mut fingerprints = column![text(format!(
+            "{} {}",
+            tr!("device-fingerprint"),
+            self.borrow_device_fingerprint()
+        ))]
         .align_items(iced::Alignment::Center);
+        if let Some(peer) = self.borrow_peer_fingerprint() {
+            fingerprints = fingerprints.push(text(format!("{} {}", tr!("peer-fingerprint"), peer)));
+        }
+        if let Some(info) = self.borrow_server_info() {
+            fingerprints = fingerprints.push(server_info_view(info));
+        }
+
+        let mut res = column![text(content_text)]
+            .spacing(20)
+            .align_items(iced::Alignment::Center);
+        if self.borrow_tracker().is_some() {
+            let progress = self.borrow_progress();
+            let phase = match progress.phase {
+                Phase::Connecting => tr!("sync-connecting"),
+                Phase::Pushing => tr!("sync-pushing"),
+                Phase::Pulling => tr!("sync-pulling"),
+                Phase::Done | Phase::Cancelled | Phase::Failed => tr!("sync-syncing"),
+            };
+            // an unknown total shows an empty bar with the count beside it
+            let total = progress.total.max(progress.done).max(1) as f32;
+            res = res.push(
+                row![
+                    text(phase),
+                    progress_bar(0.0..=total, progress.done as f32)
+                        .width(iced::Length::Fixed(200.)),
+                    text(format!(
+                        "{}/{} {} KB",
+                        progress.done,
+                        progress.total,
+                        progress.bytes / 1024
+                    )),
+                    button(text(tr!("cancel")))
+                        .padding(0)
+                        .on_press(Message::CancelSync)
+                ]
+                .spacing(10)
+                .align_items(iced::Alignment::Center),
+            );
+        }
+        if let Some((_, preview)) = self.borrow_preview() {
+            res = res.push(preview_view(preview));
+        }
+        res = res
+            .push(fingerprints)
+            .push(text(tr!("sync-client-tip")))
+            .push(text(tr!("input-ip-tip")))
+            .push(discovered)
+            .push(ip_input_row)
+            .push(pair_row)
+            .push(
+                row![
+                    sync_from_server_button,
+                    sync_to_server_button,
+                    preview_button,
+                    sync_form_file_button
+                ]
+                .spacing(20)
+                .align_items(iced::Alignment::Center),
+            )
+            .push(text(tr!("sync-server-tip")))
+            .push(server_button);
+
+        if *self.borrow_server_state() == ServerState::Closed {
+            res = res.push(self.server_settings_view());
+        }
 
         if *self.borrow_server_state() == ServerState::Opened {
             res = res
                 .push(Text::new(self.borrow_translate().tr()))
                 .push(QRCode::new(self.borrow_ip_qr_code()));
+            let feed_port = self.borrow_server_config().feed_port;
+            if let Some(feed_addr) = self
+                .borrow_server_addr()
+                .parse()
+                .ok()
+                .and_then(|addr| localnative_core::rpc::feed::feed_addr(addr, feed_port))
+            {
+                res = res.push(text(format!(
+                    "{} http://{}/atom.xml",
+                    tr!("feed-url"),
+                    feed_addr
+                )));
+            }
+            if let Some(code) = self.borrow_pairing_code() {
+                res = res.push(
+                    row![
+                        text(format!("{} {}", tr!("pairing-code"), code)).size(30),
+                        button(text(tr!("new-pairing-code")))
+                            .padding(0)
+                            .on_press(Message::NewPairingCode)
+                    ]
+                    .spacing(10)
+                    .align_items(iced::Alignment::Center),
+                );
+            }
+        }
+
+        if !self.borrow_peers().is_empty() {
+            let peers = self.borrow_peers().iter().fold(
+                column![text(tr!("paired-devices"))]
+                    .spacing(5)
+                    .align_items(iced::Alignment::Center),
+                |peers, peer| {
+                    let fingerprint = peer.fingerprint.clone();
+                    peers.push(
+                        row![
+                            text(&peer.name),
+                            text(&peer.fingerprint).size(14),
+                            text_input(&tr!("peer-sync-filter"), &peer.filter)
+                                .on_input(move |input| {
+                                    Message::PeerFilterInput(fingerprint.clone(), input)
+                                })
+                                .on_submit(Message::SavePeerFilter(peer.fingerprint.clone()))
+                                .padding(0)
+                                .width(iced::Length::Fixed(200.)),
+                            button(text(tr!("revoke")))
+                                .padding(0)
+                                .on_press(Message::RevokePeer(peer.fingerprint.clone()))
+                        ]
+                        .spacing(10)
+                        .align_items(iced::Alignment::Center),
+                    )
+                },
+            );
+            res = res.push(peers);
+        }
+
+        if !self.borrow_sync_status().is_empty() {
+            let sync_status = self.borrow_sync_status().iter().fold(
+                column![text(tr!("background-sync"))]
+                    .spacing(5)
+                    .align_items(iced::Alignment::Center),
+                |sync_status, status| {
+                    sync_status.push(
+                        row![
+                            text(if status.name.is_empty() {
+                                &status.addr
+                            } else {
+                                &status.name
+                            }),
+                            text(&status.last_attempt).size(14),
+                            text(&status.last_result)
+                        ]
+                        .spacing(10)
+                        .align_items(iced::Alignment::Center),
+                    )
+                },
+            );
+            res = res.push(sync_status);
+        }
+
+        if !self.borrow_conflicts().is_empty() {
+            let conflicts = self.borrow_conflicts().iter().fold(
+                column![text(tr!("conflicts"))]
+                    .spacing(5)
+                    .align_items(iced::Alignment::Center),
+                |conflicts, conflict| {
+                    let uuid4 = &conflict.note.uuid4;
+                    conflicts.push(
+                        row![
+                            text(&conflict.note.title),
+                            text(&conflict.copy.title),
+                            button(text(tr!("keep-note"))).padding(0).on_press(
+                                Message::ResolveConflict(uuid4.clone(), Resolution::KeepNote)
+                            ),
+                            button(text(tr!("keep-copy"))).padding(0).on_press(
+                                Message::ResolveConflict(uuid4.clone(), Resolution::KeepCopy)
+                            ),
+                            button(text(tr!("keep-both"))).padding(0).on_press(
+                                Message::ResolveConflict(uuid4.clone(), Resolution::KeepBoth)
+                            )
+                        ]
+                        .spacing(10)
+                        .align_items(iced::Alignment::Center),
+                    )
+                },
+            );
+            res = res.push(conflicts);
         }
 
         res.into()
     }
 
+    // applied when the server opens, so only shown while it is closed
+    fn server_settings_view(&self) -> Element<Message> {
+        let config = self.borrow_server_config();
+        let allow_input = text_input("192.168.1.0/24, 10.0.0.2", self.borrow_allow_input())
+            .on_input(Message::AllowInput)
+            .padding(0)
+            .width(iced::Length::Fixed(300.));
+        let rate_limit_input =
+            NumberInput::new(config.rate_limit, 100_000, Message::RateLimitInput).padding(0.);
+        let idle_timeout_input =
+            NumberInput::new(config.idle_timeout_secs, 86_400, Message::IdleTimeoutInput)
+                .padding(0.);
+        let feed_port_input = NumberInput::new(
+            config.feed_port.unwrap_or(0),
+            u16::MAX,
+            Message::FeedPortInput,
+        )
+        .padding(0.);
+        column![
+            row![
+                checkbox(
+                    tr!("server-read-only"),
+                    config.read_only,
+                    Message::ServerReadOnly
+                ),
+                checkbox(
+                    tr!("server-remote-stop"),
+                    config.remote_stop,
+                    Message::ServerRemoteStop
+                )
+            ]
+            .spacing(20),
+            row![text(tr!("server-allow")), allow_input].spacing(5),
+            row![
+                text(tr!("server-rate-limit")),
+                rate_limit_input,
+                text(tr!("server-idle-timeout")),
+                idle_timeout_input
+            ]
+            .spacing(5)
+            .align_items(iced::Alignment::Center),
+            row![text(tr!("server-feed-port")), feed_port_input]
+                .spacing(5)
+                .align_items(iced::Alignment::Center)
+        ]
+        .spacing(5)
+        .align_items(iced::Alignment::Center)
+        .into()
+    }
+
     pub fn update(&mut self, message: Message, conn: Conn) -> Command<crate::Message> {
         match message {
             Message::IpInput(input) => {
@@ -240,9 +613,13 @@ impl SyncView {
                 if let Ok(ip) = IpAddr::from_str(self.borrow_ip()) {
                     let addr = SocketAddr::new(ip, *self.borrow_port());
                     self.with_sync_state_mut(|state| *state = SyncState::Syncing);
-                    return Command::perform(
-                        client_sync_from_server(addr),
-                        crate::Message::SyncResult,
+                    let tracker = self.start_tracking();
+                    return with_server_info(
+                        addr,
+                        Command::perform(
+                            client_sync_from_server(addr, tracker),
+                            crate::Message::SyncResult,
+                        ),
                     );
                 } else {
                     self.with_sync_state_mut(|state| *state = SyncState::IpAddrParseError);
@@ -258,9 +635,13 @@ impl SyncView {
                 if let Ok(ip) = IpAddr::from_str(self.borrow_ip()) {
                     let addr = SocketAddr::new(ip, *self.borrow_port());
                     self.with_sync_state_mut(|state| *state = SyncState::Syncing);
-                    return Command::perform(
-                        client_sync_to_server(addr),
-                        crate::Message::SyncResult,
+                    let tracker = self.start_tracking();
+                    return with_server_info(
+                        addr,
+                        Command::perform(
+                            client_sync_to_server(addr, tracker),
+                            crate::Message::SyncResult,
+                        ),
                     );
                 } else {
                     self.with_sync_state_mut(|state| *state = SyncState::IpAddrParseError);
@@ -274,29 +655,170 @@ impl SyncView {
                 }
             }
             Message::SyncFromFile => {
+                // a file can come from anywhere, it is always previewed first
                 if let Some(path) = get_sync_file_path() {
-                    self.with_sync_state_mut(|state| *state = SyncState::Syncing);
+                    self.with_sync_state_mut(|state| *state = SyncState::Previewing);
 
-                    return Command::perform(sync_via_file(path, conn), crate::Message::SyncOption);
+                    return Command::perform(
+                        preview_file(path, conn),
+                        crate::Message::PreviewResult,
+                    );
                 } else {
                     self.with_sync_state_mut(|state| *state = SyncState::FilePathGetError);
                 }
             }
             Message::OpenServer => {
+                let allow = self
+                    .borrow_allow_input()
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|allow| !allow.is_empty())
+                    .map(String::from)
+                    .collect();
+                self.with_server_config_mut(|config| config.allow = allow);
+                let config = self.borrow_server_config().clone();
+                // an allowlist that does not parse keeps the server closed
+                if let Err(err) = policy::save_config(&policy::config_path(), &config) {
+                    eprintln!("server config error: {:?}", err);
+                    self.with_server_state_mut(|state| *state = ServerState::Error);
+                    return Command::none();
+                }
                 self.with_server_state_mut(|state| *state = ServerState::Starting);
 
                 return Command::perform(
-                    start_server(*self.borrow_port()),
+                    start_server(*self.borrow_port(), config),
                     crate::Message::StartServerResult,
                 );
             }
+            Message::ServerReadOnly(read_only) => {
+                self.with_server_config_mut(|config| config.read_only = read_only);
+            }
+            Message::ServerRemoteStop(remote_stop) => {
+                self.with_server_config_mut(|config| config.remote_stop = remote_stop);
+            }
+            Message::AllowInput(input) => {
+                self.with_allow_input_mut(|allow| *allow = input);
+            }
+            Message::RateLimitInput(rate_limit) => {
+                self.with_server_config_mut(|config| config.rate_limit = rate_limit);
+            }
+            Message::IdleTimeoutInput(idle_timeout) => {
+                self.with_server_config_mut(|config| config.idle_timeout_secs = idle_timeout);
+            }
+            Message::FeedPortInput(feed_port) => {
+                self.with_server_config_mut(|config| {
+                    config.feed_port = (feed_port != 0).then_some(feed_port)
+                });
+            }
             Message::Waiting => {
                 // waiting...
                 if *self.borrow_server_state() == ServerState::Error {
                     self.with_server_state_mut(|state| *state = ServerState::Closed);
                 }
             }
+            Message::PairingCodeInput(input) => {
+                if input.len() <= 6 && input.chars().all(|c| c.is_ascii_digit()) {
+                    self.with_pairing_input_mut(|code| *code = input);
+                }
+            }
+            Message::Pair => {
+                if let Ok(ip) = IpAddr::from_str(self.borrow_ip()) {
+                    let addr = SocketAddr::new(ip, *self.borrow_port());
+                    self.with_sync_state_mut(|state| *state = SyncState::Syncing);
+                    return Command::perform(
+                        client_pair(addr, self.borrow_pairing_input().clone()),
+                        crate::Message::PairResult,
+                    );
+                } else {
+                    self.with_sync_state_mut(|state| *state = SyncState::IpAddrParseError);
+                }
+            }
+            Message::NewPairingCode => {
+                self.with_pairing_code_mut(|code| *code = Some(pairing::new_code()));
+            }
+            Message::RevokePeer(fingerprint) => {
+                return Command::perform(
+                    revoke_peer(conn, fingerprint),
+                    crate::Message::PeersLoaded,
+                );
+            }
+            Message::PeerFilterInput(fingerprint, input) => {
+                self.with_peers_mut(|peers| {
+                    if let Some(peer) = peers.iter_mut().find(|p| p.fingerprint == fingerprint) {
+                        peer.filter = input;
+                    }
+                });
+            }
+            Message::SavePeerFilter(fingerprint) => {
+                let filter = self
+                    .borrow_peers()
+                    .iter()
+                    .find(|p| p.fingerprint == fingerprint)
+                    .map(|p| p.filter.clone())
+                    .unwrap_or_default();
+                return Command::perform(
+                    set_peer_filter(conn, fingerprint, filter),
+                    crate::Message::PeersLoaded,
+                );
+            }
+            Message::Discover => {
+                return Command::perform(discover(), crate::Message::Discovered);
+            }
+            Message::SelectDiscovered(addr) => {
+                self.with_ip_mut(|ip| *ip = addr.ip().to_string());
+                self.with_port_mut(|port| *port = addr.port());
+                self.with_sync_state_mut(|state| *state = SyncState::IpAddrParsePass);
+            }
+            Message::ProgressTick => {
+                if let Some(progress) = self.borrow_tracker().as_ref().map(Tracker::progress) {
+                    self.with_progress_mut(|old| *old = progress);
+                }
+            }
+            Message::CancelSync => {
+                if let Some(tracker) = self.borrow_tracker() {
+                    tracker.cancel();
+                }
+            }
+            Message::PreviewSync => {
+                if let Ok(ip) = IpAddr::from_str(self.borrow_ip()) {
+                    let addr = SocketAddr::new(ip, *self.borrow_port());
+                    self.with_sync_state_mut(|state| *state = SyncState::Previewing);
+                    return with_server_info(
+                        addr,
+                        Command::perform(preview_sync(addr), crate::Message::PreviewResult),
+                    );
+                } else {
+                    self.with_sync_state_mut(|state| *state = SyncState::IpAddrParseError);
+                }
+            }
+            Message::ConfirmSync => match self.with_preview_mut(Option::take) {
+                Some((PendingSync::Server(addr), _)) => {
+                    self.with_sync_state_mut(|state| *state = SyncState::Syncing);
+                    let tracker = self.start_tracking();
+                    return Command::perform(
+                        client_sync(addr, tracker),
+                        crate::Message::SyncResult,
+                    );
+                }
+                Some((PendingSync::File(path), _)) => {
+                    self.with_sync_state_mut(|state| *state = SyncState::Syncing);
+                    return Command::perform(sync_via_file(path, conn), crate::Message::SyncOption);
+                }
+                None => {}
+            },
+            Message::DiscardPreview => {
+                self.with_preview_mut(|preview| *preview = None);
+                self.with_sync_state_mut(|state| *state = SyncState::Waiting);
+            }
+            Message::ResolveConflict(uuid4, resolution) => {
+                return Command::perform(
+                    resolve_conflict(conn, uuid4, resolution),
+                    crate::Message::ConflictsLoaded,
+                );
+            }
             Message::CloseServer => {
+                pairing::clear_code();
+                self.with_pairing_code_mut(|code| *code = None);
                 self.with_server_state_mut(|state| *state = ServerState::Closing);
                 if let Some(cmd) = self.with_stop_mut(|stop| {
                     if let Some(stop) = stop.take() {
@@ -320,6 +842,10 @@ impl SyncView {
 
 impl Default for SyncView {
     fn default() -> Self {
+        // no file yet is the default config
+        let server_config = policy::resolve_config(None, None)
+            .map_err(|err| eprintln!("server config error: {:?}", err))
+            .unwrap_or_default();
         SyncViewBuilder {
             ip: String::new(),
             port: 2345,
@@ -328,6 +854,23 @@ impl Default for SyncView {
             sync_state: SyncState::Waiting,
             server_state: ServerState::Closed,
             stop: None,
+            device_fingerprint: localnative_core::rpc::tls::device_identity()
+                .map(|identity| identity.fingerprint())
+                .map_err(error_handle)
+                .unwrap_or_default(),
+            peer_fingerprint: None,
+            pairing_code: None,
+            pairing_input: String::new(),
+            peers: Vec::new(),
+            conflicts: Vec::new(),
+            discovered: Vec::new(),
+            sync_status: Vec::new(),
+            tracker: None,
+            progress: Progress::default(),
+            preview: None,
+            server_info: None,
+            allow_input: server_config.allow.join(", "),
+            server_config,
             translate_builder: |server_addr: &String| {
                 translate::TranslateWithArgs::new("ip-qr", translate::args("ip", server_addr))
             },
@@ -336,14 +879,207 @@ impl Default for SyncView {
     }
 }
 
+// titles listed per group, the counts cover the rest
+const PREVIEW_TITLES: usize = 5;
+
+fn server_info_view(info: &ServerInfo) -> Element<Message> {
+    let last_sync = if info.last_sync.is_empty() {
+        tr!("server-never-synced").to_string()
+    } else {
+        info.last_sync.clone()
+    };
+    column![
+        text(format!(
+            "{} {} ({} {}, {})",
+            tr!("server-name"),
+            info.name,
+            tr!("server-version"),
+            info.app_version,
+            info.schema
+        )),
+        text(format!(
+            "{} {}  {} {}",
+            tr!("server-note-count"),
+            info.note_count,
+            tr!("server-last-modified"),
+            info.last_modified
+        )),
+        text(format!("{} {}", tr!("server-last-sync"), last_sync)),
+        text(info.capabilities.join(", ")).size(14)
+    ]
+    .align_items(iced::Alignment::Center)
+    .into()
+}
+
+fn preview_view(preview: &SyncPreview) -> Element<Message> {
+    let mut report = column![text(if preview.is_empty() {
+        tr!("sync-preview-empty")
+    } else {
+        tr!("sync-preview-title")
+    })]
+    .spacing(5)
+    .align_items(iced::Alignment::Center);
+    let groups: [(&str, &Vec<PreviewNote>); 5] = [
+        ("sync-preview-push", &preview.push),
+        ("sync-preview-pull", &preview.pull),
+        ("sync-preview-update", &preview.update),
+        ("sync-preview-conflicts", &preview.conflicts),
+        ("sync-preview-delete", &preview.delete),
+    ];
+    for (key, notes) in groups.into_iter().filter(|(_, notes)| !notes.is_empty()) {
+        report = report.push(text(format!("{} {}", tr!(key), notes.len())));
+        for note in notes.iter().take(PREVIEW_TITLES) {
+            let title = if note.title.is_empty() {
+                &note.url
+            } else {
+                &note.title
+            };
+            report = report.push(text(title).size(14));
+        }
+    }
+    if preview.tombstones > 0 {
+        report = report.push(text(format!(
+            "{} {}",
+            tr!("sync-preview-tombstones"),
+            preview.tombstones
+        )));
+    }
+    report
+        .push(text(format!("{} KB", preview.bytes / 1024)))
+        .push(
+            row![
+                button(text(tr!("sync-confirm")))
+                    .padding(0)
+                    .on_press(Message::ConfirmSync),
+                button(text(tr!("cancel")))
+                    .padding(0)
+                    .on_press(Message::DiscardPreview)
+            ]
+            .spacing(10),
+        )
+        .into()
+}
+
 pub static IP_REGEX_SET: OnceCell<RegexSet> = OnceCell::new();
 
-pub async fn client_sync_from_server(addr: SocketAddr) -> anyhow::Result<()> {
-    localnative_core::rpc::client::run_sync_from_server(&addr).await
+pub async fn client_sync_from_server(addr: SocketAddr, tracker: Tracker) -> anyhow::Result<String> {
+    tracker.phase(Phase::Connecting, 0);
+    let result = localnative_core::rpc::client::run_sync_from_server(&addr.into(), &tracker).await;
+    tracker.finish(&result);
+    result
+}
+
+pub async fn client_sync_to_server(addr: SocketAddr, tracker: Tracker) -> anyhow::Result<String> {
+    tracker.phase(Phase::Connecting, 0);
+    let result = localnative_core::rpc::client::run_sync_to_server(&addr.into(), &tracker).await;
+    tracker.finish(&result);
+    result
+}
+
+// push then pull, as the preview showed
+pub async fn client_sync(addr: SocketAddr, tracker: Tracker) -> anyhow::Result<String> {
+    localnative_core::rpc::client::run_sync(&addr.into(), &tracker).await
+}
+
+pub async fn preview_sync(addr: SocketAddr) -> anyhow::Result<(PendingSync, SyncPreview)> {
+    let preview = localnative_core::rpc::client::run_preview(&addr.into()).await?;
+    Ok((PendingSync::Server(addr), preview))
+}
+
+pub async fn preview_file(path: PathBuf, conn: Conn) -> anyhow::Result<(PendingSync, SyncPreview)> {
+    let uri = path
+        .to_str()
+        .ok_or_else(|| anyhow::anyhow!("invalid path {:?}", path))?;
+    let preview = {
+        let conn = &*conn.lock().await;
+        localnative_core::cmd::preview::preview_attach(conn, uri, "")?
+    };
+    Ok((PendingSync::File(path), preview))
+}
+
+pub async fn client_pair(addr: SocketAddr, code: String) -> anyhow::Result<String> {
+    localnative_core::rpc::client::run_pair(&addr.into(), &code).await
+}
+
+pub async fn load_peers(conn: Conn) -> Option<Vec<Peer>> {
+    let conn = &*conn.lock().await;
+    localnative_core::cmd::peer::list_peers(conn)
+        .map_err(|err| println!("list peers fail:{:?}", err))
+        .ok()
+}
+
+pub async fn revoke_peer(conn: Conn, fingerprint: String) -> Option<Vec<Peer>> {
+    {
+        let conn = &*conn.lock().await;
+        localnative_core::cmd::peer::revoke_peer(conn, &fingerprint)
+            .map_err(|err| println!("revoke peer fail:{:?}", err))
+            .ok()?;
+    }
+    load_peers(conn).await
+}
+
+// takes effect from the next sync with the peer
+pub async fn set_peer_filter(conn: Conn, fingerprint: String, filter: String) -> Option<Vec<Peer>> {
+    {
+        let conn = &*conn.lock().await;
+        localnative_core::cmd::peer::set_filter(conn, &fingerprint, &filter)
+            .map_err(|err| println!("set peer filter fail:{:?}", err))
+            .ok()?;
+    }
+    load_peers(conn).await
+}
+
+// browsing blocks for the whole timeout, off the ui thread
+// the server is asked who it is on the side, a sync does not wait for it
+fn with_server_info(addr: SocketAddr, cmd: Command<crate::Message>) -> Command<crate::Message> {
+    Command::batch([
+        Command::perform(server_info(addr), crate::Message::ServerInfoLoaded),
+        cmd,
+    ])
+}
+
+pub async fn server_info(addr: SocketAddr) -> Option<ServerInfo> {
+    localnative_core::rpc::client::run_info(&addr.into())
+        .await
+        .map_err(|err| println!("server info fail:{:?}", err))
+        .ok()
+}
+
+pub async fn discover() -> Option<Vec<DiscoveredPeer>> {
+    tokio::task::spawn_blocking(|| discovery::browse(discovery::DEFAULT_BROWSE_TIMEOUT))
+        .await
+        .map_err(error_handle)
+        .ok()?
+        .map_err(|err| println!("discover fail:{:?}", err))
+        .ok()
+}
+
+pub async fn load_sync_status(conn: Conn) -> Option<Vec<SyncStatus>> {
+    let conn = &*conn.lock().await;
+    localnative_core::rpc::daemon::list_status(conn)
+        .map_err(|err| println!("sync status fail:{:?}", err))
+        .ok()
 }
 
-pub async fn client_sync_to_server(addr: SocketAddr) -> anyhow::Result<()> {
-    localnative_core::rpc::client::run_sync_to_server(&addr).await
+pub async fn load_conflicts(conn: Conn) -> Option<Vec<Conflict>> {
+    let conn = &*conn.lock().await;
+    localnative_core::cmd::conflict::list_conflicts(conn)
+        .map_err(|err| println!("list conflicts fail:{:?}", err))
+        .ok()
+}
+
+pub async fn resolve_conflict(
+    conn: Conn,
+    uuid4: String,
+    resolution: Resolution,
+) -> Option<Vec<Conflict>> {
+    {
+        let conn = &*conn.lock().await;
+        localnative_core::cmd::conflict::resolve_conflict(conn, &uuid4, resolution)
+            .map_err(|err| println!("resolve conflict fail:{:?}", err))
+            .ok()?;
+    }
+    load_conflicts(conn).await
 }
 
 pub fn get_sync_file_path() -> Option<PathBuf> {
@@ -364,7 +1100,7 @@ pub async fn sync_via_file(path: PathBuf, conn: Conn) -> Option<()> {
     tokio::task::spawn(async move {
         if let Some(uri) = path.to_str() {
             let conn = &*conn.lock().await;
-            localnative_core::cmd::sync_via_attach(conn, uri);
+            localnative_core::cmd::sync_via_attach(conn, uri, "");
         }
     })
     .await
@@ -381,9 +1117,9 @@ pub fn get_ip() -> Option<String> {
         .ok()
 }
 
-pub async fn start_server(port: u16) -> std::io::Result<Stop> {
+pub async fn start_server(port: u16, config: ServerConfig) -> std::io::Result<Stop> {
     let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), port);
-    localnative_core::rpc::server::iced_start_server(addr).await
+    localnative_core::rpc::server::iced_start_server(addr, config).await
 }
 
 pub async fn stop_server(stop: Stop) -> Option<()> {
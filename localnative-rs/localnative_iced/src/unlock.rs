@@ -0,0 +1,70 @@
+use iced::{
+    theme,
+    widget::{button, column, horizontal_space, row, text, text_input, vertical_space},
+    Color, Element,
+    Length::{Fill, Fixed},
+};
+
+use crate::tr;
+
+#[derive(Debug, Default)]
+pub struct UnlockView {
+    passphrase: String,
+    error: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    PassphraseInput(String),
+    Unlock,
+}
+
+impl UnlockView {
+    pub fn view(&self) -> Element<Message> {
+        let input = text_input(&tr!("passphrase"), &self.passphrase)
+            .password()
+            .on_input(Message::PassphraseInput)
+            .on_submit(Message::Unlock)
+            .width(Fixed(300.));
+        let mut content = column![
+            text(tr!("database-locked")).size(30),
+            input,
+            button(text(tr!("unlock"))).on_press(Message::Unlock),
+        ]
+        .spacing(10)
+        .align_items(iced::Alignment::Center);
+        if let Some(error) = &self.error {
+            content =
+                content.push(text(error).style(theme::Text::Color(Color::from_rgb8(220, 50, 47))));
+        }
+        column![
+            vertical_space(Fill),
+            row![horizontal_space(Fill), content, horizontal_space(Fill)],
+            vertical_space(Fill),
+        ]
+        .into()
+    }
+    // true once the passphrase opened the database
+    pub fn update(&mut self, msg: Message) -> bool {
+        match msg {
+            Message::PassphraseInput(passphrase) => {
+                self.passphrase = passphrase;
+                false
+            }
+            Message::Unlock => {
+                let location = localnative_core::exe::sqlite3_db_location();
+                let path = std::path::Path::new(&location);
+                match localnative_core::encryption::unlock(path, &self.passphrase) {
+                    Ok(()) => {
+                        self.passphrase.clear();
+                        true
+                    }
+                    Err(err) => {
+                        self.error = Some(format!("{} {}", tr!("unlock-fail"), err));
+                        false
+                    }
+                }
+            }
+        }
+    }
+}
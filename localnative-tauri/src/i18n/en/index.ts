@@ -26,7 +26,15 @@ const en: BaseTranslation = {
 		StopSyncServer: 'Stop Server',
 		SyncAsServerLocalAddr: 'Local Server Address: {serverAddress}',
 		SyncAsClientConnectServerNotExistModalTitle: 'Error',
-		SyncAsClientConnectServerNotExistModalContent: 'Connect failed'
+		SyncAsClientConnectServerNotExistModalContent: 'Connect failed',
+		SyncCancel: 'Cancel',
+		SyncCancelled: 'Sync cancelled, notes already exchanged are kept',
+		PairingCode: 'Pairing code: {code}',
+		NewPairingCode: 'New Code',
+		PairingCodePlaceholder: 'Pairing code shown on the server',
+		Pair: 'Pair',
+		PairDone: 'Paired, server fingerprint: {fingerprint}',
+		PairFailed: 'Pairing failed, check the code and try again'
 	},
 	Settings: {
 		Language: 'Language'
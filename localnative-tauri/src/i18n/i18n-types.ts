@@ -104,6 +104,40 @@ type RootTranslation = {
 		 * C​o​n​n​e​c​t​ ​f​a​i​l​e​d
 		 */
 		SyncAsClientConnectServerNotExistModalContent: string
+		/**
+		 * C​a​n​c​e​l
+		 */
+		SyncCancel: string
+		/**
+		 * S​y​n​c​ ​c​a​n​c​e​l​l​e​d​,​ ​n​o​t​e​s​ ​a​l​r​e​a​d​y​ ​e​x​c​h​a​n​g​e​d​ ​a​r​e​ ​k​e​p​t
+		 */
+		SyncCancelled: string
+		/**
+		 * P​a​i​r​i​n​g​ ​c​o​d​e​:​ ​{​c​o​d​e​}
+		 * @param {unknown} code
+		 */
+		PairingCode: RequiredParams<'code'>
+		/**
+		 * N​e​w​ ​C​o​d​e
+		 */
+		NewPairingCode: string
+		/**
+		 * P​a​i​r​i​n​g​ ​c​o​d​e​ ​s​h​o​w​n​ ​o​n​ ​t​h​e​ ​s​e​r​v​e​r
+		 */
+		PairingCodePlaceholder: string
+		/**
+		 * P​a​i​r
+		 */
+		Pair: string
+		/**
+		 * P​a​i​r​e​d​,​ ​s​e​r​v​e​r​ ​f​i​n​g​e​r​p​r​i​n​t​:​ ​{​f​i​n​g​e​r​p​r​i​n​t​}
+		 * @param {unknown} fingerprint
+		 */
+		PairDone: RequiredParams<'fingerprint'>
+		/**
+		 * P​a​i​r​i​n​g​ ​f​a​i​l​e​d​,​ ​c​h​e​c​k​ ​t​h​e​ ​c​o​d​e​ ​a​n​d​ ​t​r​y​ ​a​g​a​i​n
+		 */
+		PairFailed: string
 	}
 	Settings: {
 		/**
@@ -203,6 +237,38 @@ export type TranslationFunctions = {
 		 * Connect failed
 		 */
 		SyncAsClientConnectServerNotExistModalContent: () => LocalizedString
+		/**
+		 * Cancel
+		 */
+		SyncCancel: () => LocalizedString
+		/**
+		 * Sync cancelled, notes already exchanged are kept
+		 */
+		SyncCancelled: () => LocalizedString
+		/**
+		 * Pairing code: {code}
+		 */
+		PairingCode: (arg: { code: unknown }) => LocalizedString
+		/**
+		 * New Code
+		 */
+		NewPairingCode: () => LocalizedString
+		/**
+		 * Pairing code shown on the server
+		 */
+		PairingCodePlaceholder: () => LocalizedString
+		/**
+		 * Pair
+		 */
+		Pair: () => LocalizedString
+		/**
+		 * Paired, server fingerprint: {fingerprint}
+		 */
+		PairDone: (arg: { fingerprint: unknown }) => LocalizedString
+		/**
+		 * Pairing failed, check the code and try again
+		 */
+		PairFailed: () => LocalizedString
 	}
 	Settings: {
 		/**
@@ -26,7 +26,15 @@ const zh: Translation = {
 		StopSyncServer: '关闭服务器',
 		SyncAsServerLocalAddr: '本地服务器地址：{serverAddress}',
 		SyncAsClientConnectServerNotExistModalTitle: '错误',
-		SyncAsClientConnectServerNotExistModalContent: '连接失败'
+		SyncAsClientConnectServerNotExistModalContent: '连接失败',
+		SyncCancel: '取消',
+		SyncCancelled: '同步已取消，已交换的便签会保留',
+		PairingCode: '配对码：{code}',
+		NewPairingCode: '新配对码',
+		PairingCodePlaceholder: '服务器上显示的配对码',
+		Pair: '配对',
+		PairDone: '已配对，服务器指纹：{fingerprint}',
+		PairFailed: '配对失败，请检查配对码后重试'
 	},
 	Settings: {
 		Language: '语言'
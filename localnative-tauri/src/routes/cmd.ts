@@ -155,7 +155,50 @@ export function cmdClientSync(addr: string) {
     action: "client-sync",
     addr: addr,
   };
-  cmd(message);
+  return cmd(message);
+}
+
+export type SyncProgress = {
+  phase: "connecting" | "pushing" | "pulling" | "done" | "cancelled" | "failed";
+  done: number;
+  // 0 when the server cannot tell
+  total: number;
+  bytes: number;
+};
+
+// progress of the running or last client sync, null before any
+export async function cmdSyncProgress(): Promise<SyncProgress | null> {
+  const input = JSON.stringify({ action: "sync-progress" });
+  const res = await invoke<string>("input", { input });
+  return JSON.parse(res)["sync-progress"];
+}
+
+export function cmdCancelSync() {
+  const input = JSON.stringify({ action: "cancel-sync" });
+  return invoke<string>("input", { input });
+}
+
+// the server's fingerprint once both sides trust each other, null if the
+// code was rejected
+export async function cmdClientPair(
+  addr: string,
+  code: string
+): Promise<string | null> {
+  const message = {
+    action: "client-pair",
+    addr: addr,
+    code: code,
+  };
+  const input = JSON.stringify(message);
+  const res = await invoke<string>("input", { input });
+  return JSON.parse(res)["client-pair"] ?? null;
+}
+
+// a new one-time code for the server started here, valid for a few minutes
+export async function cmdPairingCode(): Promise<string> {
+  const input = JSON.stringify({ action: "pairing-code" });
+  const res = await invoke<string>("input", { input });
+  return JSON.parse(res)["pairing-code"];
 }
 
 export function cmdClientStopServer(addr: string) {
@@ -169,7 +212,7 @@ export function cmdClientStopServer(addr: string) {
 function cmd(message: any) {
   const input = JSON.stringify(message, null, 2);
 
-  invoke<string>("input", { input }).then((res) => {
+  return invoke<string>("input", { input }).then((res) => {
     const resp: { days: any; notes: any; tags: any; count: number } =
       JSON.parse(res);
 
@@ -178,6 +221,7 @@ function cmd(message: any) {
     }
 
     onNativeMessage(resp);
+    return resp;
   });
 }
 